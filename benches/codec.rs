@@ -0,0 +1,138 @@
+use bytes::{Bytes, BytesMut};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::{
+    builder::MqttMessageBuilder, connect::Connect, decoder::read_fixed_header, publish::Publish,
+    Decoder, Encoder, PacketEncoder,
+};
+use walle_mqtt_protocol::{QoS, Topic};
+
+fn publish(qos: QoS, payload_len: usize) -> Publish {
+    let builder = MqttMessageBuilder::publish()
+        .topic("/sensor/temperature")
+        .qos(qos)
+        .payload(Bytes::from(vec![0u8; payload_len]));
+    match qos {
+        QoS::AtMostOnce => builder.build().unwrap(),
+        _ => builder
+            .packet_id(PacketId::new(1).unwrap())
+            .build()
+            .unwrap(),
+    }
+}
+
+fn connect() -> Connect {
+    MqttMessageBuilder::connect()
+        .client_id("client_01")
+        .keep_alive(10)
+        .clean_session(true)
+        .username("rump")
+        .password("mq")
+        .will_qos(QoS::AtLeastOnce)
+        .will_topic("/will")
+        .will_message(Bytes::from_static(b"offline"))
+        .build()
+        .unwrap()
+}
+
+fn encode<T: Encoder>(packet: &T) -> BytesMut {
+    let mut buffer = BytesMut::new();
+    packet.encode(&mut buffer).unwrap();
+    buffer
+}
+
+fn encode_publish(c: &mut Criterion) {
+    let qos0 = publish(QoS::AtMostOnce, 64);
+    let qos1 = publish(QoS::AtLeastOnce, 1024);
+    c.bench_function("encode_publish_qos0_64b", |b| {
+        b.iter(|| encode(black_box(&qos0)))
+    });
+    c.bench_function("encode_publish_qos1_1kb", |b| {
+        b.iter(|| encode(black_box(&qos1)))
+    });
+}
+
+fn decode_publish(c: &mut Criterion) {
+    let bytes = encode(&publish(QoS::AtMostOnce, 64)).freeze();
+    c.bench_function("decode_publish_qos0_64b", |b| {
+        b.iter(|| Publish::decode(black_box(bytes.clone())).unwrap())
+    });
+}
+
+fn decode_connect(c: &mut Criterion) {
+    let bytes = encode(&connect()).freeze();
+    c.bench_function("decode_connect_with_will_and_login", |b| {
+        b.iter(|| Connect::decode(black_box(bytes.clone())).unwrap())
+    });
+}
+
+fn encode_subscribe(c: &mut Criterion) {
+    let topics = (0..5)
+        .map(|i| Topic::new(format!("/sensor/{}/+", i), QoS::AtLeastOnce))
+        .collect();
+    let subscribe = MqttMessageBuilder::subscribe()
+        .packet_id(PacketId::new(1).unwrap())
+        .topics(topics)
+        .build()
+        .unwrap();
+    c.bench_function("encode_subscribe_5_topics", |b| {
+        b.iter(|| encode(black_box(&subscribe)))
+    });
+}
+
+// QoS2的完整流程：PUBLISH -> PUBREC -> PUBREL -> PUBCOMP
+fn encode_qos2_flow(c: &mut Criterion) {
+    let publish = publish(QoS::ExactlyOnce, 64);
+    let pub_rec = MqttMessageBuilder::pub_rec()
+        .packet_id(PacketId::new(1).unwrap())
+        .build()
+        .unwrap();
+    let pub_rel = MqttMessageBuilder::pub_rel()
+        .packet_id(PacketId::new(1).unwrap())
+        .build()
+        .unwrap();
+    let pub_comp = MqttMessageBuilder::pub_comp()
+        .packet_id(PacketId::new(1).unwrap())
+        .build()
+        .unwrap();
+    c.bench_function("encode_qos2_flow", |b| {
+        b.iter(|| {
+            let mut buffer = BytesMut::new();
+            black_box(&publish).encode(&mut buffer).unwrap();
+            black_box(&pub_rec).encode(&mut buffer).unwrap();
+            black_box(&pub_rel).encode(&mut buffer).unwrap();
+            black_box(&pub_comp).encode(&mut buffer).unwrap();
+            buffer
+        })
+    });
+}
+
+fn bench_read_fixed_header(c: &mut Criterion) {
+    let bytes = encode(&publish(QoS::AtLeastOnce, 1024)).freeze();
+    c.bench_function("read_fixed_header", |b| {
+        b.iter(|| read_fixed_header(&mut black_box(bytes.clone())).unwrap())
+    });
+}
+
+// 对比预先按照serialized_len()分配容量与从空的BytesMut开始编码
+fn encode_allocation(c: &mut Criterion) {
+    let publish = publish(QoS::AtLeastOnce, 1024);
+    let mut group = c.benchmark_group("encode_publish_1kb_allocation");
+    group.bench_function("BytesMut::new", |b| b.iter(|| encode(black_box(&publish))));
+    group.bench_function("BytesMut::with_capacity", |b| {
+        b.iter(|| black_box(&publish).encode_with_hint().unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    encode_publish,
+    decode_publish,
+    decode_connect,
+    encode_subscribe,
+    encode_qos2_flow,
+    bench_read_fixed_header,
+    encode_allocation
+);
+criterion_main!(benches);
@@ -0,0 +1,100 @@
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use walle_mqtt_protocol::common::coder::{read_mqtt_str, read_mqtt_string};
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::{
+    builder::MqttMessageBuilder, connect::Connect, decode_zero_copy, publish::Publish,
+    subscribe::Subscribe, Decoder, PacketEncoder,
+};
+use walle_mqtt_protocol::{QoS, Topic};
+
+// 构建一个payload为1KB的publish报文
+fn publish_bytes() -> Bytes {
+    MqttMessageBuilder::publish()
+        .topic("/sensor/temperature")
+        .qos(QoS::AtLeastOnce)
+        .packet_id(PacketId::new(1).unwrap())
+        .payload(Bytes::from(vec![0u8; 1024]))
+        .build()
+        .unwrap()
+        .encode_with_hint()
+        .unwrap()
+        .freeze()
+}
+
+fn decode_publish(c: &mut Criterion) {
+    let bytes = publish_bytes();
+    let mut group = c.benchmark_group("decode_publish_1kb");
+    group.bench_function("Publish::decode", |b| {
+        b.iter(|| Publish::decode(black_box(bytes.clone())).unwrap())
+    });
+    group.bench_function("decode_zero_copy", |b| {
+        b.iter(|| decode_zero_copy(black_box(bytes.clone())).unwrap())
+    });
+    group.finish();
+}
+
+fn connect_bytes() -> Bytes {
+    MqttMessageBuilder::connect()
+        .client_id("client_01")
+        .keep_alive(10)
+        .clean_session(true)
+        .username("rump")
+        .password("mq")
+        .will_qos(QoS::AtLeastOnce)
+        .will_topic("/will")
+        .will_message(Bytes::from_static(b"offline"))
+        .build()
+        .unwrap()
+        .encode_with_hint()
+        .unwrap()
+        .freeze()
+}
+
+fn subscribe_bytes() -> Bytes {
+    let topics = (0..5)
+        .map(|i| Topic::new(format!("/sensor/{}/+", i), QoS::AtLeastOnce))
+        .collect();
+    MqttMessageBuilder::subscribe()
+        .packet_id(PacketId::new(1).unwrap())
+        .topics(topics)
+        .build()
+        .unwrap()
+        .encode_with_hint()
+        .unwrap()
+        .freeze()
+}
+
+// 包含字符串字段的报文，分别在默认特性和`--features bytes-strings`下运行对比
+fn decode_string_fields(c: &mut Criterion) {
+    let publish = publish_bytes();
+    let connect = connect_bytes();
+    let subscribe = subscribe_bytes();
+    let mut group = c.benchmark_group("decode_string_fields");
+    group.bench_function("publish_1kb", |b| {
+        b.iter(|| Publish::decode(black_box(publish.clone())).unwrap())
+    });
+    group.bench_function("connect_with_will_and_login", |b| {
+        b.iter(|| Connect::decode(black_box(connect.clone())).unwrap())
+    });
+    group.bench_function("subscribe_5_topics", |b| {
+        b.iter(|| Subscribe::decode(black_box(subscribe.clone())).unwrap())
+    });
+    group.finish();
+}
+
+// 读取报文中的topic：复制为String与直接持有Bytes
+fn read_topic(c: &mut Criterion) {
+    let bytes = Bytes::from_static(b"\x00\x13/sensor/temperature");
+    let mut group = c.benchmark_group("read_topic");
+    group.bench_function("read_mqtt_string", |b| {
+        b.iter(|| read_mqtt_string(&mut black_box(bytes.clone())).unwrap())
+    });
+    group.bench_function("read_mqtt_str", |b| {
+        b.iter(|| read_mqtt_str(&mut black_box(bytes.clone())).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_publish, decode_string_fields, read_topic);
+criterion_main!(benches);
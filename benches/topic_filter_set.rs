@@ -0,0 +1,39 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use walle_mqtt_protocol::common::topic::TopicFilter;
+use walle_mqtt_protocol::common::topic_filter_set::TopicFilterSet;
+use walle_mqtt_protocol::QoS;
+
+// 10000个精确订阅 + 100个通配符订阅
+fn subscriptions() -> TopicFilterSet<usize> {
+    let mut set = TopicFilterSet::new();
+    for i in 0..10_000 {
+        let filter = TopicFilter::new(format!("device/{}/status", i), QoS::AtMostOnce).unwrap();
+        set.insert(filter, i);
+    }
+    for i in 0..100 {
+        let filter = TopicFilter::new(format!("device/{}/#", i), QoS::AtMostOnce).unwrap();
+        set.insert(filter, 10_000 + i);
+    }
+    set
+}
+
+fn matching_subscribers(c: &mut Criterion) {
+    let set = subscriptions();
+    let mut group = c.benchmark_group("topic_filter_set_10k_exact_100_wildcard");
+    group.bench_function("exact_and_wildcard", |b| {
+        b.iter(|| {
+            set.matching_subscribers(black_box("device/42/status"))
+                .len()
+        })
+    });
+    group.bench_function("exact_only", |b| {
+        b.iter(|| {
+            set.matching_subscribers(black_box("device/5000/status"))
+                .len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, matching_subscribers);
+criterion_main!(benches);
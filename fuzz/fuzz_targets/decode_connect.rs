@@ -0,0 +1,13 @@
+//! 只对CONNECT报文的解码器进行模糊测试
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use walle_mqtt_protocol::v4::{connect::Connect, Decoder};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::copy_from_slice(data);
+    if std::panic::catch_unwind(|| Connect::decode(bytes)).is_err() {
+        panic!("CONNECT解码器panic，输入为：{:02x?}", data);
+    }
+});
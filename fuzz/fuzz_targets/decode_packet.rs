@@ -0,0 +1,50 @@
+//! 对fixed_header以及各个报文的解码器进行模糊测试：
+//! 任意输入都只能返回`Ok`或者`Err(ProtoError)`，不能panic
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+use walle_mqtt_protocol::error::ProtoError;
+use walle_mqtt_protocol::v4::{
+    conn_ack::ConnAck, connect::Connect, decode_zero_copy, decoder::read_fixed_header,
+    dis_connect::DisConnect, ping_req::PingReq, ping_resp::PingResp, pub_ack::PubAck,
+    pub_comp::PubComp, pub_rec::PubRec, pub_rel::PubRel, publish::Publish, sub_ack::SubAck,
+    subscribe::Subscribe, un_suback::UnSubAck, un_subscribe::UnSubscribe, Decoder,
+};
+use walle_mqtt_protocol::{v5, MessageType};
+
+fn decode<T: Decoder<Error = ProtoError>>(bytes: Bytes) -> Result<(), ProtoError> {
+    T::decode(bytes).map(|_| ())
+}
+
+// 先读取fixed_header，再根据报文类型交给对应的解码器
+fn decode_packet(data: &[u8]) -> Result<(), ProtoError> {
+    let bytes = Bytes::copy_from_slice(data);
+    let _ = decode_zero_copy(bytes.clone());
+    let _ = v5::decode(bytes.clone());
+    let fixed_header = read_fixed_header(&mut bytes.clone())?;
+    match fixed_header.message_type() {
+        MessageType::CONNECT => decode::<Connect>(bytes),
+        MessageType::CONNACK => decode::<ConnAck>(bytes),
+        MessageType::PUBLISH => decode::<Publish>(bytes),
+        MessageType::PUBACK => decode::<PubAck>(bytes),
+        MessageType::PUBREC => decode::<PubRec>(bytes),
+        MessageType::PUBREL => decode::<PubRel>(bytes),
+        MessageType::PUBCOMP => decode::<PubComp>(bytes),
+        MessageType::SUBSCRIBE => decode::<Subscribe>(bytes),
+        MessageType::SUBACK => decode::<SubAck>(bytes),
+        MessageType::UNSUBSCRIBE => decode::<UnSubscribe>(bytes),
+        MessageType::UNSUBACK => decode::<UnSubAck>(bytes),
+        MessageType::PINGREQ => decode::<PingReq>(bytes),
+        MessageType::PINGRESP => decode::<PingResp>(bytes),
+        MessageType::DISCONNECT => decode::<DisConnect>(bytes),
+        // v3.1.1中没有AUTH报文，交给v5的解码器
+        MessageType::AUTH => v5::decode(bytes).map(|_| ()),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if std::panic::catch_unwind(|| decode_packet(data)).is_err() {
+        panic!("解码器panic，输入为：{:02x?}", data);
+    }
+});
@@ -0,0 +1,97 @@
+use crate::error::ProtoError;
+
+/// client_id允许使用的字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientIdCharset {
+    /// 协议规定服务端必须接受的字符：0-9a-zA-Z
+    Alphanumeric,
+    /// 任意UTF-8字符
+    #[default]
+    Any,
+}
+
+impl ClientIdCharset {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            ClientIdCharset::Alphanumeric => c.is_ascii_alphanumeric(),
+            ClientIdCharset::Any => true,
+        }
+    }
+}
+
+/**
+ client_id的检查策略，默认只限制长度不超过65535，
+ [`ClientIdPolicy::strict`]对应协议中服务端必须接受的1-23个字母或数字，例如：
+
+```rust
+use walle_mqtt_protocol::common::client_id::{validate_client_id, ClientIdPolicy};
+let policy = ClientIdPolicy::strict();
+assert!(validate_client_id("client01", &policy).is_ok());
+assert!(validate_client_id("client-01", &policy).is_err());
+assert!(validate_client_id("client-01", &ClientIdPolicy::default()).is_ok());
+```
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIdPolicy {
+    pub max_len: usize,
+    pub charset: ClientIdCharset,
+}
+
+impl ClientIdPolicy {
+    pub fn new(max_len: usize, charset: ClientIdCharset) -> Self {
+        Self { max_len, charset }
+    }
+
+    pub fn strict() -> Self {
+        Self::new(23, ClientIdCharset::Alphanumeric)
+    }
+}
+
+impl Default for ClientIdPolicy {
+    fn default() -> Self {
+        Self::new(u16::MAX as usize, ClientIdCharset::Any)
+    }
+}
+
+/// 按照策略检查client_id，max_len按字符数计算。
+/// 空的client_id不在这里检查，它是否合法取决于clean_session
+pub fn validate_client_id(client_id: &str, policy: &ClientIdPolicy) -> Result<(), ProtoError> {
+    if client_id.chars().count() > policy.max_len {
+        return Err(ProtoError::InvalidClientId("client_id超出最大长度"));
+    }
+    if !client_id.chars().all(|c| policy.charset.contains(c)) {
+        return Err(ProtoError::InvalidClientId("client_id中包含不允许的字符"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_client_id, ClientIdCharset, ClientIdPolicy};
+    use crate::error::ProtoError;
+
+    #[test]
+    fn strict_policy_should_be_work() {
+        let policy = ClientIdPolicy::strict();
+        assert!(validate_client_id("", &policy).is_ok());
+        assert!(validate_client_id(&"a".repeat(23), &policy).is_ok());
+        assert_eq!(
+            validate_client_id(&"a".repeat(24), &policy),
+            Err(ProtoError::InvalidClientId("client_id超出最大长度"))
+        );
+        for client_id in ["client_01", "设备01", "a b"] {
+            assert_eq!(
+                validate_client_id(client_id, &policy),
+                Err(ProtoError::InvalidClientId("client_id中包含不允许的字符"))
+            );
+        }
+    }
+
+    #[test]
+    fn custom_policy_should_count_chars() {
+        let policy = ClientIdPolicy::new(4, ClientIdCharset::Any);
+        assert!(validate_client_id("设备0001", &policy).is_err());
+        assert!(validate_client_id("设备01", &policy).is_ok());
+        assert!(validate_client_id(&"设".repeat(30_000), &ClientIdPolicy::default()).is_ok());
+    }
+}
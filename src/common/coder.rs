@@ -0,0 +1,129 @@
+//! 网络字节序(大端)的基础读写函数，v4与v5共用
+use crate::common::mqtt_string::MqttString;
+use crate::error::ProtoError;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub fn read_u8(stream: &mut Bytes) -> Result<u8, ProtoError> {
+    if stream.is_empty() {
+        return Err(ProtoError::NotKnow);
+    }
+    Ok(stream.get_u8())
+}
+
+pub fn read_u16(stream: &mut Bytes) -> Result<u16, ProtoError> {
+    if stream.len() < 2 {
+        return Err(ProtoError::NotKnow);
+    }
+    Ok(stream.get_u16())
+}
+
+pub fn read_u32(stream: &mut Bytes) -> Result<u32, ProtoError> {
+    if stream.len() < 4 {
+        return Err(ProtoError::NotKnow);
+    }
+    Ok(stream.get_u32())
+}
+
+pub fn write_u8(stream: &mut BytesMut, value: u8) {
+    stream.put_u8(value);
+}
+
+pub fn write_u16(stream: &mut BytesMut, value: u16) {
+    stream.put_u16(value);
+}
+
+pub fn write_u32(stream: &mut BytesMut, value: u32) {
+    stream.put_u32(value);
+}
+
+///读取数据到bytes
+pub fn read_mqtt_bytes(stream: &mut Bytes) -> Result<Bytes, ProtoError> {
+    let len = read_u16(stream)? as usize;
+    if len > stream.len() {
+        return Err(ProtoError::NotKnow);
+    }
+    Ok(stream.split_to(len))
+}
+
+///读取数据到字符串
+pub fn read_mqtt_string(stream: &mut Bytes) -> Result<String, ProtoError> {
+    let s = read_mqtt_bytes(stream)?;
+    match String::from_utf8(s.to_vec()) {
+        Ok(v) => Ok(v),
+        Err(_e) => Err(ProtoError::NotKnow),
+    }
+}
+
+/// 读取字符串，与报文共用同一块内存，不会复制字符串内容
+pub fn read_mqtt_str(stream: &mut Bytes) -> Result<MqttString, ProtoError> {
+    MqttString::from_bytes(read_mqtt_bytes(stream)?)
+}
+
+/// 写入两字节长度 + 数据，返回写入的字节数，数据长度超过65535字节时返回错误
+pub fn write_mqtt_bytes(stream: &mut BytesMut, bytes: &[u8]) -> Result<usize, ProtoError> {
+    let len = bytes.len();
+    if len > u16::MAX as usize {
+        return Err(ProtoError::StringTooLong(len));
+    }
+    stream.put_u16(len as u16);
+    stream.extend_from_slice(bytes);
+    Ok(2 + len)
+}
+
+/// Serializes a string to stream
+pub fn write_mqtt_string(stream: &mut BytesMut, string: &str) -> Result<usize, ProtoError> {
+    write_mqtt_bytes(stream, string.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProtoError;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn read_and_write_should_round_trip() {
+        let mut buffer = BytesMut::new();
+        write_u8(&mut buffer, 0xab);
+        write_u16(&mut buffer, 0x1234);
+        write_u32(&mut buffer, 0xdead_beef);
+        assert_eq!(write_mqtt_bytes(&mut buffer, &[0x00, 0xff]).unwrap(), 4);
+        assert_eq!(write_mqtt_string(&mut buffer, "温度").unwrap(), 8);
+        assert_eq!(&buffer[..7], &[0xab, 0x12, 0x34, 0xde, 0xad, 0xbe, 0xef]);
+
+        let mut stream = buffer.freeze();
+        assert_eq!(read_u8(&mut stream), Ok(0xab));
+        assert_eq!(read_u16(&mut stream), Ok(0x1234));
+        assert_eq!(read_u32(&mut stream), Ok(0xdead_beef));
+        assert_eq!(
+            read_mqtt_bytes(&mut stream).unwrap().as_ref(),
+            &[0x00, 0xff]
+        );
+        assert_eq!(read_mqtt_string(&mut stream).unwrap(), "温度");
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn insufficient_bytes_should_return_error() {
+        assert_eq!(read_u8(&mut Bytes::new()), Err(ProtoError::NotKnow));
+        assert_eq!(
+            read_u16(&mut Bytes::from_static(&[0x01])),
+            Err(ProtoError::NotKnow)
+        );
+        assert_eq!(
+            read_u32(&mut Bytes::from_static(&[0x01, 0x02, 0x03])),
+            Err(ProtoError::NotKnow)
+        );
+        // 声明的长度超过剩余的数据
+        let mut stream = Bytes::from_static(&[0x00, 0x03, b'a', b'b']);
+        assert!(read_mqtt_bytes(&mut stream).is_err());
+        assert!(read_mqtt_string(&mut Bytes::from_static(&[0x00])).is_err());
+        // 不是合法的UTF-8
+        let mut stream = Bytes::from_static(&[0x00, 0x01, 0xff]);
+        assert!(read_mqtt_string(&mut stream).is_err());
+        assert_eq!(
+            write_mqtt_bytes(&mut BytesMut::new(), &vec![0; 65_536]),
+            Err(ProtoError::StringTooLong(65_536))
+        );
+    }
+}
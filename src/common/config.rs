@@ -0,0 +1,49 @@
+use crate::v5::property::MAX_VARIABLE_BYTE_INTEGER;
+
+/// 报文的最大长度：1字节的byte1 + 4字节的剩余长度 + 最大剩余长度
+pub const MAX_PACKET_SIZE: usize = 1 + 4 + MAX_VARIABLE_BYTE_INTEGER;
+
+/// 解码配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeConfig {
+    /// 允许的最大报文长度(fixed_header + 剩余长度)，为None时不做限制
+    pub max_packet_size: Option<usize>,
+    /// 为true时检查PUBLISH报文的topic name，参见[`crate::common::topic::validate_topic_name`]
+    pub strict_topic_name: bool,
+    /// 为true时拒绝dup为1的QoS0 PUBLISH报文，为false时忽略其中的dup标志
+    pub strict_dup_flag: bool,
+}
+
+impl Default for DecodeConfig {
+    fn default() -> Self {
+        Self {
+            max_packet_size: Some(MAX_PACKET_SIZE),
+            strict_topic_name: false,
+            strict_dup_flag: true,
+        }
+    }
+}
+
+impl DecodeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置允许的最大报文长度
+    pub fn max_packet_size(mut self, max_packet_size: Option<usize>) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// 设置是否检查PUBLISH报文的topic name
+    pub fn strict_topic_name(mut self, strict_topic_name: bool) -> Self {
+        self.strict_topic_name = strict_topic_name;
+        self
+    }
+
+    /// 设置是否拒绝dup为1的QoS0 PUBLISH报文
+    pub fn strict_dup_flag(mut self, strict_dup_flag: bool) -> Self {
+        self.strict_dup_flag = strict_dup_flag;
+        self
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::ProtoError;
+use crate::v4::publish::Publish;
+use crate::QoS;
+use std::collections::{HashMap, HashSet};
+
+/// 收到PUBLISH报文之后应该如何处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// 新的消息，需要交给应用
+    Deliver,
+    /// dup为1并且message_id仍在处理中的重发消息，只需要再次回复PUBACK或PUBREC
+    Redeliver,
+}
+
+/// 接收方处理QoS 2消息的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qos2State {
+    /// 收到了PUBLISH，还没有发送PUBREC
+    Received,
+    /// 已经发送PUBREC，等待PUBREL
+    RecSent,
+    /// 收到了PUBREL，还没有发送PUBCOMP
+    RelReceived,
+}
+
+/**
+ 接收方记录处理中的QoS 1和QoS 2消息的message_id，用于识别重发的消息以及被重复使用的message_id。
+
+ QoS 2消息的完整流程为：PUBLISH -> PUBREC -> PUBREL -> PUBCOMP，例如：
+
+```rust
+use walle_mqtt_protocol::common::in_flight::{Action, InFlightTracker};
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::QoS;
+let publish = MqttMessageBuilder::publish()
+    .topic("/a")
+    .qos(QoS::ExactlyOnce)
+    .packet_id(PacketId::MIN)
+    .build()
+    .unwrap();
+let mut tracker = InFlightTracker::new();
+assert_eq!(tracker.on_publish(&publish).unwrap(), Action::Deliver);
+tracker.on_pubrec(1).unwrap();
+tracker.on_pubrel(1).unwrap();
+tracker.on_pubcomp(1).unwrap();
+assert!(tracker.is_empty());
+```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct InFlightTracker {
+    // 已经收到但还没有回复PUBACK的QoS 1消息
+    qos1: HashSet<u16>,
+    // 还没有完成的QoS 2消息
+    qos2: HashMap<u16, Qos2State>,
+}
+
+impl InFlightTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn qos2_state(&self, id: u16) -> Option<Qos2State> {
+        self.qos2.get(&id).copied()
+    }
+
+    /// 是否包含处理中的message_id
+    pub fn contains(&self, id: u16) -> bool {
+        self.qos1.contains(&id) || self.qos2.contains_key(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.qos1.is_empty() && self.qos2.is_empty()
+    }
+
+    /// 收到PUBLISH报文，message_id仍在处理中时，dup为1视为重发，否则返回错误
+    pub fn on_publish(&mut self, publish: &Publish) -> Result<Action, ProtoError> {
+        let qos = publish.qos();
+        if qos == QoS::AtMostOnce {
+            return Ok(Action::Deliver);
+        }
+        let id = publish
+            .variable_header_ref()
+            .packet_id()
+            .ok_or(ProtoError::QoS12RequiresPacketId)?
+            .get();
+        if self.contains(id) {
+            return match publish.dup() {
+                true => Ok(Action::Redeliver),
+                false => Err(ProtoError::DuplicatePacketId(id)),
+            };
+        }
+        match qos {
+            QoS::AtLeastOnce => {
+                self.qos1.insert(id);
+            }
+            _ => {
+                self.qos2.insert(id, Qos2State::Received);
+            }
+        }
+        Ok(Action::Deliver)
+    }
+
+    /// 发送了PUBACK，返回message_id之前是否在处理中
+    pub fn on_puback(&mut self, id: u16) -> bool {
+        self.qos1.remove(&id)
+    }
+
+    /// 发送了PUBREC，重发的PUBLISH也需要再次回复PUBREC
+    pub fn on_pubrec(&mut self, id: u16) -> Result<(), ProtoError> {
+        self.advance(id, |state| match state {
+            Qos2State::Received | Qos2State::RecSent => Some(Qos2State::RecSent),
+            Qos2State::RelReceived => None,
+        })
+    }
+
+    /// 收到了PUBREL，发送方可能会重发PUBREL
+    pub fn on_pubrel(&mut self, id: u16) -> Result<(), ProtoError> {
+        self.advance(id, |state| match state {
+            Qos2State::RecSent | Qos2State::RelReceived => Some(Qos2State::RelReceived),
+            Qos2State::Received => None,
+        })
+    }
+
+    /// 发送了PUBCOMP，message_id可以被重新使用
+    pub fn on_pubcomp(&mut self, id: u16) -> Result<(), ProtoError> {
+        match self.qos2.get(&id) {
+            Some(Qos2State::RelReceived) => {
+                self.qos2.remove(&id);
+                Ok(())
+            }
+            _ => Err(ProtoError::OutOfOrderQos2(id)),
+        }
+    }
+
+    // 按照next更新QoS 2消息的阶段，next返回None时说明报文顺序错误
+    fn advance(
+        &mut self,
+        id: u16,
+        next: impl FnOnce(Qos2State) -> Option<Qos2State>,
+    ) -> Result<(), ProtoError> {
+        let state = self
+            .qos2
+            .get_mut(&id)
+            .ok_or(ProtoError::OutOfOrderQos2(id))?;
+        *state = next(*state).ok_or(ProtoError::OutOfOrderQos2(id))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, InFlightTracker, Qos2State};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::builder::MqttMessageBuilder;
+    use crate::v4::publish::Publish;
+    use crate::QoS;
+
+    fn publish(qos: QoS, id: u16, dup: bool) -> Publish {
+        MqttMessageBuilder::publish()
+            .topic("/a")
+            .qos(qos)
+            .packet_id(PacketId::new(id).unwrap())
+            .dup(dup)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn qos2_state_machine_should_be_work() {
+        let mut tracker = InFlightTracker::new();
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::ExactlyOnce, 10, false)),
+            Ok(Action::Deliver)
+        );
+        assert_eq!(tracker.qos2_state(10), Some(Qos2State::Received));
+        // PUBREL和PUBCOMP不能早于PUBREC
+        assert_eq!(tracker.on_pubrel(10), Err(ProtoError::OutOfOrderQos2(10)));
+        assert_eq!(tracker.on_pubcomp(10), Err(ProtoError::OutOfOrderQos2(10)));
+        tracker.on_pubrec(10).unwrap();
+        assert_eq!(tracker.qos2_state(10), Some(Qos2State::RecSent));
+        // 发送方没有收到PUBREC，重发PUBLISH
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::ExactlyOnce, 10, true)),
+            Ok(Action::Redeliver)
+        );
+        tracker.on_pubrec(10).unwrap();
+        assert_eq!(tracker.on_pubcomp(10), Err(ProtoError::OutOfOrderQos2(10)));
+        tracker.on_pubrel(10).unwrap();
+        tracker.on_pubrel(10).unwrap();
+        assert_eq!(tracker.qos2_state(10), Some(Qos2State::RelReceived));
+        assert_eq!(tracker.on_pubrec(10), Err(ProtoError::OutOfOrderQos2(10)));
+        tracker.on_pubcomp(10).unwrap();
+        assert_eq!(tracker.qos2_state(10), None);
+        assert!(tracker.is_empty());
+        // 完成之后message_id可以被重新使用
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::ExactlyOnce, 10, false)),
+            Ok(Action::Deliver)
+        );
+        assert_eq!(tracker.on_pubrec(11), Err(ProtoError::OutOfOrderQos2(11)));
+    }
+
+    #[test]
+    fn reused_packet_id_should_return_error() {
+        let mut tracker = InFlightTracker::new();
+        let qos0 = MqttMessageBuilder::publish().topic("/a").build().unwrap();
+        assert_eq!(tracker.on_publish(&qos0), Ok(Action::Deliver));
+        assert!(tracker.is_empty());
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::AtLeastOnce, 1, false)),
+            Ok(Action::Deliver)
+        );
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::AtLeastOnce, 1, true)),
+            Ok(Action::Redeliver)
+        );
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::AtLeastOnce, 1, false)),
+            Err(ProtoError::DuplicatePacketId(1))
+        );
+        // 同一个message_id不能同时用于QoS 1和QoS 2消息
+        assert_eq!(
+            tracker.on_publish(&publish(QoS::ExactlyOnce, 1, false)),
+            Err(ProtoError::DuplicatePacketId(1))
+        );
+        assert!(tracker.on_puback(1));
+        assert!(!tracker.on_puback(1));
+        assert!(tracker.is_empty());
+    }
+}
@@ -0,0 +1,12 @@
+//! v4与v5共用的配置等内容
+pub mod client_id;
+pub mod coder;
+pub mod config;
+pub mod in_flight;
+pub mod mqtt_string;
+pub mod packet_id;
+pub mod qos_flow;
+pub mod state;
+pub mod topic;
+pub mod topic_filter_set;
+pub mod var_int;
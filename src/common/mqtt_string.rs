@@ -0,0 +1,214 @@
+use crate::error::ProtoError;
+use bytes::Bytes;
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/**
+ 零拷贝的MQTT UTF-8字符串，内部直接持有报文中的Bytes。
+
+ 构造时校验UTF-8以及空字符(U+0000)，以&str访问时不会分配新的内存，
+ clone只会增加Bytes的引用计数，不会复制字符串内容。
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::common::mqtt_string::MqttString;
+let topic = MqttString::from_bytes(Bytes::from_static(b"/sensor/temp")).unwrap();
+assert_eq!(topic, "/sensor/temp");
+assert!(topic.starts_with("/sensor"));
+assert!(MqttString::from_bytes(Bytes::from_static(b"/a\0")).is_err());
+```
+
+ v4 PUBLISH报文中的topic([`TopicRef`](crate::v4::publish::TopicRef))内部就是MqttString，
+ 其他字符串字段参见[`StringField`]。
+ */
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct MqttString(Bytes);
+
+impl MqttString {
+    /// bytes不是合法的UTF-8或者包含空字符时返回[`ProtoError::MalformedString`]
+    pub fn from_bytes(bytes: Bytes) -> Result<Self, ProtoError> {
+        // U+0000在UTF-8中只会以单个0字节出现，直接在字节上查找比按字符查找快
+        if bytes.contains(&0) || std::str::from_utf8(&bytes).is_err() {
+            return Err(ProtoError::MalformedString);
+        }
+        Ok(Self(bytes))
+    }
+
+    /// 不检查空字符，调用方需要保证s不包含空字符或者之后会检查，
+    /// 例如已经通过[`validate_topic_name`]检查的topic
+    ///
+    /// [`validate_topic_name`]: crate::common::topic::validate_topic_name
+    pub(crate) fn from_string_unchecked(s: String) -> Self {
+        Self(Bytes::from(s))
+    }
+
+    pub fn as_str(&self) -> &str {
+        // 构造时已经校验过UTF-8，这里不会失败
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+
+    /// 返回原始的Bytes
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+/**
+v4报文中client_id、username、遗嘱topic以及订阅的topic字段的类型：
+开启`bytes-strings`特性时为[`MqttString`]，解码时直接持有报文中的Bytes；否则为String。
+
+两种类型都可以通过Deref当作&str使用。解码时MqttString还会拒绝空字符(U+0000)，
+本地构建报文时与String一样不做检查，由各个报文的validate检查。
+*/
+#[cfg(feature = "bytes-strings")]
+pub type StringField = MqttString;
+#[cfg(not(feature = "bytes-strings"))]
+pub type StringField = String;
+
+/// 读取字符串字段，开启`bytes-strings`时不会复制字符串内容
+#[cfg(feature = "bytes-strings")]
+pub(crate) fn read_string_field(stream: &mut Bytes) -> Result<StringField, ProtoError> {
+    crate::common::coder::read_mqtt_str(stream)
+}
+
+#[cfg(not(feature = "bytes-strings"))]
+pub(crate) fn read_string_field(stream: &mut Bytes) -> Result<StringField, ProtoError> {
+    crate::common::coder::read_mqtt_string(stream)
+}
+
+/// 本地构建报文时把String转换为字符串字段，不检查空字符
+#[cfg(feature = "bytes-strings")]
+pub(crate) fn string_field(s: String) -> StringField {
+    MqttString::from_string_unchecked(s)
+}
+
+#[cfg(not(feature = "bytes-strings"))]
+pub(crate) fn string_field(s: String) -> StringField {
+    s
+}
+
+impl Deref for MqttString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for MqttString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// 与str的hash保持一致，这样HashMap<MqttString, _>可以直接用&str查询
+impl Borrow<str> for MqttString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Hash for MqttString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for MqttString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for MqttString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for MqttString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for MqttString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl TryFrom<String> for MqttString {
+    type Error = ProtoError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_bytes(Bytes::from(s))
+    }
+}
+
+impl TryFrom<&str> for MqttString {
+    type Error = ProtoError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::from_bytes(Bytes::copy_from_slice(s.as_bytes()))
+    }
+}
+
+impl From<MqttString> for String {
+    fn from(s: MqttString) -> Self {
+        s.as_str().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MqttString;
+    use crate::common::coder::{read_mqtt_str, write_mqtt_string};
+    use crate::error::ProtoError;
+    use bytes::{Bytes, BytesMut};
+    use std::collections::HashMap;
+
+    #[test]
+    fn from_bytes_should_validate_utf8_and_null() {
+        let s = MqttString::from_bytes(Bytes::from("温度/1")).unwrap();
+        assert_eq!(s, "温度/1");
+        assert_eq!(s.len(), 8);
+        assert_eq!(s.to_string(), "温度/1");
+        assert_eq!(format!("{:?}", s), "\"温度/1\"");
+        assert_eq!(
+            MqttString::from_bytes(Bytes::from_static(&[0xff, 0xfe])),
+            Err(ProtoError::MalformedString)
+        );
+        assert_eq!(
+            MqttString::try_from("a\0b"),
+            Err(ProtoError::MalformedString)
+        );
+    }
+
+    #[test]
+    fn read_mqtt_str_should_not_copy() {
+        let mut buffer = BytesMut::new();
+        write_mqtt_string(&mut buffer, "/a/b").unwrap();
+        buffer.extend_from_slice(b"rest");
+        let frame = buffer.freeze();
+        let mut stream = frame.clone();
+        let s = read_mqtt_str(&mut stream).unwrap();
+        assert_eq!(s, "/a/b");
+        assert_eq!(stream.as_ref(), b"rest");
+        // 与原始报文共用同一块内存
+        assert_eq!(s.as_bytes().as_ptr(), frame[2..].as_ptr());
+        assert_eq!(s.clone().into_bytes().as_ptr(), s.as_ptr());
+
+        let mut map = HashMap::new();
+        map.insert(s, 1);
+        assert_eq!(map.get("/a/b"), Some(&1));
+    }
+}
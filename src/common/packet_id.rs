@@ -0,0 +1,95 @@
+use crate::error::ProtoError;
+use std::fmt;
+use std::num::NonZeroU16;
+
+/**
+报文标识符(Packet Identifier)，即message_id。
+
+SUBSCRIBE、UNSUBSCRIBE以及QoS大于0的PUBLISH报文都必须携带报文标识符，
+协议规定它的取值范围为1-65535，所以这里用NonZeroU16来表示，
+0或者超过65535的值在构造时就会返回[`ProtoError::InvalidPacketId`]。
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketId(NonZeroU16);
+
+impl PacketId {
+    /// 最小的报文标识符
+    pub const MIN: PacketId = PacketId(NonZeroU16::MIN);
+    /// 最大的报文标识符
+    pub const MAX: PacketId = PacketId(NonZeroU16::MAX);
+
+    /// value为0时返回None
+    pub const fn new(value: u16) -> Option<Self> {
+        match NonZeroU16::new(value) {
+            Some(value) => Some(PacketId(value)),
+            None => None,
+        }
+    }
+
+    pub fn get(&self) -> u16 {
+        self.0.get()
+    }
+}
+
+impl TryFrom<u16> for PacketId {
+    type Error = ProtoError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        PacketId::new(value).ok_or(ProtoError::InvalidPacketId(value as usize))
+    }
+}
+
+impl TryFrom<usize> for PacketId {
+    type Error = ProtoError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        u16::try_from(value)
+            .ok()
+            .and_then(PacketId::new)
+            .ok_or(ProtoError::InvalidPacketId(value))
+    }
+}
+
+impl From<PacketId> for u16 {
+    fn from(value: PacketId) -> Self {
+        value.get()
+    }
+}
+
+impl From<PacketId> for usize {
+    fn from(value: PacketId) -> Self {
+        value.get() as usize
+    }
+}
+
+impl fmt::Display for PacketId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PacketId;
+    use crate::error::ProtoError;
+
+    #[test]
+    fn packet_id_should_be_non_zero_u16() {
+        assert_eq!(PacketId::try_from(1u16).unwrap(), PacketId::MIN);
+        assert_eq!(PacketId::try_from(65_535usize).unwrap(), PacketId::MAX);
+        assert_eq!(u16::from(PacketId::new(1234).unwrap()), 1234);
+        assert_eq!(
+            PacketId::try_from(0u16).unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+        assert_eq!(
+            PacketId::try_from(0usize).unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+        assert_eq!(
+            PacketId::try_from(65_536usize).unwrap_err(),
+            ProtoError::InvalidPacketId(65_536)
+        );
+    }
+}
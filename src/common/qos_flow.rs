@@ -0,0 +1,256 @@
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::pub_comp::PubComp;
+use crate::v4::pub_rec::PubRec;
+use crate::v4::pub_rel::PubRel;
+use crate::v4::publish::Publish;
+use crate::QoS;
+
+/// 发送方处理一条QoS 2消息的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PublisherState {
+    #[default]
+    Idle,
+    /// 已经发送PUBLISH，等待PUBREC
+    WaitingForPubRec(u16),
+    /// 已经发送PUBREL，等待PUBCOMP
+    WaitingForPubComp(u16),
+}
+
+/**
+ 发送方(通常是客户端)的QoS 2流程：PUBLISH -> PUBREC -> PUBREL -> PUBCOMP，
+ 每个实例同一时间只处理一条消息，多条消息参见[`crate::common::in_flight::InFlightTracker`]。例如：
+
+```rust
+use walle_mqtt_protocol::common::qos_flow::{PublisherFlow, PublisherState};
+use walle_mqtt_protocol::v4::{pub_comp::PubComp, pub_rec::PubRec};
+let mut flow = PublisherFlow::new();
+let packet_id = flow.publish(7).unwrap();
+let pub_rel = flow.on_pubrec(&PubRec::new(packet_id)).unwrap();
+assert_eq!(pub_rel.message_id(), 7);
+flow.on_pubcomp(&PubComp::new(packet_id)).unwrap();
+assert_eq!(flow.state(), PublisherState::Idle);
+```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct PublisherFlow {
+    state: PublisherState,
+}
+
+impl PublisherFlow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> PublisherState {
+        self.state
+    }
+
+    /// 开始发送一条消息，返回构建PUBLISH报文使用的message_id
+    pub fn publish(&mut self, id: u16) -> Result<PacketId, ProtoError> {
+        if self.state != PublisherState::Idle {
+            return Err(ProtoError::UnexpectedPacketId(id));
+        }
+        let packet_id = PacketId::try_from(id)?;
+        self.state = PublisherState::WaitingForPubRec(id);
+        Ok(packet_id)
+    }
+
+    /// 收到PUBREC，返回需要发送的PUBREL
+    pub fn on_pubrec(&mut self, pub_rec: &PubRec) -> Result<PubRel, ProtoError> {
+        let id = pub_rec.message_id() as u16;
+        match self.state {
+            PublisherState::WaitingForPubRec(expected) if expected == id => {
+                self.state = PublisherState::WaitingForPubComp(id);
+                Ok(PubRel::new(PacketId::try_from(id)?))
+            }
+            _ => Err(ProtoError::UnexpectedPacketId(id)),
+        }
+    }
+
+    /// 收到PUBCOMP，流程结束
+    pub fn on_pubcomp(&mut self, pub_comp: &PubComp) -> Result<(), ProtoError> {
+        let id = pub_comp.message_id() as u16;
+        match self.state {
+            PublisherState::WaitingForPubComp(expected) if expected == id => {
+                self.state = PublisherState::Idle;
+                Ok(())
+            }
+            _ => Err(ProtoError::UnexpectedPacketId(id)),
+        }
+    }
+}
+
+/// 接收方处理一条QoS 2消息的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReceiverState {
+    #[default]
+    Idle,
+    /// 已经发送PUBREC，等待PUBREL
+    WaitingForPubRel(u16),
+}
+
+/**
+接收方(通常是服务端)的QoS 2流程，与[`PublisherFlow`]对应。
+等待PUBREL时收到dup为1的相同PUBLISH，说明PUBREC丢失，会再次返回PUBREC。
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ReceiverFlow {
+    state: ReceiverState,
+}
+
+impl ReceiverFlow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> ReceiverState {
+        self.state
+    }
+
+    /// 收到QoS 2的PUBLISH，返回需要发送的PUBREC
+    pub fn on_publish(&mut self, publish: &Publish) -> Result<PubRec, ProtoError> {
+        if publish.qos() != QoS::ExactlyOnce {
+            return Err(ProtoError::QoSError(publish.qos() as u8));
+        }
+        let packet_id = publish
+            .variable_header_ref()
+            .packet_id()
+            .ok_or(ProtoError::QoS12RequiresPacketId)?;
+        let id = packet_id.get();
+        match self.state {
+            ReceiverState::Idle => {}
+            ReceiverState::WaitingForPubRel(expected) if expected == id && publish.dup() => {}
+            _ => return Err(ProtoError::UnexpectedPacketId(id)),
+        }
+        self.state = ReceiverState::WaitingForPubRel(id);
+        Ok(PubRec::new(packet_id))
+    }
+
+    /// 收到PUBREL，返回需要发送的PUBCOMP，流程结束
+    pub fn on_pubrel(&mut self, pub_rel: &PubRel) -> Result<PubComp, ProtoError> {
+        let id = pub_rel.message_id() as u16;
+        match self.state {
+            ReceiverState::WaitingForPubRel(expected) if expected == id => {
+                self.state = ReceiverState::Idle;
+                Ok(PubComp::new(PacketId::try_from(id)?))
+            }
+            _ => Err(ProtoError::UnexpectedPacketId(id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PublisherFlow, PublisherState, ReceiverFlow, ReceiverState};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::builder::MqttMessageBuilder;
+    use crate::v4::publish::Publish;
+    use crate::v4::{pub_comp::PubComp, pub_rec::PubRec, pub_rel::PubRel};
+    use crate::QoS;
+
+    fn id(id: u16) -> PacketId {
+        PacketId::new(id).unwrap()
+    }
+
+    fn publish(qos: QoS, packet_id: u16, dup: bool) -> Publish {
+        MqttMessageBuilder::publish()
+            .topic("/a")
+            .qos(qos)
+            .packet_id(id(packet_id))
+            .dup(dup)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn publisher_flow_should_be_work() {
+        let mut flow = PublisherFlow::new();
+        // Idle状态下不能收到PUBREC和PUBCOMP
+        assert_eq!(
+            flow.on_pubrec(&PubRec::new(id(1))),
+            Err(ProtoError::UnexpectedPacketId(1))
+        );
+        assert_eq!(
+            flow.on_pubcomp(&PubComp::new(id(1))),
+            Err(ProtoError::UnexpectedPacketId(1))
+        );
+        assert_eq!(flow.publish(0), Err(ProtoError::InvalidPacketId(0)));
+        assert_eq!(flow.state(), PublisherState::Idle);
+
+        assert_eq!(flow.publish(1), Ok(id(1)));
+        assert_eq!(flow.state(), PublisherState::WaitingForPubRec(1));
+        assert_eq!(flow.publish(2), Err(ProtoError::UnexpectedPacketId(2)));
+        assert_eq!(
+            flow.on_pubrec(&PubRec::new(id(2))),
+            Err(ProtoError::UnexpectedPacketId(2))
+        );
+        assert!(flow.on_pubcomp(&PubComp::new(id(1))).is_err());
+        assert_eq!(flow.on_pubrec(&PubRec::new(id(1))), Ok(PubRel::new(id(1))));
+        assert_eq!(flow.state(), PublisherState::WaitingForPubComp(1));
+
+        assert!(flow.on_pubrec(&PubRec::new(id(1))).is_err());
+        assert_eq!(
+            flow.on_pubcomp(&PubComp::new(id(2))),
+            Err(ProtoError::UnexpectedPacketId(2))
+        );
+        assert_eq!(flow.on_pubcomp(&PubComp::new(id(1))), Ok(()));
+        assert_eq!(flow.state(), PublisherState::Idle);
+        assert_eq!(flow.publish(2), Ok(id(2)));
+    }
+
+    #[test]
+    fn receiver_flow_should_be_work() {
+        let mut flow = ReceiverFlow::new();
+        assert_eq!(
+            flow.on_pubrel(&PubRel::new(id(5))),
+            Err(ProtoError::UnexpectedPacketId(5))
+        );
+        assert_eq!(
+            flow.on_publish(&publish(QoS::AtLeastOnce, 5, false)),
+            Err(ProtoError::QoSError(1))
+        );
+
+        assert_eq!(
+            flow.on_publish(&publish(QoS::ExactlyOnce, 5, false)),
+            Ok(PubRec::new(id(5)))
+        );
+        assert_eq!(flow.state(), ReceiverState::WaitingForPubRel(5));
+        // PUBREC丢失，发送方重发PUBLISH
+        assert_eq!(
+            flow.on_publish(&publish(QoS::ExactlyOnce, 5, true)),
+            Ok(PubRec::new(id(5)))
+        );
+        // 上一条消息还没有完成
+        assert_eq!(
+            flow.on_publish(&publish(QoS::ExactlyOnce, 5, false)),
+            Err(ProtoError::UnexpectedPacketId(5))
+        );
+        assert_eq!(
+            flow.on_publish(&publish(QoS::ExactlyOnce, 6, false)),
+            Err(ProtoError::UnexpectedPacketId(6))
+        );
+        assert_eq!(
+            flow.on_pubrel(&PubRel::new(id(6))),
+            Err(ProtoError::UnexpectedPacketId(6))
+        );
+        assert_eq!(flow.on_pubrel(&PubRel::new(id(5))), Ok(PubComp::new(id(5))));
+        assert_eq!(flow.state(), ReceiverState::Idle);
+        assert!(flow.on_pubrel(&PubRel::new(id(5))).is_err());
+    }
+
+    #[test]
+    fn publisher_and_receiver_should_complete_handshake() {
+        let (mut publisher, mut receiver) = (PublisherFlow::new(), ReceiverFlow::new());
+        let packet_id = publisher.publish(42).unwrap();
+        let pub_rec = receiver
+            .on_publish(&publish(QoS::ExactlyOnce, packet_id.get(), false))
+            .unwrap();
+        let pub_rel = publisher.on_pubrec(&pub_rec).unwrap();
+        let pub_comp = receiver.on_pubrel(&pub_rel).unwrap();
+        publisher.on_pubcomp(&pub_comp).unwrap();
+        assert_eq!(publisher.state(), PublisherState::Idle);
+        assert_eq!(receiver.state(), ReceiverState::Idle);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::error::ProtoError;
+use crate::v4::{conn_ack::ConnAckType, Packet};
+use crate::MessageType;
+
+/// 连接所处的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionState {
+    /// 等待CONNECT以及CONNACK
+    #[default]
+    Connecting,
+    /// 收到了成功的CONNACK
+    Connected,
+    /// 发送了DISCONNECT或者CONNACK拒绝了连接
+    Disconnected,
+}
+
+impl ConnectionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionState::Connecting => "Connecting",
+            ConnectionState::Connected => "Connected",
+            ConnectionState::Disconnected => "Disconnected",
+        }
+    }
+}
+
+/**
+ 按照报文的发送方检查报文顺序，合法时更新连接状态：
+ - 客户端首先发送CONNECT，在收到CONNACK之前不能发送其他报文
+ - 服务端只能使用CONNACK回复CONNECT，连接建立后不能再发送CONNACK
+ - 客户端发送DISCONNECT之后连接结束
+
+ 协议允许客户端发送CONNECT后不等待CONNACK直接发送其他报文，这里的检查更加严格，例如：
+
+```rust
+use walle_mqtt_protocol::common::state::{ConnectionContext, ConnectionState};
+use walle_mqtt_protocol::v4::{builder::MqttMessageBuilder, conn_ack::{ConnAck, ConnAckType}, Packet};
+let mut context = ConnectionContext::new();
+let connect = MqttMessageBuilder::connect().client_id("client01").build().unwrap();
+assert!(context.validate_client_packet(&Packet::Connect(connect)).is_ok());
+let conn_ack = ConnAck::new(ConnAckType::Success).unwrap();
+assert!(context.validate_server_packet(&Packet::ConnAck(conn_ack)).is_ok());
+assert_eq!(context.state(), ConnectionState::Connected);
+```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionContext {
+    state: ConnectionState,
+    // 是否已经发送了CONNECT
+    connect_sent: bool,
+}
+
+impl ConnectionContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// 检查客户端发送的报文
+    pub fn validate_client_packet(&mut self, packet: &Packet) -> Result<(), ProtoError> {
+        let packet_type = packet.message_type();
+        match (self.state, packet_type) {
+            (ConnectionState::Connecting, MessageType::CONNECT) if !self.connect_sent => {
+                self.connect_sent = true;
+            }
+            (ConnectionState::Connected, MessageType::DISCONNECT) => {
+                self.state = ConnectionState::Disconnected;
+            }
+            (
+                ConnectionState::Connected,
+                MessageType::PUBLISH
+                | MessageType::PUBACK
+                | MessageType::PUBREC
+                | MessageType::PUBREL
+                | MessageType::PUBCOMP
+                | MessageType::SUBSCRIBE
+                | MessageType::UNSUBSCRIBE
+                | MessageType::PINGREQ,
+            ) => {}
+            _ => return Err(self.invalid(packet_type)),
+        }
+        Ok(())
+    }
+
+    /// 检查服务端发送的报文，CONNACK拒绝连接时状态变为Disconnected
+    pub fn validate_server_packet(&mut self, packet: &Packet) -> Result<(), ProtoError> {
+        let packet_type = packet.message_type();
+        match (self.state, packet) {
+            (ConnectionState::Connecting, Packet::ConnAck(conn_ack)) if self.connect_sent => {
+                self.state = match conn_ack.conn_ack_type() {
+                    ConnAckType::Success => ConnectionState::Connected,
+                    _ => ConnectionState::Disconnected,
+                };
+            }
+            (
+                ConnectionState::Connected,
+                Packet::Publish(_)
+                | Packet::PubAck(_)
+                | Packet::PubRec(_)
+                | Packet::PubRel(_)
+                | Packet::PubComp(_)
+                | Packet::SubAck(_)
+                | Packet::UnSubAck(_)
+                | Packet::PingResp(_),
+            ) => {}
+            _ => return Err(self.invalid(packet_type)),
+        }
+        Ok(())
+    }
+
+    fn invalid(&self, packet_type: MessageType) -> ProtoError {
+        ProtoError::InvalidPacketForState {
+            packet_type,
+            state: self.state.as_str(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectionContext, ConnectionState};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::builder::MqttMessageBuilder;
+    use crate::v4::conn_ack::{ConnAck, ConnAckType};
+    use crate::v4::pub_ack::PubAck;
+    use crate::v4::Packet;
+    use crate::MessageType;
+
+    fn connect() -> Packet {
+        Packet::Connect(
+            MqttMessageBuilder::connect()
+                .client_id("client01")
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn conn_ack(conn_ack_type: ConnAckType) -> Packet {
+        Packet::ConnAck(ConnAck::new(conn_ack_type).unwrap())
+    }
+
+    fn publish() -> Packet {
+        Packet::Publish(MqttMessageBuilder::publish().topic("/a").build().unwrap())
+    }
+
+    #[test]
+    fn publish_before_conn_ack_should_return_error() {
+        let mut context = ConnectionContext::new();
+        assert_eq!(
+            context.validate_client_packet(&publish()),
+            Err(ProtoError::InvalidPacketForState {
+                packet_type: MessageType::PUBLISH,
+                state: "Connecting",
+            })
+        );
+        context.validate_client_packet(&connect()).unwrap();
+        assert!(context.validate_client_packet(&publish()).is_err());
+        assert!(context.validate_server_packet(&publish()).is_err());
+        context
+            .validate_server_packet(&conn_ack(ConnAckType::Success))
+            .unwrap();
+        assert_eq!(context.state(), ConnectionState::Connected);
+        context.validate_client_packet(&publish()).unwrap();
+        context.validate_server_packet(&publish()).unwrap();
+        let pub_ack = Packet::PubAck(PubAck::new(PacketId::MIN));
+        context.validate_server_packet(&pub_ack).unwrap();
+    }
+
+    #[test]
+    fn connect_after_connected_should_return_error() {
+        let mut context = ConnectionContext::new();
+        context.validate_client_packet(&connect()).unwrap();
+        assert!(context.validate_client_packet(&connect()).is_err());
+        context
+            .validate_server_packet(&conn_ack(ConnAckType::Success))
+            .unwrap();
+        assert_eq!(
+            context.validate_client_packet(&connect()),
+            Err(ProtoError::InvalidPacketForState {
+                packet_type: MessageType::CONNECT,
+                state: "Connected",
+            })
+        );
+        assert!(context
+            .validate_server_packet(&conn_ack(ConnAckType::Success))
+            .is_err());
+
+        let disconnect = Packet::DisConnect(MqttMessageBuilder::disconnect().build().unwrap());
+        context.validate_client_packet(&disconnect).unwrap();
+        assert_eq!(context.state(), ConnectionState::Disconnected);
+        assert!(context.validate_client_packet(&publish()).is_err());
+    }
+
+    #[test]
+    fn refused_conn_ack_should_end_connection() {
+        let mut context = ConnectionContext::new();
+        assert!(context
+            .validate_server_packet(&conn_ack(ConnAckType::Success))
+            .is_err());
+        context.validate_client_packet(&connect()).unwrap();
+        context
+            .validate_server_packet(&conn_ack(ConnAckType::NotAuthorized))
+            .unwrap();
+        assert_eq!(context.state(), ConnectionState::Disconnected);
+        assert!(context.validate_client_packet(&publish()).is_err());
+    }
+}
@@ -0,0 +1,929 @@
+use crate::common::coder;
+use crate::common::mqtt_string::{read_string_field, string_field, StringField};
+use crate::error::ProtoError;
+use crate::v4::Encoder;
+use crate::{QoS, Topic};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::ops::Deref;
+
+/**
+ PUBLISH报文中的topic name，构建时会检查是否合法，不能包含通配符，例如：
+
+```rust
+use walle_mqtt_protocol::common::topic::TopicName;
+assert!(TopicName::new("sport/tennis").is_ok());
+assert!(TopicName::new("devices/+").is_err());
+```
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TopicName(String);
+
+impl TopicName {
+    pub fn new(name: impl Into<String>) -> Result<Self, ProtoError> {
+        let name = name.into();
+        validate_topic_name(&name)?;
+        Ok(Self(name))
+    }
+
+    /**
+     使用各个层级构建topic name，层级中不能包含`/`和空字符，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::topic::TopicName;
+    let topic = TopicName::from_levels(["", "a", "", "b"]).unwrap();
+    assert_eq!(&*topic, "/a//b");
+    assert_eq!(topic.levels().collect::<Vec<_>>(), ["", "a", "", "b"]);
+    ```
+     */
+    pub fn from_levels<I>(levels: I) -> Result<Self, ProtoError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut name = String::new();
+        for (index, level) in levels.into_iter().enumerate() {
+            let level = level.as_ref();
+            if level.contains('/') {
+                return Err(ProtoError::InvalidTopicName("层级中不能包含/"));
+            }
+            if index > 0 {
+                name.push('/');
+            }
+            name.push_str(level);
+        }
+        Self::new(name)
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// 按照`/`分隔的各个层级，空的层级也会返回，`/a//b`返回`["", "a", "", "b"]`
+    pub fn levels(&self) -> impl Iterator<Item = &str> {
+        self.0.split('/')
+    }
+
+    /// 去掉最后一个层级之后的topic，只有一个层级时返回None
+    pub fn parent(&self) -> Option<&str> {
+        self.0.rsplit_once('/').map(|(parent, _)| parent)
+    }
+}
+
+impl Deref for TopicName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Encoder for TopicName {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        coder::write_mqtt_string(buffer, &self.0)
+    }
+}
+
+/**
+ SUBSCRIBE和UNSUBSCRIBE报文中的topic filter，构建时会检查通配符的位置，例如：
+
+```rust
+use walle_mqtt_protocol::common::topic::TopicFilter;
+use walle_mqtt_protocol::QoS;
+let filter = TopicFilter::new("sport/+/player1", QoS::AtLeastOnce).unwrap();
+assert!(filter.matches("sport/tennis/player1"));
+assert!(TopicFilter::new("sport+", QoS::AtLeastOnce).is_err());
+```
+
+UNSUBSCRIBE报文只使用path，qos会被忽略。以`$share/`开头的共享订阅需要使用[`SharedTopicFilter::parse`]解析，
+再通过`TopicFilter::from`转换。
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "TopicFilterRepr", into = "TopicFilterRepr")
+)]
+pub struct TopicFilter {
+    path: StringField,
+    qos: QoS,
+    // 格式正确的共享订阅中ShareName之后的topic filter在path中的起始位置，
+    // 构建时解析一次，匹配时不需要再次解析
+    shared_filter_start: Option<usize>,
+}
+
+impl TopicFilter {
+    pub fn new(path: impl Into<String>, qos: QoS) -> Result<Self, ProtoError> {
+        let path = path.into();
+        if path.starts_with(SHARED_SUBSCRIPTION_PREFIX) {
+            return Err(ProtoError::UseSharedTopicFilter);
+        }
+        validate_topic_filter(&path)?;
+        Ok(Self {
+            path: string_field(path),
+            qos,
+            shared_filter_start: None,
+        })
+    }
+
+    /// 不做检查，解码时使用，由[`crate::v4::subscribe::Subscribe::validate`]按需检查
+    pub(crate) fn new_unchecked(path: StringField, qos: QoS) -> Self {
+        let shared_filter_start = shared_filter(&path).map(|filter| path.len() - filter.len());
+        Self {
+            path,
+            qos,
+            shared_filter_start,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 按照`/`分隔的各个层级，空的层级也会返回
+    pub fn levels(&self) -> impl Iterator<Item = &str> {
+        self.path.split('/')
+    }
+
+    /// 去掉最后一个层级之后的topic filter，只有一个层级时返回None
+    pub fn parent(&self) -> Option<&str> {
+        self.path.rsplit_once('/').map(|(parent, _)| parent)
+    }
+
+    pub fn qos(&self) -> QoS {
+        self.qos
+    }
+
+    /// 读取SUBSCRIBE报文的payload，订阅选项的高6位为保留位，必须为0，QoS不能为3，
+    /// 否则返回带有topic位置的[`ProtoError::InvalidSubscriptionOptions`]
+    pub(crate) fn read_filters(stream: &mut Bytes) -> Result<Vec<TopicFilter>, ProtoError> {
+        let mut resp = Vec::new();
+        while !stream.is_empty() {
+            let (Ok(path), Ok(byte)) = (read_string_field(stream), coder::read_u8(stream)) else {
+                return Err(ProtoError::ReadTopicError);
+            };
+            if path.is_empty() {
+                return Err(ProtoError::EmptyTopicFilter);
+            }
+            let invalid = ProtoError::InvalidSubscriptionOptions {
+                topic_index: resp.len(),
+                byte,
+            };
+            if byte & 0b1111_1100 != 0 {
+                return Err(invalid);
+            }
+            let qos = QoS::try_from(byte).map_err(|_| invalid)?;
+            resp.push(TopicFilter::new_unchecked(path, qos));
+        }
+        Ok(resp)
+    }
+
+    /// 判断topic name是否与当前的topic filter匹配，参见[`matches()`]
+    pub fn matches(&self, topic_name: &str) -> bool {
+        let start = self.shared_filter_start.unwrap_or(0);
+        matches_levels(&self.path[start..], topic_name)
+    }
+
+    /// 是否为格式正确的共享订阅，参见[`SharedTopicFilter::parse`]
+    pub fn is_shared(&self) -> bool {
+        self.shared_filter_start.is_some()
+    }
+
+    /// 共享订阅的ShareName，不是共享订阅时返回None
+    pub fn share_name(&self) -> Option<&str> {
+        let start = self.shared_filter_start?;
+        // path为`$share/{ShareName}/{filter}`，start指向filter
+        Some(&self.path[SHARED_SUBSCRIPTION_PREFIX.len()..start - 1])
+    }
+}
+
+// serde只使用path和qos，反序列化时重新解析共享订阅
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "TopicFilter")]
+struct TopicFilterRepr {
+    path: StringField,
+    qos: QoS,
+}
+
+#[cfg(feature = "serde")]
+impl From<TopicFilterRepr> for TopicFilter {
+    fn from(repr: TopicFilterRepr) -> Self {
+        Self::new_unchecked(repr.path, repr.qos)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<TopicFilter> for TopicFilterRepr {
+    fn from(filter: TopicFilter) -> Self {
+        Self {
+            path: filter.path,
+            qos: filter.qos,
+        }
+    }
+}
+
+impl Deref for TopicFilter {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.path
+    }
+}
+
+/// SUBSCRIBE报文中的格式：path + 1字节的订阅选项
+impl Encoder for TopicFilter {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let len = coder::write_mqtt_string(buffer, &self.path)?;
+        buffer.put_u8(self.qos as u8);
+        Ok(len + 1)
+    }
+}
+
+/// 兼容之前使用[`Topic`]的代码，不做检查
+impl From<Topic> for TopicFilter {
+    fn from(topic: Topic) -> Self {
+        Self::new_unchecked(topic.name(), topic.qos())
+    }
+}
+
+impl From<TopicFilter> for Topic {
+    fn from(filter: TopicFilter) -> Self {
+        Topic::from_field(filter.path, filter.qos)
+    }
+}
+
+/**
+ 检查PUBLISH报文中的topic name是否合法，broker也可以用来检查收到的报文：
+ - 不能为空
+ - 不能包含通配符`+`和`#`
+ - 不能包含空字符U+0000
+
+```rust
+use walle_mqtt_protocol::common::topic::validate_topic_name;
+assert!(validate_topic_name("$SYS/broker/load").is_ok());
+assert!(validate_topic_name("sport/+/scores").is_err());
+```
+ */
+pub fn validate_topic_name(topic: &str) -> Result<(), ProtoError> {
+    if topic.is_empty() {
+        return Err(ProtoError::InvalidTopicName("topic name不能为空"));
+    }
+    if topic.contains(['+', '#']) {
+        return Err(ProtoError::InvalidTopicName("topic name不能包含通配符"));
+    }
+    if topic.contains('\u{0}') {
+        return Err(ProtoError::InvalidTopicName("topic name不能包含空字符"));
+    }
+    Ok(())
+}
+
+/**
+ 按照MQTT 3.1.1的规则检查SUBSCRIBE和UNSUBSCRIBE报文中的topic filter：
+ - 不能为空
+ - `#`必须单独占据一层，并且只能是最后一层
+ - `+`必须单独占据一层
+ - 不能包含空字符U+0000
+
+单独检查时无法得知topic filter所在的位置，`topic_index`固定为0。
+
+```rust
+use walle_mqtt_protocol::common::topic::validate_topic_filter;
+assert!(validate_topic_filter("+/tennis/#").is_ok());
+assert!(validate_topic_filter("sport/ten#nis").is_err());
+```
+ */
+pub fn validate_topic_filter(filter: &str) -> Result<(), ProtoError> {
+    let invalid = |reason| ProtoError::InvalidTopicFilter {
+        topic_index: 0,
+        reason,
+    };
+    if filter.is_empty() {
+        return Err(ProtoError::EmptyTopicFilter);
+    }
+    if filter.contains('\u{0}') {
+        return Err(invalid("topic filter不能包含空字符"));
+    }
+    let mut levels = filter.split('/').peekable();
+    while let Some(level) = levels.next() {
+        if level.contains('#') && (level != "#" || levels.peek().is_some()) {
+            return Err(invalid("#必须单独作为最后一层"));
+        }
+        if level.contains('+') && level != "+" {
+            return Err(invalid("+必须单独占据一层"));
+        }
+    }
+    Ok(())
+}
+
+/// 依次检查多个topic filter，出错时填入出错的topic filter的位置
+pub(crate) fn validate_topic_filters<'a>(
+    filters: impl IntoIterator<Item = &'a str>,
+) -> Result<(), ProtoError> {
+    for (index, filter) in filters.into_iter().enumerate() {
+        validate_topic_filter(filter).map_err(|e| match e {
+            ProtoError::InvalidTopicFilter { reason, .. } => ProtoError::InvalidTopicFilter {
+                topic_index: index,
+                reason,
+            },
+            e => e,
+        })?;
+    }
+    Ok(())
+}
+
+/// 共享订阅的前缀，完整的格式为`$share/{ShareName}/{filter}`
+pub const SHARED_SUBSCRIPTION_PREFIX: &str = "$share/";
+
+/**
+ 共享订阅的topic filter，格式为`$share/{ShareName}/{filter}`，例如：
+
+```rust
+use walle_mqtt_protocol::common::topic::{SharedTopicFilter, TopicFilter};
+use walle_mqtt_protocol::QoS;
+let shared = SharedTopicFilter::parse("$share/g1/sensors/#", QoS::AtLeastOnce).unwrap();
+assert_eq!(shared.share_name, "g1");
+assert_eq!(shared.filter.path(), "sensors/#");
+assert_eq!(shared.to_filter_string(), "$share/g1/sensors/#");
+let filter = TopicFilter::from(shared);
+assert!(filter.is_shared());
+```
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharedTopicFilter {
+    pub share_name: String,
+    // ShareName之后真正用于匹配的topic filter
+    pub filter: TopicFilter,
+}
+
+impl SharedTopicFilter {
+    /// ShareName不能为空，不能包含`+`、`#`和`/`，之后的部分按照[`TopicFilter::new`]检查
+    pub fn parse(s: &str, qos: QoS) -> Result<Self, ProtoError> {
+        let (share_name, filter) = split_shared(s)?;
+        Ok(Self {
+            share_name: share_name.to_string(),
+            filter: TopicFilter::new_unchecked(string_field(filter.to_string()), qos),
+        })
+    }
+
+    /// 还原出完整的topic filter
+    pub fn to_filter_string(&self) -> String {
+        format!(
+            "{}{}/{}",
+            SHARED_SUBSCRIPTION_PREFIX,
+            self.share_name,
+            self.filter.path()
+        )
+    }
+}
+
+/// 转换为SUBSCRIBE报文中使用的完整topic filter
+impl From<SharedTopicFilter> for TopicFilter {
+    fn from(shared: SharedTopicFilter) -> Self {
+        let path = shared.to_filter_string();
+        Self {
+            shared_filter_start: Some(path.len() - shared.filter.path.len()),
+            path: string_field(path),
+            qos: shared.filter.qos,
+        }
+    }
+}
+
+// 按照`$share/{ShareName}/{filter}`拆分出ShareName和filter，不会分配内存
+fn split_shared(s: &str) -> Result<(&str, &str), ProtoError> {
+    let invalid = |reason| ProtoError::InvalidTopicFilter {
+        topic_index: 0,
+        reason,
+    };
+    let rest = s
+        .strip_prefix(SHARED_SUBSCRIPTION_PREFIX)
+        .ok_or(invalid("共享订阅必须以$share/开头"))?;
+    // 以第一个`/`分隔，所以ShareName中不会包含`/`
+    let (share_name, filter) = rest
+        .split_once('/')
+        .ok_or(invalid("ShareName之后必须有topic filter"))?;
+    if share_name.is_empty() {
+        return Err(invalid("ShareName不能为空"));
+    }
+    if share_name.contains(['+', '#']) {
+        return Err(invalid("ShareName不能包含通配符"));
+    }
+    if filter.starts_with(SHARED_SUBSCRIPTION_PREFIX) {
+        return Err(ProtoError::UseSharedTopicFilter);
+    }
+    validate_topic_filter(filter)?;
+    Ok((share_name, filter))
+}
+
+// 格式正确的共享订阅返回ShareName之后的topic filter
+pub(crate) fn shared_filter(filter: &str) -> Option<&str> {
+    split_shared(filter).ok().map(|(_, filter)| filter)
+}
+
+/// 共享订阅，同一个group中的订阅者轮流接收消息
+#[deprecated(note = "请使用SharedTopicFilter")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharedSubscription {
+    // ShareName
+    pub group: String,
+    // 真正用于匹配的topic filter
+    pub filter: String,
+}
+
+#[allow(deprecated)]
+impl From<SharedTopicFilter> for SharedSubscription {
+    fn from(shared: SharedTopicFilter) -> Self {
+        Self {
+            group: shared.share_name,
+            filter: shared.filter.path().to_string(),
+        }
+    }
+}
+
+/**
+ 解析共享订阅，不是共享订阅或者格式错误时返回None：
+ - ShareName不能为空，不能包含`+`、`#`和`/`
+ - ShareName之后的topic filter必须合法，参见[`validate_topic_filter`]
+
+```rust
+# #![allow(deprecated)]
+use walle_mqtt_protocol::common::topic::parse_shared;
+let shared = parse_shared("$share/g1/sensors/#").unwrap();
+assert_eq!(shared.group, "g1");
+assert_eq!(shared.filter, "sensors/#");
+assert!(parse_shared("$share//x").is_none());
+```
+ */
+#[deprecated(note = "请使用SharedTopicFilter::parse")]
+#[allow(deprecated)]
+pub fn parse_shared(filter: &str) -> Option<SharedSubscription> {
+    SharedTopicFilter::parse(filter, QoS::AtMostOnce)
+        .ok()
+        .map(SharedSubscription::from)
+}
+
+/**
+ 判断topic name是否与topic filter匹配：
+ - `+`匹配一层，`#`匹配剩余的所有层，包括父级本身，即`sport/#`也匹配`sport`
+ - 以`+`或者`#`开头的topic filter不匹配以`$`开头的topic name，例如`$SYS/broker/load`
+ - 空的层级也是一层，`a//b`与`a/b`不匹配
+ - 共享订阅只使用ShareName之后的topic filter匹配
+
+不会检查topic filter和topic name是否合法，参见[`validate_topic_filter`]和[`validate_topic_name`]。
+
+```rust
+use walle_mqtt_protocol::common::topic::matches;
+assert!(matches("a/+/c/#", "a/b/c/d"));
+assert!(!matches("#", "$SYS/broker/load"));
+```
+ */
+pub fn matches(filter: &str, topic_name: &str) -> bool {
+    matches_levels(shared_filter(filter).unwrap_or(filter), topic_name)
+}
+
+// 逐层匹配，不处理共享订阅
+fn matches_levels(filter: &str, topic_name: &str) -> bool {
+    if topic_name.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic_name.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/**
+ 先检查topic filter是否合法，再判断topic name是否与topic filter匹配，匹配规则参见[`matches()`]。
+
+```rust
+use walle_mqtt_protocol::common::topic::topic_matches_filter;
+assert_eq!(topic_matches_filter("$SYS/broker/load", "$SYS/#"), Ok(true));
+assert_eq!(topic_matches_filter("$SYS/broker/load", "+/broker/load"), Ok(false));
+assert!(topic_matches_filter("sport/tennis", "sport/#/tennis").is_err());
+```
+ */
+pub fn topic_matches_filter(topic: &str, filter: &str) -> Result<bool, ProtoError> {
+    validate_topic_filter(filter)?;
+    Ok(matches(filter, topic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        matches, topic_matches_filter, validate_topic_filter, validate_topic_filters,
+        validate_topic_name, SharedTopicFilter, TopicFilter, TopicName,
+    };
+    #[allow(deprecated)]
+    use super::{parse_shared, SharedSubscription};
+    use crate::common::mqtt_string::string_field;
+    use crate::error::ProtoError;
+    use crate::v4::Encoder;
+    use crate::QoS;
+    use bytes::BytesMut;
+
+    #[test]
+    fn validate_topic_name_should_be_work() {
+        assert!(validate_topic_name("$SYS/broker/load").is_ok());
+        assert!(validate_topic_name("/").is_ok());
+        assert_eq!(
+            validate_topic_name("").unwrap_err(),
+            ProtoError::InvalidTopicName("topic name不能为空")
+        );
+        for topic in ["sport/+/scores", "sport/#", "#"] {
+            assert_eq!(
+                validate_topic_name(topic).unwrap_err(),
+                ProtoError::InvalidTopicName("topic name不能包含通配符")
+            );
+        }
+        assert_eq!(
+            validate_topic_name("sport/\u{0}").unwrap_err(),
+            ProtoError::InvalidTopicName("topic name不能包含空字符")
+        );
+    }
+
+    #[test]
+    fn validate_topic_filter_should_be_work() {
+        for filter in [
+            "sport/tennis/player1/#",
+            "sport/#",
+            "#",
+            "+",
+            "+/tennis/#",
+            "sport/+/player1",
+            "/+",
+            "$SYS/#",
+        ] {
+            assert!(validate_topic_filter(filter).is_ok(), "{}", filter);
+        }
+        for filter in [
+            "sport/tennis#",
+            "sport/tennis/#/ranking",
+            "sport/ten#nis",
+            "#/",
+        ] {
+            assert_eq!(
+                validate_topic_filter(filter).unwrap_err(),
+                ProtoError::InvalidTopicFilter {
+                    topic_index: 0,
+                    reason: "#必须单独作为最后一层"
+                },
+                "{}",
+                filter
+            );
+        }
+        for filter in ["sport+", "a/+b/c", "++"] {
+            assert_eq!(
+                validate_topic_filter(filter).unwrap_err(),
+                ProtoError::InvalidTopicFilter {
+                    topic_index: 0,
+                    reason: "+必须单独占据一层"
+                },
+                "{}",
+                filter
+            );
+        }
+        assert_eq!(
+            validate_topic_filter("").unwrap_err(),
+            ProtoError::EmptyTopicFilter
+        );
+        assert!(validate_topic_filter("a/\u{0}").is_err());
+    }
+
+    #[test]
+    fn validate_topic_filters_should_report_index() {
+        assert!(matches!(
+            validate_topic_filters(["a/#", "b/+", "sport+"]),
+            Err(ProtoError::InvalidTopicFilter { topic_index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn matches_should_be_work() {
+        let cases = [
+            // 多层通配符
+            ("sport/tennis/player1/#", "sport/tennis/player1", true),
+            (
+                "sport/tennis/player1/#",
+                "sport/tennis/player1/ranking",
+                true,
+            ),
+            (
+                "sport/tennis/player1/#",
+                "sport/tennis/player1/score/wimbledon",
+                true,
+            ),
+            ("sport/#", "sport", true),
+            ("#", "sport/tennis", true),
+            ("#", "/", true),
+            ("sport/tennis/#", "sport/tennis", true),
+            ("sport/tennis/#", "sport/tennisplayer1", false),
+            // 单层通配符
+            ("sport/tennis/+", "sport/tennis/player1", true),
+            ("sport/tennis/+", "sport/tennis/player2", true),
+            ("sport/tennis/+", "sport/tennis/player1/ranking", false),
+            ("sport/+", "sport", false),
+            ("sport/+", "sport/", true),
+            ("+", "sport", true),
+            ("+", "/finance", false),
+            ("+/+", "/finance", true),
+            ("/+", "/finance", true),
+            ("+/tennis/#", "sport/tennis/player1", true),
+            ("+/tennis/#", "sport/football", false),
+            // 空的层级
+            ("a//b", "a//b", true),
+            ("a//b", "a/b", false),
+            ("a/+/b", "a//b", true),
+            // 以$开头的topic
+            ("#", "$SYS/broker/load", false),
+            ("+/monitor/Clients", "$SYS/monitor/Clients", false),
+            ("$SYS/#", "$SYS/broker/load", true),
+            ("$SYS/monitor/+", "$SYS/monitor/Clients", true),
+            // 没有通配符
+            ("sport/tennis", "sport/tennis", true),
+            ("sport/tennis", "sport/Tennis", false),
+            ("sport/tennis", "sport/tennis/player1", false),
+        ];
+        for (filter, topic_name, expected) in cases {
+            assert_eq!(
+                matches(filter, topic_name),
+                expected,
+                "{} {}",
+                filter,
+                topic_name
+            );
+        }
+    }
+
+    #[test]
+    fn topic_matches_filter_should_be_work() {
+        let cases = [
+            // 规范中的示例
+            ("sport/tennis/player1", "sport/tennis/player1/#", true),
+            (
+                "sport/tennis/player1/ranking",
+                "sport/tennis/player1/#",
+                true,
+            ),
+            (
+                "sport/tennis/player1/score/wimbledon",
+                "sport/tennis/player1/#",
+                true,
+            ),
+            ("sport", "sport/#", true),
+            ("sport/tennis/player1", "sport/tennis/+", true),
+            ("sport/tennis/player1/ranking", "sport/tennis/+", false),
+            ("sport", "sport/+", false),
+            ("sport/", "sport/+", true),
+            ("sport", "+", true),
+            ("/finance", "+/+", true),
+            ("/finance", "/+", true),
+            ("/finance", "+", false),
+            ("sport/tennis/player1", "+/tennis/#", true),
+            // 单独的#匹配所有不以$开头的topic
+            ("a", "#", true),
+            ("a/b/c", "#", true),
+            ("/", "#", true),
+            ("$SYS", "#", false),
+            ("$share/g/a", "#", false),
+            // 以$开头的topic不能被第一层的通配符匹配
+            ("$SYS/broker/load", "+/broker/load", false),
+            ("$SYS/broker/load", "+/+/+", false),
+            ("$SYS/broker/load", "$SYS/#", true),
+            ("$SYS", "$SYS/#", true),
+            ("$SYS/broker/clients/total", "$SYS/broker/+/total", true),
+            ("$data/a", "$data/+", true),
+            // 空的层级，+只匹配一层
+            ("a//b", "a/+/b", true),
+            ("a/x/y/b", "a/+/b", false),
+            ("a//b", "a/b", false),
+            ("//", "+/+/+", true),
+            ("//", "+/+", false),
+            ("a/b", "a/b/", false),
+        ];
+        for (topic, filter, expected) in cases {
+            assert_eq!(
+                topic_matches_filter(topic, filter),
+                Ok(expected),
+                "{} {}",
+                topic,
+                filter
+            );
+        }
+        for filter in [
+            "sport/#/tennis",
+            "sport/tennis#",
+            "#/a",
+            "sport+",
+            "+a/b",
+            "a/b+/c",
+        ] {
+            assert!(
+                matches!(
+                    topic_matches_filter("sport/tennis", filter),
+                    Err(ProtoError::InvalidTopicFilter { .. })
+                ),
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[test]
+    fn constructors_should_validate() {
+        let name = TopicName::new("sport/tennis").unwrap();
+        assert_eq!(&*name, "sport/tennis");
+        assert!(name.starts_with("sport"));
+        assert!(matches!(
+            TopicName::new("devices/+"),
+            Err(ProtoError::InvalidTopicName(_))
+        ));
+        assert!(TopicName::new("").is_err());
+
+        let filter = TopicFilter::new("devices/+", QoS::AtLeastOnce).unwrap();
+        assert_eq!(filter.path(), "devices/+");
+        assert_eq!(filter.len(), 9);
+        assert!(matches!(
+            TopicFilter::new("sport/ten#nis", QoS::AtMostOnce),
+            Err(ProtoError::InvalidTopicFilter { .. })
+        ));
+        assert_eq!(
+            TopicFilter::new("", QoS::AtMostOnce).unwrap_err(),
+            ProtoError::EmptyTopicFilter
+        );
+    }
+
+    #[test]
+    fn encode_should_be_work() {
+        let mut buffer = BytesMut::new();
+        assert_eq!(TopicName::new("a/b").unwrap().encode(&mut buffer), Ok(5));
+        let filter = TopicFilter::new("a/#", QoS::ExactlyOnce).unwrap();
+        assert_eq!(filter.encode(&mut buffer), Ok(6));
+        assert_eq!(&buffer[..], b"\x00\x03a/b\x00\x03a/#\x02");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn parse_shared_should_be_work() {
+        assert_eq!(
+            parse_shared("$share/g1/sensors/#"),
+            Some(SharedSubscription {
+                group: "g1".to_string(),
+                filter: "sensors/#".to_string()
+            })
+        );
+        // ShareName之后的第一个`/`之后都是topic filter
+        assert_eq!(parse_shared("$share/g1//x").unwrap().filter, "/x");
+        for filter in [
+            "$share//x",
+            "$share/g+1/x",
+            "$share/g#/x",
+            "$share/g1",
+            "$share/g1/",
+            "$share/g1/a#",
+            "sensors/#",
+            "$SYS/#",
+        ] {
+            assert!(parse_shared(filter).is_none(), "{}", filter);
+        }
+        let shared = SharedTopicFilter::parse("$share/g1/sensors/#", QoS::AtLeastOnce).unwrap();
+        assert_eq!(
+            SharedSubscription::from(shared),
+            parse_shared("$share/g1/sensors/#").unwrap()
+        );
+        let shared = SharedTopicFilter::parse("$share/g1/sensors/+", QoS::AtMostOnce).unwrap();
+        let filter = TopicFilter::from(shared);
+        assert!(filter.is_shared());
+        assert!(filter.matches("sensors/temp"));
+        assert!(!filter.matches("$share/g1/sensors/temp"));
+        assert!(!TopicFilter::new("sensors/+", QoS::AtMostOnce)
+            .unwrap()
+            .is_shared());
+    }
+
+    #[test]
+    fn shared_filter_should_be_parsed_once() {
+        let shared = SharedTopicFilter::parse("$share/g1/a/+/c", QoS::AtLeastOnce).unwrap();
+        let filter = TopicFilter::from(shared);
+        assert_eq!(filter.shared_filter_start, Some("$share/g1/".len()));
+        assert_eq!(filter.share_name(), Some("g1"));
+        assert!(filter.matches("a/b/c"));
+        assert!(!filter.matches("$share/g1/a/b/c"));
+        // 解码得到的topic filter同样在构建时解析共享订阅
+        let decoded = TopicFilter::new_unchecked(
+            string_field("$share/g1/a/+/c".to_string()),
+            QoS::AtLeastOnce,
+        );
+        assert_eq!(decoded, filter);
+        // 格式错误的共享订阅按照普通的topic filter匹配
+        let malformed =
+            TopicFilter::new_unchecked(string_field("$share//a".to_string()), QoS::AtMostOnce);
+        assert!(!malformed.is_shared());
+        assert_eq!(malformed.share_name(), None);
+        assert!(malformed.matches("$share//a"));
+        assert_eq!(
+            TopicFilter::new("a/#", QoS::AtMostOnce)
+                .unwrap()
+                .share_name(),
+            None
+        );
+    }
+
+    #[test]
+    fn shared_topic_filter_should_be_work() {
+        let shared = SharedTopicFilter::parse("$share/consumer-1/a/+/c", QoS::ExactlyOnce).unwrap();
+        assert_eq!(shared.share_name, "consumer-1");
+        assert_eq!(
+            shared.filter,
+            TopicFilter::new("a/+/c", QoS::ExactlyOnce).unwrap()
+        );
+        assert_eq!(shared.to_filter_string(), "$share/consumer-1/a/+/c");
+        assert_eq!(
+            TopicFilter::from(shared),
+            TopicFilter::new_unchecked(
+                string_field("$share/consumer-1/a/+/c".to_string()),
+                QoS::ExactlyOnce
+            )
+        );
+        // ShareName可以是$和其他字符
+        assert!(SharedTopicFilter::parse("$share/$g.1/#", QoS::AtMostOnce).is_ok());
+
+        for filter in [
+            "$share//a",
+            "$share/g+/a",
+            "$share/+/a",
+            "$share/g#/a",
+            "$share/#",
+            "$share/g",
+            "$share/g/",
+            "$share/g/a/#/b",
+            "share/g/a",
+        ] {
+            assert!(
+                matches!(
+                    SharedTopicFilter::parse(filter, QoS::AtMostOnce),
+                    Err(ProtoError::InvalidTopicFilter { .. } | ProtoError::EmptyTopicFilter)
+                ),
+                "{}",
+                filter
+            );
+        }
+        assert_eq!(
+            TopicFilter::new("$share/g/a", QoS::AtMostOnce),
+            Err(ProtoError::UseSharedTopicFilter)
+        );
+        assert_eq!(
+            SharedTopicFilter::parse("$share/g/$share/h/a", QoS::AtMostOnce),
+            Err(ProtoError::UseSharedTopicFilter)
+        );
+    }
+
+    #[test]
+    fn levels_and_from_levels_should_be_work() {
+        let levels = ["", "a", "b", "sport", "$SYS", "中文", " "];
+        // 生成所有长度为1-3的层级组合，from_levels(levels())必须还原出相同的topic
+        for a in levels {
+            for b in levels {
+                for c in levels {
+                    for name in [
+                        a.to_string(),
+                        format!("{}/{}", a, b),
+                        format!("{}/{}/{}", a, b, c),
+                    ] {
+                        let Ok(topic) = TopicName::new(name.as_str()) else {
+                            continue;
+                        };
+                        let rebuilt = TopicName::from_levels(topic.levels()).unwrap();
+                        assert_eq!(rebuilt, topic);
+                        assert_eq!(topic.levels().count(), name.matches('/').count() + 1);
+                    }
+                }
+            }
+        }
+        let topic = TopicName::new("/a//b").unwrap();
+        assert_eq!(topic.levels().collect::<Vec<_>>(), ["", "a", "", "b"]);
+        assert_eq!(topic.parent(), Some("/a/"));
+        assert_eq!(TopicName::new("a").unwrap().parent(), None);
+        let filter = TopicFilter::new("sport/+/#", QoS::AtMostOnce).unwrap();
+        assert_eq!(filter.levels().collect::<Vec<_>>(), ["sport", "+", "#"]);
+        assert_eq!(filter.parent(), Some("sport/+"));
+    }
+
+    #[test]
+    fn from_levels_should_return_error() {
+        assert!(matches!(
+            TopicName::from_levels(["a/b", "c"]),
+            Err(ProtoError::InvalidTopicName(_))
+        ));
+        assert!(TopicName::from_levels(["a", "b\u{0}"]).is_err());
+        assert!(TopicName::from_levels(["a", "+"]).is_err());
+        assert!(TopicName::from_levels(Vec::<String>::new()).is_err());
+        let topic = TopicName::from_levels(vec!["a".to_string(), String::new()]).unwrap();
+        assert_eq!(&*topic, "a/");
+    }
+}
@@ -0,0 +1,183 @@
+use super::topic::{TopicFilter, SHARED_SUBSCRIPTION_PREFIX};
+use std::collections::HashMap;
+
+/**
+ 订阅关系表，broker可以用来查找某个topic的所有订阅者，`T`为订阅者的上下文，例如client_id。
+
+ 不含通配符的topic filter保存在HashMap中，查找时为O(1)；
+ 包含`+`或者`#`的topic filter需要逐个匹配，查找时为O(n)。
+
+```rust
+use walle_mqtt_protocol::common::topic::TopicFilter;
+use walle_mqtt_protocol::common::topic_filter_set::TopicFilterSet;
+use walle_mqtt_protocol::QoS;
+let mut set = TopicFilterSet::new();
+set.insert(TopicFilter::new("sport/tennis", QoS::AtMostOnce).unwrap(), "client_01");
+set.insert(TopicFilter::new("sport/#", QoS::AtMostOnce).unwrap(), "client_02");
+assert_eq!(set.matching_subscribers("sport/tennis"), vec![&"client_01", &"client_02"]);
+assert_eq!(set.matching_subscribers("sport"), vec![&"client_02"]);
+```
+ */
+#[derive(Debug, Clone)]
+pub struct TopicFilterSet<T> {
+    // 不含通配符的topic filter
+    exact: HashMap<String, Vec<T>>,
+    // 包含通配符的topic filter
+    wildcards: Vec<(TopicFilter, Vec<T>)>,
+}
+
+impl<T> Default for TopicFilterSet<T> {
+    fn default() -> Self {
+        Self {
+            exact: HashMap::new(),
+            wildcards: Vec::new(),
+        }
+    }
+}
+
+impl<T> TopicFilterSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一个订阅
+    pub fn insert(&mut self, filter: TopicFilter, subscriber: T) {
+        if !is_wildcard(&filter) {
+            self.exact
+                .entry(filter.path().to_string())
+                .or_default()
+                .push(subscriber);
+            return;
+        }
+        match self
+            .wildcards
+            .iter_mut()
+            .find(|(f, _)| f.path() == filter.path())
+        {
+            Some((_, subscribers)) => subscribers.push(subscriber),
+            None => self.wildcards.push((filter, vec![subscriber])),
+        }
+    }
+
+    /// 删除一个订阅，返回是否找到了对应的订阅
+    pub fn remove(&mut self, filter: &str, subscriber: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        if !is_wildcard(filter) {
+            let Some(subscribers) = self.exact.get_mut(filter) else {
+                return false;
+            };
+            let removed = remove_subscriber(subscribers, subscriber);
+            if subscribers.is_empty() {
+                self.exact.remove(filter);
+            }
+            return removed;
+        }
+        let Some(index) = self.wildcards.iter().position(|(f, _)| f.path() == filter) else {
+            return false;
+        };
+        let removed = remove_subscriber(&mut self.wildcards[index].1, subscriber);
+        if self.wildcards[index].1.is_empty() {
+            self.wildcards.remove(index);
+        }
+        removed
+    }
+
+    /// 查找与topic name匹配的所有订阅者，先查找精确匹配，再逐个匹配通配符
+    pub fn matching_subscribers(&self, topic_name: &str) -> Vec<&T> {
+        let mut resp: Vec<&T> = self
+            .exact
+            .get(topic_name)
+            .map(|subscribers| subscribers.iter().collect())
+            .unwrap_or_default();
+        for (filter, subscribers) in &self.wildcards {
+            if filter.matches(topic_name) {
+                resp.extend(subscribers);
+            }
+        }
+        resp
+    }
+
+    /// 订阅的数量
+    pub fn len(&self) -> usize {
+        self.exact.values().map(Vec::len).sum::<usize>()
+            + self
+                .wildcards
+                .iter()
+                .map(|(_, subscribers)| subscribers.len())
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.is_empty() && self.wildcards.is_empty()
+    }
+}
+
+// 共享订阅需要去掉ShareName之后再匹配，和通配符一样逐个匹配
+fn is_wildcard(filter: &str) -> bool {
+    filter.contains(['+', '#']) || filter.starts_with(SHARED_SUBSCRIPTION_PREFIX)
+}
+
+// 只删除第一个相同的订阅者
+fn remove_subscriber<T: PartialEq>(subscribers: &mut Vec<T>, subscriber: &T) -> bool {
+    match subscribers.iter().position(|s| s == subscriber) {
+        Some(index) => {
+            subscribers.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopicFilterSet;
+    use crate::common::topic::{SharedTopicFilter, TopicFilter};
+    use crate::QoS;
+
+    fn filter(path: &str) -> TopicFilter {
+        TopicFilter::new(path, QoS::AtMostOnce).unwrap()
+    }
+
+    #[test]
+    fn matching_subscribers_should_be_work() {
+        let mut set = TopicFilterSet::new();
+        for i in 0..10_000 {
+            set.insert(filter(&format!("device/{}/status", i)), i);
+        }
+        for i in 0..100 {
+            set.insert(filter(&format!("device/{}/#", i)), 10_000 + i);
+        }
+        set.insert(filter("device/+/status"), 20_000);
+        assert_eq!(set.len(), 10_101);
+        assert_eq!(
+            set.matching_subscribers("device/42/status"),
+            vec![&42, &10_042, &20_000]
+        );
+        assert_eq!(
+            set.matching_subscribers("device/5000/status"),
+            vec![&5000, &20_000]
+        );
+        assert_eq!(set.matching_subscribers("device/42"), vec![&10_042]);
+        assert!(set.matching_subscribers("other/42/status").is_empty());
+        let shared = SharedTopicFilter::parse("$share/g1/other/42/status", QoS::AtMostOnce);
+        set.insert(shared.unwrap().into(), 30_000);
+        assert_eq!(set.matching_subscribers("other/42/status"), vec![&30_000]);
+    }
+
+    #[test]
+    fn remove_should_be_work() {
+        let mut set = TopicFilterSet::new();
+        set.insert(filter("a/b"), "c1");
+        set.insert(filter("a/b"), "c2");
+        set.insert(filter("a/+"), "c1");
+        assert!(set.remove("a/b", &"c1"));
+        assert!(!set.remove("a/b", &"c1"));
+        assert!(set.remove("a/+", &"c1"));
+        assert!(!set.remove("a/#", &"c1"));
+        assert_eq!(set.matching_subscribers("a/b"), vec![&"c2"]);
+        assert!(set.remove("a/b", &"c2"));
+        assert!(set.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::ProtoError;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/**
+ Variable Byte Integer，用于fixed_header中的剩余长度以及MQTT 5.0中的属性长度等。
+
+ 每个字节的低7位存放数据，最高位表示后面是否还有字节，最多4个字节，
+ 所以能表示的最大值为268_435_455：
+
+| 值                        | 字节数 |
+| ------------------------- | ------ |
+| 0 - 127                   | 1      |
+| 128 - 16_383              | 2      |
+| 16_384 - 2_097_151        | 3      |
+| 2_097_152 - 268_435_455   | 4      |
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VarInt(u32);
+
+impl VarInt {
+    /// VarInt能表示的最大值
+    pub const MAX: u32 = 268_435_455;
+
+    /// 编码之后所占的字节数
+    pub fn encoded_len(&self) -> usize {
+        match self.0 {
+            0..=127 => 1,
+            128..=16_383 => 2,
+            16_384..=2_097_151 => 3,
+            _ => 4,
+        }
+    }
+
+    /// 写入1-4个字节，返回写入的字节数
+    pub fn encode(&self, buf: &mut BytesMut) -> usize {
+        let mut x = self.0;
+        let mut count = 0;
+        loop {
+            let mut byte = (x % 128) as u8;
+            x /= 128;
+            if x > 0 {
+                byte |= 0x80;
+            }
+            buf.put_u8(byte);
+            count += 1;
+            if x == 0 {
+                return count;
+            }
+        }
+    }
+
+    /// 从buf中读取一个VarInt，返回读取到的值以及消费的字节数
+    pub fn decode(buf: &mut Bytes) -> Result<(VarInt, usize), ProtoError> {
+        let mut value = 0;
+        for count in 1..=4 {
+            if !buf.has_remaining() {
+                return Err(ProtoError::NotKnow);
+            }
+            let byte = buf.get_u8() as u32;
+            value += (byte & 0x7F) << (7 * (count - 1));
+            if (byte & 0x80) == 0 {
+                return Ok((VarInt(value), count));
+            }
+        }
+        // 第4个字节的最高位依然为1
+        Err(ProtoError::NotKnow)
+    }
+}
+
+impl TryFrom<u32> for VarInt {
+    type Error = ProtoError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value > VarInt::MAX {
+            return Err(ProtoError::OutOfMaxRemainingLength(value as usize));
+        }
+        Ok(VarInt(value))
+    }
+}
+
+impl TryFrom<usize> for VarInt {
+    type Error = ProtoError;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        if value > VarInt::MAX as usize {
+            return Err(ProtoError::OutOfMaxRemainingLength(value));
+        }
+        Ok(VarInt(value as u32))
+    }
+}
+
+impl From<VarInt> for u32 {
+    fn from(value: VarInt) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VarInt;
+    use crate::error::ProtoError;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn boundary_values_should_be_work() {
+        let cases: [(u32, &[u8]); 8] = [
+            (0, &[0x00]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (16_383, &[0xff, 0x7f]),
+            (16_384, &[0x80, 0x80, 0x01]),
+            (2_097_151, &[0xff, 0xff, 0x7f]),
+            (2_097_152, &[0x80, 0x80, 0x80, 0x01]),
+            (268_435_455, &[0xff, 0xff, 0xff, 0x7f]),
+        ];
+        for (value, expected) in cases {
+            let var_int = VarInt::try_from(value).unwrap();
+            let mut buf = BytesMut::new();
+            assert_eq!(var_int.encode(&mut buf), expected.len());
+            assert_eq!(var_int.encoded_len(), expected.len());
+            assert_eq!(buf.as_ref(), expected);
+            let mut bytes = buf.freeze();
+            assert_eq!(
+                VarInt::decode(&mut bytes).unwrap(),
+                (var_int, expected.len())
+            );
+            assert!(bytes.is_empty());
+            assert_eq!(u32::from(var_int), value);
+        }
+    }
+
+    #[test]
+    fn invalid_values_should_return_error() {
+        assert_eq!(
+            VarInt::try_from(268_435_456u32).unwrap_err(),
+            ProtoError::OutOfMaxRemainingLength(268_435_456)
+        );
+        assert_eq!(
+            VarInt::try_from(268_435_456usize).unwrap_err(),
+            ProtoError::OutOfMaxRemainingLength(268_435_456)
+        );
+        // 超过4个字节
+        let mut bytes = Bytes::from_static(&[0xff, 0xff, 0xff, 0xff, 0x01]);
+        assert!(VarInt::decode(&mut bytes).is_err());
+        // 数据不完整
+        let mut bytes = Bytes::from_static(&[0x80]);
+        assert!(VarInt::decode(&mut bytes).is_err());
+    }
+}
@@ -1,5 +1,10 @@
+use crate::MessageType;
+
 /// Error during serialization and deserialization
+///
+/// 叶子错误的`source()`返回`None`，`MessageTypeError`和`TopicAliasError`的`source()`返回其包装的错误。
 #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
 pub enum ProtoError {
     #[error("not know")]
     NotKnow,
@@ -26,6 +31,112 @@ pub enum ProtoError {
     EncodeVariableHeaderError,
     #[error("编码remaining_length错误！")]
     EncodeRemainingLengthError,
+    #[error("错误的剩余长度：{0}")]
+    UnexpectedRemainingLength(usize),
+    #[error("{0:?}报文的标志位错误：{1:#06b}")]
+    InvalidFixedHeaderFlags(MessageType, u8),
+    #[error("报文长度{actual}超出了限制{limit}")]
+    PacketTooLarge { actual: usize, limit: usize },
+    #[error("报文不完整，还需要{needed}个字节")]
+    Incomplete { needed: usize },
+    #[error("非法的message_id：{0}，取值范围为1-65535")]
+    InvalidPacketId(usize),
+    #[error("QoS为1或2的PUBLISH报文必须设置message_id")]
+    QoS12RequiresPacketId,
+    #[error("不是合法的UTF-8字符串")]
+    MalformedString,
+    #[error("QoS为0的PUBLISH报文不能包含message_id")]
+    QoS0WithPacketId,
+    #[error("QoS为0的PUBLISH报文dup必须为0")]
+    DupOnQoS0,
+    #[error("message_id：{0}仍在处理中，不能用于新的消息")]
+    DuplicatePacketId(u16),
+    #[error("message_id：{0}的QoS 2报文顺序错误")]
+    OutOfOrderQos2(u16),
+    #[error("当前状态下不应该收到message_id为{0}的报文")]
+    UnexpectedPacketId(u16),
+    #[error("字符串或二进制数据的长度{0}超过了65535字节")]
+    StringTooLong(usize),
+    #[error("fixed_header中的报文类型与报文不一致")]
+    MessageTypeMismatch,
+    #[error("CONNECT报文的保留标志位必须为0")]
+    ConnectReservedFlagError,
+    #[error("CONNACK报文连接确认标志的保留位必须为0")]
+    ConnAckReservedFlagError,
+    #[error("will_flag为0时，will_qos和will_retain必须为0")]
+    WillFlagsWithoutWill,
+    #[error("没有设置遗嘱信息时不能设置will_retain")]
+    WillRetainWithoutWill,
+    #[error("password_flag为1时，username_flag必须为1")]
+    PasswordWithoutUsername,
+    #[error("遗嘱信息不完整，will_topic和will_message必须同时设置")]
+    IncompleteLastWill,
+    #[error("SUBSCRIBE报文至少需要包含一个topic")]
+    EmptySubscription,
+    #[error("应答报文的message_id：{got}与请求的message_id：{expected}不一致")]
+    PacketIdMismatch { expected: usize, got: usize },
+    #[error("SUBACK中返回码的数量与SUBSCRIBE中topic filter的数量不一致")]
+    AckCountMismatch,
+    #[error("第{0}个topic filter订阅失败")]
+    SubscriptionFailed(usize),
+    #[error("UNSUBSCRIBE报文至少需要包含一个topic")]
+    EmptyUnsubscription,
+    #[error("SUBACK和UNSUBACK报文至少需要包含一个原因码")]
+    EmptyReasonCodes,
+    #[error("第{topic_index}个topic filter的订阅选项非法：{byte:#010b}")]
+    InvalidSubscriptionOptions { topic_index: usize, byte: u8 },
+    #[error("非法的topic name：{0}")]
+    InvalidTopicName(&'static str),
+    #[error("第{topic_index}个topic filter非法：{reason}")]
+    InvalidTopicFilter {
+        topic_index: usize,
+        reason: &'static str,
+    },
+    #[error("topic filter不能为空")]
+    EmptyTopicFilter,
+    #[error("共享订阅不能设置no_local")]
+    NoLocalOnSharedSubscription,
+    #[error("以$share/开头的topic filter需要使用SharedTopicFilter::parse解析")]
+    UseSharedTopicFilter,
+    #[error("遗嘱topic不能为空")]
+    WillTopicRequired,
+    #[error("遗嘱topic不能包含通配符")]
+    WillTopicContainsWildcard,
+    #[error("没有设置遗嘱消息")]
+    WillMessageRequired,
+    #[error("CONNACK报文中未知的返回码：{0:#04x}")]
+    UnknownConnAckType(u8),
+    #[error("SUBACK报文中错误的返回码：{0:#04x}")]
+    InvalidSubAckReturnCode(u8),
+    #[error("未知的原因码：{0:#04x}")]
+    UnknownReasonCode(u8),
+    #[error("非法或重复的属性：{0:#04x}")]
+    InvalidProperty(u8),
+    #[error("AUTH报文缺少认证方法，或者与CONNECT中的认证方法不一致")]
+    InvalidAuthMethod,
+    #[error("MQTT 5.0中没有定义的属性标识符：{0:#04x}")]
+    UnknownProperty(u8),
+    #[error("属性长度{len}超出了报文剩余的{remaining}个字节")]
+    TruncatedProperties { len: usize, remaining: usize },
+    #[error("{packet}报文中不允许出现属性：{id:#04x}")]
+    PropertyNotAllowed { packet: MessageType, id: u8 },
+    #[error("{packet_type}报文不能在{state}状态下发送")]
+    InvalidPacketForState {
+        packet_type: MessageType,
+        state: &'static str,
+    },
+    #[error("不支持的协议级别：{0}")]
+    UnsupportedVersion(u8),
+    #[error("CONNECT报文中的protocol name必须为MQTT")]
+    InvalidProtocolName,
+    #[error("非法的client_id：{0}")]
+    InvalidClientId(&'static str),
+    #[error("无法将字符串解析为{0}")]
+    ParseError(&'static str),
+    #[error("IO错误：{0:?}")]
+    Io(std::io::ErrorKind),
+    #[error("topic alias错误：{0}")]
+    TopicAliasError(#[from] TopicAliasError),
 }
 
 /// 消息构建错误相关
@@ -36,3 +147,67 @@ pub enum BuildError {
     #[error("MQTT报文判断错误：{0}")]
     MessageTypeError(usize),
 }
+
+/// MQTT 5.0 topic alias相关错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TopicAliasError {
+    #[error("未找到topic alias：{0}")]
+    AliasNotFound(u16),
+    #[error("非法的topic alias：{0}")]
+    InvalidAlias(u16),
+    #[error("topic为空时必须携带topic alias")]
+    MissingAlias,
+}
+
+/// CONNECT报文中违反协议约束的字段，参见[`Connect::validate`](crate::v4::connect::Connect::validate)
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    /// 协议只要求服务端接受1-23个字节的client_id，服务端也可以接受更长的client_id
+    #[error("client_id长度为{0}，超过了23个字节")]
+    ClientIdTooLong(usize),
+    #[error("client_id为空时clean_session必须为1")]
+    ClientIdEmpty,
+    /// will_flag为1，但是没有遗嘱信息
+    #[error("设置了will_flag但是没有遗嘱消息")]
+    WillTopicWithoutWillMessage,
+    /// 有遗嘱消息，但是遗嘱topic为空
+    #[error("遗嘱消息没有topic")]
+    WillMessageWithoutWillTopic,
+    /// 只是警告，keep_alive为0表示关闭心跳机制
+    #[error("keep_alive为0，服务端不会检查心跳")]
+    KeepAliveZero,
+    #[error("遗嘱topic不能包含通配符")]
+    TopicContainsWildcard,
+    #[error("protocol name与protocol level不匹配：{0}")]
+    ProtocolNameMismatch(String),
+    #[error("MQTT 3.1.1中设置password时必须设置username")]
+    PasswordWithoutUsername,
+}
+
+impl ValidationError {
+    /// 只是警告，报文依然是合法的
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            ValidationError::ClientIdTooLong(_) | ValidationError::KeepAliveZero
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildError, ProtoError};
+    use std::error::Error;
+
+    #[test]
+    fn source_should_be_work() {
+        assert!(ProtoError::NotKnow.source().is_none());
+        assert!(ProtoError::EncodeVariableHeaderError.source().is_none());
+        let err = ProtoError::from(BuildError::MessageTypeError(15));
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<BuildError>(),
+            Some(&BuildError::MessageTypeError(15))
+        );
+    }
+}
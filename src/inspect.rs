@@ -0,0 +1,266 @@
+//! 调试用的报文查看工具，将网络上收到的原始字节解码为可读的单行文本
+use crate::error::ProtoError;
+use crate::v4::{decode_zero_copy, Packet};
+use bytes::Bytes;
+use std::fmt::Write;
+
+// 超过这个长度的payload会被截断
+const MAX_PAYLOAD_LEN: usize = 64;
+
+/**
+ 解码一个完整的v4报文，返回可读的文本，用于记录从网络上收到的原始字节。
+
+ 与`Packet`的`Display`不同，这里会输出payload的内容：可以按UTF-8解析时输出字符串，否则输出十六进制，
+ 超过64个字节时截断并在末尾加上`...[N bytes]`。CONNECT报文中的password输出为`"[REDACTED]"`。
+
+```rust
+use walle_mqtt_protocol::inspect::inspect;
+let bytes = [0x30, 0x0a, 0x00, 0x04, b'/', b'a', b'/', b'b', b'2', b'5', b'.', b'4'];
+assert_eq!(
+    inspect(&bytes).unwrap(),
+    "PUBLISH topic=\"/a/b\" qos=0 retain=false dup=false payload(4)=\"25.4\""
+);
+```
+ */
+pub fn inspect(bytes: &[u8]) -> Result<String, ProtoError> {
+    let packet = decode_zero_copy(Bytes::copy_from_slice(bytes))?;
+    Ok(inspect_packet(&packet))
+}
+
+/// 与[`inspect`]相同，直接使用已经解码的报文
+pub fn inspect_packet(packet: &Packet) -> String {
+    let mut out = packet.message_type().to_string();
+    // 写入String不会失败
+    let _ = write_details(&mut out, packet);
+    out
+}
+
+fn write_details(out: &mut String, packet: &Packet) -> std::fmt::Result {
+    match packet {
+        Packet::Connect(connect) => {
+            write!(
+                out,
+                " client={:?} keepalive={}",
+                connect.client_id(),
+                connect.keep_alive()
+            )?;
+            if let Some(will) = connect.last_will() {
+                write!(out, " will={:?} qos={}", will.topic_name, will.qos as u8)?;
+            }
+            if let Some(login) = connect.login() {
+                write!(
+                    out,
+                    " username={:?} password=\"[REDACTED]\"",
+                    login.username_str()
+                )?;
+            }
+        }
+        Packet::ConnAck(conn_ack) => {
+            write!(out, " return_code={}", u8::from(conn_ack.return_code()))?;
+        }
+        Packet::Publish(publish) => {
+            if let Some(message_id) = publish.variable_header_ref().message_id() {
+                write!(out, " [id={}]", message_id)?;
+            }
+            write!(
+                out,
+                " topic={:?} qos={} retain={} dup={} payload({})=",
+                publish.topic(),
+                publish.qos(),
+                publish.retain(),
+                publish.dup(),
+                publish.payload_ref().len()
+            )?;
+            write_payload(out, publish.payload_ref())?;
+        }
+        Packet::PubAck(packet) => write!(out, " [id={}]", packet.message_id())?,
+        Packet::PubRec(packet) => write!(out, " [id={}]", packet.message_id())?,
+        Packet::PubRel(packet) => write!(out, " [id={}]", packet.message_id())?,
+        Packet::PubComp(packet) => write!(out, " [id={}]", packet.message_id())?,
+        Packet::UnSubAck(packet) => write!(out, " [id={}]", packet.message_id())?,
+        Packet::Subscribe(subscribe) => {
+            write!(
+                out,
+                " [id={}]",
+                subscribe.variable_header_ref().message_id()
+            )?;
+            for (filter, qos) in subscribe.iter() {
+                write!(out, " {:?} qos={}", filter, qos)?;
+            }
+        }
+        Packet::SubAck(sub_ack) => {
+            write!(
+                out,
+                " [id={}] codes={:?}",
+                sub_ack.message_id(),
+                sub_ack.acks_raw()
+            )?;
+        }
+        Packet::UnSubscribe(unsubscribe) => {
+            write!(out, " [id={}]", unsubscribe.message_id())?;
+            for filter in unsubscribe.topic_filters() {
+                write!(out, " {:?}", filter.path())?;
+            }
+        }
+        Packet::PingReq(_) | Packet::PingResp(_) | Packet::DisConnect(_) => {}
+    }
+    Ok(())
+}
+
+// UTF-8的payload输出为字符串，否则输出为十六进制
+fn write_payload(out: &mut String, payload: &[u8]) -> std::fmt::Result {
+    let shown = &payload[..payload.len().min(MAX_PAYLOAD_LEN)];
+    let text = match std::str::from_utf8(shown) {
+        Ok(text) => Some(text),
+        // 截断的位置可能落在一个多字节字符的中间
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&shown[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    };
+    match text {
+        Some(text) => write!(out, "{:?}", text)?,
+        None => {
+            out.push_str("0x");
+            for byte in shown {
+                write!(out, "{:02x}", byte)?;
+            }
+        }
+    }
+    if payload.len() > MAX_PAYLOAD_LEN {
+        write!(out, "...[{} bytes]", payload.len())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inspect, inspect_packet};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::builder::MqttMessageBuilder;
+    use crate::v4::conn_ack::{ConnAck, ConnAckType};
+    use crate::v4::sub_ack::SubscribeReturnCode;
+    use crate::v4::{ping_req::PingReq, pub_rec::PubRec, Packet};
+    use crate::{QoS, Topic};
+    use bytes::Bytes;
+
+    fn inspect_encoded(packet: Packet) -> String {
+        let bytes = packet.encode_to_bytes().unwrap();
+        inspect(&bytes).unwrap()
+    }
+
+    #[test]
+    fn inspect_publish_should_be_work() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/sensor/temp")
+            .qos(QoS::AtLeastOnce)
+            .packet_id(PacketId::new(42).unwrap())
+            .payload(Bytes::from("25.4°C"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            inspect_encoded(Packet::Publish(publish)),
+            "PUBLISH [id=42] topic=\"/sensor/temp\" qos=1 retain=false dup=false payload(7)=\"25.4°C\""
+        );
+
+        // 超过64个字节时截断，二进制内容输出十六进制
+        let publish = MqttMessageBuilder::publish()
+            .topic("/fw")
+            .retain(true)
+            .payload(Bytes::from(vec![0xff; 100]))
+            .build()
+            .unwrap();
+        let text = inspect_encoded(Packet::Publish(publish));
+        let expected = format!(
+            "PUBLISH topic=\"/fw\" qos=0 retain=true dup=false payload(100)=0x{}...[100 bytes]",
+            "ff".repeat(64)
+        );
+        assert_eq!(text, expected);
+
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload(Bytes::from("温".repeat(30)))
+            .build()
+            .unwrap();
+        let text = inspect_packet(&Packet::Publish(publish));
+        assert!(text.ends_with(&format!("\"{}\"...[90 bytes]", "温".repeat(21))));
+    }
+
+    #[test]
+    fn inspect_connect_should_redact_password() {
+        let connect = MqttMessageBuilder::connect()
+            .client_id("device01")
+            .keep_alive(60)
+            .will("/lwt", Bytes::from_static(b"bye"), QoS::AtLeastOnce, false)
+            .build()
+            .unwrap();
+        assert_eq!(
+            inspect_encoded(Packet::Connect(connect)),
+            "CONNECT client=\"device01\" keepalive=60 will=\"/lwt\" qos=1"
+        );
+
+        let connect = MqttMessageBuilder::connect()
+            .client_id("device01")
+            .username("admin")
+            .password("hunter2")
+            .build()
+            .unwrap();
+        let text = inspect_encoded(Packet::Connect(connect));
+        assert_eq!(
+            text,
+            "CONNECT client=\"device01\" keepalive=60 username=\"admin\" password=\"[REDACTED]\""
+        );
+        assert!(!text.contains("hunter2"));
+    }
+
+    #[test]
+    fn inspect_other_packets_should_be_work() {
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(2).unwrap())
+            .topic(Topic::new("/a".to_string(), QoS::AtMostOnce))
+            .topic(Topic::new("/b/#".to_string(), QoS::ExactlyOnce))
+            .build()
+            .unwrap();
+        let sub_ack = MqttMessageBuilder::sub_ack()
+            .packet_id(PacketId::new(2).unwrap())
+            .acks(vec![
+                SubscribeReturnCode::SuccessQoS0,
+                SubscribeReturnCode::Failure,
+            ])
+            .build()
+            .unwrap();
+        let unsubscribe = MqttMessageBuilder::unsubscriber()
+            .packet_id(PacketId::new(3).unwrap())
+            .topices(vec!["/a".to_string()])
+            .build()
+            .unwrap();
+        let cases = [
+            (
+                Packet::ConnAck(ConnAck::new(ConnAckType::NotAuthorized).unwrap()),
+                "CONNACK return_code=5",
+            ),
+            (Packet::PubRec(PubRec::new(PacketId::MIN)), "PUBREC [id=1]"),
+            (
+                Packet::Subscribe(subscribe),
+                "SUBSCRIBE [id=2] \"/a\" qos=0 \"/b/#\" qos=2",
+            ),
+            (Packet::SubAck(sub_ack), "SUBACK [id=2] codes=[0, 128]"),
+            (
+                Packet::UnSubscribe(unsubscribe),
+                "UNSUBSCRIBE [id=3] \"/a\"",
+            ),
+            (Packet::PingReq(PingReq::new()), "PINGREQ"),
+        ];
+        for (packet, expected) in cases {
+            assert_eq!(inspect_encoded(packet), expected);
+        }
+    }
+
+    #[test]
+    fn inspect_invalid_bytes_should_return_error() {
+        assert!(inspect(&[]).is_err());
+        assert_eq!(
+            inspect(&[0x30, 0x05, 0x00]),
+            Err(ProtoError::Incomplete { needed: 4 })
+        );
+    }
+}
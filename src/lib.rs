@@ -21,23 +21,80 @@
 */
 
 use bytes::{BufMut, Bytes, BytesMut};
-use error::ProtoError;
-use v4::{decoder, Encoder};
+use common::mqtt_string::{string_field, StringField};
+use error::{BuildError, ProtoError};
+use std::fmt;
+use std::str::FromStr;
+use v4::Encoder;
+pub mod common;
 pub mod error;
+pub mod inspect;
 pub mod v4;
+pub mod v5;
 
 /// MQTT报文中protocol name字段
-pub const PROTOCOL_NAME: &'static str = "MQTT";
+pub const PROTOCOL_NAME: &str = "MQTT";
 
-/// mqtt协议不同的版本，这里取最常用的两个版本
-#[derive(Debug, Clone, PartialEq)]
+/// MQTT 3.1中的protocol name字段
+pub const PROTOCOL_NAME_V3: &str = "MQIsdp";
+
+/// mqtt协议不同的版本，V3只用于兼容旧设备，
+/// 除了CONNECT报文中的protocol name和protocol level之外与V4相同
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MqttVersion {
+    V3,
     V4,
     V5,
 }
 
+impl MqttVersion {
+    /// CONNECT报文中的protocol name
+    pub fn protocol_name(&self) -> &'static str {
+        match self {
+            MqttVersion::V3 => PROTOCOL_NAME_V3,
+            MqttVersion::V4 | MqttVersion::V5 => PROTOCOL_NAME,
+        }
+    }
+
+    /// CONNECT报文中的protocol level
+    pub fn protocol_level(&self) -> u8 {
+        match self {
+            MqttVersion::V3 => 3,
+            MqttVersion::V4 => 4,
+            MqttVersion::V5 => 5,
+        }
+    }
+}
+
+/// 输出协议版本号："3.1"、"3.1.1"或者"5.0"
+impl fmt::Display for MqttVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MqttVersion::V3 => f.write_str("3.1"),
+            MqttVersion::V4 => f.write_str("3.1.1"),
+            MqttVersion::V5 => f.write_str("5.0"),
+        }
+    }
+}
+
+/// 可以使用协议版本号或者protocol level解析："3.1"、"3"、"3.1.1"、"4"、"5.0"、"5"
+impl FromStr for MqttVersion {
+    type Err = ProtoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "3.1" | "3" => Ok(MqttVersion::V3),
+            "3.1.1" | "4" => Ok(MqttVersion::V4),
+            "5.0" | "5" => Ok(MqttVersion::V5),
+            _ => Err(ProtoError::ParseError("MqttVersion")),
+        }
+    }
+}
+
 /// 数据类型
-#[derive(Debug, Clone, PartialEq, Default, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MessageType {
     #[default]
     CONNECT,
@@ -54,6 +111,98 @@ pub enum MessageType {
     UNSUBSCRIBE,
     UNSUBACK,
     DISCONNECT,
+    // MQTT 5.0新增的认证报文
+    AUTH,
+}
+
+/// 根据fixed_header首字节的高4位得到报文类型，0为保留值，15为MQTT 5.0中的AUTH报文
+impl TryFrom<u8> for MessageType {
+    type Error = BuildError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(MessageType::CONNECT),
+            2 => Ok(MessageType::CONNACK),
+            3 => Ok(MessageType::PUBLISH),
+            4 => Ok(MessageType::PUBACK),
+            5 => Ok(MessageType::PUBREC),
+            6 => Ok(MessageType::PUBREL),
+            7 => Ok(MessageType::PUBCOMP),
+            8 => Ok(MessageType::SUBSCRIBE),
+            9 => Ok(MessageType::SUBACK),
+            10 => Ok(MessageType::UNSUBSCRIBE),
+            11 => Ok(MessageType::UNSUBACK),
+            12 => Ok(MessageType::PINGREQ),
+            13 => Ok(MessageType::PINGRESP),
+            14 => Ok(MessageType::DISCONNECT),
+            15 => Ok(MessageType::AUTH),
+            n => Err(BuildError::MessageTypeError(n as usize)),
+        }
+    }
+}
+
+impl MessageType {
+    /// 协议中的报文名称，例如"CONNECT"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageType::CONNECT => "CONNECT",
+            MessageType::CONNACK => "CONNACK",
+            MessageType::PUBLISH => "PUBLISH",
+            MessageType::PUBACK => "PUBACK",
+            MessageType::PUBREC => "PUBREC",
+            MessageType::PUBREL => "PUBREL",
+            MessageType::PUBCOMP => "PUBCOMP",
+            MessageType::SUBSCRIBE => "SUBSCRIBE",
+            MessageType::SUBACK => "SUBACK",
+            MessageType::UNSUBSCRIBE => "UNSUBSCRIBE",
+            MessageType::UNSUBACK => "UNSUBACK",
+            MessageType::PINGREQ => "PINGREQ",
+            MessageType::PINGRESP => "PINGRESP",
+            MessageType::DISCONNECT => "DISCONNECT",
+            MessageType::AUTH => "AUTH",
+        }
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 使用报文名称解析，不区分大小写
+impl FromStr for MessageType {
+    type Err = ProtoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        (1..=15u8)
+            .filter_map(|value| MessageType::try_from(value).ok())
+            .find(|message_type| message_type.as_str().eq_ignore_ascii_case(s))
+            .ok_or(ProtoError::ParseError("MessageType"))
+    }
+}
+
+/// 报文类型对应的值，即fixed_header首字节的高4位
+impl From<MessageType> for u8 {
+    fn from(value: MessageType) -> Self {
+        match value {
+            MessageType::CONNECT => 1,
+            MessageType::CONNACK => 2,
+            MessageType::PUBLISH => 3,
+            MessageType::PUBACK => 4,
+            MessageType::PUBREC => 5,
+            MessageType::PUBREL => 6,
+            MessageType::PUBCOMP => 7,
+            MessageType::SUBSCRIBE => 8,
+            MessageType::SUBACK => 9,
+            MessageType::UNSUBSCRIBE => 10,
+            MessageType::UNSUBACK => 11,
+            MessageType::PINGREQ => 12,
+            MessageType::PINGRESP => 13,
+            MessageType::DISCONNECT => 14,
+            MessageType::AUTH => 15,
+        }
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////
@@ -64,8 +213,9 @@ pub enum MessageType {
 /// - ExactlyOnce：使用2表示
 /////////////////////////////////////////////////////////////////////////
 #[repr(u8)]
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[allow(clippy::enum_variant_names)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QoS {
     // 最多
     #[default]
@@ -74,6 +224,62 @@ pub enum QoS {
     ExactlyOnce = 2,
 }
 
+impl QoS {
+    /// 返回两个QoS中较低的一个，例如转发消息时取发布者QoS与订阅者QoS的较小值
+    pub fn min_with(self, other: QoS) -> QoS {
+        self.min(other)
+    }
+
+    /// 当前QoS是否不低于other
+    pub fn is_at_least(self, other: QoS) -> bool {
+        self >= other
+    }
+
+    /// 接收方是否需要回复PUBACK或PUBREC
+    pub fn requires_ack(&self) -> bool {
+        *self != QoS::AtMostOnce
+    }
+
+    /// PUBLISH报文是否需要携带message_id，与[`QoS::requires_ack`]相同
+    pub fn requires_packet_id(&self) -> bool {
+        self.requires_ack()
+    }
+}
+
+/// 默认输出数字"0"、"1"、"2"，使用`{:#}`时输出名称，例如"at-most-once"
+impl fmt::Display for QoS {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str(match self {
+                QoS::AtMostOnce => "at-most-once",
+                QoS::AtLeastOnce => "at-least-once",
+                QoS::ExactlyOnce => "exactly-once",
+            })
+        } else {
+            write!(f, "{}", *self as u8)
+        }
+    }
+}
+
+/// 可以使用数字或者名称解析，例如"1"或者"at-least-once"
+impl FromStr for QoS {
+    type Err = ProtoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" | "at-most-once" => Ok(QoS::AtMostOnce),
+            "1" | "at-least-once" => Ok(QoS::AtLeastOnce),
+            "2" | "exactly-once" => Ok(QoS::ExactlyOnce),
+            _ => Err(ProtoError::ParseError("QoS")),
+        }
+    }
+}
+
+/// 服务端授予的QoS：不超过服务端支持的最大QoS
+pub fn granted_qos(requested: QoS, maximum: QoS) -> QoS {
+    requested.min_with(maximum)
+}
+
 impl From<QoS> for u8 {
     fn from(value: QoS) -> Self {
         match value {
@@ -99,23 +305,43 @@ impl TryFrom<u8> for QoS {
 /////////////////////////////////////////////////////////////////////////
 /// topic,客户端与服务端做信息交互的时候给消息做的标签
 /////////////////////////////////////////////////////////////////////////
-#[derive(Debug, Default, Clone, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, PartialOrd, Ord, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Topic {
-    name: String,
+    name: StringField,
     qos: QoS,
     name_len: usize,
 }
 impl Topic {
     pub fn new(name: String, qos: QoS) -> Self {
+        Self::from_field(string_field(name), qos)
+    }
+    // 解码时直接使用读取到的字段，开启`bytes-strings`时不会复制
+    pub(crate) fn from_field(name: StringField, qos: QoS) -> Self {
         Self {
-            name: name.clone(),
-            qos,
             name_len: name.len(),
+            name,
+            qos,
         }
     }
-    pub fn name(&self) -> String {
+    pub fn name(&self) -> StringField {
         self.name.clone()
     }
+    pub fn name_ref(&self) -> &str {
+        &self.name
+    }
+    /// 按照`/`分隔的各个层级，空的层级也会返回，`/a//b`返回`["", "a", "", "b"]`
+    pub fn levels(&self) -> impl Iterator<Item = &str> {
+        self.name.split('/')
+    }
+    /// 判断topic name是否与当前的topic filter匹配，参见[`common::topic::matches`]
+    pub fn matches(&self, topic_name: &str) -> bool {
+        common::topic::matches(&self.name, topic_name)
+    }
+    /// 是否为格式正确的共享订阅，参见[`common::topic::SharedTopicFilter::parse`]
+    pub fn is_shared(&self) -> bool {
+        common::topic::shared_filter(&self.name).is_some()
+    }
     pub fn qos(&self) -> QoS {
         self.qos
     }
@@ -125,35 +351,19 @@ impl Topic {
 }
 
 impl Topic {
+    /// 读取SUBSCRIBE报文的payload，订阅选项的高6位为保留位，必须为0，QoS不能为3，
+    /// 否则返回带有topic位置的[`ProtoError::InvalidSubscriptionOptions`]
     pub fn read_topics(stream: &mut Bytes) -> Result<Vec<Topic>, ProtoError> {
-        let mut resp: Vec<Topic> = Vec::new();
-        while !stream.is_empty() {
-            if let (Ok(topic_name), Ok(qos)) =
-                (decoder::read_mqtt_string(stream), decoder::read_u8(stream))
-            {
-                let qos = QoS::try_from(qos);
-                match qos {
-                    Ok(qos) => {
-                        let topic = Topic::new(topic_name, qos);
-                        resp.push(topic);
-                    }
-                    Err(e) => return Err(e),
-                }
-            } else {
-                return Err(ProtoError::ReadTopicError);
-            }
-        }
-        Ok(resp)
+        let filters = common::topic::TopicFilter::read_filters(stream)?;
+        Ok(filters.into_iter().map(Topic::from).collect())
     }
 }
 
 impl Encoder for Topic {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
-        let topic_len = self.name_len;
-        buffer.put_u16(topic_len as u16);
-        buffer.put_slice(self.name.as_bytes());
+        let len = common::coder::write_mqtt_string(buffer, &self.name)?;
         buffer.put_u8(self.qos as u8);
-        Ok(topic_len + 3)
+        Ok(len + 1)
     }
 }
 
@@ -161,7 +371,9 @@ impl Encoder for Topic {
 mod tests {
     use bytes::Bytes;
 
+    use crate::error::{BuildError, ProtoError};
     use crate::v4::builder::MqttMessageBuilder;
+    use crate::{granted_qos, MessageType, MqttVersion, QoS, Topic};
 
     #[test]
     fn test() {
@@ -179,4 +391,94 @@ mod tests {
             .build();
         println!("connect = {:?}", connect);
     }
+
+    #[test]
+    fn message_type_to_and_from_u8_should_be_work() {
+        for value in 1..=15u8 {
+            let message_type = MessageType::try_from(value).unwrap();
+            assert_eq!(u8::from(message_type), value);
+        }
+        assert_eq!(u8::from(MessageType::PUBREL), 6);
+        assert_eq!(u8::from(MessageType::PINGREQ), 12);
+        assert_eq!(u8::from(MessageType::AUTH), 15);
+        for value in [0u8, 16] {
+            assert_eq!(
+                MessageType::try_from(value).unwrap_err(),
+                BuildError::MessageTypeError(value as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn topic_matches_should_be_work() {
+        let topic = Topic::new("sport/+/player1".to_string(), QoS::AtMostOnce);
+        assert!(topic.matches("sport/tennis/player1"));
+        assert!(!topic.matches("sport/tennis/player2"));
+    }
+
+    #[test]
+    fn display_and_from_str_should_be_work() {
+        for qos in [QoS::AtMostOnce, QoS::AtLeastOnce, QoS::ExactlyOnce] {
+            assert_eq!(qos.to_string().parse::<QoS>(), Ok(qos));
+            assert_eq!(format!("{:#}", qos).parse::<QoS>(), Ok(qos));
+        }
+        assert_eq!(QoS::ExactlyOnce.to_string(), "2");
+        assert_eq!(format!("{:#}", QoS::AtLeastOnce), "at-least-once");
+        assert_eq!("3".parse::<QoS>(), Err(ProtoError::ParseError("QoS")));
+
+        assert_eq!(MqttVersion::V3.to_string(), "3.1");
+        assert_eq!("3".parse::<MqttVersion>(), Ok(MqttVersion::V3));
+        assert_eq!(MqttVersion::V4.to_string(), "3.1.1");
+        assert_eq!(MqttVersion::V5.to_string(), "5.0");
+        assert_eq!("4".parse::<MqttVersion>(), Ok(MqttVersion::V4));
+        assert_eq!("5".parse::<MqttVersion>(), Ok(MqttVersion::V5));
+        assert!("3.0".parse::<MqttVersion>().is_err());
+
+        for value in 1..=15u8 {
+            let message_type = MessageType::try_from(value).unwrap();
+            assert_eq!(message_type.to_string().parse(), Ok(message_type));
+        }
+        assert_eq!(MessageType::PINGRESP.to_string(), "PINGRESP");
+        assert_eq!("connack".parse(), Ok(MessageType::CONNACK));
+        assert!("PING".parse::<MessageType>().is_err());
+    }
+
+    #[test]
+    fn qos_ordering_should_be_work() {
+        let all = [QoS::AtMostOnce, QoS::AtLeastOnce, QoS::ExactlyOnce];
+        for a in all {
+            for b in all {
+                let expected = if (a as u8) < (b as u8) { a } else { b };
+                assert_eq!(a.min_with(b), expected);
+                assert_eq!(granted_qos(a, b), expected);
+                assert_eq!(a.is_at_least(b), a as u8 >= b as u8);
+            }
+        }
+        assert_eq!(all.iter().max(), Some(&QoS::ExactlyOnce));
+        assert_eq!(
+            QoS::min(QoS::ExactlyOnce, QoS::AtLeastOnce),
+            QoS::AtLeastOnce
+        );
+        assert_eq!(
+            QoS::max(QoS::AtMostOnce, QoS::AtLeastOnce),
+            QoS::AtLeastOnce
+        );
+        assert!(QoS::ExactlyOnce >= QoS::AtLeastOnce);
+
+        let mut sorted = [QoS::ExactlyOnce, QoS::AtMostOnce, QoS::AtLeastOnce];
+        sorted.sort();
+        assert_eq!(sorted, all);
+        for qos in all {
+            assert_eq!(qos.requires_ack(), qos != QoS::AtMostOnce);
+            assert_eq!(qos.requires_packet_id(), qos.requires_ack());
+        }
+
+        // 相同topic按照QoS排序
+        let mut topics = [
+            Topic::new("/a".to_string(), QoS::ExactlyOnce),
+            Topic::new("/a".to_string(), QoS::AtMostOnce),
+        ];
+        topics.sort();
+        assert_eq!(topics[0].qos(), QoS::AtMostOnce);
+    }
 }
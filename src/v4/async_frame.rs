@@ -0,0 +1,262 @@
+//! 基于tokio的异步分帧，需要开启`tokio` feature
+use super::{Encoder, PacketEncoder};
+use crate::common::{config::DecodeConfig, var_int::VarInt};
+use crate::error::ProtoError;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::future::Future;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+impl From<io::Error> for ProtoError {
+    fn from(e: io::Error) -> Self {
+        ProtoError::Io(e.kind())
+    }
+}
+
+/**
+ 从AsyncRead中逐个读取完整的MQTT报文，返回的Bytes包含fixed_header，
+ 可以直接交给[`super::decode_zero_copy`]或者各个报文的Decoder解码。
+
+```rust,no_run
+use walle_mqtt_protocol::v4::{async_frame::MqttFramed, decode_zero_copy};
+# async fn run(stream: impl tokio::io::AsyncRead + Unpin) {
+let mut framed = MqttFramed::new(stream);
+while let Some(frame) = framed.next_packet().await {
+    let packet = decode_zero_copy(frame.unwrap());
+}
+# }
+```
+ */
+pub struct MqttFramed<R> {
+    reader: R,
+    config: DecodeConfig,
+}
+
+impl<R: AsyncRead + Unpin> MqttFramed<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, DecodeConfig::default())
+    }
+
+    /// 使用指定的解码配置，超过max_packet_size的报文在读取剩余内容之前返回错误
+    pub fn with_config(reader: R, config: DecodeConfig) -> Self {
+        Self { reader, config }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// 读取下一个报文，在报文边界处读到EOF时返回None
+    pub async fn next_packet(&mut self) -> Option<Result<Bytes, ProtoError>> {
+        let mut byte1 = [0u8; 1];
+        match self.reader.read_exact(&mut byte1).await {
+            Ok(_) => Some(self.read_frame(byte1[0]).await),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+
+    async fn read_frame(&mut self, byte1: u8) -> Result<Bytes, ProtoError> {
+        // 剩余长度为1-4个字节，最高位为0的字节是最后一个字节
+        let mut header = BytesMut::with_capacity(5);
+        header.put_u8(byte1);
+        loop {
+            let byte = self.reader.read_u8().await?;
+            header.put_u8(byte);
+            if byte & 0x80 == 0 || header.len() == 5 {
+                break;
+            }
+        }
+        let (remaining_length, _) = VarInt::decode(&mut Bytes::copy_from_slice(&header[1..]))?;
+        let remaining_length = u32::from(remaining_length) as usize;
+        let packet_size = header.len() + remaining_length;
+        if let Some(limit) = self.config.max_packet_size {
+            if packet_size > limit {
+                return Err(ProtoError::PacketTooLarge {
+                    actual: packet_size,
+                    limit,
+                });
+            }
+        }
+        let header_len = header.len();
+        header.resize(packet_size, 0);
+        self.reader.read_exact(&mut header[header_len..]).await?;
+        Ok(header.freeze())
+    }
+}
+
+/// 将报文编码之后写入AsyncWrite
+pub struct MqttFramedWriter<W> {
+    writer: W,
+    buffer: BytesMut,
+}
+
+impl<W: AsyncWrite + Unpin> MqttFramedWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// 编码并写入一个完整的报文，编码用的缓冲区在多次写入之间复用
+    pub async fn write_packet(&mut self, packet: &impl Encoder) -> Result<(), ProtoError> {
+        self.buffer.clear();
+        packet.encode(&mut self.buffer)?;
+        self.writer.write_all(&self.buffer).await?;
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<(), ProtoError> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+/**
+ 将单个报文直接编码并写入AsyncWrite，按照`serialized_len()`一次性分配缓冲区，返回写入的字节数。
+
+ 需要连续写入多个报文时，[`MqttFramedWriter`]会复用同一个缓冲区。
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::{async_frame::AsyncEncoder, pub_ack::PubAck};
+# #[tokio::main(flavor = "current_thread")]
+# async fn main() {
+let mut out = Vec::new();
+let len = PubAck::new(PacketId::MIN).async_encode(&mut out).await.unwrap();
+assert_eq!(len, 4);
+assert_eq!(out, [0x40, 0x02, 0x00, 0x01]);
+# }
+```
+ */
+pub trait AsyncEncoder {
+    fn async_encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> impl Future<Output = Result<usize, ProtoError>> + Send;
+}
+
+impl<T: PacketEncoder> AsyncEncoder for T {
+    async fn async_encode<W: AsyncWrite + Unpin + Send>(
+        &self,
+        writer: &mut W,
+    ) -> Result<usize, ProtoError> {
+        let buffer = self.encode_with_hint()?;
+        writer.write_all(&buffer).await?;
+        Ok(buffer.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncEncoder, MqttFramed, MqttFramedWriter};
+    use crate::common::{config::DecodeConfig, packet_id::PacketId};
+    use crate::error::ProtoError;
+    use crate::v4::{builder::MqttMessageBuilder, decode_zero_copy, Packet, PacketEncoder};
+    use crate::QoS;
+    use bytes::Bytes;
+    use tokio::io::{AsyncWriteExt, BufWriter};
+
+    // 异步写入与同步编码的结果必须一致
+    async fn assert_same_as_sync(packet: &impl PacketEncoder) {
+        let mut writer = BufWriter::new(Vec::new());
+        let len = packet.async_encode(&mut writer).await.unwrap();
+        writer.flush().await.unwrap();
+        let expected = packet.encode_with_hint().unwrap();
+        assert_eq!(len, expected.len());
+        assert_eq!(writer.into_inner(), expected.as_ref());
+    }
+
+    #[tokio::test]
+    async fn async_encode_should_match_sync_encode() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/sensor/温度")
+            .qos(QoS::ExactlyOnce)
+            .packet_id(PacketId::new(300).unwrap())
+            .payload(Bytes::from(vec![7u8; 200]))
+            .build()
+            .unwrap();
+        assert_same_as_sync(&publish).await;
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .username("admin")
+            .password("hunter2")
+            .build()
+            .unwrap();
+        assert_same_as_sync(&connect).await;
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::MIN)
+            .add("/a/#", QoS::AtLeastOnce)
+            .build()
+            .unwrap();
+        assert_same_as_sync(&subscribe).await;
+        assert_same_as_sync(&crate::v4::pub_rel::PubRel::new(PacketId::MAX)).await;
+        assert_same_as_sync(&crate::v4::ping_req::PingReq::new()).await;
+    }
+
+    #[tokio::test]
+    async fn send_and_receive_packets_should_be_work() {
+        let (client, server) = tokio::io::duplex(1024);
+        let writer = tokio::spawn(async move {
+            let mut writer = MqttFramedWriter::new(client);
+            for i in 1..=1000u16 {
+                // payload长度不同，剩余长度有1个字节也有2个字节的
+                let publish = MqttMessageBuilder::publish()
+                    .topic("/sensor")
+                    .qos(QoS::AtLeastOnce)
+                    .packet_id(PacketId::new(i).unwrap())
+                    .payload(Bytes::from(vec![0u8; i as usize % 300]))
+                    .build()
+                    .unwrap();
+                writer.write_packet(&publish).await.unwrap();
+            }
+        });
+        let mut framed = MqttFramed::new(server);
+        let mut count = 0;
+        while let Some(frame) = framed.next_packet().await {
+            count += 1;
+            match decode_zero_copy(frame.unwrap()).unwrap() {
+                Packet::Publish(publish) => {
+                    assert_eq!(publish.variable_header_ref().message_id(), Some(count));
+                    assert_eq!(publish.payload_ref().len(), count % 300);
+                }
+                packet => panic!("错误的报文：{:?}", packet),
+            }
+        }
+        writer.await.unwrap();
+        assert_eq!(count, 1000);
+    }
+
+    #[tokio::test]
+    async fn truncated_frame_should_return_error() {
+        let (mut client, server) = tokio::io::duplex(64);
+        // 剩余长度为2，只写入了1个字节
+        client.write_all(&[0x40, 0x02, 0x00]).await.unwrap();
+        drop(client);
+        let mut framed = MqttFramed::new(server);
+        assert_eq!(
+            framed.next_packet().await.unwrap().unwrap_err(),
+            ProtoError::Io(std::io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[tokio::test]
+    async fn too_large_packet_should_return_error() {
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(&[0x30, 0xff, 0x7f]).await.unwrap();
+        let config = DecodeConfig::new().max_packet_size(Some(1024));
+        let mut framed = MqttFramed::with_config(server, config);
+        assert_eq!(
+            framed.next_packet().await.unwrap().unwrap_err(),
+            ProtoError::PacketTooLarge {
+                actual: 3 + 16_383,
+                limit: 1024
+            }
+        );
+    }
+}
@@ -4,22 +4,29 @@ use super::{
     dis_connect::DisConnect,
     fixed_header::FixedHeaderBuilder,
     publish::{Publish, PublishVariableHeader},
-    sub_ack::SubAck,
+    sub_ack::{SubAck, SubscribeReturnCode},
     subscribe::Subscribe,
     un_subscribe::UnSubscribe,
     GeneralVariableHeader,
 };
+use crate::common::client_id::{validate_client_id, ClientIdPolicy};
+use crate::common::mqtt_string::string_field;
+use crate::common::packet_id::PacketId;
+use crate::common::topic::{validate_topic_filters, validate_topic_name, TopicFilter, TopicName};
 use crate::v4::pub_ack::PubAck;
 use crate::v4::pub_comp::PubComp;
 use crate::v4::pub_rec::PubRec;
 use crate::v4::pub_rel::PubRel;
 use crate::v4::un_suback::UnSubAck;
-use crate::{error::ProtoError, MqttVersion, QoS, Topic, PROTOCOL_NAME};
+use crate::{error::ProtoError, MqttVersion, QoS, Topic};
 use bytes::Bytes;
+use std::marker::PhantomData;
+use tracing::warn;
 
 /**
 Mqtt报文构建器，用于快速构建具体的消息构建器：
  - ConnectBuilder：连接报文构建器
+ - LastWillBuilder：遗嘱信息构建器
  - ConnAckBuilder: 连接确认报文构建器
  - DisconnectBuilder：断开链接报文构建器
  - PublishBuilder: 发布报文构建器
@@ -38,6 +45,9 @@ impl MqttMessageBuilder {
     pub fn connect() -> ConnectBuilder {
         ConnectBuilder::new()
     }
+    pub fn last_will() -> LastWillBuilder {
+        LastWillBuilder::new()
+    }
     pub fn disconnect() -> DisconnectBuilder {
         DisconnectBuilder::new()
     }
@@ -89,10 +99,7 @@ let connect: Result<Connect, ProtoError> = MqttMessageBuilder::connect()
             .username("rump")
              .password("mq")
             .protocol_level(MqttVersion::V4)
-             .retain(false)
-             .will_qos(QoS::AtLeastOnce)
-             .will_topic("/a")
-             .will_message(Bytes::from_static(b"offline"))
+             .will("/a", Bytes::from_static(b"offline"), QoS::AtLeastOnce, false)
              .build();
 ```
  */
@@ -102,11 +109,18 @@ pub struct ConnectBuilder {
     client_id: String,
     clean_session: bool,
     username: Option<String>,
-    password: Option<String>,
+    password: Option<Bytes>,
     will_qos: QoS,
     will_topic: Option<String>,
     retain: bool,
     will_message: Option<Bytes>,
+    client_id_policy: Option<ClientIdPolicy>,
+}
+
+impl Default for ConnectBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConnectBuilder {
@@ -122,6 +136,7 @@ impl ConnectBuilder {
             will_topic: None,
             retain: false,
             will_message: None,
+            client_id_policy: None,
         }
     }
     /// 设置protocol_level
@@ -151,7 +166,12 @@ impl ConnectBuilder {
     }
     /// 设置password
     pub fn password(mut self, password: &str) -> Self {
-        self.password = Some(password.to_string());
+        self.password = Some(Bytes::from(password.to_string()));
+        self
+    }
+    /// 以二进制的方式设置password
+    pub fn password_bytes(mut self, password: Bytes) -> Self {
+        self.password = Some(password);
         self
     }
     /// 设置will_qos
@@ -174,20 +194,71 @@ impl ConnectBuilder {
         self.will_message = Some(will_message);
         self
     }
+
+    /// 一次性设置遗嘱信息(推荐使用)，message可以为空(Bytes::new())
+    pub fn will(mut self, topic: &str, message: Bytes, qos: QoS, retain: bool) -> Self {
+        self.will_topic = Some(topic.to_string());
+        self.will_message = Some(message);
+        self.will_qos = qos;
+        self.retain = retain;
+        self
+    }
+    /// 使用[`LastWillBuilder`]构建好的遗嘱信息，与[`ConnectBuilder::will`]相同
+    pub fn last_will(self, last_will: LastWill) -> Self {
+        self.will(
+            &last_will.topic_name,
+            last_will.message,
+            last_will.qos,
+            last_will.retain,
+        )
+    }
+    /// 构建时按照策略检查client_id，不设置时只检查空client_id与clean_session的关系
+    pub fn validate_client_id(mut self, policy: ClientIdPolicy) -> Self {
+        self.client_id_policy = Some(policy);
+        self
+    }
+
     /// 构建CONNECT报文
     pub fn build(self) -> Result<Connect, ProtoError> {
+        // 空的client_id只能和clean_session=1一起使用，否则服务端会拒绝连接
+        if self.client_id.is_empty() && !self.clean_session {
+            return Err(ProtoError::InvalidClientId(
+                "client_id为空时clean_session必须为1",
+            ));
+        }
+        if let Some(policy) = &self.client_id_policy {
+            validate_client_id(&self.client_id, policy)?;
+        }
         // 初始化值
         let client_id = self.client_id;
-        let username_flag = false;
-        let password_flag = false;
-        let mut will_flag = false;
-        let will_retain = false;
-        let will_qos = QoS::AtMostOnce;
-        let clean_session = false;
-        let will_topic = self.will_topic.clone();
-        if self.will_topic.is_some() && self.will_message.is_some() {
-            will_flag = true;
+        // 构建 Login，username和password必须同时设置，为空的字段不会被写入报文，
+        // connect_flags与Login::flags保持一致，解码之后得到的报文与构建的报文相同
+        let login = match (self.username, self.password) {
+            (Some(username), Some(password)) => Some(Login::new(username, password)),
+            _ => None,
         }
+        .filter(|login| login.flags() != 0);
+        let login_flags = login.as_ref().map_or(0, Login::flags);
+        let username_flag = login_flags & 0x80 != 0;
+        let password_flag = login_flags & 0x40 != 0;
+        let mut will_flag = false;
+        let mut will_retain = false;
+        let mut will_qos = QoS::AtMostOnce;
+        let clean_session = self.clean_session;
+        // 构建LastWill，will_topic和will_message必须同时设置
+        let last_will = match (self.will_topic, self.will_message) {
+            (Some(topic), Some(message)) => {
+                validate_topic_name(&topic)?;
+                will_flag = true;
+                will_retain = self.retain;
+                will_qos = self.will_qos;
+                Some(LastWill::new(topic, message, self.will_qos, self.retain))
+            }
+            // 没有遗嘱信息时不能设置will_retain
+            (None, None) if self.retain => return Err(ProtoError::WillRetainWithoutWill),
+            (None, None) => None,
+            _ => return Err(ProtoError::IncompleteLastWill),
+        };
         // 构建ConnFlags
         let conn_flags = ConnectFlags::new(
             username_flag,
@@ -199,41 +270,23 @@ impl ConnectBuilder {
         );
         // 构建可变报头
         let variable_header = ConnectVariableHeader::new(
-            PROTOCOL_NAME.to_string(),
+            self.protocol_level.protocol_name().to_string(),
             self.protocol_level,
             conn_flags,
             self.keep_alive,
         );
-        let mut login = None;
-        // 构建 Login
-        if self.username.is_some() && self.password.is_some() {
-            login = Some(Login::new(self.username.unwrap(), self.password.unwrap()));
-        }
         // 计算login_len
         let login_len = match &login {
             Some(login) => login.len(),
             None => 0,
         };
-        // 构建LastWill
-        let last_will: Option<LastWill> = match will_topic {
-            Some(topic) => Some(LastWill::new(
-                topic,
-                self.will_message.unwrap(),
-                self.will_qos,
-                self.retain,
-            )),
-            None => None,
-        };
         // 计算last_will_len
         let last_will_len = match &last_will {
             Some(t) => t.len(),
             None => 0,
         };
         let remaining_length = {
-            let mut len = 2 + PROTOCOL_NAME.len() // protocol name
-                + 1  // protocol version
-                + 1  // connect flags
-                + 2; // keep alive
+            let mut len = variable_header.len();
             len += 2 + client_id.len();
             // last will len
             len += last_will_len;
@@ -244,7 +297,6 @@ impl ConnectBuilder {
         let fixed_header = FixedHeaderBuilder::new()
             .connect()
             .dup(Some(false))
-            .qos(Some(QoS::AtMostOnce))
             .retain(Some(false))
             .remaining_length(remaining_length)
             .build();
@@ -252,7 +304,7 @@ impl ConnectBuilder {
             Ok(fixed_header) => Ok(Connect {
                 fixed_header,
                 variable_header,
-                client_id,
+                client_id: string_field(client_id),
                 last_will,
                 login,
             }),
@@ -261,9 +313,96 @@ impl ConnectBuilder {
     }
 }
 
+///////////////////////////////////
+/// LastWill Builder
+///////////////////////////////////
+/**
+ 遗嘱信息构建器，构建好的[`LastWill`]可以通过[`ConnectBuilder::last_will`]在多个CONNECT报文中复用，例如：
+
+```rust
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::QoS;
+let last_will = MqttMessageBuilder::last_will()
+    .topic("/devices/offline")
+    .message_str("device01")
+    .qos(QoS::AtLeastOnce)
+    .build()
+    .unwrap();
+let connect = MqttMessageBuilder::connect()
+    .client_id("device01")
+    .last_will(last_will)
+    .build()
+    .unwrap();
+assert_eq!(connect.will_qos(), Some(QoS::AtLeastOnce));
+```
+ */
+#[derive(Debug, Clone, Default)]
+pub struct LastWillBuilder {
+    topic: String,
+    message: Option<Bytes>,
+    qos: QoS,
+    retain: bool,
+}
+
+impl LastWillBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn topic(mut self, topic: &str) -> Self {
+        self.topic = topic.to_string();
+        self
+    }
+
+    /// 协议允许遗嘱消息为空，需要时可以设置为Bytes::new()
+    pub fn message(mut self, message: Bytes) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    pub fn message_str(self, message: &str) -> Self {
+        self.message(Bytes::copy_from_slice(message.as_bytes()))
+    }
+
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// topic不能为空、不能包含通配符，必须设置遗嘱消息
+    pub fn build(self) -> Result<LastWill, ProtoError> {
+        if self.topic.is_empty() {
+            return Err(ProtoError::WillTopicRequired);
+        }
+        if self.topic.contains(['+', '#']) {
+            return Err(ProtoError::WillTopicContainsWildcard);
+        }
+        validate_topic_name(&self.topic)?;
+        let message = self.message.ok_or(ProtoError::WillMessageRequired)?;
+        Ok(LastWill::new(self.topic, message, self.qos, self.retain))
+    }
+}
+
 ///////////////////////////////////
 /// ConnAck Builder
 ///////////////////////////////////
+/**
+ 连接确认报文构建器，例如：
+
+```rust
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v4::conn_ack::ConnAckType;
+let conn_ack = MqttMessageBuilder::conn_ack()
+             .conn_ack_type(ConnAckType::Success)
+             .build();
+assert!(conn_ack.is_ok());
+```
+ */
 pub struct ConnAckBuilder {
     conn_ack_type: ConnAckType,
 }
@@ -280,26 +419,54 @@ impl ConnAckBuilder {
         self
     }
 
-    pub fn build(&self) -> ConnAck {
-        ConnAck::new(self.conn_ack_type.clone()).unwrap()
+    pub fn build(&self) -> Result<ConnAck, ProtoError> {
+        ConnAck::new(self.conn_ack_type.clone())
     }
 }
 
+/// [`PublishBuilder`]的状态：还没有设置topic，不能调用build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTopic;
+
+/// [`PublishBuilder`]的状态：已经设置了topic
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HasTopic;
+
 ///////////////////////////////////
 /// Publish Builder
 ///////////////////////////////////
-pub struct PublishBuilder {
+/**
+ PUBLISH报文构建器，只有调用topic()或者topic_name()之后才能调用build()，
+ 没有设置topic时无法通过编译：
+
+```compile_fail
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+let publish = MqttMessageBuilder::publish().payload_str("x").build();
+```
+
+```rust
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+let publish = MqttMessageBuilder::publish().payload_str("x").topic("/a").build();
+assert!(publish.is_ok());
+```
+ */
+pub struct PublishBuilder<S = NoTopic> {
     // topic
-    topic: String,
+    pub(crate) topic: String,
     // publish报文的message_id,当QoS为0的时候不设置QoS
-    message_id: Option<usize>,
-    qos: QoS,
-    retain: bool,
-    dup: bool,
-    payload: Bytes,
+    pub(crate) message_id: Option<usize>,
+    pub(crate) qos: QoS,
+    pub(crate) retain: bool,
+    pub(crate) dup: bool,
+    pub(crate) payload: Bytes,
+    // payload是否为UTF-8字符串，为true时build会检查payload
+    pub(crate) utf8_payload: bool,
+    // 内容类型，v4报文中没有这个字段，转换为v5构建器时使用
+    pub(crate) content_type: Option<String>,
+    state: PhantomData<S>,
 }
 
-impl PublishBuilder {
+impl PublishBuilder<NoTopic> {
     fn new() -> Self {
         Self {
             topic: String::new(),
@@ -308,14 +475,42 @@ impl PublishBuilder {
             retain: false,
             dup: false,
             payload: Bytes::new(),
+            utf8_payload: false,
+            content_type: None,
+            state: PhantomData,
         }
     }
+}
+
+impl<S> PublishBuilder<S> {
     /// 设置topic
-    pub fn topic(mut self, topic: &str) -> Self {
-        self.topic = topic.to_string();
-        self
+    pub fn topic(self, topic: &str) -> PublishBuilder<HasTopic> {
+        self.with_topic(topic.to_string())
+    }
+    /// 设置已经检查过的topic name
+    pub fn topic_name(self, topic: TopicName) -> PublishBuilder<HasTopic> {
+        self.with_topic(topic.into_string())
+    }
+    fn with_topic(self, topic: String) -> PublishBuilder<HasTopic> {
+        PublishBuilder {
+            topic,
+            message_id: self.message_id,
+            qos: self.qos,
+            retain: self.retain,
+            dup: self.dup,
+            payload: self.payload,
+            utf8_payload: self.utf8_payload,
+            content_type: self.content_type,
+            state: PhantomData,
+        }
     }
     /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = Some(packet_id.into());
+        self
+    }
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = Some(message_id);
         self
@@ -350,8 +545,50 @@ impl PublishBuilder {
         self.payload = payload;
         self
     }
+    /// 设置UTF-8字符串payload，之后即使用payload()替换了payload，build时也会检查UTF-8。
+    /// v4报文中没有payload format indicator，报文本身不会变化
+    pub fn payload_utf8(mut self, payload: &str) -> Self {
+        self.payload = Bytes::from(payload.to_string());
+        self.utf8_payload = true;
+        self
+    }
+    /// 设置内容类型，例如"application/json"，只在转换为v5构建器时生效
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+}
+
+impl PublishBuilder<HasTopic> {
     /// 构建PUBLISH报文
+    ///
+    /// QoS为1或2时必须设置message_id，QoS为0时设置的message_id会被忽略，
+    /// QoS为0时不能设置dup
     pub fn build(self) -> Result<Publish, ProtoError> {
+        //0、检查topic、payload、dup和message_id
+        validate_topic_name(&self.topic)?;
+        if self.dup && self.qos == QoS::AtMostOnce {
+            return Err(ProtoError::DupOnQoS0);
+        }
+        if self.utf8_payload && std::str::from_utf8(&self.payload).is_err() {
+            return Err(ProtoError::MalformedString);
+        }
+        match (self.qos, self.message_id) {
+            (QoS::AtMostOnce, Some(message_id)) => {
+                warn!(
+                    "QoS为0的PUBLISH报文不需要message_id，忽略message_id：{}",
+                    message_id
+                );
+            }
+            (QoS::AtLeastOnce | QoS::ExactlyOnce, None) => {
+                return Err(ProtoError::QoS12RequiresPacketId);
+            }
+            _ => {}
+        }
+        let message_id = match self.qos {
+            QoS::AtMostOnce => None,
+            _ => self.message_id.map(PacketId::try_from).transpose()?,
+        };
         //1、构建fixed_header
         let fixed_header = FixedHeaderBuilder::new()
             .publish()
@@ -361,13 +598,7 @@ impl PublishBuilder {
             .build();
         //2、构建variable_header
         // let variable_header = PublishVariableHeader::new(self.topic, self.message_id);
-        let variable_header = {
-            if self.qos == QoS::AtMostOnce {
-                PublishVariableHeader::new(self.topic, None, Some(QoS::AtMostOnce))
-            } else {
-                PublishVariableHeader::new(self.topic, self.message_id, Some(self.qos))
-            }
-        };
+        let variable_header = PublishVariableHeader::new(self.topic, message_id, Some(self.qos))?;
 
         //3、计算剩余长度
         let remaining_length = variable_header.variable_header_len() + self.payload.len();
@@ -389,18 +620,32 @@ pub struct PubAckBuilder {
     message_id: usize,
 }
 
+impl Default for PubAckBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PubAckBuilder {
     pub fn new() -> Self {
         Self { message_id: 0 }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
     pub fn build(&self) -> Result<PubAck, ProtoError> {
-        Ok(PubAck::new(self.message_id))
+        Ok(PubAck::new(PacketId::try_from(self.message_id)?))
     }
 }
 
@@ -409,17 +654,19 @@ impl PubAckBuilder {
 ///////////////////////////////////
 pub struct DisconnectBuilder {}
 
+impl Default for DisconnectBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DisconnectBuilder {
     pub fn new() -> Self {
         Self {}
     }
 
     pub fn build(&self) -> Result<DisConnect, ProtoError> {
-        let resp = FixedHeaderBuilder::new().dis_connect().build();
-        match resp {
-            Ok(fixed_header) => Ok(DisConnect::new(fixed_header)),
-            Err(e) => Err(e),
-        }
+        Ok(DisConnect::default())
     }
 }
 
@@ -430,18 +677,32 @@ pub struct PubRelBuilder {
     message_id: usize,
 }
 
+impl Default for PubRelBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PubRelBuilder {
     pub fn new() -> Self {
         Self { message_id: 0 }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
     pub fn build(&self) -> Result<PubRel, ProtoError> {
-        Ok(PubRel::new(self.message_id))
+        Ok(PubRel::new(PacketId::try_from(self.message_id)?))
     }
 }
 
@@ -452,18 +713,32 @@ pub struct PubRecBuilder {
     message_id: usize,
 }
 
+impl Default for PubRecBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PubRecBuilder {
     pub fn new() -> Self {
         Self { message_id: 0 }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
     pub fn build(&self) -> Result<PubRec, ProtoError> {
-        Ok(PubRec::new(self.message_id))
+        Ok(PubRec::new(PacketId::try_from(self.message_id)?))
     }
 }
 
@@ -474,18 +749,86 @@ pub struct PubCompBuilder {
     message_id: usize,
 }
 
+impl Default for PubCompBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PubCompBuilder {
     pub fn new() -> Self {
         Self { message_id: 0 }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
     pub fn build(&self) -> Result<PubComp, ProtoError> {
-        Ok(PubComp::new(self.message_id))
+        Ok(PubComp::new(PacketId::try_from(self.message_id)?))
+    }
+}
+
+/**
+ 一次性构建QoS 2发布流程中的4个报文：PUBLISH、PUBREC、PUBREL、PUBCOMP，
+ 它们使用同一个packet_id，packet_id为0时返回[`ProtoError::InvalidPacketId`]，例如：
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v4::builder::{is_complete_qos2_flow, qos2_flow};
+let (publish, pub_rec, pub_rel, pub_comp) =
+    qos2_flow("/a", Bytes::from_static(b"hello"), 10).unwrap();
+assert!(is_complete_qos2_flow(&publish, &pub_rec, &pub_rel, &pub_comp));
+assert!(qos2_flow("/a", Bytes::new(), 0).is_err());
+```
+ */
+pub fn qos2_flow(
+    topic: &str,
+    payload: Bytes,
+    packet_id: u16,
+) -> Result<(Publish, PubRec, PubRel, PubComp), ProtoError> {
+    let packet_id = PacketId::try_from(packet_id)?;
+    let publish = MqttMessageBuilder::publish()
+        .topic(topic)
+        .qos(QoS::ExactlyOnce)
+        .dup(false)
+        .packet_id(packet_id)
+        .payload(payload)
+        .build()?;
+    let pub_rec = MqttMessageBuilder::pub_rec().packet_id(packet_id).build()?;
+    let pub_rel = MqttMessageBuilder::pub_rel().packet_id(packet_id).build()?;
+    let pub_comp = MqttMessageBuilder::pub_comp()
+        .packet_id(packet_id)
+        .build()?;
+    Ok((publish, pub_rec, pub_rel, pub_comp))
+}
+
+/// 检查QoS 2发布流程中的4个报文是否使用同一个packet_id，PUBLISH的QoS必须为2
+pub fn is_complete_qos2_flow(
+    publish: &Publish,
+    pub_rec: &PubRec,
+    pub_rel: &PubRel,
+    pub_comp: &PubComp,
+) -> bool {
+    if publish.qos() != QoS::ExactlyOnce {
+        return false;
+    }
+    match publish.variable_header_ref().message_id() {
+        Some(message_id) => {
+            pub_rec.message_id() == message_id
+                && pub_rel.message_id() == message_id
+                && pub_comp.message_id() == message_id
+        }
+        None => false,
     }
 }
 
@@ -497,6 +840,12 @@ pub struct SubscribeBuilder {
     message_id: usize,
 }
 
+impl Default for SubscribeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SubscribeBuilder {
     pub fn new() -> Self {
         Self {
@@ -505,29 +854,54 @@ impl SubscribeBuilder {
         }
     }
 
-    pub fn topics(mut self, topices: Vec<Topic>) -> Self {
-        self.topics = topices;
+    /// 设置所有的topic filter，可以是[`TopicFilter`]或者[`Topic`]
+    pub fn topics<T: Into<Topic>>(mut self, topices: Vec<T>) -> Self {
+        self.topics = topices.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
         self
     }
 
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
-    pub fn topic(mut self, topic: Topic) -> Self {
-        self.topics.push(topic);
+    /// 添加一个topic filter，可以是[`TopicFilter`]或者[`Topic`]
+    pub fn topic(mut self, topic: impl Into<Topic>) -> Self {
+        self.topics.push(topic.into());
         self
     }
 
+    /// 以topic filter和QoS添加一个订阅，topic filter在build时检查
+    pub fn add(self, topic: &str, qos: QoS) -> Self {
+        self.topic(Topic::new(topic.to_string(), qos))
+    }
+
     pub fn build(self) -> Result<Subscribe, ProtoError> {
-        if let (Ok(fixed_header), variable_header) = (
-            FixedHeaderBuilder::new().subscribe().build(),
-            GeneralVariableHeader::new(self.message_id),
-        ) {
-            return Ok(Subscribe::new(fixed_header, variable_header, self.topics));
+        if self.topics.is_empty() {
+            return Err(ProtoError::EmptySubscription);
         }
-        Err(ProtoError::NotKnow)
+        validate_topic_filters(self.topics.iter().map(Topic::name_ref))?;
+        let variable_header = GeneralVariableHeader::new(PacketId::try_from(self.message_id)?);
+        // 剩余长度 = message_id + 每个topic(2字节长度 + topic + 1字节QoS)
+        let remaining_length = self
+            .topics
+            .iter()
+            .fold(variable_header.len(), |len, topic| {
+                len + topic.name_len() + 3
+            });
+        let fixed_header = FixedHeaderBuilder::new()
+            .subscribe()
+            .remaining_length(remaining_length)
+            .build()?;
+        Ok(Subscribe::new(fixed_header, variable_header, self.topics))
     }
 }
 
@@ -535,34 +909,46 @@ impl SubscribeBuilder {
 /// SubAck Builder
 ///////////////////////////////////
 pub struct SubAckBuilder {
-    qos: QoS,
     message_id: usize,
-    pub acks: Vec<u8>,
+    pub acks: Vec<SubscribeReturnCode>,
+}
+
+impl Default for SubAckBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SubAckBuilder {
     pub fn new() -> SubAckBuilder {
         SubAckBuilder {
-            qos: QoS::AtMostOnce,
             message_id: 0,
             acks: Vec::new(),
         }
     }
-    
+
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
-    pub fn acks(mut self, acks: Vec<u8>) -> Self {
+    pub fn acks(mut self, acks: Vec<SubscribeReturnCode>) -> Self {
         self.acks = acks;
         self
     }
     pub fn build(self) -> Result<SubAck, ProtoError> {
         let fixed_header = FixedHeaderBuilder::new().sub_ack().build();
         match fixed_header {
-            Ok(mut fixed_header) => {
-                fixed_header.set_remaining_length(2 + self.acks.len());
-                let variable_header = GeneralVariableHeader::new(self.message_id);
+            Ok(fixed_header) => {
+                let variable_header =
+                    GeneralVariableHeader::new(PacketId::try_from(self.message_id)?);
                 Ok(SubAck::new(fixed_header, variable_header, self.acks))
             }
             Err(e) => Err(e),
@@ -578,6 +964,12 @@ pub struct UnsubscriberBuilder {
     topices: Vec<String>,
 }
 
+impl Default for UnsubscriberBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UnsubscriberBuilder {
     pub fn new() -> Self {
         Self {
@@ -586,6 +978,14 @@ impl UnsubscriberBuilder {
         }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
@@ -607,18 +1007,26 @@ impl UnsubscriberBuilder {
     }
 
     pub fn build(&self) -> Result<UnSubscribe, ProtoError> {
+        if self.topices.is_empty() {
+            return Err(ProtoError::EmptyUnsubscription);
+        }
+        validate_topic_filters(self.topices.iter().map(String::as_str))?;
         let resp = FixedHeaderBuilder::new().un_subscribe().build();
         match resp {
             Ok(mut fixed_header) => {
                 let remaining_len = self.remaining_length();
 
-                let variable_header = GeneralVariableHeader::new(self.message_id);
+                let variable_header =
+                    GeneralVariableHeader::new(PacketId::try_from(self.message_id)?);
                 fixed_header.set_remaining_length(remaining_len + variable_header.len());
-                Ok(UnSubscribe::new(
-                    fixed_header,
-                    variable_header,
-                    self.topices.clone(),
-                ))
+                let filters = self
+                    .topices
+                    .iter()
+                    .map(|path| {
+                        TopicFilter::new_unchecked(string_field(path.clone()), QoS::AtMostOnce)
+                    })
+                    .collect();
+                Ok(UnSubscribe::new(fixed_header, variable_header, filters))
             }
             Err(e) => Err(e),
         }
@@ -632,35 +1040,85 @@ pub struct UnsubAckBuilder {
     message_id: usize,
 }
 
+impl Default for UnsubAckBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UnsubAckBuilder {
     pub fn new() -> Self {
         Self { message_id: 0 }
     }
 
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
     pub fn message_id(mut self, message_id: usize) -> Self {
         self.message_id = message_id;
         self
     }
 
     pub fn build(self) -> Result<UnSubAck, ProtoError> {
-        let resp = FixedHeaderBuilder::new().un_suback().build();
-        match resp {
-            Ok(mut fixed_header) => {
-                let variable_header = GeneralVariableHeader::new(self.message_id);
-                fixed_header.set_remaining_length(variable_header.len());
-                Ok(UnSubAck::new(fixed_header, variable_header))
-            }
-            Err(e) => Err(e),
-        }
+        Ok(UnSubAck::new(PacketId::try_from(self.message_id)?))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::MqttMessageBuilder;
-    use crate::v4::Encoder;
+    use crate::common::client_id::ClientIdPolicy;
+    use crate::common::mqtt_string::string_field;
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{conn_ack::ConnAckType, Encoder};
+    use crate::{QoS, Topic};
     use bytes::{Bytes, BytesMut};
 
+    #[test]
+    fn last_will_builder_should_be_work() {
+        let direct = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .will("/will", Bytes::from_static(b"bye"), QoS::ExactlyOnce, true)
+            .build()
+            .unwrap();
+        let last_will = MqttMessageBuilder::last_will()
+            .topic("/will")
+            .message_str("bye")
+            .qos(QoS::ExactlyOnce)
+            .retain(true)
+            .build()
+            .unwrap();
+        let prebuilt = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .last_will(last_will)
+            .build()
+            .unwrap();
+        let (mut direct_bytes, mut prebuilt_bytes) = (BytesMut::new(), BytesMut::new());
+        direct.encode(&mut direct_bytes).unwrap();
+        prebuilt.encode(&mut prebuilt_bytes).unwrap();
+        assert_eq!(direct_bytes, prebuilt_bytes);
+        assert_eq!(direct, prebuilt);
+
+        let builder = MqttMessageBuilder::last_will().message(Bytes::new());
+        assert_eq!(builder.clone().build(), Err(ProtoError::WillTopicRequired));
+        assert_eq!(
+            builder.clone().topic("/a/+").build(),
+            Err(ProtoError::WillTopicContainsWildcard)
+        );
+        assert_eq!(
+            MqttMessageBuilder::last_will().topic("/a").build(),
+            Err(ProtoError::WillMessageRequired)
+        );
+        // 遗嘱消息可以为空
+        assert!(builder.topic("/a").build().is_ok());
+    }
+
     #[test]
     fn build_connect_test() {
         let connect = MqttMessageBuilder::connect()
@@ -681,9 +1139,198 @@ mod tests {
         // println!("bytes = {:?}", bytes);
     }
 
+    #[test]
+    fn utf8_payload_should_be_validated() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_utf8("{\"温度\": 20}")
+            .content_type("application/json")
+            .build()
+            .unwrap();
+        assert_eq!(publish.try_payload_as_str(), Ok("{\"温度\": 20}"));
+        // 报文本身与payload_str相同
+        let plain = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_str("{\"温度\": 20}")
+            .build()
+            .unwrap();
+        assert_eq!(publish, plain);
+
+        let resp = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_utf8("")
+            .payload(Bytes::from_static(&[0xff, 0xfe]))
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::MalformedString);
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload(Bytes::from_static(&[0xff, 0xfe]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            publish.try_payload_as_str(),
+            Err(ProtoError::MalformedString)
+        );
+    }
+
+    #[test]
+    fn qos2_flow_should_share_packet_id() {
+        use super::{is_complete_qos2_flow, qos2_flow};
+        use crate::v4::{
+            decode_zero_copy, pub_comp::PubComp, pub_rec::PubRec, pub_rel::PubRel,
+            publish::Publish, Decoder, Packet,
+        };
+
+        let (publish, pub_rec, pub_rel, pub_comp) =
+            qos2_flow("/a/b", Bytes::from_static(b"hello"), 513).unwrap();
+        assert_eq!(publish.qos(), QoS::ExactlyOnce);
+        assert!(!publish.dup());
+
+        let packets = [
+            Packet::Publish(publish),
+            Packet::PubRec(pub_rec),
+            Packet::PubRel(pub_rel),
+            Packet::PubComp(pub_comp),
+        ];
+        let frames: Vec<_> = packets
+            .iter()
+            .map(|packet| packet.encode_to_bytes().unwrap().freeze())
+            .collect();
+        for (frame, packet) in frames.iter().zip(&packets) {
+            assert_eq!(&decode_zero_copy(frame.clone()).unwrap(), packet);
+        }
+        let publish = Publish::decode(frames[0].clone()).unwrap();
+        let pub_rec = PubRec::decode(frames[1].clone()).unwrap();
+        let pub_rel = PubRel::decode(frames[2].clone()).unwrap();
+        let pub_comp = PubComp::decode(frames[3].clone()).unwrap();
+        assert_eq!(publish.variable_header_ref().message_id(), Some(513));
+        assert!(is_complete_qos2_flow(
+            &publish, &pub_rec, &pub_rel, &pub_comp
+        ));
+
+        let (_, other_rec, _, _) = qos2_flow("/a/b", Bytes::new(), 514).unwrap();
+        assert!(!is_complete_qos2_flow(
+            &publish, &other_rec, &pub_rel, &pub_comp
+        ));
+        assert_eq!(
+            qos2_flow("/a/b", Bytes::new(), 0).unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+    }
+
+    #[test]
+    fn empty_client_id_requires_clean_session() {
+        let resp = MqttMessageBuilder::connect().build();
+        assert_eq!(
+            resp.unwrap_err(),
+            ProtoError::InvalidClientId("client_id为空时clean_session必须为1")
+        );
+        let connect = MqttMessageBuilder::connect()
+            .clean_session(true)
+            .build()
+            .unwrap();
+        assert!(connect.clean_session());
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        // connect flags
+        assert_eq!(bytes[9], 0x02);
+    }
+
+    #[test]
+    fn build_connect_with_client_id_policy() {
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .validate_client_id(ClientIdPolicy::strict())
+            .build();
+        assert!(matches!(resp, Err(ProtoError::InvalidClientId(_))));
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .validate_client_id(ClientIdPolicy::strict())
+            .build();
+        assert!(resp.is_ok());
+        // 空client_id不受策略影响
+        let resp = MqttMessageBuilder::connect()
+            .clean_session(true)
+            .validate_client_id(ClientIdPolicy::strict())
+            .build();
+        assert!(resp.is_ok());
+    }
+
     #[test]
     fn test() {
         let b = Bytes::from_static(b"this is will message!").len();
         println!("b = {}", b);
     }
+
+    #[test]
+    fn build_conn_ack_test() {
+        let conn_ack = MqttMessageBuilder::conn_ack()
+            .conn_ack_type(ConnAckType::BadUsernameOrPassword)
+            .build()
+            .unwrap();
+        assert_eq!(conn_ack.conn_ack_type(), ConnAckType::BadUsernameOrPassword);
+    }
+
+    #[test]
+    fn build_subscribe_should_propagate_fixed_header_error() {
+        // 只伪造topic的长度，避免真正分配超长的topic
+        let topic = Topic {
+            name: string_field("/a".to_string()),
+            qos: QoS::AtMostOnce,
+            name_len: 268_435_455,
+        };
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic(topic)
+            .build();
+        assert_eq!(
+            resp.unwrap_err(),
+            ProtoError::OutOfMaxRemainingLength(268_435_455 + 2 + 3)
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn invalid_message_id_should_return_error() {
+        for message_id in [0, 65_536] {
+            let resp = MqttMessageBuilder::pub_ack().message_id(message_id).build();
+            assert_eq!(resp.unwrap_err(), ProtoError::InvalidPacketId(message_id));
+            let resp = MqttMessageBuilder::publish()
+                .topic("/a")
+                .qos(QoS::AtLeastOnce)
+                .message_id(message_id)
+                .build();
+            assert_eq!(resp.unwrap_err(), ProtoError::InvalidPacketId(message_id));
+        }
+        // 没有设置message_id
+        let resp = MqttMessageBuilder::unsub_ack().build();
+        assert_eq!(resp.unwrap_err(), ProtoError::InvalidPacketId(0));
+        let pub_ack = MqttMessageBuilder::pub_ack().message_id(65_535).build();
+        assert_eq!(pub_ack.unwrap().message_id(), 65_535);
+    }
+
+    #[test]
+    fn build_publish_with_invalid_topic_should_return_error() {
+        for topic in ["", "sport/+/scores", "sport/#", "a\u{0}b"] {
+            let resp = MqttMessageBuilder::publish().topic(topic).build();
+            assert!(matches!(resp, Err(ProtoError::InvalidTopicName(_))));
+        }
+        let resp = MqttMessageBuilder::publish()
+            .topic("$SYS/broker/load")
+            .build();
+        assert!(resp.is_ok());
+    }
+
+    #[test]
+    fn build_publish_with_dup_on_qos0_should_return_error() {
+        let resp = MqttMessageBuilder::publish().topic("/a").dup(true).build();
+        assert_eq!(resp.unwrap_err(), ProtoError::DupOnQoS0);
+        let resp = MqttMessageBuilder::publish()
+            .topic("/a")
+            .qos(QoS::AtLeastOnce)
+            .packet_id(PacketId::MIN)
+            .dup(true)
+            .build();
+        assert!(resp.unwrap().fixed_header_ref().dup().unwrap());
+    }
 }
@@ -1,12 +1,14 @@
+use super::builder::{ConnAckBuilder, MqttMessageBuilder};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+use crate::common::coder;
 use crate::error::ProtoError;
 use crate::QoS;
 
 use super::{
     decoder,
     fixed_header::{FixedHeader, FixedHeaderBuilder},
-    Decoder, Encoder, VariableDecoder,
+    BodyDecoder, Decoder, Encoder, PacketEncoder, VariableDecoder,
 };
 
 /// 链接回执报文
@@ -19,13 +21,30 @@ use super::{
  | byte3 | 0 | 0 | 0 | 0 | 0 | 0 | 0 |sp |
  | byte4 | 连 |接 |返 |回 | 码 | C | R | C |
 */
-#[derive(Debug, PartialOrd, Clone, PartialEq)]
+#[derive(Debug, PartialOrd, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnAck {
     fixed_header: FixedHeader,
     variable_header: ConnAckVariableHeader,
 }
 
 impl ConnAck {
+    /**
+     CONNACK报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::v4::conn_ack::{ConnAck, ConnAckType};
+    let conn_ack = ConnAck::builder()
+        .conn_ack_type(ConnAckType::NotAuthorized)
+        .build()
+        .unwrap();
+    assert_eq!(conn_ack.conn_ack_type(), ConnAckType::NotAuthorized);
+    ```
+     */
+    pub fn builder() -> ConnAckBuilder {
+        MqttMessageBuilder::conn_ack()
+    }
+
     pub fn new(conn_ack_type: ConnAckType) -> Result<ConnAck, ProtoError> {
         let fixed_header = FixedHeaderBuilder::new().conn_ack().build();
         match fixed_header {
@@ -40,22 +59,74 @@ impl ConnAck {
     pub fn conn_ack_type(&self) -> ConnAckType {
         self.variable_header.conn_ack_type.clone()
     }
+    /// 连接返回码，与[`ConnAck::conn_ack_type`]相同，可以通过`u8::from`得到协议中的数值
+    pub fn return_code(&self) -> ConnAckType {
+        self.conn_ack_type()
+    }
+    /// 服务端是否接受了连接
+    pub fn is_success(&self) -> bool {
+        self.variable_header.conn_ack_type == ConnAckType::Success
+    }
 }
 
-#[derive(PartialOrd, Debug, Clone, PartialEq)]
+#[derive(PartialOrd, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConnAckType {
-    // 连接成功
+    // 0x00，连接成功
     Success,
-    // 版本错误
+    // 0x01，服务端不支持客户端请求的协议版本
     ProtoVersionError,
-    // 不符合规定的client_id
+    // 0x02，不符合规定的client_id
     IdentifierRejected,
-    // 服务不可用
+    // 0x03，服务不可用
     ServiceUnavailable,
-    // 账号或者密码错误
+    // 0x04，账号或者密码错误
     BadUsernameOrPassword,
-    // 未授权
-    NotAuthentication,
+    // 0x05，未授权
+    NotAuthorized,
+}
+
+/// 转换为MQTT 3.1.1中CONNACK的返回码
+impl From<ConnAckType> for u8 {
+    fn from(value: ConnAckType) -> Self {
+        match value {
+            ConnAckType::Success => 0x00,
+            ConnAckType::ProtoVersionError => 0x01,
+            ConnAckType::IdentifierRejected => 0x02,
+            ConnAckType::ServiceUnavailable => 0x03,
+            ConnAckType::BadUsernameOrPassword => 0x04,
+            ConnAckType::NotAuthorized => 0x05,
+        }
+    }
+}
+
+/// 6-255为保留的返回码
+impl TryFrom<u8> for ConnAckType {
+    type Error = ProtoError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(ConnAckType::Success),
+            0x01 => Ok(ConnAckType::ProtoVersionError),
+            0x02 => Ok(ConnAckType::IdentifierRejected),
+            0x03 => Ok(ConnAckType::ServiceUnavailable),
+            0x04 => Ok(ConnAckType::BadUsernameOrPassword),
+            0x05 => Ok(ConnAckType::NotAuthorized),
+            n => Err(ProtoError::UnknownConnAckType(n)),
+        }
+    }
+}
+
+impl ConnAckType {
+    /// 将CONNECT报文的解码错误转换为CONNACK的返回码，
+    /// 返回None时服务端不需要回复CONNACK，直接断开连接即可
+    pub fn from_connect_error(error: &ProtoError) -> Option<ConnAckType> {
+        match error {
+            ProtoError::UnsupportedVersion(_) => Some(ConnAckType::ProtoVersionError),
+            ProtoError::InvalidClientId(_) => Some(ConnAckType::IdentifierRejected),
+            _ => None,
+        }
+    }
 }
 //////////////////////////////////////////////////////////
 /// 为ConnAck实现Encoder trait
@@ -74,6 +145,12 @@ impl Encoder for ConnAck {
         }
     }
 }
+
+impl PacketEncoder for ConnAck {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + 2
+    }
+}
 //////////////////////////////////////////////////////////
 /// 为ConnAck实现Decoder trait
 /////////////////////////////////////////////////////////
@@ -94,7 +171,7 @@ impl Decoder for ConnAck {
                         fixed_header,
                         variable_header,
                     }),
-                    Err(e) => return Err(e),
+                    Err(e) => Err(e),
                 }
             }
             Err(e) => Err(e),
@@ -102,7 +179,21 @@ impl Decoder for ConnAck {
     }
 }
 
-#[derive(Debug, PartialOrd, Clone, PartialEq)]
+impl BodyDecoder for ConnAck {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        if body.len() < 2 {
+            return Err(ProtoError::NotKnow);
+        }
+        let variable_header = ConnAckVariableHeader::decode(&mut body, fixed_header.qos())?;
+        Ok(ConnAck {
+            fixed_header,
+            variable_header,
+        })
+    }
+}
+
+#[derive(Debug, PartialOrd, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnAckVariableHeader {
     session_present: bool,
     conn_ack_type: ConnAckType,
@@ -123,32 +214,8 @@ impl ConnAckVariableHeader {
 impl Encoder for ConnAckVariableHeader {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
         buffer.put_u8(0b0000_0000);
-        match &self.conn_ack_type {
-            ConnAckType::Success => {
-                buffer.put_u8(0b0000_0000);
-                Ok(2)
-            }
-            ConnAckType::ProtoVersionError => {
-                buffer.put_u8(0b0000_0001);
-                Ok(2)
-            }
-            ConnAckType::IdentifierRejected => {
-                buffer.put_u8(0b0000_0010);
-                Ok(2)
-            }
-            ConnAckType::ServiceUnavailable => {
-                buffer.put_u8(0b0000_0011);
-                Ok(2)
-            }
-            ConnAckType::BadUsernameOrPassword => {
-                buffer.put_u8(0b0000_0100);
-                Ok(2)
-            }
-            ConnAckType::NotAuthentication => {
-                buffer.put_u8(0b0000_0101);
-                Ok(2)
-            }
-        }
+        buffer.put_u8(u8::from(self.conn_ack_type.clone()));
+        Ok(2)
     }
 }
 
@@ -159,20 +226,10 @@ impl VariableDecoder for ConnAckVariableHeader {
     type Item = ConnAckVariableHeader;
 
     fn decode(bytes: &mut Bytes, _qos: Option<QoS>) -> Result<Self::Item, ProtoError> {
-        let b1 = bytes.get_u8();
+        let b1 = coder::read_u8(bytes)?;
         if b1 == 0 {
-            let b2 = bytes.get_u8();
-            let con_ack_type = match b2 {
-                0b0000_0000 => ConnAckType::Success,
-                0b0000_0001 => ConnAckType::ProtoVersionError,
-                0b0000_0010 => ConnAckType::IdentifierRejected,
-                0b0000_0011 => ConnAckType::ServiceUnavailable,
-                0b0000_0100 => ConnAckType::BadUsernameOrPassword,
-                0b0000_0101 => ConnAckType::NotAuthentication,
-                _ => {
-                    return Err(ProtoError::NotKnow);
-                }
-            };
+            let b2 = coder::read_u8(bytes)?;
+            let con_ack_type = ConnAckType::try_from(b2)?;
             Ok(ConnAckVariableHeader::new(con_ack_type))
         } else {
             Err(ProtoError::NotKnow)
@@ -182,21 +239,103 @@ impl VariableDecoder for ConnAckVariableHeader {
 
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use bytes::{Bytes, BytesMut};
 
+    use crate::error::ProtoError;
     use crate::v4::{builder::MqttMessageBuilder, Decoder, Encoder};
 
-    use super::ConnAck;
+    use super::{ConnAck, ConnAckType};
 
     #[test]
     fn encode_and_decode_for_connack_should_be_work() {
         let resp = MqttMessageBuilder::conn_ack()
-            .conn_ack_type(super::ConnAckType::NotAuthentication)
-            .build();
+            .conn_ack_type(ConnAckType::NotAuthorized)
+            .build()
+            .unwrap();
         println!("conn_ack: {:?}", resp);
         let mut buffer = BytesMut::new();
         let _count = resp.encode(&mut buffer);
         let conn_ack = ConnAck::decode(buffer.freeze()).unwrap();
         println!("conn_ack: {:?}", conn_ack);
     }
+
+    #[test]
+    fn conn_ack_type_to_and_from_u8_should_be_work() {
+        let types = [
+            ConnAckType::Success,
+            ConnAckType::ProtoVersionError,
+            ConnAckType::IdentifierRejected,
+            ConnAckType::ServiceUnavailable,
+            ConnAckType::BadUsernameOrPassword,
+            ConnAckType::NotAuthorized,
+        ];
+        for (code, conn_ack_type) in types.into_iter().enumerate() {
+            assert_eq!(u8::from(conn_ack_type.clone()), code as u8);
+            assert_eq!(ConnAckType::try_from(code as u8).unwrap(), conn_ack_type);
+            let mut buffer = BytesMut::new();
+            ConnAck::new(conn_ack_type.clone())
+                .unwrap()
+                .encode(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.as_ref(), &[0x20, 0x02, 0x00, code as u8]);
+            let conn_ack = ConnAck::decode(buffer.freeze()).unwrap();
+            assert_eq!(conn_ack.return_code(), conn_ack_type);
+            assert_eq!(conn_ack.is_success(), code == 0);
+        }
+        for code in 6..=u8::MAX {
+            assert_eq!(
+                ConnAckType::try_from(code).unwrap_err(),
+                ProtoError::UnknownConnAckType(code)
+            );
+        }
+        let frame = Bytes::from_static(&[0x20, 0x02, 0x00, 0x06]);
+        assert_eq!(
+            ConnAck::decode(frame).unwrap_err(),
+            ProtoError::UnknownConnAckType(6)
+        );
+    }
+
+    #[test]
+    fn conn_ack_return_codes_should_match_spec() {
+        // MQTT 3.1.1 3.2.2.3节中的返回码
+        let table = [
+            (ConnAckType::Success, 0x00),
+            (ConnAckType::ProtoVersionError, 0x01),
+            (ConnAckType::IdentifierRejected, 0x02),
+            (ConnAckType::ServiceUnavailable, 0x03),
+            (ConnAckType::BadUsernameOrPassword, 0x04),
+            (ConnAckType::NotAuthorized, 0x05),
+        ];
+        for (conn_ack_type, code) in table {
+            let mut buffer = BytesMut::new();
+            ConnAck::new(conn_ack_type.clone())
+                .unwrap()
+                .encode(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer[3], code, "{:?}", conn_ack_type);
+            let frame = Bytes::copy_from_slice(&[0x20, 0x02, 0x00, code]);
+            assert_eq!(ConnAck::decode(frame).unwrap().return_code(), conn_ack_type);
+        }
+    }
+
+    #[test]
+    fn unsupported_version_should_map_to_proto_version_error() {
+        // 协议级别为9
+        let frame = Bytes::from_static(&[
+            0x10, 0x0e, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x09, 0x02, 0x00, 0x0a, 0x00, 0x02,
+            b'i', b'd',
+        ]);
+        let err = crate::v4::connect::Connect::decode(frame.clone()).unwrap_err();
+        assert_eq!(err, ProtoError::UnsupportedVersion(9));
+        assert_eq!(crate::v4::decode_zero_copy(frame).unwrap_err(), err);
+        assert_eq!(
+            ConnAckType::from_connect_error(&err),
+            Some(ConnAckType::ProtoVersionError)
+        );
+        assert_eq!(
+            ConnAckType::from_connect_error(&ProtoError::InvalidClientId("client_id超出最大长度")),
+            Some(ConnAckType::IdentifierRejected)
+        );
+        assert_eq!(ConnAckType::from_connect_error(&ProtoError::NotKnow), None);
+    }
 }
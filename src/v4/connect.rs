@@ -1,29 +1,55 @@
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use crate::{error::ProtoError, MqttVersion, QoS, PROTOCOL_NAME};
+use super::builder::{ConnectBuilder, MqttMessageBuilder};
 use super::{
-    decoder::{self, *},
-    fixed_header::FixedHeader,
-    Decoder, Encoder, VariableDecoder,
+    decoder, fixed_header::FixedHeader, publish::PayloadPreview, BodyDecoder, Decoder, Encoder,
+    PacketEncoder, VariableDecoder,
+};
+use crate::common::coder::{
+    read_mqtt_bytes, read_mqtt_string, read_u16, read_u8, write_mqtt_bytes, write_mqtt_string,
 };
+use crate::common::mqtt_string::{read_string_field, string_field, StringField};
+use crate::common::topic::validate_topic_name;
+use crate::error::{ProtoError, ValidationError};
+use crate::{MqttVersion, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
 //////////////////////////////////////////////////////
-/// Connect报文
+/// Connect报文，通过keep_alive()、clean_session()、last_will()等方法读取各个字段，
+/// 需要拿走所有权时使用[`Connect::into_parts`]
 //////////////////////////////////////////////////////
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[warn(unused_assignments)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Connect {
     // 固定报头
-    pub fixed_header: FixedHeader,
+    pub(crate) fixed_header: FixedHeader,
     // 可变报头
-    pub variable_header: ConnectVariableHeader,
+    pub(crate) variable_header: ConnectVariableHeader,
     // 客户端id
-    pub client_id: String,
+    pub(crate) client_id: StringField,
     // 客户端遗嘱信息
-    pub last_will: Option<LastWill>,
+    pub(crate) last_will: Option<LastWill>,
     // 连接信息
-    pub login: Option<Login>,
+    pub(crate) login: Option<Login>,
 }
 
 impl Connect {
+    /**
+     CONNECT报文构建器，与[`MqttMessageBuilder::connect`]相同，例如：
+
+    ```rust
+    use walle_mqtt_protocol::v4::connect::Connect;
+    let connect = Connect::builder()
+        .client_id("client01")
+        .keep_alive(30)
+        .build()
+        .unwrap();
+    assert_eq!(connect.keep_alive(), 30);
+    ```
+     */
+    pub fn builder() -> ConnectBuilder {
+        MqttMessageBuilder::connect()
+    }
+
     pub fn new(
         fixed_header: FixedHeader,
         variable_header: ConnectVariableHeader,
@@ -34,17 +60,138 @@ impl Connect {
         Self {
             fixed_header,
             variable_header,
-            client_id,
+            client_id: string_field(client_id),
             last_will,
             login,
         }
     }
 
+    /// 拆分报文，各个字段都不会被复制
+    pub fn into_parts(
+        self,
+    ) -> (
+        FixedHeader,
+        ConnectVariableHeader,
+        StringField,
+        Option<LastWill>,
+        Option<Login>,
+    ) {
+        (
+            self.fixed_header,
+            self.variable_header,
+            self.client_id,
+            self.last_will,
+            self.login,
+        )
+    }
+
+    pub fn fixed_header_ref(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+
+    pub fn variable_header_ref(&self) -> &ConnectVariableHeader {
+        &self.variable_header
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub fn keep_alive(&self) -> u16 {
+        self.variable_header.keep_alive
+    }
+
+    pub fn clean_session(&self) -> bool {
+        self.variable_header.connect_flags.clean_session
+    }
+
+    pub fn protocol_level(&self) -> MqttVersion {
+        self.variable_header.protocol_level()
+    }
+
+    pub fn last_will(&self) -> Option<&LastWill> {
+        self.last_will.as_ref()
+    }
+
+    pub fn has_will(&self) -> bool {
+        self.last_will.is_some()
+    }
+
+    /// 没有遗嘱信息时返回None
+    pub fn will_qos(&self) -> Option<QoS> {
+        self.last_will.as_ref().map(|last_will| last_will.qos)
+    }
+
+    pub fn login(&self) -> Option<&Login> {
+        self.login.as_ref()
+    }
+
+    /**
+     检查报文中的字段是否满足协议的约束，返回所有的问题而不是只返回第一个，例如：
+
+    ```rust
+    use walle_mqtt_protocol::error::ValidationError;
+    use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+    let connect = MqttMessageBuilder::connect()
+        .client_id("client01")
+        .keep_alive(0)
+        .build()
+        .unwrap();
+    assert_eq!(connect.validate(), Err(vec![ValidationError::KeepAliveZero]));
+    // KeepAliveZero只是警告
+    assert!(connect.is_valid());
+    ```
+     */
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut violations = Vec::new();
+        if self.client_id.len() > 23 {
+            violations.push(ValidationError::ClientIdTooLong(self.client_id.len()));
+        }
+        if self.client_id.is_empty() && !self.clean_session() {
+            violations.push(ValidationError::ClientIdEmpty);
+        }
+        match &self.last_will {
+            Some(last_will) if last_will.topic_name.is_empty() => {
+                violations.push(ValidationError::WillMessageWithoutWillTopic)
+            }
+            Some(last_will) if last_will.topic_name.contains(['+', '#']) => {
+                violations.push(ValidationError::TopicContainsWildcard)
+            }
+            Some(_) => {}
+            None if self.variable_header.connect_flags.will_flag => {
+                violations.push(ValidationError::WillTopicWithoutWillMessage)
+            }
+            None => {}
+        }
+        if self.keep_alive() == 0 {
+            violations.push(ValidationError::KeepAliveZero);
+        }
+        let protocol_name = &self.variable_header.protocol_name;
+        if protocol_name != self.protocol_level().protocol_name() {
+            violations.push(ValidationError::ProtocolNameMismatch(protocol_name.clone()));
+        }
+        if let Some(login) = &self.login {
+            if login.username.is_empty() && !login.password.is_empty() {
+                violations.push(ValidationError::PasswordWithoutUsername);
+            }
+        }
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    /// [`Connect::validate`]没有返回警告之外的问题
+    pub fn is_valid(&self) -> bool {
+        match self.validate() {
+            Ok(()) => true,
+            Err(violations) => violations.iter().all(ValidationError::is_warning),
+        }
+    }
+
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        let mut len = 2 + PROTOCOL_NAME.len() // protocol name
-                              + 1            // protocol version
-                              + 1            // connect flags
-                              + 2; // keep alive
+        let mut len = self.variable_header.len();
 
         len += 2 + self.client_id.len();
         // last will len
@@ -64,25 +211,19 @@ impl Connect {
 //////////////////////////////////////////////////////
 impl Encoder for Connect {
     fn encode(&self, buffer: &mut bytes::BytesMut) -> Result<usize, ProtoError> {
-        let _count = self.fixed_header.encode(buffer).unwrap();
+        let _count = self.fixed_header.encode(buffer)?;
         // variable_header
-        write_mqtt_string(buffer, PROTOCOL_NAME);
+        write_mqtt_string(buffer, &self.variable_header.protocol_name)?;
 
         // 写protocol_level
-        match self.variable_header.protocol_level {
-            MqttVersion::V4 => buffer.put_u8(0x04),
-            MqttVersion::V5 => buffer.put_u8(0x05),
-        }
+        buffer.put_u8(self.variable_header.protocol_level.protocol_level());
         // connect_flags
         let mut connect_flags = 0;
         if self.variable_header.connect_flags.clean_session {
             connect_flags |= 0x02;
         }
-        match &self.login {
-            Some(_login) => {
-                connect_flags |= 0xc0;
-            }
-            None => {}
+        if let Some(login) = &self.login {
+            connect_flags |= login.flags();
         }
         if self.variable_header.connect_flags.will_retain {
             connect_flags |= 0x20;
@@ -96,25 +237,28 @@ impl Encoder for Connect {
                 connect_flags |= 0x10;
             }
         }
-        match &self.last_will {
-            Some(_last_will) => {
-                connect_flags |= 0x04;
-            }
-            None => {}
+        if self.last_will.is_some() {
+            connect_flags |= 0x04;
         }
         buffer.put_u8(connect_flags);
         buffer.put_u16(self.variable_header.keep_alive());
-        write_mqtt_string(buffer, &self.client_id);
+        write_mqtt_string(buffer, &self.client_id)?;
         if let Some(last_will) = &self.last_will {
-            connect_flags |= last_will.write(buffer)?;
+            last_will.write(buffer)?;
         }
         if let Some(login) = &self.login {
-            connect_flags |= login.write(buffer);
+            login.write(buffer)?;
         }
         Ok(self.len())
     }
 }
 
+impl PacketEncoder for Connect {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.len()
+    }
+}
+
 //////////////////////////////////////////////////////
 /// 为Connect实现Decoder trait
 //////////////////////////////////////////////////////
@@ -134,19 +278,18 @@ impl Decoder for Connect {
                 match resp {
                     Ok(variable_header) => {
                         // connect报文的variable_header是固定的8个字节
-                        let client_id = read_mqtt_string(&mut bytes)?;
+                        let client_id = read_string_field(&mut bytes)?;
                         // bytes.advance(variable_header.len());
                         let last_will =
-                            LastWill::read_last_will(&mut bytes, &variable_header.connect_flags);
-                        let login = Login::read_login(&mut bytes, &variable_header.connect_flags);
-                        let connect = Connect::new(
+                            LastWill::read_last_will(&mut bytes, &variable_header.connect_flags)?;
+                        let login = Login::read_login(&mut bytes, &variable_header.connect_flags)?;
+                        Ok(Connect {
                             fixed_header,
                             variable_header,
                             client_id,
                             last_will,
                             login,
-                        );
-                        Ok(connect)
+                        })
                     }
                     Err(e) => Err(e),
                 }
@@ -156,10 +299,27 @@ impl Decoder for Connect {
     }
 }
 
+impl BodyDecoder for Connect {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = ConnectVariableHeader::decode(&mut body, fixed_header.qos())?;
+        let client_id = read_string_field(&mut body)?;
+        let last_will = LastWill::read_last_will(&mut body, &variable_header.connect_flags)?;
+        let login = Login::read_login(&mut body, &variable_header.connect_flags)?;
+        Ok(Connect {
+            fixed_header,
+            variable_header,
+            client_id,
+            last_will,
+            login,
+        })
+    }
+}
+
 //////////////////////////////////////////////
 /// ConnectVariableHeader
 /////////////////////////////////////////////
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnectVariableHeader {
     // 协议名称
     protocol_name: String,
@@ -197,8 +357,10 @@ impl ConnectVariableHeader {
     pub fn keep_alive(&self) -> u16 {
         self.keep_alive
     }
+    /// protocol name + protocol level + connect flags + keep alive
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        8
+        2 + self.protocol_name.len() + 1 + 1 + 2
     }
 }
 
@@ -209,21 +371,23 @@ impl VariableDecoder for ConnectVariableHeader {
         let resp = read_mqtt_string(stream);
         match resp {
             Ok(protocol_name) => {
-                if protocol_name != PROTOCOL_NAME {
+                let protocol_level = read_u8(stream)?;
+                let protocol = match protocol_level {
+                    3 => MqttVersion::V3,
+                    4 => MqttVersion::V4,
+                    5 => MqttVersion::V5,
+                    num => return Err(ProtoError::UnsupportedVersion(num)),
+                };
+                // protocol name必须与protocol level对应："MQIsdp"对应3，"MQTT"对应4和5
+                if protocol_name != protocol.protocol_name() {
                     Err(ProtoError::NotKnow)
                 } else {
-                    let protocol_level = read_u8(stream).unwrap();
-                    let protocol = match protocol_level {
-                        4 => MqttVersion::V4,
-                        5 => MqttVersion::V5,
-                        _num => return Err(ProtoError::NotKnow),
-                    };
                     let connect_flags_u8 = read_u8(stream)?;
                     let connect_flags = ConnectFlags::from_u8(connect_flags_u8);
                     let keep_alive = read_u16(stream)?;
                     match connect_flags {
                         Ok(flags) => Ok(ConnectVariableHeader::new(
-                            PROTOCOL_NAME.to_owned(),
+                            protocol_name,
                             protocol,
                             flags,
                             keep_alive,
@@ -246,7 +410,8 @@ impl VariableDecoder for ConnectVariableHeader {
 |byte8|         x       |       x       |       x     |    x      |    x    |      x    |        x      |    0     |
 
  */
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConnectFlags {
     username_flag: bool,
     password_flag: bool,
@@ -285,11 +450,27 @@ impl ConnectFlags {
         self.will_flag
     }
 
+    /// 解析连接标志位，以下情况视为非法报文：
+    /// - 保留标志位不为0
+    /// - will_flag为0时，will_qos或will_retain不为0
+    /// - username_flag为0时，password_flag为1
     fn from_u8(byte: u8) -> Result<Self, ProtoError> {
+        // reserved
+        if byte & 0b0000_0001 != 0 {
+            return Err(ProtoError::ConnectReservedFlagError);
+        }
         // username_flag
         let username_flag = byte >> 7 != 0;
         // password_flag
         let password_flag = (byte & 0b0100_0000) != 0;
+        if password_flag && !username_flag {
+            return Err(ProtoError::PasswordWithoutUsername);
+        }
+        // will_flag
+        let will_flag = (byte & 0b0000_0100) != 0;
+        if !will_flag && (byte & 0b0011_1000) != 0 {
+            return Err(ProtoError::WillFlagsWithoutWill);
+        }
         // will_retain
         let will_retain = (byte & 0b0010_0000) != 0;
         let will_qos_value = (byte & 0b0001_1000) >> 3;
@@ -299,8 +480,6 @@ impl ConnectFlags {
             2 => QoS::ExactlyOnce,
             _ => return Err(ProtoError::QoSError(will_qos_value)),
         };
-        // will_flag
-        let will_flag = (byte & 0b0000_0100) != 0;
         // clean_session
         let clean_session = (byte & 0b10) != 0;
         Ok(Self {
@@ -315,26 +494,46 @@ impl ConnectFlags {
 }
 
 /// 客户端登陆信息
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Login {
     // 账号信息
-    pub username: String,
-    // 密码信息
-    pub password: String,
+    pub username: StringField,
+    // 密码信息，MQTT协议中password是二进制数据，不要求是UTF-8字符串
+    pub password: Bytes,
 }
 
 impl Login {
-    pub fn new(username: String, password: String) -> Self {
-        Self { username, password }
+    pub fn new(username: String, password: Bytes) -> Self {
+        Self {
+            username: string_field(username),
+            password,
+        }
     }
 
+    #[deprecated(note = "请使用username_str")]
     pub fn username(&self) -> String {
-        self.username.clone()
+        self.username.to_string()
+    }
+
+    pub fn username_str(&self) -> &str {
+        &self.username
     }
 
-    pub fn password(&self) -> String {
+    #[deprecated(note = "请使用password_ref")]
+    pub fn password(&self) -> Bytes {
         self.password.clone()
     }
+
+    pub fn password_ref(&self) -> &Bytes {
+        &self.password
+    }
+
+    /// 当password是合法的UTF-8字符串时，以&str的方式返回
+    pub fn password_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.password).ok()
+    }
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         let mut len = 0;
         if !self.username.is_empty() {
@@ -345,42 +544,53 @@ impl Login {
         }
         len
     }
-    pub fn write(&self, buffer: &mut BytesMut) -> u8 {
+    /// username_flag和password_flag，为空的字段不会被写入报文
+    pub fn flags(&self) -> u8 {
         let mut connect_flags = 0;
         if !self.username.is_empty() {
             connect_flags |= 0x80;
-            write_mqtt_string(buffer, &self.username);
         }
-
         if !self.password.is_empty() {
             connect_flags |= 0x40;
-            write_mqtt_string(buffer, &self.password);
         }
         connect_flags
     }
+    pub fn write(&self, buffer: &mut BytesMut) -> Result<u8, ProtoError> {
+        if !self.username.is_empty() {
+            write_mqtt_string(buffer, &self.username)?;
+        }
+        if !self.password.is_empty() {
+            write_mqtt_bytes(buffer, &self.password)?;
+        }
+        Ok(self.flags())
+    }
 }
 impl Login {
-    fn read_login(stream: &mut Bytes, connect_flags: &ConnectFlags) -> Option<Self> {
-        let mut username = String::new();
-        let mut password = String::new();
+    fn read_login(
+        stream: &mut Bytes,
+        connect_flags: &ConnectFlags,
+    ) -> Result<Option<Self>, ProtoError> {
+        let mut username = StringField::default();
+        let mut password = Bytes::new();
         if connect_flags.username_flag {
-            username = read_mqtt_string(stream).unwrap();
+            username = read_string_field(stream)?;
         }
         if connect_flags.password_flag {
-            password = read_mqtt_string(stream).unwrap();
+            password = read_mqtt_bytes(stream)?;
         }
         if username.is_empty() && password.is_empty() {
-            return None;
+            return Ok(None);
         }
-        Some(Login::new(username, password))
+        Ok(Some(Login { username, password }))
     }
 }
 
 /// 客户端遗嘱信息
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LastWill {
     // 主题
-    pub topic_name: String,
+    pub topic_name: StringField,
     // 遗嘱消息的内容
     pub message: Bytes,
     // 遗嘱消息的质量
@@ -389,15 +599,28 @@ pub struct LastWill {
     pub retain: bool,
 }
 
+/// 与Publish相同，遗嘱消息只输出长度和前32个字节
+impl fmt::Debug for LastWill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LastWill")
+            .field("topic_name", &self.topic_name)
+            .field("message", &PayloadPreview(&self.message))
+            .field("qos", &self.qos)
+            .field("retain", &self.retain)
+            .finish()
+    }
+}
+
 impl LastWill {
     pub fn new(topic_name: String, message: Bytes, qos: QoS, retain: bool) -> Self {
         Self {
-            topic_name,
+            topic_name: string_field(topic_name),
             message,
             qos,
             retain,
         }
     }
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         let mut len = 0;
         len += 2 + self.topic_name.len() + 2 + self.message.len();
@@ -410,28 +633,32 @@ impl LastWill {
         if self.retain {
             connect_flags |= 0x20;
         }
-        write_mqtt_string(buffer, &self.topic_name);
-        write_mqtt_bytes(buffer, &self.message);
+        write_mqtt_string(buffer, &self.topic_name)?;
+        write_mqtt_bytes(buffer, &self.message)?;
         Ok(connect_flags)
     }
 }
 
 impl LastWill {
     // 读取last_will的内容，这里的stream就是connect报文中的payload内容，fixed_header和variable_header已经去除
-    fn read_last_will(stream: &mut Bytes, connect_flags: &ConnectFlags) -> Option<Self> {
+    fn read_last_will(
+        stream: &mut Bytes,
+        connect_flags: &ConnectFlags,
+    ) -> Result<Option<Self>, ProtoError> {
         match connect_flags.will_flag {
             true => {
-                let will_topic = read_mqtt_string(stream).unwrap();
-                let will_payload = read_mqtt_bytes(stream).unwrap();
-                let last_will = LastWill::new(
-                    will_topic,
-                    will_payload,
-                    connect_flags.will_qos,
-                    connect_flags.will_retain,
-                );
-                Some(last_will)
+                let will_topic = read_string_field(stream)?;
+                // will_topic是topic name，不能包含通配符
+                validate_topic_name(&will_topic)?;
+                let will_payload = read_mqtt_bytes(stream)?;
+                Ok(Some(LastWill {
+                    topic_name: will_topic,
+                    message: will_payload,
+                    qos: connect_flags.will_qos,
+                    retain: connect_flags.will_retain,
+                }))
             }
-            false => None,
+            false => Ok(None),
         }
     }
 }
@@ -441,40 +668,12 @@ mod tests {
     use bytes::{Bytes, BytesMut};
 
     use crate::{
-        v4::{
-            builder::MqttMessageBuilder,
-            fixed_header::{FixedHeader, FixedHeaderBuilder},
-            Decoder, Encoder,
-        },
+        v4::{builder::MqttMessageBuilder, Decoder, Encoder},
         PROTOCOL_NAME,
     };
 
     use super::{Connect, ConnectFlags, ConnectVariableHeader};
 
-    fn build_fixed_header() -> Option<FixedHeader> {
-        let fixed_header = FixedHeaderBuilder::new()
-            .connect()
-            .dup(Some(true))
-            .qos(Some(crate::QoS::AtLeastOnce))
-            .retain(Some(false))
-            .remaining_length(12)
-            .build();
-        match fixed_header {
-            Ok(fixed_header) => Some(fixed_header),
-            Err(_) => None,
-        }
-    }
-
-    fn build_connect_variable_header() -> Option<ConnectVariableHeader> {
-        let conn_flags = ConnectFlags::new(true, true, false, crate::QoS::AtMostOnce, true, false);
-        let variable_header = ConnectVariableHeader::new(
-            PROTOCOL_NAME.to_owned(),
-            crate::MqttVersion::V4,
-            conn_flags,
-            60,
-        );
-        Some(variable_header)
-    }
     // 创建一个connect报文
     fn build_connect() -> Option<Connect> {
         let connect = MqttMessageBuilder::connect()
@@ -492,6 +691,148 @@ mod tests {
         Some(connect.unwrap())
     }
 
+    #[test]
+    fn validate_should_return_all_violations() {
+        use super::{LastWill, Login};
+        use crate::error::ValidationError;
+        use crate::MqttVersion;
+
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .will(
+                "/will",
+                Bytes::from_static(b"bye"),
+                crate::QoS::AtLeastOnce,
+                false,
+            )
+            .build()
+            .unwrap();
+        assert_eq!(connect.validate(), Ok(()));
+        assert!(connect.is_valid());
+
+        let (fixed_header, _, _, _, _) = connect.clone().into_parts();
+        let flags = ConnectFlags::new(false, true, false, crate::QoS::AtMostOnce, true, false);
+        let variable_header =
+            ConnectVariableHeader::new("MQIsdp".to_string(), MqttVersion::V4, flags, 0);
+        let login = Login::new(String::new(), Bytes::from_static(b"secret"));
+        let connect = Connect::new(
+            fixed_header.clone(),
+            variable_header,
+            String::new(),
+            None,
+            Some(login),
+        );
+        assert_eq!(
+            connect.validate(),
+            Err(vec![
+                ValidationError::ClientIdEmpty,
+                ValidationError::WillTopicWithoutWillMessage,
+                ValidationError::KeepAliveZero,
+                ValidationError::ProtocolNameMismatch("MQIsdp".to_string()),
+                ValidationError::PasswordWithoutUsername,
+            ])
+        );
+        assert!(!connect.is_valid());
+
+        let flags = ConnectFlags::new(false, false, false, crate::QoS::AtMostOnce, true, true);
+        let variable_header =
+            ConnectVariableHeader::new(PROTOCOL_NAME.to_string(), MqttVersion::V4, flags, 60);
+        let last_will = LastWill::new(
+            "/a/#".to_string(),
+            Bytes::new(),
+            crate::QoS::AtMostOnce,
+            false,
+        );
+        let connect = Connect::new(
+            fixed_header,
+            variable_header,
+            "c".repeat(30),
+            Some(last_will),
+            None,
+        );
+        assert_eq!(
+            connect.validate(),
+            Err(vec![
+                ValidationError::ClientIdTooLong(30),
+                ValidationError::TopicContainsWildcard,
+            ])
+        );
+        assert!(!connect.is_valid());
+    }
+
+    #[test]
+    fn debug_should_truncate_will_message() {
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client01")
+            .will_topic("/will")
+            .will_message(Bytes::from(vec![0x5a; 100]))
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", connect);
+        assert!(debug.contains("message: len=100"), "{}", debug);
+        assert_eq!(debug.matches("5a").count(), 32, "{}", debug);
+    }
+
+    #[test]
+    fn into_parts_should_reencode_identically() {
+        let connect = build_connect().unwrap();
+        let mut original = BytesMut::new();
+        connect.encode(&mut original).unwrap();
+        let (fixed_header, variable_header, client_id, last_will, login) =
+            Connect::decode(original.clone().freeze())
+                .unwrap()
+                .into_parts();
+        assert_eq!(client_id, "client_01");
+        assert_eq!(last_will.as_ref().unwrap().topic_name, "/a");
+        let connect = Connect::new(
+            fixed_header,
+            variable_header,
+            client_id.to_string(),
+            last_will,
+            login,
+        );
+        let mut buffer = BytesMut::new();
+        connect.encode(&mut buffer).unwrap();
+        assert_eq!(buffer, original);
+    }
+
+    #[test]
+    fn encode_and_decode_v3_connect_should_be_work() {
+        let builders = [
+            MqttMessageBuilder::connect(),
+            MqttMessageBuilder::connect().will(
+                "/a",
+                Bytes::from_static(b"offline"),
+                crate::QoS::AtLeastOnce,
+                true,
+            ),
+        ];
+        for builder in builders {
+            let connect = builder
+                .client_id("legacy01")
+                .clean_session(true)
+                .protocol_level(crate::MqttVersion::V3)
+                .build()
+                .unwrap();
+            let mut bytes = BytesMut::new();
+            let len = connect.encode(&mut bytes).unwrap();
+            assert_eq!(&bytes[2..11], b"\x00\x06MQIsdp\x03");
+            assert_eq!(bytes.len(), connect.fixed_header.len() + len);
+            let decoded = Connect::decode(bytes.freeze()).unwrap();
+            assert_eq!(decoded.variable_header_ref().protocol_name(), "MQIsdp");
+            assert_eq!(decoded.protocol_level(), crate::MqttVersion::V3);
+            assert_eq!(decoded.variable_header_ref(), connect.variable_header_ref());
+            assert_eq!(decoded.client_id(), connect.client_id());
+            assert_eq!(decoded.last_will(), connect.last_will());
+        }
+        // protocol name与protocol level不对应
+        let frame = Bytes::from_static(&[
+            0x10, 0x0e, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x03, 0x02, 0x00, 0x3c, 0x00, 0x02,
+            b'i', b'd',
+        ]);
+        assert!(Connect::decode(frame).is_err());
+    }
+
     #[test]
     fn encode_and_decode_for_connect_should_be_work() {
         let connect = build_connect().unwrap();
@@ -515,4 +856,248 @@ mod tests {
             Err(_err) => println!("编解码出错"),
         }
     }
+
+    #[test]
+    fn connect_getters_should_be_work() {
+        let connect = build_connect().unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let connect = Connect::decode(bytes.freeze()).unwrap();
+        assert_eq!(connect.client_id(), "client_01");
+        assert_eq!(connect.keep_alive(), 10);
+        assert!(connect.clean_session());
+        assert_eq!(connect.protocol_level(), crate::MqttVersion::V4);
+        assert!(connect.has_will());
+        assert_eq!(connect.will_qos(), Some(crate::QoS::AtLeastOnce));
+        assert_eq!(connect.last_will().unwrap().topic_name, "/a");
+        assert_eq!(connect.login().unwrap().username_str(), "rump");
+
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .build()
+            .unwrap();
+        assert!(!connect.clean_session());
+        assert!(!connect.has_will());
+        assert_eq!(connect.will_qos(), None);
+        assert!(connect.login().is_none());
+    }
+
+    #[test]
+    fn binary_password_should_be_work() {
+        // 非UTF-8的二进制密码，例如HMAC
+        let password = Bytes::from_static(&[0xff, 0x00, 0xfe, 0x80, 0x01, 0xc3]);
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .username("rump")
+            .password_bytes(password.clone())
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let connect = Connect::decode(bytes.freeze()).unwrap();
+        let login = connect.login().unwrap();
+        assert_eq!(login.username_str(), "rump");
+        assert_eq!(login.password_ref(), &password);
+        assert_eq!(login.password_str(), None);
+    }
+
+    #[test]
+    fn text_password_should_be_readable_as_str() {
+        let connect = build_connect().unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let connect = Connect::decode(bytes.freeze()).unwrap();
+        assert_eq!(connect.login().unwrap().password_str(), Some("mq"));
+    }
+
+    #[test]
+    fn truncated_password_should_return_error() {
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .username("rump")
+            .password("mq")
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let bytes = bytes.freeze();
+        let truncated = bytes.slice(..bytes.len() - 1);
+        assert!(Connect::decode(truncated).is_err());
+    }
+
+    #[test]
+    fn malformed_connect_flags_should_return_error() {
+        use crate::error::ProtoError;
+        // 保留标志位为1
+        assert_eq!(
+            ConnectFlags::from_u8(0x01),
+            Err(ProtoError::ConnectReservedFlagError)
+        );
+        // will_flag为0，will_qos不为0
+        assert_eq!(
+            ConnectFlags::from_u8(0x18),
+            Err(ProtoError::WillFlagsWithoutWill)
+        );
+        // username_flag为0，password_flag为1
+        assert_eq!(
+            ConnectFlags::from_u8(0x40),
+            Err(ProtoError::PasswordWithoutUsername)
+        );
+        assert!(ConnectFlags::from_u8(0xc6).is_ok());
+    }
+
+    #[test]
+    fn incomplete_last_will_should_return_error() {
+        use crate::error::ProtoError;
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .will_topic("/a")
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::IncompleteLastWill);
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .will_message(Bytes::from_static(b"offline"))
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::IncompleteLastWill);
+    }
+
+    #[test]
+    fn empty_will_message_should_be_work() {
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .will("/a", Bytes::new(), crate::QoS::AtMostOnce, false)
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let connect = Connect::decode(bytes.freeze()).unwrap();
+        assert!(connect.has_will());
+        let last_will = connect.last_will().unwrap();
+        assert_eq!(last_will.topic_name, "/a");
+        assert!(last_will.message.is_empty());
+    }
+
+    #[test]
+    fn truncated_will_should_return_error() {
+        // will_flag为1，但是payload中只有client_id，没有will_topic和will_message
+        let frame = Bytes::from_static(&[
+            0x10, 0x0e, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x04, 0x00, 0x0a, 0x00, 0x02,
+            b'i', b'd',
+        ]);
+        assert!(Connect::decode(frame.clone()).is_err());
+        assert!(crate::v4::decode_zero_copy(frame).is_err());
+    }
+
+    #[test]
+    fn truncated_connect_should_return_error() {
+        let frame = Bytes::from_static(&[0x10, 0x0a, 0x00, 0x04, b'M']);
+        assert!(Connect::decode(frame.clone()).is_err());
+        assert!(crate::v4::decode_zero_copy(frame).is_err());
+
+        // 完整报文的任意前缀都不能解码成功
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .username("admin")
+            .password("123456")
+            .will(
+                "/a",
+                Bytes::from_static(b"bye"),
+                crate::QoS::AtLeastOnce,
+                false,
+            )
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        connect.encode(&mut bytes).unwrap();
+        let bytes = bytes.freeze();
+        for len in 0..bytes.len() {
+            let prefix = bytes.slice(..len);
+            assert!(Connect::decode(prefix.clone()).is_err(), "len = {}", len);
+            assert!(
+                crate::v4::decode_zero_copy(prefix).is_err(),
+                "len = {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn wildcard_will_topic_should_return_error() {
+        use crate::error::ProtoError;
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .will(
+                "devices/#",
+                Bytes::from_static(b"bye"),
+                crate::QoS::AtMostOnce,
+                false,
+            )
+            .build();
+        assert!(matches!(resp, Err(ProtoError::InvalidTopicName(_))));
+        // will_topic为"a/+"
+        let frame = Bytes::from_static(&[
+            0x10, 0x16, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x06, 0x00, 0x0a, 0x00, 0x02,
+            b'i', b'd', 0x00, 0x03, b'a', b'/', b'+', 0x00, 0x01, b'x',
+        ]);
+        assert!(matches!(
+            Connect::decode(frame.clone()),
+            Err(ProtoError::InvalidTopicName(_))
+        ));
+        assert!(matches!(
+            crate::v4::decode_zero_copy(frame),
+            Err(ProtoError::InvalidTopicName(_))
+        ));
+    }
+
+    #[test]
+    fn will_qos_and_retain_should_be_encoded_in_flags() {
+        use crate::error::ProtoError;
+        use crate::QoS;
+        for (qos, retain) in [
+            (QoS::AtMostOnce, false),
+            (QoS::AtLeastOnce, false),
+            (QoS::ExactlyOnce, false),
+            (QoS::AtMostOnce, true),
+            (QoS::ExactlyOnce, true),
+        ] {
+            let connect = MqttMessageBuilder::connect()
+                .client_id("client_01")
+                .will("/will", Bytes::from_static(b"bye"), qos, retain)
+                .build()
+                .unwrap();
+            let mut bytes = BytesMut::new();
+            connect.encode(&mut bytes).unwrap();
+            // fixed_header(2) + 协议名(6) + 协议级别(1)之后是connect_flags
+            let flags = bytes[9];
+            assert_eq!((flags >> 3) & 0b11, u8::from(qos));
+            assert_eq!(flags & 0b0010_0000 != 0, retain);
+            assert_eq!(flags & 0b0000_0100, 0b0000_0100);
+            let decoded = Connect::decode(bytes.freeze()).unwrap();
+            let last_will = decoded.last_will().unwrap();
+            assert_eq!((last_will.qos, last_will.retain), (qos, retain));
+        }
+        let resp = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .retain(true)
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::WillRetainWithoutWill);
+    }
+
+    #[cfg(feature = "bytes-strings")]
+    #[test]
+    fn decode_should_share_frame_memory() {
+        let mut bytes = BytesMut::new();
+        build_connect().unwrap().encode(&mut bytes).unwrap();
+        let frame = bytes.freeze();
+        let range = frame.as_ptr_range();
+        let (_, _, client_id, last_will, login) =
+            Connect::decode(frame.clone()).unwrap().into_parts();
+        for field in [
+            client_id,
+            last_will.unwrap().topic_name,
+            login.unwrap().username,
+        ] {
+            assert!(range.contains(&field.as_bytes().as_ptr()));
+        }
+    }
 }
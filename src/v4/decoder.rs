@@ -1,19 +1,60 @@
 use super::fixed_header::{FixedHeader, FixedHeaderBuilder};
+use crate::common::coder;
+use crate::common::config::DecodeConfig;
 use crate::{error::ProtoError, MessageType, QoS};
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use std::slice::Iter;
 use tracing::warn;
 
+// 基础读写函数已经移动到common::coder，这里只为外部代码保留原来的路径
+
+#[deprecated(note = "请使用common::coder::read_u8")]
+pub fn read_u8(stream: &mut Bytes) -> Result<u8, ProtoError> {
+    coder::read_u8(stream)
+}
+
+#[deprecated(note = "请使用common::coder::read_u16")]
+pub fn read_u16(stream: &mut Bytes) -> Result<u16, ProtoError> {
+    coder::read_u16(stream)
+}
+
+#[deprecated(note = "请使用common::coder::read_u32")]
+pub fn read_u32(stream: &mut Bytes) -> Result<u32, ProtoError> {
+    coder::read_u32(stream)
+}
+
+#[deprecated(note = "请使用common::coder::read_mqtt_bytes")]
+pub fn read_mqtt_bytes(stream: &mut Bytes) -> Result<Bytes, ProtoError> {
+    coder::read_mqtt_bytes(stream)
+}
+
+#[deprecated(note = "请使用common::coder::read_mqtt_string")]
+pub fn read_mqtt_string(stream: &mut Bytes) -> Result<String, ProtoError> {
+    coder::read_mqtt_string(stream)
+}
+
+#[deprecated(note = "请使用common::coder::write_mqtt_bytes")]
+pub fn write_mqtt_bytes(stream: &mut BytesMut, bytes: &[u8]) -> Result<usize, ProtoError> {
+    coder::write_mqtt_bytes(stream, bytes)
+}
+
+#[deprecated(note = "请使用common::coder::write_mqtt_string")]
+pub fn write_mqtt_string(stream: &mut BytesMut, string: &str) -> Result<usize, ProtoError> {
+    coder::write_mqtt_string(stream, string)
+}
+
 /// 从Bytes中读取固定报头
 pub fn read_fixed_header(stream: &mut Bytes) -> Result<FixedHeader, ProtoError> {
     // 由于fixed_header的长度在2-5个字节之间，所以stream_len的长度必须要大与等于2
     let stream_len = stream.len();
-    if stream_len < 2 && stream_len > 5 {
+    if stream_len < 2 {
         return Err(ProtoError::FixedHeaderLengthError(stream_len));
     }
     let mut iter = stream.iter();
     // 拿到首字节byte1
-    let byte1 = iter.next().unwrap();
+    let byte1 = iter
+        .next()
+        .ok_or(ProtoError::FixedHeaderLengthError(stream_len))?;
     // 确定fixed_header的类型
     let resp = check_fixed_header_type(byte1);
     match resp {
@@ -29,13 +70,46 @@ pub fn read_fixed_header(stream: &mut Bytes) -> Result<FixedHeader, ProtoError>
     }
 }
 
+/// 按照config读取固定报头，strict_dup_flag为false时忽略QoS为0的PUBLISH报文中的dup标志
+pub fn read_fixed_header_with_config(
+    stream: &mut Bytes,
+    config: &DecodeConfig,
+) -> Result<FixedHeader, ProtoError> {
+    match read_fixed_header(stream) {
+        Err(ProtoError::DupOnQoS0) if !config.strict_dup_flag => {
+            // fixed_header最多5个字节，清除byte1中的dup位之后重新解析
+            let mut header = BytesMut::from(&stream[..stream.len().min(5)]);
+            header[0] &= !0b0000_1000;
+            read_fixed_header(&mut header.freeze())
+        }
+        resp => resp,
+    }
+}
+
+/// 跳过bytes开头的fixed_header，返回剩余长度范围内的报文内容(与bytes共享同一块内存)，
+/// 报文不完整时返回[`ProtoError::Incomplete`]
+pub(crate) fn take_body(mut bytes: Bytes, fixed_header: &FixedHeader) -> Result<Bytes, ProtoError> {
+    bytes.advance(fixed_header.len());
+    let remaining_length = fixed_header.remaining_length();
+    if bytes.len() < remaining_length {
+        return Err(ProtoError::Incomplete {
+            needed: remaining_length - bytes.len(),
+        });
+    }
+    // 对于由Vec转换而来的Bytes，truncate会触发一次内存分配，所以只在确实有多余数据时才截断
+    if bytes.len() > remaining_length {
+        bytes.truncate(remaining_length);
+    }
+    Ok(bytes)
+}
+
 pub fn parse_fixed_header(mut stream: Iter<u8>) -> Result<FixedHeader, ProtoError> {
     let stream_len = stream.len();
     if stream_len < 2 {
         return Err(ProtoError::NotKnow);
     }
     // 拿到首字节byte1
-    let byte1 = stream.next().unwrap();
+    let byte1 = stream.next().ok_or(ProtoError::NotKnow)?;
     // 确定fixed_header的类型
     let resp = check_fixed_header_type(byte1);
     match resp {
@@ -57,23 +131,7 @@ pub fn parse_fixed_header(mut stream: Iter<u8>) -> Result<FixedHeader, ProtoErro
 
 /// 根据首字节校验fixed_header的类型
 pub fn check_fixed_header_type(byte1: &u8) -> Result<MessageType, ProtoError> {
-    match byte1 >> 4 {
-        1 => Ok(MessageType::CONNECT),
-        2 => Ok(MessageType::CONNACK),
-        3 => Ok(MessageType::PUBLISH),
-        4 => Ok(MessageType::PUBACK),
-        5 => Ok(MessageType::PUBREC),
-        6 => Ok(MessageType::PUBREL),
-        7 => Ok(MessageType::PUBCOMP),
-        8 => Ok(MessageType::SUBSCRIBE),
-        9 => Ok(MessageType::SUBACK),
-        10 => Ok(MessageType::UNSUBSCRIBE),
-        11 => Ok(MessageType::UNSUBACK),
-        12 => Ok(MessageType::PINGREQ),
-        13 => Ok(MessageType::PINGRESP),
-        14 => Ok(MessageType::DISCONNECT),
-        _ => Err(ProtoError::NotKnow),
-    }
+    Ok(MessageType::try_from(byte1 >> 4)?)
 }
 /// 获取fixed_header的其他值：dup、qos、retain，不包括剩余长度
 pub fn check_fixed_header_options(
@@ -84,7 +142,7 @@ pub fn check_fixed_header_options(
     let mut qos: Option<QoS> = None;
     let mut retain: Option<bool> = Some(false);
     // 根据message_type创建制定的fixed_header_budiler
-    let fixed_header_builder = FixedHeaderBuilder::from_message_type(message_type.clone());
+    let fixed_header_builder = FixedHeaderBuilder::from_message_type(message_type);
     // 获取低4位数
     let low_4 = byte1 & 0b0000_1111;
     match message_type {
@@ -102,6 +160,10 @@ pub fn check_fixed_header_options(
                 2 => qos = Some(QoS::ExactlyOnce),
                 x => return Err(ProtoError::QoSError(x)),
             }
+            // QoS为0的PUBLISH报文dup必须为0
+            if dup == Some(true) && qos == Some(QoS::AtMostOnce) {
+                return Err(ProtoError::DupOnQoS0);
+            }
             //处理b0位数据，这里决定了retain标志
             match low_4 & 0b0000_0001 {
                 0 => retain = Some(false),
@@ -138,13 +200,14 @@ pub fn check_fixed_header_options(
                 .retain(retain)
                 .build()
         }
-        _ => match low_4 & 0b0000_1111 {
+        // CONNECT、CONNACK、PUBACK、PUBREC、PUBCOMP、SUBACK、UNSUBACK、PINGREQ、PINGRESP、DISCONNECT的标志位必须为0
+        _ => match low_4 {
             0 => fixed_header_builder
                 .dup(dup)
                 .qos(qos)
                 .retain(retain)
                 .build(),
-            _ => Err(ProtoError::NotKnow),
+            flags => Err(ProtoError::InvalidFixedHeaderFlags(message_type, flags)),
         },
     }
 }
@@ -180,43 +243,79 @@ pub fn check_remain_length(
     Ok(fixed_header)
 }
 
-///读取数据到bytes
-pub fn read_mqtt_bytes(stream: &mut Bytes) -> Result<Bytes, ProtoError> {
-    let len = read_u16(stream)? as usize;
-    if len > stream.len() {
-        return Err(ProtoError::NotKnow);
-    }
-    Ok(stream.split_to(len))
-}
-///读取数据到字符串
-pub fn read_mqtt_string(stream: &mut Bytes) -> Result<String, ProtoError> {
-    let s = read_mqtt_bytes(stream)?;
-    match String::from_utf8(s.to_vec()) {
-        Ok(v) => Ok(v),
-        Err(_e) => Err(ProtoError::NotKnow),
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::read_fixed_header;
+    use crate::error::ProtoError;
+    use crate::MessageType;
+    use bytes::Bytes;
 
-pub fn read_u16(stream: &mut Bytes) -> Result<u16, ProtoError> {
-    if stream.len() < 2 {
-        return Err(ProtoError::NotKnow);
+    #[test]
+    fn nonzero_flags_should_return_error() {
+        let cases = [
+            (0x21, MessageType::CONNACK),
+            (0x42, MessageType::PUBACK),
+            (0x54, MessageType::PUBREC),
+            (0x78, MessageType::PUBCOMP),
+            (0x91, MessageType::SUBACK),
+            (0xb2, MessageType::UNSUBACK),
+            (0xe4, MessageType::DISCONNECT),
+        ];
+        for (byte1, message_type) in cases {
+            let mut bytes = Bytes::from(vec![byte1, 0x02, 0x00, 0x01]);
+            assert_eq!(
+                read_fixed_header(&mut bytes).unwrap_err(),
+                ProtoError::InvalidFixedHeaderFlags(message_type, byte1 & 0x0f)
+            );
+        }
     }
-    Ok(stream.get_u16())
-}
 
-pub fn read_u8(stream: &mut Bytes) -> Result<u8, ProtoError> {
-    if stream.is_empty() {
-        return Err(ProtoError::NotKnow);
+    #[test]
+    fn dup_on_qos0_should_return_error() {
+        // 0x39在0x38的基础上设置了retain
+        for byte1 in [0x38, 0x39] {
+            let mut bytes = Bytes::from(vec![byte1, 0x03, 0x00, 0x01, b'a']);
+            assert_eq!(
+                read_fixed_header(&mut bytes).unwrap_err(),
+                ProtoError::DupOnQoS0
+            );
+        }
+        // dup为1，QoS为1的报文是合法的
+        let mut bytes = Bytes::from_static(&[0x3a, 0x05, 0x00, 0x01, b'a', 0x00, 0x01]);
+        assert!(read_fixed_header(&mut bytes).unwrap().dup().unwrap());
     }
-    Ok(stream.get_u8())
-}
 
-pub fn write_mqtt_bytes(stream: &mut BytesMut, bytes: &[u8]) {
-    stream.put_u16(bytes.len() as u16);
-    stream.extend_from_slice(bytes);
-}
+    #[test]
+    fn short_stream_should_return_length_error() {
+        for frame in [&[][..], &[0x30][..]] {
+            let mut bytes = Bytes::copy_from_slice(frame);
+            assert_eq!(
+                read_fixed_header(&mut bytes).unwrap_err(),
+                ProtoError::FixedHeaderLengthError(frame.len())
+            );
+        }
+        // 两个字节已经是一个完整的固定头
+        let mut bytes = Bytes::from_static(&[0xc0, 0x00]);
+        assert_eq!(
+            read_fixed_header(&mut bytes).unwrap().message_type(),
+            MessageType::PINGREQ
+        );
+    }
 
-/// Serializes a string to stream
-pub fn write_mqtt_string(stream: &mut BytesMut, string: &str) {
-    write_mqtt_bytes(stream, string.as_bytes());
+    // 原来的路径只为外部代码保留，行为与common::coder一致
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_coder_paths_should_still_work() {
+        let mut buffer = bytes::BytesMut::new();
+        assert_eq!(super::write_mqtt_string(&mut buffer, "/a"), Ok(4));
+        assert_eq!(super::write_mqtt_bytes(&mut buffer, b"xy"), Ok(4));
+        buffer.extend_from_slice(&[0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03]);
+        let mut bytes = buffer.freeze();
+        assert_eq!(super::read_mqtt_string(&mut bytes).unwrap(), "/a");
+        assert_eq!(super::read_mqtt_bytes(&mut bytes).unwrap().as_ref(), b"xy");
+        assert_eq!(super::read_u8(&mut bytes), Ok(1));
+        assert_eq!(super::read_u16(&mut bytes), Ok(2));
+        assert_eq!(super::read_u32(&mut bytes), Ok(3));
+        assert!(bytes.is_empty());
+    }
 }
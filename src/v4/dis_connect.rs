@@ -1,6 +1,8 @@
-use super::{decoder, Decoder, Encoder};
+use super::builder::{DisconnectBuilder, MqttMessageBuilder};
+use super::{decoder, BodyDecoder, Decoder, Encoder, PacketEncoder};
 use crate::error::ProtoError;
 use crate::v4::fixed_header::FixedHeader;
+use crate::MessageType;
 use bytes::{Bytes, BytesMut};
 
 /// 断开连接报文
@@ -10,11 +12,32 @@ use bytes::{Bytes, BytesMut};
 /// | byte1 | 1   | 1   | 1   | 0   | 0   | 0   | 0   | 0   |
 /// | byte2 | 0   | 0   | 0   | 0   | 0   | 0   | 0   | 0   |
 ///
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisConnect {
     fixed_header: FixedHeader,
 }
+/// 与`DisConnect::builder().build()`得到的报文相同
+impl Default for DisConnect {
+    fn default() -> Self {
+        Self::new(FixedHeader::empty(MessageType::DISCONNECT))
+    }
+}
+
 impl DisConnect {
+    /**
+     DISCONNECT报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::v4::dis_connect::DisConnect;
+    let disconnect = DisConnect::builder().build().unwrap();
+    assert_eq!(disconnect, DisConnect::default());
+    ```
+     */
+    pub fn builder() -> DisconnectBuilder {
+        MqttMessageBuilder::disconnect()
+    }
+
     pub fn new(fixed_header: FixedHeader) -> Self {
         Self { fixed_header }
     }
@@ -26,14 +49,57 @@ impl Encoder for DisConnect {
     }
 }
 
+impl PacketEncoder for DisConnect {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len()
+    }
+}
+
 impl Decoder for DisConnect {
     type Item = DisConnect;
     type Error = ProtoError;
     fn decode(mut bytes: Bytes) -> Result<Self::Item, ProtoError> {
-        let resp = decoder::read_fixed_header(&mut bytes);
-        match resp {
-            Ok(fixed_header) => Ok(DisConnect::new(fixed_header)),
-            Err(e) => Err(e),
+        let fixed_header = decoder::read_fixed_header(&mut bytes)?;
+        if fixed_header.message_type() != MessageType::DISCONNECT {
+            return Err(ProtoError::NotKnow);
         }
+        // 剩余长度必须为0
+        if fixed_header.remaining_length() != 0 {
+            return Err(ProtoError::UnexpectedRemainingLength(
+                fixed_header.remaining_length(),
+            ));
+        }
+        Ok(DisConnect::new(fixed_header))
+    }
+}
+
+impl BodyDecoder for DisConnect {
+    fn decode_body(fixed_header: FixedHeader, body: Bytes) -> Result<Self, ProtoError> {
+        if !body.is_empty() {
+            return Err(ProtoError::UnexpectedRemainingLength(body.len()));
+        }
+        Ok(DisConnect::new(fixed_header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisConnect;
+    use crate::error::ProtoError;
+    use crate::v4::Decoder;
+    use crate::MessageType;
+    use bytes::Bytes;
+
+    #[test]
+    fn bad_disconnect_frame_should_return_error() {
+        assert!(DisConnect::decode(Bytes::from_static(&[0xe0, 0x00])).is_ok());
+        assert_eq!(
+            DisConnect::decode(Bytes::from_static(&[0xe2, 0x00])).unwrap_err(),
+            ProtoError::InvalidFixedHeaderFlags(MessageType::DISCONNECT, 0x02)
+        );
+        assert_eq!(
+            DisConnect::decode(Bytes::from_static(&[0xe0, 0x01, 0x00])).unwrap_err(),
+            ProtoError::UnexpectedRemainingLength(1)
+        );
     }
 }
@@ -1,9 +1,7 @@
-use super::{
-    publish::{FOUR_BYTE_MAX_LEN, ONE_BYTE_MAX_LEN, THREE_BYTE_MAX_LEN, TWO_BYTE_MAX_LEN},
-    Encoder,
-};
-use crate::{error::ProtoError, MessageType, QoS};
+use super::Encoder;
+use crate::common::var_int::VarInt;
 use crate::error::BuildError;
+use crate::{error::ProtoError, MessageType, QoS};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use tracing::debug;
 
@@ -18,7 +16,8 @@ byte 1   | MQTT Control Packet Type | Flags for each type      |
          +-----------------------------------------------------+
 ```
 */
-#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FixedHeader {
     // 消息类型
     message_type: MessageType,
@@ -52,9 +51,13 @@ impl FixedHeader {
             fixed_handler_len,
         }
     }
+    /// 没有可变报头和payload的报文使用的固定报头，例如PINGREQ
+    pub(crate) fn empty(message_type: MessageType) -> Self {
+        Self::new(message_type, Some(false), None, Some(false), 0, 2)
+    }
     // message_type
     pub fn message_type(&self) -> MessageType {
-        self.message_type.clone()
+        self.message_type
     }
     // dup
     pub fn dup(&self) -> Option<bool> {
@@ -72,10 +75,15 @@ impl FixedHeader {
     pub fn remaining_length(&self) -> usize {
         self.remaining_length
     }
+    // 设置剩余长度，同时更新fixed_header本身的长度
     pub fn set_remaining_length(&mut self, remaining_length: usize) {
         self.remaining_length = remaining_length;
+        if let Ok(len) = remaining_length_len(remaining_length) {
+            self.fixed_handler_len = len + 1;
+        }
     }
     // 返回fixed_header的长度
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.fixed_handler_len
     }
@@ -86,6 +94,37 @@ impl FixedHeader {
     pub fn set_qos(&mut self, qos: QoS) {
         self.qos = Some(qos)
     }
+
+    pub fn set_dup(&mut self, dup: bool) {
+        self.dup = Some(dup)
+    }
+
+    pub fn set_retain(&mut self, retain: bool) {
+        self.retain = Some(retain)
+    }
+
+    /// 严格校验puback、pubrec、pubrel、pubcomp报文的固定报头：
+    /// - 报文类型必须与message_type一致
+    /// - dup、retain必须为0，PUBREL的QoS位固定为01，其余报文的QoS位必须为00
+    /// - 剩余长度必须为2
+    pub(crate) fn validate_ack(&self, message_type: MessageType) -> Result<(), ProtoError> {
+        if self.message_type != message_type {
+            return Err(ProtoError::MessageTypeMismatch);
+        }
+        if self.dup.unwrap_or(false) {
+            return Err(ProtoError::DupValueError(1));
+        }
+        if self.retain.unwrap_or(false) {
+            return Err(ProtoError::RetainValueError(1));
+        }
+        if let Some(qos) = self.qos {
+            return Err(ProtoError::QoSError(qos as u8));
+        }
+        if self.remaining_length != 2 {
+            return Err(ProtoError::UnexpectedRemainingLength(self.remaining_length));
+        }
+        Ok(())
+    }
     // 根据mqtt报文首字节校验fixed_header是否正确,check方法执行之后byte的首字节去掉了
     pub fn check(byte1: &mut Bytes) -> Result<MessageType, BuildError> {
         let b = byte1.get_u8();
@@ -94,23 +133,7 @@ impl FixedHeader {
 
     // 根据mqtt报文首字节校验fixed_header是否正确,check方法执行之后byte的首字节去掉了
     pub fn check_with_u8(byte1: u8) -> Result<MessageType, BuildError> {
-        match byte1 >> 4 {
-            1 => Ok(MessageType::CONNECT),
-            2 => Ok(MessageType::CONNACK),
-            3 => Ok(MessageType::PUBLISH),
-            4 => Ok(MessageType::PUBACK),
-            5 => Ok(MessageType::PUBREC),
-            6 => Ok(MessageType::PUBREL),
-            7 => Ok(MessageType::PUBCOMP),
-            8 => Ok(MessageType::SUBSCRIBE),
-            9 => Ok(MessageType::SUBACK),
-            10 => Ok(MessageType::UNSUBSCRIBE),
-            11 => Ok(MessageType::UNSUBACK),
-            12 => Ok(MessageType::PINGREQ),
-            13 => Ok(MessageType::PINGRESP),
-            14 => Ok(MessageType::DISCONNECT),
-            n => Err(BuildError::MessageTypeError(n as usize)),
-        }
+        MessageType::try_from(byte1 >> 4)
     }
 }
 
@@ -134,6 +157,7 @@ impl Encoder for FixedHeader {
             MessageType::DISCONNECT => disconnect_fixed_header_encode(self, buffer),
             MessageType::PINGREQ => pingreq_fixed_header_encode(self, buffer),
             MessageType::PINGRESP => pingresp_fixed_header_encode(self, buffer),
+            MessageType::AUTH => auth_fixed_header_encode(self, buffer),
         }
     }
 }
@@ -143,26 +167,9 @@ fn connect_fixed_header_encode(
     fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    buffer.put_u8(0b0001_0000);
-    if fixed_header.remaining_length() > 268_435_455 {
-        return Err(ProtoError::OutOfMaxRemainingLength(
-            fixed_header.remaining_length,
-        ));
-    }
-    let mut done = false;
-    let mut x = fixed_header.remaining_length();
-    let mut count = 0;
-    while !done {
-        let mut byte = (x % 128) as u8;
-        x /= 128;
-        if x > 0 {
-            byte |= 128;
-        }
-        buffer.put_u8(byte);
-        count += 1;
-        done = x == 0;
-    }
-    Ok(count)
+    buffer.put_u8(u8::from(MessageType::CONNECT) << 4);
+    let remaining_length_len = encode_remaining_len(fixed_header.remaining_length(), buffer)?;
+    Ok(1 + remaining_length_len)
 }
 /// 对connack报文中固定头的编码
 fn connack_fixed_header_encode(
@@ -170,7 +177,7 @@ fn connack_fixed_header_encode(
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
     // fixed_header 的第一个字节
-    buffer.put_u8(0b0010_0000);
+    buffer.put_u8(u8::from(MessageType::CONNACK) << 4);
     // connAck报文的剩余长度是2个字节
     buffer.put_u8(0b0000_0010);
     Ok(2)
@@ -181,7 +188,7 @@ fn pingreq_fixed_header_encode(
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
     // fixed_header 的第一个字节
-    buffer.put_u8(0b1100_0000);
+    buffer.put_u8(u8::from(MessageType::PINGREQ) << 4);
     // connAck报文的剩余长度是2个字节
     buffer.put_u8(0b0000_0000);
     Ok(2)
@@ -191,7 +198,7 @@ fn pingresp_fixed_header_encode(
     _fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    buffer.put_u8(0b1101_0000);
+    buffer.put_u8(u8::from(MessageType::PINGRESP) << 4);
     buffer.put_u8(0b0000_0000);
     Ok(2)
 }
@@ -203,20 +210,15 @@ fn publish_fixed_header_encode(
 ) -> Result<usize, ProtoError> {
     let mut resp: usize = 0;
     // 写入byte1
-    let mut byte1: u8 = 0b0000_0000;
-    let qos = fixed_header.qos().unwrap();
-    match qos {
-        QoS::AtMostOnce => byte1 = 0b0011_0000,
-        QoS::AtLeastOnce => byte1 = 0b0011_0000 | 0b0000_0010,
-        QoS::ExactlyOnce => byte1 = 0b0011_0000 | 0b0000_0100,
-    }
-    let dup = fixed_header.dup().unwrap();
-    if dup == true {
-        byte1 = byte1 | 0b0000_1000;
+    let qos = fixed_header.qos().unwrap_or(QoS::AtMostOnce);
+    let mut byte1: u8 = u8::from(MessageType::PUBLISH) << 4 | (qos as u8) << 1;
+    let dup = fixed_header.dup().unwrap_or(false);
+    if dup {
+        byte1 |= 0b0000_1000;
     }
-    let retain = fixed_header.retain().unwrap();
-    if retain == true {
-        byte1 = byte1 | 0b0000_0001;
+    let retain = fixed_header.retain().unwrap_or(false);
+    if retain {
+        byte1 |= 0b0000_0001;
     }
     buffer.put_u8(byte1);
     resp += 1;
@@ -230,47 +232,55 @@ fn publish_fixed_header_encode(
 }
 /// 对puback报文中固定头的编码
 fn puback_fixed_header_encode(
-    _fixed_header: &FixedHeader,
+    fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    // fixed_header 的第一个字节
-    buffer.put_u8(0b0100_0000);
-    // connAck报文的剩余长度是2个字节
-    buffer.put_u8(0b0000_0010);
-    Ok(2)
+    ack_fixed_header_encode(u8::from(MessageType::PUBACK) << 4, fixed_header, buffer)
 }
 /// 对pubrec报文中固定头的编码
 fn pubrec_fixed_header_encode(
-    _fixed_header: &FixedHeader,
+    fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    // fixed_header 的第一个字节
-    buffer.put_u8(0b0101_0000);
-    // connAck报文的剩余长度是2个字节
-    buffer.put_u8(0b0000_0010);
-    Ok(2)
+    ack_fixed_header_encode(u8::from(MessageType::PUBREC) << 4, fixed_header, buffer)
 }
 /// 对pubrel报文中固定头的编码
 fn pubrel_fixed_header_encode(
-    _fixed_header: &FixedHeader,
+    fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    // fixed_header 的第一个字节
-    buffer.put_u8(0b0110_0000);
-    // connAck报文的剩余长度是2个字节
-    buffer.put_u8(0b0000_0010);
-    Ok(2)
+    ack_fixed_header_encode(
+        u8::from(MessageType::PUBREL) << 4 | 0b0010,
+        fixed_header,
+        buffer,
+    )
 }
 /// 对pubcomp报文中固定头的编码
 fn pubcomp_fixed_header_encode(
-    _fixed_header: &FixedHeader,
+    fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    // fixed_header 的第一个字节
-    buffer.put_u8(0b0111_0000);
-    // connAck报文的剩余长度是2个字节
-    buffer.put_u8(0b0000_0010);
-    Ok(2)
+    ack_fixed_header_encode(u8::from(MessageType::PUBCOMP) << 4, fixed_header, buffer)
+}
+/// 对puback、pubrec、pubrel、pubcomp报文中固定头的编码
+/// 直接使用fixed_header中的标志位和剩余长度，保证解码之后再编码的报文与原始报文一致
+fn ack_fixed_header_encode(
+    mut byte1: u8,
+    fixed_header: &FixedHeader,
+    buffer: &mut BytesMut,
+) -> Result<usize, ProtoError> {
+    if fixed_header.dup().unwrap_or(false) {
+        byte1 |= 0b0000_1000;
+    }
+    if let Some(qos) = fixed_header.qos() {
+        byte1 |= (qos as u8) << 1;
+    }
+    if fixed_header.retain().unwrap_or(false) {
+        byte1 |= 0b0000_0001;
+    }
+    buffer.put_u8(byte1);
+    let remaining_length_len = encode_remaining_len(fixed_header.remaining_length(), buffer)?;
+    Ok(1 + remaining_length_len)
 }
 /// 对subscribe报文中固定头的编码
 fn subscribe_fixed_header_encode(
@@ -279,7 +289,7 @@ fn subscribe_fixed_header_encode(
 ) -> Result<usize, ProtoError> {
     let mut resp: usize = 0;
     // 写入byte1
-    let byte1: u8 = 0b1000_0010;
+    let byte1: u8 = u8::from(MessageType::SUBSCRIBE) << 4 | 0b0010;
     buffer.put_u8(byte1);
     resp += 1;
     // 写入剩余长度
@@ -291,21 +301,23 @@ fn subscribe_fixed_header_encode(
     }
 }
 /// 对suback报文中固定头的编码
+///
+/// 剩余长度为2 + 返回码的数量，不能固定为3
 fn suback_fixed_header_encode(
-    _fixed_header: &FixedHeader,
+    fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
     // fixed_header 的第一个字节
-    buffer.put_u8(0b1001_0000);
-    buffer.put_u8(0b0000_0011);
-    Ok(2)
+    buffer.put_u8(u8::from(MessageType::SUBACK) << 4);
+    let size = encode_remaining_len(fixed_header.remaining_length(), buffer)?;
+    Ok(1 + size)
 }
 /// 对unsubscribe报文中固定头的编码,
 fn unsubscribe_fixed_header_encode(
     fixed_header: &FixedHeader,
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
-    buffer.put_u8(0b1010_0010);
+    buffer.put_u8(u8::from(MessageType::UNSUBSCRIBE) << 4 | 0b0010);
     let remaining_length = fixed_header.remaining_length();
     let encode_resp = encode_remaining_len(remaining_length, buffer);
     match encode_resp {
@@ -319,7 +331,7 @@ fn unsuback_fixed_header_encode(
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
     // fixed_header 的第一个字节
-    buffer.put_u8(0b1011_0000);
+    buffer.put_u8(u8::from(MessageType::UNSUBACK) << 4);
     let remaining_length = fixed_header.remaining_length();
     let encode_resp = encode_remaining_len(remaining_length, buffer);
     match encode_resp {
@@ -333,11 +345,20 @@ fn disconnect_fixed_header_encode(
     buffer: &mut BytesMut,
 ) -> Result<usize, ProtoError> {
     // fixed_header 的第一个字节
-    buffer.put_u8(0b1110_0000);
+    buffer.put_u8(u8::from(MessageType::DISCONNECT) << 4);
     // connAck报文的剩余长度是2个字节
     buffer.put_u8(0b0000_0000);
     Ok(2)
 }
+/// 对auth报文中固定头的编码，AUTH报文只存在于MQTT 5.0中
+fn auth_fixed_header_encode(
+    fixed_header: &FixedHeader,
+    buffer: &mut BytesMut,
+) -> Result<usize, ProtoError> {
+    buffer.put_u8(u8::from(MessageType::AUTH) << 4);
+    let remaining_length_len = encode_remaining_len(fixed_header.remaining_length(), buffer)?;
+    Ok(1 + remaining_length_len)
+}
 
 //////////////////////////////////////////////////////
 /// 固定报头构造器
@@ -355,6 +376,12 @@ pub struct FixedHeaderBuilder {
     remaining_length: usize,
 }
 
+impl Default for FixedHeaderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FixedHeaderBuilder {
     pub fn new() -> Self {
         Self {
@@ -414,31 +441,35 @@ impl FixedHeaderBuilder {
     // 构建pub_ack报文
     pub fn pub_ack(mut self) -> Self {
         self.message_type = MessageType::PUBACK;
+        self.remaining_length = 2;
         self
     }
 
     // 构建pub_rec报文
     pub fn pub_rec(mut self) -> Self {
         self.message_type = MessageType::PUBREC;
+        self.remaining_length = 2;
         self
     }
 
     // 构建pub_rel报文
     pub fn pub_rel(mut self) -> Self {
         self.message_type = MessageType::PUBREL;
+        self.remaining_length = 2;
         self
     }
 
     // 构建pub_comp报文
     pub fn pub_comp(mut self) -> Self {
         self.message_type = MessageType::PUBCOMP;
+        self.remaining_length = 2;
         self
     }
 
     // 构建subscribe报文
     pub fn subscribe(mut self) -> Self {
+        // 标志位固定为0b0010，与解码时一样不记录qos
         self.message_type = MessageType::SUBSCRIBE;
-        self.qos = Some(QoS::AtLeastOnce);
         self
     }
     // 构建sub_ack报文
@@ -458,6 +489,12 @@ impl FixedHeaderBuilder {
         self.message_type = MessageType::UNSUBACK;
         self
     }
+
+    // 构建auth报文，只用于MQTT 5.0
+    pub fn auth(mut self) -> Self {
+        self.message_type = MessageType::AUTH;
+        self
+    }
 }
 
 //////////////////////////////////////////////////////
@@ -503,72 +540,21 @@ impl FixedHeaderBuilder {
 
 // 通过剩余长度计算出剩余长度的值所占的字节数
 fn remaining_length_len(remaining_length: usize) -> Result<usize, ProtoError> {
-    if remaining_length < ONE_BYTE_MAX_LEN {
-        Ok(1)
-    } else if remaining_length < TWO_BYTE_MAX_LEN {
-        Ok(2)
-    } else if remaining_length < THREE_BYTE_MAX_LEN {
-        Ok(3)
-    } else if remaining_length < FOUR_BYTE_MAX_LEN {
-        Ok(4)
-    } else {
-        Err(ProtoError::NotKnow)
-    }
+    Ok(VarInt::try_from(remaining_length)?.encoded_len())
 }
 
-//TODO 添加注释, 这里可能有问题
+// 写入剩余长度，返回写入的字节数
 fn encode_remaining_len(remaining_len: usize, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
     debug!("remaining_len = {}", remaining_len);
-    let mut resp: usize = 0;
-    // 1、判断remaining_len的范围
-    if remaining_len < ONE_BYTE_MAX_LEN {
-        buffer.put_u8(remaining_len as u8);
-        resp = 1;
-    } else if remaining_len < TWO_BYTE_MAX_LEN {
-        let byte2_data = remaining_len / 128;
-        let byte1 = remaining_len % 128;
-        let byte1 = byte1 + 128;
-        let byte2 = byte2_data % 128;
-        buffer.put_u8(byte1 as u8);
-        buffer.put_u8(byte2 as u8);
-        resp = 2;
-    } else if remaining_len < THREE_BYTE_MAX_LEN {
-        let byte2_data = remaining_len / 128;
-        let byte3_data = byte2_data / 128;
-        let byte1 = remaining_len % 128;
-        let byte1 = byte1 + 128;
-        let byte2 = byte2_data % 128;
-        let byte2 = byte2 + 128;
-        let byte3 = byte3_data % 128;
-        buffer.put_u8(byte1 as u8);
-        buffer.put_u8(byte2 as u8);
-        buffer.put_u8(byte3 as u8);
-        resp = 3;
-    } else if remaining_len < FOUR_BYTE_MAX_LEN {
-        let byte2_data = remaining_len / 128;
-        let byte3_data = byte2_data / 128;
-        let byte4_data = byte3_data / 128;
-        let byte1 = remaining_len % 128;
-        let byte1 = byte1 + 128;
-        let byte2 = byte2_data % 128;
-        let byte2 = byte2 + 128;
-        let byte3 = byte3_data % 128;
-        let byte3 = byte3 + 128;
-        let byte4 = byte4_data % 128;
-        buffer.put_u8(byte1 as u8);
-        buffer.put_u8(byte2 as u8);
-        buffer.put_u8(byte3 as u8);
-        buffer.put_u8(byte4 as u8);
-        resp = 4;
-    } else {
-        return Err(ProtoError::OutOfMaxRemainingLength(remaining_len));
-    }
-    Ok(resp)
+    Ok(VarInt::try_from(remaining_len)?.encode(buffer))
 }
 
 #[cfg(test)]
 mod tests {
     use super::FixedHeaderBuilder;
+    use crate::common::var_int::VarInt;
+    use crate::v4::{decoder::read_fixed_header, Encoder};
+    use bytes::BytesMut;
     use tracing::info;
 
     #[test]
@@ -582,4 +568,30 @@ mod tests {
             .build();
         info!("fixed_header = {:?}", fixed_header);
     }
+
+    #[test]
+    fn remaining_length_boundary_should_be_work() {
+        for remaining_length in [127, 128, 16_383, 16_384, 2_097_151, 268_435_455] {
+            let fixed_header = FixedHeaderBuilder::new()
+                .publish()
+                .qos(Some(crate::QoS::AtMostOnce))
+                .dup(Some(false))
+                .retain(Some(false))
+                .remaining_length(remaining_length)
+                .build()
+                .unwrap();
+            let mut buffer = BytesMut::new();
+            let len = fixed_header.encode(&mut buffer).unwrap();
+            assert_eq!(len, buffer.len());
+            assert_eq!(len, fixed_header.len());
+            let mut expected = BytesMut::new();
+            VarInt::try_from(remaining_length)
+                .unwrap()
+                .encode(&mut expected);
+            assert_eq!(&buffer[1..], expected.as_ref());
+            let decoded = read_fixed_header(&mut buffer.freeze()).unwrap();
+            assert_eq!(decoded.remaining_length(), remaining_length);
+            assert_eq!(decoded.len(), len);
+        }
+    }
 }
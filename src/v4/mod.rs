@@ -1,3 +1,7 @@
+#[cfg(feature = "tokio")]
+pub mod async_frame;
+#[cfg(feature = "tokio")]
+pub use async_frame::AsyncEncoder;
 pub mod builder;
 pub mod conn_ack;
 pub mod connect;
@@ -19,6 +23,7 @@ pub mod un_subscribe;
 use self::conn_ack::ConnAck;
 use self::connect::Connect;
 use self::dis_connect::DisConnect;
+use self::fixed_header::FixedHeader;
 use self::ping_req::PingReq;
 use self::ping_resp::PingResp;
 use self::pub_ack::PubAck;
@@ -30,14 +35,21 @@ use self::sub_ack::SubAck;
 use self::subscribe::Subscribe;
 use self::un_suback::UnSubAck;
 use self::un_subscribe::UnSubscribe;
-use crate::error::ProtoError;
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crate::common::coder;
+use crate::common::config::DecodeConfig;
+use crate::common::packet_id::PacketId;
+use crate::common::topic::validate_topic_name;
+use crate::error::{BuildError, ProtoError};
+use crate::MessageType;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
 
 use crate::QoS;
 use anyhow::Result;
 
 /// MQTT报文，包含了MQTT-v3.1.1版本中的所有MQTT报文
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Packet {
     // 连接报文
     Connect(Connect),
@@ -68,11 +80,267 @@ pub enum Packet {
     DisConnect(DisConnect),
 }
 
+impl Packet {
+    /// 报文类型
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Packet::Connect(_) => MessageType::CONNECT,
+            Packet::ConnAck(_) => MessageType::CONNACK,
+            Packet::Publish(_) => MessageType::PUBLISH,
+            Packet::PubAck(_) => MessageType::PUBACK,
+            Packet::PubRel(_) => MessageType::PUBREL,
+            Packet::PubRec(_) => MessageType::PUBREC,
+            Packet::PubComp(_) => MessageType::PUBCOMP,
+            Packet::PingReq(_) => MessageType::PINGREQ,
+            Packet::PingResp(_) => MessageType::PINGRESP,
+            Packet::Subscribe(_) => MessageType::SUBSCRIBE,
+            Packet::SubAck(_) => MessageType::SUBACK,
+            Packet::UnSubscribe(_) => MessageType::UNSUBSCRIBE,
+            Packet::UnSubAck(_) => MessageType::UNSUBACK,
+            Packet::DisConnect(_) => MessageType::DISCONNECT,
+        }
+    }
+
+    /// 报文编码之后的总长度
+    pub fn serialized_len(&self) -> usize {
+        match self {
+            Packet::Connect(packet) => packet.serialized_len(),
+            Packet::ConnAck(packet) => packet.serialized_len(),
+            Packet::Publish(packet) => packet.serialized_len(),
+            Packet::PubAck(packet) => packet.serialized_len(),
+            Packet::PubRel(packet) => packet.serialized_len(),
+            Packet::PubRec(packet) => packet.serialized_len(),
+            Packet::PubComp(packet) => packet.serialized_len(),
+            Packet::PingReq(packet) => packet.serialized_len(),
+            Packet::PingResp(packet) => packet.serialized_len(),
+            Packet::Subscribe(packet) => packet.serialized_len(),
+            Packet::SubAck(packet) => packet.serialized_len(),
+            Packet::UnSubscribe(packet) => packet.serialized_len(),
+            Packet::UnSubAck(packet) => packet.serialized_len(),
+            Packet::DisConnect(packet) => packet.serialized_len(),
+        }
+    }
+
+    /// 将报文编码到一个预先分配好容量的BytesMut中
+    pub fn encode_to_bytes(&self) -> Result<BytesMut, ProtoError> {
+        match self {
+            Packet::Connect(packet) => packet.encode_with_hint(),
+            Packet::ConnAck(packet) => packet.encode_with_hint(),
+            Packet::Publish(packet) => packet.encode_with_hint(),
+            Packet::PubAck(packet) => packet.encode_with_hint(),
+            Packet::PubRel(packet) => packet.encode_with_hint(),
+            Packet::PubRec(packet) => packet.encode_with_hint(),
+            Packet::PubComp(packet) => packet.encode_with_hint(),
+            Packet::PingReq(packet) => packet.encode_with_hint(),
+            Packet::PingResp(packet) => packet.encode_with_hint(),
+            Packet::Subscribe(packet) => packet.encode_with_hint(),
+            Packet::SubAck(packet) => packet.encode_with_hint(),
+            Packet::UnSubscribe(packet) => packet.encode_with_hint(),
+            Packet::UnSubAck(packet) => packet.encode_with_hint(),
+            Packet::DisConnect(packet) => packet.encode_with_hint(),
+        }
+    }
+
+    /// 与`to_string()`相同，返回报文的单行摘要
+    pub fn summary(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// 输出单行的报文摘要，用于日志，例如`PUBLISH topic=/a qos=1 mid=42 retain=false payload=13B`，
+/// payload、will message以及password只输出长度或者是否存在，不会输出内容
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message_type())?;
+        match self {
+            Packet::Connect(connect) => write!(
+                f,
+                " client={} keep_alive={} clean={} will={}",
+                connect.client_id(),
+                connect.keep_alive(),
+                connect.clean_session(),
+                connect.has_will()
+            ),
+            Packet::ConnAck(conn_ack) => write!(f, " code={}", u8::from(conn_ack.return_code())),
+            Packet::Publish(publish) => {
+                write!(f, " topic={} qos={}", publish.topic(), publish.qos())?;
+                if let Some(message_id) = publish.variable_header_ref().message_id() {
+                    write!(f, " mid={}", message_id)?;
+                }
+                write!(
+                    f,
+                    " retain={} payload={}B",
+                    publish.retain(),
+                    publish.payload_ref().len()
+                )
+            }
+            Packet::PubAck(packet) => write!(f, " mid={}", packet.message_id()),
+            Packet::PubRel(packet) => write!(f, " mid={}", packet.message_id()),
+            Packet::PubRec(packet) => write!(f, " mid={}", packet.message_id()),
+            Packet::PubComp(packet) => write!(f, " mid={}", packet.message_id()),
+            Packet::UnSubAck(packet) => write!(f, " mid={}", packet.message_id()),
+            Packet::Subscribe(subscribe) => {
+                let filters: Vec<String> = subscribe
+                    .iter()
+                    .map(|(filter, qos)| format!("{}:{}", filter, qos))
+                    .collect();
+                write!(
+                    f,
+                    " mid={} filters=[{}]",
+                    subscribe.variable_header_ref().message_id(),
+                    filters.join(", ")
+                )
+            }
+            Packet::SubAck(sub_ack) => {
+                let codes: Vec<String> = sub_ack
+                    .acks_raw()
+                    .iter()
+                    .map(|code| code.to_string())
+                    .collect();
+                write!(
+                    f,
+                    " mid={} codes=[{}]",
+                    sub_ack.message_id(),
+                    codes.join(", ")
+                )
+            }
+            Packet::UnSubscribe(unsubscribe) => {
+                let filters: Vec<&str> = unsubscribe
+                    .topic_filters()
+                    .iter()
+                    .map(|filter| filter.path())
+                    .collect();
+                write!(
+                    f,
+                    " mid={} filters=[{}]",
+                    unsubscribe.message_id(),
+                    filters.join(", ")
+                )
+            }
+            Packet::PingReq(_) | Packet::PingResp(_) | Packet::DisConnect(_) => Ok(()),
+        }
+    }
+}
+
 /// 编码
 pub trait Encoder: Sync + Send + 'static {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError>;
 }
 
+/// 零拷贝解码：只解析一次fixed_header，之后将剩余的Bytes(与buf共享同一块内存)直接交给对应报文的解码器
+/// 报文不完整时返回[`ProtoError::Incomplete`]
+pub fn decode_zero_copy(mut buf: Bytes) -> Result<Packet, ProtoError> {
+    let fixed_header = decoder::read_fixed_header(&mut buf)?;
+    let body = decoder::take_body(buf, &fixed_header)?;
+    decode_packet_body(fixed_header, body)
+}
+
+/// 按照config解码：在解析报文内容之前，先根据fixed_header中的剩余长度检查报文的总长度，
+/// 这样不需要等待完整的报文到达就可以拒绝超长的报文。没有超长但是不完整的报文返回[`ProtoError::Incomplete`]
+pub fn decode_with_config(mut buf: Bytes, config: &DecodeConfig) -> Result<Packet, ProtoError> {
+    let fixed_header = decoder::read_fixed_header_with_config(&mut buf, config)?;
+    let packet_size = fixed_header.len() + fixed_header.remaining_length();
+    if let Some(limit) = config.max_packet_size {
+        if packet_size > limit {
+            return Err(ProtoError::PacketTooLarge {
+                actual: packet_size,
+                limit,
+            });
+        }
+    }
+    let body = decoder::take_body(buf, &fixed_header)?;
+    let packet = decode_packet_body(fixed_header, body)?;
+    if let Packet::Publish(publish) = &packet {
+        if config.strict_topic_name {
+            validate_topic_name(publish.topic())?;
+        }
+    }
+    Ok(packet)
+}
+
+/**
+ client检查broker回复的SUBACK是否与发送的SUBSCRIBE对应，返回每个topic filter被授予的QoS，
+ 顺序与SUBSCRIBE中的topic filter一致。任意一个topic filter订阅失败时返回[`ProtoError::SubscriptionFailed`]，
+ 其中为失败的topic filter的下标。
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v4::sub_ack::SubAck;
+use walle_mqtt_protocol::v4::verify_suback_for_subscribe;
+use walle_mqtt_protocol::QoS;
+let subscribe = MqttMessageBuilder::subscribe()
+    .packet_id(PacketId::MIN)
+    .add("/a", QoS::ExactlyOnce)
+    .build()
+    .unwrap();
+let sub_ack = SubAck::from_subscribe(&subscribe, &subscribe.grant(QoS::AtLeastOnce));
+assert_eq!(
+    verify_suback_for_subscribe(&subscribe, &sub_ack).unwrap(),
+    vec![QoS::AtLeastOnce]
+);
+```
+ */
+pub fn verify_suback_for_subscribe(
+    subscribe: &Subscribe,
+    sub_ack: &SubAck,
+) -> Result<Vec<QoS>, ProtoError> {
+    let expected = subscribe.variable_header_ref().message_id();
+    if sub_ack.message_id() != expected {
+        return Err(ProtoError::PacketIdMismatch {
+            expected,
+            got: sub_ack.message_id(),
+        });
+    }
+    if sub_ack.acks().len() != subscribe.topic_filters().len() {
+        return Err(ProtoError::AckCountMismatch);
+    }
+    sub_ack
+        .acks()
+        .iter()
+        .enumerate()
+        .map(|(index, ack)| ack.qos().ok_or(ProtoError::SubscriptionFailed(index)))
+        .collect()
+}
+
+// 根据fixed_header中的报文类型，将剩余的内容交给对应报文的解码器
+fn decode_packet_body(fixed_header: FixedHeader, buf: Bytes) -> Result<Packet, ProtoError> {
+    let packet = match fixed_header.message_type() {
+        MessageType::CONNECT => Packet::Connect(Connect::decode_body(fixed_header, buf)?),
+        MessageType::CONNACK => Packet::ConnAck(ConnAck::decode_body(fixed_header, buf)?),
+        MessageType::PUBLISH => Packet::Publish(Publish::decode_body(fixed_header, buf)?),
+        MessageType::PUBACK => Packet::PubAck(PubAck::decode_body(fixed_header, buf)?),
+        MessageType::PUBREC => Packet::PubRec(PubRec::decode_body(fixed_header, buf)?),
+        MessageType::PUBREL => Packet::PubRel(PubRel::decode_body(fixed_header, buf)?),
+        MessageType::PUBCOMP => Packet::PubComp(PubComp::decode_body(fixed_header, buf)?),
+        MessageType::SUBSCRIBE => Packet::Subscribe(Subscribe::decode_body(fixed_header, buf)?),
+        MessageType::SUBACK => Packet::SubAck(SubAck::decode_body(fixed_header, buf)?),
+        MessageType::UNSUBSCRIBE => {
+            Packet::UnSubscribe(UnSubscribe::decode_body(fixed_header, buf)?)
+        }
+        MessageType::UNSUBACK => Packet::UnSubAck(UnSubAck::decode_body(fixed_header, buf)?),
+        MessageType::PINGREQ => Packet::PingReq(PingReq::decode_body(fixed_header, buf)?),
+        MessageType::PINGRESP => Packet::PingResp(PingResp::decode_body(fixed_header, buf)?),
+        MessageType::DISCONNECT => Packet::DisConnect(DisConnect::decode_body(fixed_header, buf)?),
+        // MQTT-v3.1.1中没有AUTH报文
+        MessageType::AUTH => return Err(BuildError::MessageTypeError(15).into()),
+    };
+    Ok(packet)
+}
+
+/// 报文编码，在Encoder的基础上提供报文编码之后的总长度，用于提前分配BytesMut的容量
+pub trait PacketEncoder: Encoder {
+    /// 报文编码之后的总长度(fixed_header + variable_header + payload)，不会分配内存
+    fn serialized_len(&self) -> usize;
+
+    /// 按照serialized_len()预先分配BytesMut，然后进行编码
+    fn encode_with_hint(&self) -> Result<BytesMut, ProtoError> {
+        let mut buffer = BytesMut::with_capacity(self.serialized_len());
+        self.encode(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
 /// 解码
 pub trait Decoder: Sync + Send + 'static {
     // 定义的返回类型
@@ -81,6 +349,31 @@ pub trait Decoder: Sync + Send + 'static {
     type Error;
     // 将bytes解析为对应的报文
     fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error>;
+
+    /// 从bytes的开头解码一个报文，返回报文以及之后剩余的字节(与bytes共享同一块内存)，
+    /// 用于从网络缓冲区中依次解析多个报文。bytes中的内容不足一个完整报文时返回
+    /// [`ProtoError::Incomplete`]，其中为还需要读取的字节数
+    fn decode_with_remaining(mut bytes: Bytes) -> Result<(Self::Item, Bytes), Self::Error>
+    where
+        Self::Error: From<ProtoError>,
+    {
+        let fixed_header = decoder::read_fixed_header(&mut bytes)?;
+        let total = fixed_header.len() + fixed_header.remaining_length();
+        if bytes.len() < total {
+            return Err(ProtoError::Incomplete {
+                needed: total - bytes.len(),
+            }
+            .into());
+        }
+        let remaining = bytes.split_off(total);
+        Ok((Self::decode(bytes)?, remaining))
+    }
+}
+
+/// 报文体的解码器，在fixed_header已经解析完成的情况下，对可变报头和有效载荷进行解码
+pub trait BodyDecoder: Sized {
+    // body中只包含可变报头和有效载荷，不包含fixed_header
+    fn decode_body(fixed_header: FixedHeader, body: Bytes) -> Result<Self, ProtoError>;
 }
 
 /// 可变报头的解码器
@@ -94,20 +387,26 @@ pub trait VariableDecoder: Sync + Send + 'static {
 //////////////////////////////////////////////////////
 /// 通用可变头，只有message_id
 //////////////////////////////////////////////////////
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GeneralVariableHeader {
-    message_id: usize,
+    message_id: PacketId,
 }
 
 impl GeneralVariableHeader {
-    pub fn new(message_id: usize) -> Self {
+    pub fn new(message_id: PacketId) -> Self {
         Self { message_id }
     }
 
     pub fn message_id(&self) -> usize {
+        self.message_id.into()
+    }
+
+    pub fn packet_id(&self) -> PacketId {
         self.message_id
     }
 
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         2
     }
@@ -118,8 +417,7 @@ impl GeneralVariableHeader {
 //////////////////////////////////////////////////////
 impl Encoder for GeneralVariableHeader {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
-        let message_id = self.message_id as u16;
-        buffer.put_u16(message_id);
+        buffer.put_u16(self.message_id.get());
         Ok(2)
     }
 }
@@ -131,7 +429,423 @@ impl VariableDecoder for GeneralVariableHeader {
     type Item = GeneralVariableHeader;
 
     fn decode(bytes: &mut Bytes, _qos: Option<QoS>) -> Result<Self::Item, ProtoError> {
-        let message_id = bytes.get_u16() as usize;
+        // message_id不能为0
+        let message_id = PacketId::try_from(coder::read_u16(bytes)?)?;
         Ok(GeneralVariableHeader { message_id })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        builder::MqttMessageBuilder, conn_ack::ConnAck, conn_ack::ConnAckType, decode_with_config,
+        decode_zero_copy, ping_req::PingReq, ping_resp::PingResp, pub_ack::PubAck,
+        pub_comp::PubComp, pub_rec::PubRec, pub_rel::PubRel, publish::Publish, sub_ack::SubAck,
+        sub_ack::SubscribeReturnCode, verify_suback_for_subscribe, Decoder, Encoder,
+        GeneralVariableHeader, Packet, VariableDecoder,
+    };
+    use crate::common::packet_id::PacketId;
+    use crate::{common::config::DecodeConfig, error::ProtoError, QoS, Topic};
+    use bytes::{Bytes, BytesMut};
+
+    fn packets() -> Vec<Packet> {
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .username("rump")
+            .password("mq")
+            .will_qos(QoS::AtLeastOnce)
+            .will_topic("/a")
+            .will_message(Bytes::from_static(b"offline"))
+            .build()
+            .unwrap();
+        // payload超过127字节，剩余长度需要两个字节
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .qos(QoS::AtLeastOnce)
+            .packet_id(PacketId::new(1).unwrap())
+            .payload(Bytes::from(vec![1u8; 300]))
+            .build()
+            .unwrap();
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(2).unwrap())
+            .topic(Topic::new("/a".to_string(), QoS::AtMostOnce))
+            .topic(Topic::new("/b/c".to_string(), QoS::ExactlyOnce))
+            .build()
+            .unwrap();
+        let sub_ack = MqttMessageBuilder::sub_ack()
+            .packet_id(PacketId::new(2).unwrap())
+            .acks(vec![SubscribeReturnCode::SuccessQoS1])
+            .build()
+            .unwrap();
+        let unsubscribe = MqttMessageBuilder::unsubscriber()
+            .packet_id(PacketId::new(3).unwrap())
+            .topices(vec!["/a".to_string(), "/b/c".to_string()])
+            .build()
+            .unwrap();
+        let unsub_ack = MqttMessageBuilder::unsub_ack()
+            .packet_id(PacketId::new(3).unwrap())
+            .build()
+            .unwrap();
+        let disconnect = MqttMessageBuilder::disconnect().build().unwrap();
+        vec![
+            Packet::Connect(connect),
+            Packet::ConnAck(ConnAck::new(ConnAckType::Success).unwrap()),
+            Packet::Publish(publish),
+            Packet::PubAck(PubAck::new(PacketId::MIN)),
+            Packet::PubRel(PubRel::new(PacketId::MIN)),
+            Packet::PubRec(PubRec::new(PacketId::MIN)),
+            Packet::PubComp(PubComp::new(PacketId::MIN)),
+            Packet::PingReq(PingReq::new()),
+            Packet::PingResp(PingResp::new()),
+            Packet::Subscribe(subscribe),
+            Packet::SubAck(sub_ack),
+            Packet::UnSubscribe(unsubscribe),
+            Packet::UnSubAck(unsub_ack),
+            Packet::DisConnect(disconnect),
+        ]
+    }
+
+    #[test]
+    fn display_should_be_single_line_summary() {
+        let summaries: Vec<String> = packets().iter().map(Packet::summary).collect();
+        assert_eq!(
+            summaries,
+            [
+                "CONNECT client=client_01 keep_alive=60 clean=false will=true",
+                "CONNACK code=0",
+                "PUBLISH topic=/test qos=1 mid=1 retain=false payload=300B",
+                "PUBACK mid=1",
+                "PUBREL mid=1",
+                "PUBREC mid=1",
+                "PUBCOMP mid=1",
+                "PINGREQ",
+                "PINGRESP",
+                "SUBSCRIBE mid=2 filters=[/a:0, /b/c:2]",
+                "SUBACK mid=2 codes=[1]",
+                "UNSUBSCRIBE mid=3 filters=[/a, /b/c]",
+                "UNSUBACK mid=3",
+                "DISCONNECT",
+            ]
+        );
+        // QoS 0的PUBLISH没有message_id，payload的内容不会被输出
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .retain(true)
+            .payload(Bytes::from_static(b"secret"))
+            .build()
+            .unwrap();
+        let summary = Packet::Publish(publish).to_string();
+        assert_eq!(summary, "PUBLISH topic=/a qos=0 retain=true payload=6B");
+    }
+
+    #[test]
+    fn serialized_len_should_equal_encoded_len() {
+        for packet in packets() {
+            let buffer: BytesMut = packet.encode_to_bytes().unwrap();
+            assert_eq!(packet.serialized_len(), buffer.len(), "{:?}", packet);
+            assert!(buffer.capacity() >= packet.serialized_len());
+        }
+    }
+
+    #[test]
+    fn cloned_packet_should_equal_original() {
+        let packets = packets();
+        assert_eq!(packets.len(), 14);
+        for packet in &packets {
+            assert_eq!(&packet.clone(), packet);
+        }
+        // 不同的报文不相等
+        assert_ne!(packets[3], Packet::PubAck(PubAck::new(PacketId::MAX)));
+        assert_ne!(packets[3], packets[4]);
+    }
+
+    #[test]
+    fn decode_zero_copy_should_be_work() {
+        for packet in packets() {
+            let bytes = packet.encode_to_bytes().unwrap().freeze();
+            let decoded = decode_zero_copy(bytes.clone()).unwrap();
+            assert_eq!(decoded.encode_to_bytes().unwrap().freeze(), bytes);
+            assert_eq!(decoded, packet);
+        }
+    }
+
+    #[test]
+    fn decode_zero_copy_should_return_incomplete_for_short_buffer() {
+        // 剩余长度为5，只到达了3个字节
+        let bytes = Bytes::from_static(&[0x30, 0x05, 0x00, 0x03, b'a']);
+        assert_eq!(
+            decode_zero_copy(bytes).unwrap_err(),
+            ProtoError::Incomplete { needed: 2 }
+        );
+    }
+
+    #[test]
+    fn decode_zero_copy_should_share_publish_buffer() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .payload(Bytes::from(vec![7u8; 1024]))
+            .build()
+            .unwrap();
+        let bytes = Packet::Publish(publish).encode_to_bytes().unwrap().freeze();
+        let range = bytes.as_ptr_range();
+        match decode_zero_copy(bytes.clone()).unwrap() {
+            Packet::Publish(publish) => {
+                assert_eq!(publish.topic(), "/test");
+                assert!(range.contains(&publish.payload_ref().as_ptr()));
+                let topic = publish.variable_header_ref().topic_name().clone();
+                assert!(range.contains(&topic.as_bytes().as_ptr()));
+            }
+            packet => panic!("unexpected packet: {:?}", packet),
+        }
+    }
+
+    #[test]
+    fn ack_packets_should_be_forwarded_unchanged() {
+        // 像代理一样：解码原始报文，再编码，得到的字节必须和原始报文一致
+        fn forward<T: Decoder<Item = T, Error = ProtoError> + Encoder>(raw: &[u8]) -> T {
+            let packet = T::decode(Bytes::copy_from_slice(raw)).unwrap();
+            let mut buffer = BytesMut::new();
+            let len = packet.encode(&mut buffer).unwrap();
+            assert_eq!(len, raw.len());
+            assert_eq!(buffer.as_ref(), raw);
+            packet
+        }
+        assert!(forward::<PubAck>(&[0x40, 0x02, 0x12, 0x34])
+            .validate()
+            .is_ok());
+        assert!(forward::<PubRec>(&[0x50, 0x02, 0x12, 0x34])
+            .validate()
+            .is_ok());
+        assert!(forward::<PubRel>(&[0x62, 0x02, 0x12, 0x34])
+            .validate()
+            .is_ok());
+        assert!(forward::<PubComp>(&[0x70, 0x02, 0x12, 0x34])
+            .validate()
+            .is_ok());
+        // dup置位的PUBREL可以被转发，但是严格校验不通过
+        let pub_rel = forward::<PubRel>(&[0x6a, 0x02, 0x12, 0x34]);
+        assert_eq!(pub_rel.validate(), Err(ProtoError::DupValueError(1)));
+        // 类型不一致的报文同样原样转发
+        let pub_ack = forward::<PubAck>(&[0x50, 0x02, 0x12, 0x34]);
+        assert_eq!(pub_ack.validate(), Err(ProtoError::MessageTypeMismatch));
+    }
+
+    #[test]
+    fn decode_with_config_should_reject_oversized_packet() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .payload(Bytes::from(vec![0u8; 1024 * 1024]))
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        let len = publish.encode(&mut bytes).unwrap();
+        let bytes = bytes.freeze();
+        let config = DecodeConfig::new().max_packet_size(Some(512 * 1024));
+        assert_eq!(
+            decode_with_config(bytes.clone(), &config).unwrap_err(),
+            ProtoError::PacketTooLarge {
+                actual: len,
+                limit: 512 * 1024
+            }
+        );
+        // 只有fixed_header到达时就可以拒绝
+        assert!(matches!(
+            decode_with_config(bytes.slice(..4), &config),
+            Err(ProtoError::PacketTooLarge { .. })
+        ));
+        assert!(decode_with_config(bytes.clone(), &DecodeConfig::default()).is_ok());
+        assert!(
+            decode_with_config(bytes.clone(), &DecodeConfig::new().max_packet_size(None)).is_ok()
+        );
+        // 没有超出限制但是不完整的报文
+        assert_eq!(
+            decode_with_config(bytes.slice(..len - 1), &DecodeConfig::default()).unwrap_err(),
+            ProtoError::Incomplete { needed: 1 }
+        );
+    }
+
+    #[test]
+    fn strict_topic_name_should_be_work() {
+        // topic为"a/#"
+        let bytes = Bytes::from_static(&[0x30, 0x05, 0x00, 0x03, b'a', b'/', b'#']);
+        assert!(decode_with_config(bytes.clone(), &DecodeConfig::default()).is_ok());
+        let config = DecodeConfig::new().strict_topic_name(true);
+        assert!(matches!(
+            decode_with_config(bytes, &config),
+            Err(ProtoError::InvalidTopicName(_))
+        ));
+    }
+
+    #[test]
+    fn lenient_dup_flag_should_ignore_dup_on_qos0() {
+        // dup为1，QoS为0，topic为"a"
+        let bytes = Bytes::from_static(&[0x38, 0x03, 0x00, 0x01, b'a']);
+        assert_eq!(
+            decode_zero_copy(bytes.clone()).unwrap_err(),
+            ProtoError::DupOnQoS0
+        );
+        assert_eq!(
+            decode_with_config(bytes.clone(), &DecodeConfig::default()).unwrap_err(),
+            ProtoError::DupOnQoS0
+        );
+        let config = DecodeConfig::new().strict_dup_flag(false);
+        match decode_with_config(bytes, &config).unwrap() {
+            Packet::Publish(publish) => {
+                assert_eq!(publish.qos(), QoS::AtMostOnce);
+                assert!(!publish.fixed_header_ref().dup().unwrap());
+            }
+            packet => panic!("{:?}", packet),
+        }
+    }
+
+    #[test]
+    fn truncated_frames_should_return_error_instead_of_panic() {
+        // 剩余长度与实际内容不一致，可变报头不完整
+        let frames: [&[u8]; 9] = [
+            &[0x20, 0x01],
+            &[0x40, 0x02, 0x56],
+            &[0x50, 0x00],
+            &[0x62, 0x01, 0x0b],
+            &[0x70, 0x02, 0xe3],
+            &[0x82, 0x02, 0x26],
+            &[0x90, 0x01, 0xd4],
+            &[0xa2, 0x00, 0x11],
+            &[0xb0, 0x00, 0xed],
+        ];
+        for frame in frames {
+            let bytes = Bytes::from_static(frame);
+            assert!(decode_zero_copy(bytes.clone()).is_err());
+            let resp = match frame[0] >> 4 {
+                2 => ConnAck::decode(bytes).map(|_| ()),
+                4 => PubAck::decode(bytes).map(|_| ()),
+                5 => PubRec::decode(bytes).map(|_| ()),
+                6 => PubRel::decode(bytes).map(|_| ()),
+                7 => PubComp::decode(bytes).map(|_| ()),
+                8 => super::subscribe::Subscribe::decode(bytes).map(|_| ()),
+                9 => super::sub_ack::SubAck::decode(bytes).map(|_| ()),
+                10 => super::un_subscribe::UnSubscribe::decode(bytes).map(|_| ()),
+                _ => super::un_suback::UnSubAck::decode(bytes).map(|_| ()),
+            };
+            assert!(resp.is_err(), "{:02x?}", frame);
+        }
+    }
+
+    #[test]
+    fn zero_packet_id_should_return_error() {
+        // SUBSCRIBE、UNSUBSCRIBE以及QoS1的PUBLISH，message_id都为0
+        let frames: [&[u8]; 3] = [
+            &[0x82, 0x06, 0x00, 0x00, 0x00, 0x01, b'a', 0x00],
+            &[0xa2, 0x05, 0x00, 0x00, 0x00, 0x01, b'a'],
+            &[0x32, 0x05, 0x00, 0x01, b'a', 0x00, 0x00],
+        ];
+        for frame in frames {
+            let bytes = Bytes::from_static(frame);
+            assert_eq!(
+                decode_zero_copy(bytes.clone()).unwrap_err(),
+                ProtoError::InvalidPacketId(0)
+            );
+            let resp = match frame[0] >> 4 {
+                8 => super::subscribe::Subscribe::decode(bytes).map(|_| ()),
+                10 => super::un_subscribe::UnSubscribe::decode(bytes).map(|_| ()),
+                _ => super::publish::Publish::decode(bytes).map(|_| ()),
+            };
+            assert_eq!(resp.unwrap_err(), ProtoError::InvalidPacketId(0));
+        }
+    }
+
+    #[test]
+    fn compile_check() {
+        // GeneralVariableHeader只有VariableDecoder一个decode入口，
+        // Decoder::decode和decode_zero_copy两条路径都经过它
+        let mut body = Bytes::from_static(&[0x00, 0x05]);
+        let variable_header = <GeneralVariableHeader as VariableDecoder>::decode(&mut body, None);
+        assert_eq!(variable_header.unwrap().message_id(), 5);
+
+        let frame = Bytes::from_static(&[0x90, 0x03, 0x00, 0x05, 0x01]);
+        assert_eq!(SubAck::decode(frame.clone()).unwrap().message_id(), 5);
+        assert!(matches!(decode_zero_copy(frame), Ok(Packet::SubAck(p)) if p.message_id() == 5));
+
+        let frame = Bytes::from_static(&[0x40, 0x02, 0x00, 0x05]);
+        assert_eq!(PubAck::decode(frame.clone()).unwrap().message_id(), 5);
+        assert!(matches!(decode_zero_copy(frame), Ok(Packet::PubAck(p)) if p.message_id() == 5));
+
+        let frame = Bytes::from_static(&[0x70, 0x02, 0x00, 0x05]);
+        assert_eq!(PubComp::decode(frame.clone()).unwrap().message_id(), 5);
+        assert!(matches!(decode_zero_copy(frame), Ok(Packet::PubComp(p)) if p.message_id() == 5));
+    }
+
+    #[test]
+    fn verify_suback_for_subscribe_should_be_work() {
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(7).unwrap())
+            .add("/a", QoS::ExactlyOnce)
+            .add("/b/#", QoS::AtLeastOnce)
+            .add("/c", QoS::AtMostOnce)
+            .build()
+            .unwrap();
+        let sub_ack = |id: u16, acks: Vec<SubscribeReturnCode>| {
+            MqttMessageBuilder::sub_ack()
+                .packet_id(PacketId::new(id).unwrap())
+                .acks(acks)
+                .build()
+                .unwrap()
+        };
+        use SubscribeReturnCode::*;
+        assert_eq!(
+            verify_suback_for_subscribe(
+                &subscribe,
+                &sub_ack(7, vec![SuccessQoS1, SuccessQoS1, SuccessQoS0])
+            ),
+            Ok(vec![QoS::AtLeastOnce, QoS::AtLeastOnce, QoS::AtMostOnce])
+        );
+        assert_eq!(
+            verify_suback_for_subscribe(
+                &subscribe,
+                &sub_ack(8, vec![SuccessQoS1, SuccessQoS1, SuccessQoS0])
+            ),
+            Err(ProtoError::PacketIdMismatch {
+                expected: 7,
+                got: 8
+            })
+        );
+        assert_eq!(
+            verify_suback_for_subscribe(&subscribe, &sub_ack(7, vec![SuccessQoS2, SuccessQoS1])),
+            Err(ProtoError::AckCountMismatch)
+        );
+        assert_eq!(
+            verify_suback_for_subscribe(
+                &subscribe,
+                &sub_ack(7, vec![SuccessQoS2, Failure, Failure])
+            ),
+            Err(ProtoError::SubscriptionFailed(1))
+        );
+    }
+
+    #[test]
+    fn decode_with_remaining_should_be_work() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload(Bytes::from_static(b"hello"))
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        PubAck::new(PacketId::new(9).unwrap())
+            .encode(&mut buffer)
+            .unwrap();
+        publish.encode(&mut buffer).unwrap();
+        let bytes = buffer.freeze();
+
+        let (pub_ack, remaining) = PubAck::decode_with_remaining(bytes.clone()).unwrap();
+        assert_eq!(pub_ack.message_id(), 9);
+        assert_eq!(remaining, bytes.slice(4..));
+        let (decoded, remaining) = Publish::decode_with_remaining(remaining).unwrap();
+        assert_eq!(decoded, publish);
+        assert!(remaining.is_empty());
+
+        // 不完整的报文
+        assert_eq!(
+            Publish::decode_with_remaining(bytes.slice(4..bytes.len() - 1)).unwrap_err(),
+            ProtoError::Incomplete { needed: 1 }
+        );
+    }
+}
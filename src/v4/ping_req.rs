@@ -1,12 +1,13 @@
-use bytes::Bytes;
-use bytes::BytesMut;
 use super::decoder::read_fixed_header;
-use super::Decoder;
 use super::fixed_header::FixedHeader;
-use super::fixed_header::FixedHeaderBuilder;
+use super::BodyDecoder;
+use super::Decoder;
 use super::Encoder;
+use super::PacketEncoder;
 use crate::error::ProtoError;
 use crate::MessageType;
+use bytes::Bytes;
+use bytes::BytesMut;
 /////////////////////////////////////////////////////////////
 /// 心跳报文
 ///
@@ -15,23 +16,23 @@ use crate::MessageType;
 /// | byte1 | 1   | 1   | 0   | 0   | 0   | 0   | 0   | 0   |
 /// | byte2 | 0   | 0   | 0   | 0   | 0   | 0   | 0   | 0   |
 /////////////////////////////////////////////////////////////
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PingReq {
     // 固定报头
     fixed_header: FixedHeader,
 }
 
+impl Default for PingReq {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PingReq {
     pub fn new() -> Self {
-        let fixed_header = FixedHeaderBuilder::new()
-            .ping_req()
-            .dup(Some(false))
-            .qos(Some(crate::QoS::AtMostOnce))
-            .retain(Some(false))
-            .remaining_length(0)
-            .build();
         Self {
-            fixed_header: fixed_header.unwrap(),
+            fixed_header: FixedHeader::empty(MessageType::PINGREQ),
         }
     }
 
@@ -48,6 +49,12 @@ impl Encoder for PingReq {
         self.fixed_header.encode(buffer)
     }
 }
+
+impl PacketEncoder for PingReq {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len()
+    }
+}
 //////////////////////////////////////////////////////
 /// 为PingReq实现Decoder trait
 //////////////////////////////////////////////////////
@@ -58,20 +65,34 @@ impl Decoder for PingReq {
         let resp = read_fixed_header(&mut stream);
         match resp {
             Ok(fixed_header) => {
-                if fixed_header.message_type() == MessageType::PINGREQ {
-                    Ok(PingReq::from_fixed_header(fixed_header))
-                } else {
-                    Err(ProtoError::NotKnow)
+                if fixed_header.message_type() != MessageType::PINGREQ {
+                    return Err(ProtoError::NotKnow);
+                }
+                // 剩余长度必须为0
+                if fixed_header.remaining_length() != 0 {
+                    return Err(ProtoError::UnexpectedRemainingLength(
+                        fixed_header.remaining_length(),
+                    ));
                 }
+                Ok(PingReq::from_fixed_header(fixed_header))
             }
             Err(err) => Err(err),
         }
     }
 }
 
+impl BodyDecoder for PingReq {
+    fn decode_body(fixed_header: FixedHeader, body: Bytes) -> Result<Self, ProtoError> {
+        if !body.is_empty() {
+            return Err(ProtoError::UnexpectedRemainingLength(body.len()));
+        }
+        Ok(PingReq::from_fixed_header(fixed_header))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use bytes::{BytesMut};
+    use bytes::BytesMut;
 
     use crate::v4::Encoder;
 
@@ -85,4 +106,21 @@ mod tests {
         // let buf = buffer.freeze();
         println!("buffer = {:#?}", &buffer[..]);
     }
+
+    #[test]
+    fn bad_ping_req_frame_should_return_error() {
+        use crate::error::ProtoError;
+        use crate::v4::Decoder;
+        use crate::MessageType;
+        use bytes::Bytes;
+        assert!(PingReq::decode(Bytes::from_static(&[0xc0, 0x00])).is_ok());
+        assert_eq!(
+            PingReq::decode(Bytes::from_static(&[0xc3, 0x00])).unwrap_err(),
+            ProtoError::InvalidFixedHeaderFlags(MessageType::PINGREQ, 0x03)
+        );
+        assert_eq!(
+            PingReq::decode(Bytes::from_static(&[0xc0, 0x05, 1, 2, 3, 4, 5])).unwrap_err(),
+            ProtoError::UnexpectedRemainingLength(5)
+        );
+    }
 }
@@ -1,9 +1,9 @@
-use bytes::{Bytes, BytesMut};
 use super::decoder::read_fixed_header;
 use super::fixed_header::FixedHeader;
-use super::{fixed_header::FixedHeaderBuilder, Decoder, Encoder};
+use super::{BodyDecoder, Decoder, Encoder, PacketEncoder};
 use crate::error::ProtoError;
 use crate::MessageType;
+use bytes::{Bytes, BytesMut};
 
 /// 心跳应答报文
 ///
@@ -12,22 +12,22 @@ use crate::MessageType;
 /// | byte1 | 1   | 1   | 0   | 1   | 0   | 0   | 0   | 0   |
 /// | byte2 | 0   | 0   | 0   | 0   | 0   | 0   | 0   | 0   |
 
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PingResp {
     fixed_header: FixedHeader,
 }
 
+impl Default for PingResp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PingResp {
     pub fn new() -> Self {
-        let fixed_header = FixedHeaderBuilder::new()
-            .ping_resp()
-            .dup(Some(false))
-            .qos(Some(crate::QoS::AtMostOnce))
-            .retain(Some(false))
-            .remaining_length(0)
-            .build();
         Self {
-            fixed_header: fixed_header.unwrap(),
+            fixed_header: FixedHeader::empty(MessageType::PINGRESP),
         }
     }
     pub fn from_fixed_header(fixed_header: FixedHeader) -> Self {
@@ -44,6 +44,12 @@ impl Encoder for PingResp {
     }
 }
 
+impl PacketEncoder for PingResp {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len()
+    }
+}
+
 //////////////////////////////////////////////////////
 /// 为PingResp实现Decoder trait
 //////////////////////////////////////////////////////
@@ -54,13 +60,49 @@ impl Decoder for PingResp {
         let resp = read_fixed_header(&mut stream);
         match resp {
             Ok(fixed_header) => {
-                if fixed_header.message_type() == MessageType::PINGRESP {
-                    Ok(PingResp::from_fixed_header(fixed_header))
-                } else {
-                    Err(ProtoError::NotKnow)
+                if fixed_header.message_type() != MessageType::PINGRESP {
+                    return Err(ProtoError::NotKnow);
+                }
+                // 剩余长度必须为0
+                if fixed_header.remaining_length() != 0 {
+                    return Err(ProtoError::UnexpectedRemainingLength(
+                        fixed_header.remaining_length(),
+                    ));
                 }
+                Ok(PingResp::from_fixed_header(fixed_header))
             }
             Err(err) => Err(err),
         }
     }
 }
+
+impl BodyDecoder for PingResp {
+    fn decode_body(fixed_header: FixedHeader, body: Bytes) -> Result<Self, ProtoError> {
+        if !body.is_empty() {
+            return Err(ProtoError::UnexpectedRemainingLength(body.len()));
+        }
+        Ok(PingResp::from_fixed_header(fixed_header))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PingResp;
+
+    #[test]
+    fn bad_ping_resp_frame_should_return_error() {
+        use crate::error::ProtoError;
+        use crate::v4::Decoder;
+        use crate::MessageType;
+        use bytes::Bytes;
+        assert!(PingResp::decode(Bytes::from_static(&[0xd0, 0x00])).is_ok());
+        assert_eq!(
+            PingResp::decode(Bytes::from_static(&[0xd3, 0x00])).unwrap_err(),
+            ProtoError::InvalidFixedHeaderFlags(MessageType::PINGRESP, 0x03)
+        );
+        assert_eq!(
+            PingResp::decode(Bytes::from_static(&[0xd0, 0x05, 1, 2, 3, 4, 5])).unwrap_err(),
+            ProtoError::UnexpectedRemainingLength(5)
+        );
+    }
+}
@@ -1,10 +1,13 @@
+use super::builder::{MqttMessageBuilder, PubRecBuilder};
 use super::{
     fixed_header::{FixedHeader, FixedHeaderBuilder},
-    Decoder, Encoder,
+    BodyDecoder, Decoder, Encoder, PacketEncoder,
 };
+use crate::common::packet_id::PacketId;
 use crate::error::ProtoError;
 use crate::v4::{decoder, GeneralVariableHeader, VariableDecoder};
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crate::MessageType;
+use bytes::{Buf, Bytes, BytesMut};
 
 ///
 ///
@@ -15,22 +18,49 @@ use bytes::{Buf, BufMut, Bytes, BytesMut};
 /// | byte3 | 报   | 文   | 标  | 识   | 符  | M   | S   | B   |
 /// | byte4 | 报   | 文   | 标  | 识   | 符  | L   | S   | B   |
 
-#[derive(Debug,Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubRec {
     fixed_header: FixedHeader,
     variable_header: GeneralVariableHeader,
 }
 
 impl PubRec {
-    pub fn new(message_id: usize) -> Self {
+    /**
+     PUBREC报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::pub_rec::PubRec;
+    let pub_rec = PubRec::builder()
+        .packet_id(PacketId::MAX)
+        .build()
+        .unwrap();
+    assert_eq!(pub_rec.message_id(), 65535);
+    ```
+     */
+    pub fn builder() -> PubRecBuilder {
+        MqttMessageBuilder::pub_rec()
+    }
+
+    pub fn new(message_id: PacketId) -> Self {
         Self {
-            fixed_header: FixedHeaderBuilder::new().pub_rel().build().unwrap(),
+            fixed_header: FixedHeaderBuilder::new().pub_rec().build().unwrap(),
             variable_header: GeneralVariableHeader::new(message_id),
         }
     }
 
+    pub fn fixed_header(&self) -> FixedHeader {
+        self.fixed_header.clone()
+    }
+
     pub fn message_id(&self) -> usize {
-        self.variable_header.message_id
+        self.variable_header.message_id()
+    }
+
+    /// 严格校验报文，编码和解码时都不会主动校验，需要的时候由应用调用
+    pub fn validate(&self) -> Result<(), ProtoError> {
+        self.fixed_header.validate_ack(MessageType::PUBREC)
     }
 }
 
@@ -39,17 +69,15 @@ impl PubRec {
 //////////////////////////////////////////////////////
 impl Encoder for PubRec {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
-        let fixed_header = FixedHeaderBuilder::new().pub_rec().build();
-        match fixed_header {
-            Ok(fixed_header) => {
-                if let Ok(_resp) = fixed_header.encode(buffer) {
-                    buffer.put_u16(self.variable_header.message_id() as u16);
-                    return Ok(4);
-                }
-                Err(ProtoError::EncodeVariableHeaderError)
-            }
-            Err(err) => Err(err),
-        }
+        let fixed_header_len = self.fixed_header.encode(buffer)?;
+        let variable_header_len = self.variable_header.encode(buffer)?;
+        Ok(fixed_header_len + variable_header_len)
+    }
+}
+
+impl PacketEncoder for PubRec {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.variable_header.len()
     }
 }
 
@@ -73,10 +101,38 @@ impl Decoder for PubRec {
                         fixed_header,
                         variable_header,
                     }),
-                    Err(e) => return Err(e),
+                    Err(e) => Err(e),
                 }
             }
             Err(e) => Err(e),
         }
     }
 }
+
+impl BodyDecoder for PubRec {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        Ok(PubRec {
+            fixed_header,
+            variable_header,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PubRec;
+    use crate::common::packet_id::PacketId;
+    use crate::v4::Encoder;
+    use crate::MessageType;
+    use bytes::BytesMut;
+
+    #[test]
+    fn pub_rec_new_should_use_pub_rec_fixed_header() {
+        let pub_rec = PubRec::new(PacketId::new(0x1234).unwrap());
+        assert_eq!(pub_rec.fixed_header().message_type(), MessageType::PUBREC);
+        let mut buffer = BytesMut::new();
+        assert_eq!(pub_rec.encode(&mut buffer).unwrap(), 4);
+        assert_eq!(buffer.as_ref(), &[0x50, 0x02, 0x12, 0x34]);
+    }
+}
@@ -1,10 +1,13 @@
+use super::builder::{MqttMessageBuilder, PubRelBuilder};
 use super::{
     fixed_header::{FixedHeader, FixedHeaderBuilder},
-    Decoder, Encoder,
+    BodyDecoder, Decoder, Encoder, PacketEncoder,
 };
+use crate::common::packet_id::PacketId;
 use crate::error::ProtoError;
 use crate::v4::{decoder, GeneralVariableHeader, VariableDecoder};
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crate::MessageType;
+use bytes::{Buf, Bytes, BytesMut};
 
 /// | Bit   | 7   | 6   | 5   | 4   | 3   | 2   | 1   | 0   |
 /// | ----- | --- | --- | --- | --- | --- | --- | --- | --- |
@@ -13,22 +16,49 @@ use bytes::{Buf, BufMut, Bytes, BytesMut};
 /// | byte3 | 报  | 文   | 标  | 识  | 符   | M   | S   | B   |
 /// | byte4 | 报  | 文   | 标  | 识  | 符   | L   | S   | B   |
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PubRel {
     fixed_header: FixedHeader,
     variable_header: GeneralVariableHeader,
 }
 
 impl PubRel {
-    pub fn new(message_id: usize) -> Self {
+    /**
+     PUBREL报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::pub_rel::PubRel;
+    let pub_rel = PubRel::builder()
+        .packet_id(PacketId::new(8).unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(pub_rel.message_id(), 8);
+    ```
+     */
+    pub fn builder() -> PubRelBuilder {
+        MqttMessageBuilder::pub_rel()
+    }
+
+    pub fn new(message_id: PacketId) -> Self {
         Self {
             fixed_header: FixedHeaderBuilder::new().pub_rel().build().unwrap(),
             variable_header: GeneralVariableHeader::new(message_id),
         }
     }
 
+    pub fn fixed_header(&self) -> FixedHeader {
+        self.fixed_header.clone()
+    }
+
     pub fn message_id(&self) -> usize {
-        self.variable_header.message_id
+        self.variable_header.message_id()
+    }
+
+    /// 严格校验报文，编码和解码时都不会主动校验，需要的时候由应用调用
+    pub fn validate(&self) -> Result<(), ProtoError> {
+        self.fixed_header.validate_ack(MessageType::PUBREL)
     }
 }
 
@@ -37,17 +67,15 @@ impl PubRel {
 //////////////////////////////////////////////////////
 impl Encoder for PubRel {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
-        let fixed_header = FixedHeaderBuilder::new().pub_rel().build();
-        match fixed_header {
-            Ok(fixed_header) => {
-                if let Ok(_resp) = fixed_header.encode(buffer) {
-                    buffer.put_u16(self.variable_header.message_id() as u16);
-                    return Ok(4);
-                }
-                Err(ProtoError::EncodeVariableHeaderError)
-            }
-            Err(err) => Err(err),
-        }
+        let fixed_header_len = self.fixed_header.encode(buffer)?;
+        let variable_header_len = self.variable_header.encode(buffer)?;
+        Ok(fixed_header_len + variable_header_len)
+    }
+}
+
+impl PacketEncoder for PubRel {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.variable_header.len()
     }
 }
 
@@ -71,10 +99,40 @@ impl Decoder for PubRel {
                         fixed_header,
                         variable_header,
                     }),
-                    Err(e) => return Err(e),
+                    Err(e) => Err(e),
                 }
             }
             Err(e) => Err(e),
         }
     }
 }
+
+impl BodyDecoder for PubRel {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        Ok(PubRel {
+            fixed_header,
+            variable_header,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PubRel;
+    use crate::common::packet_id::PacketId;
+    use crate::v4::{Decoder, Encoder};
+    use bytes::BytesMut;
+
+    #[test]
+    fn pub_rel_fixed_header_should_set_reserved_bit() {
+        // MQTT 3.1.1 3.6.1节：PUBREL的首字节必须为0x62
+        let pub_rel = PubRel::new(PacketId::new(0x1234).unwrap());
+        let mut buffer = BytesMut::new();
+        assert_eq!(pub_rel.encode(&mut buffer).unwrap(), 4);
+        assert_eq!(buffer[0], 0x62);
+        assert_eq!(buffer.as_ref(), &[0x62, 0x02, 0x12, 0x34]);
+        let decoded = PubRel::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, pub_rel);
+    }
+}
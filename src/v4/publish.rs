@@ -1,12 +1,18 @@
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use tracing::debug;
-use crate::error::ProtoError;
-use crate::QoS;
+use super::builder::{MqttMessageBuilder, PublishBuilder};
 use super::{
-    decoder::{self, read_mqtt_string, read_u16},
-    fixed_header::FixedHeader,
-    Decoder, Encoder, VariableDecoder,
+    decoder, fixed_header::FixedHeader, BodyDecoder, Decoder, Encoder, PacketEncoder,
+    VariableDecoder,
 };
+use crate::common::coder::{read_mqtt_str, read_u16, write_mqtt_bytes};
+use crate::common::mqtt_string::MqttString;
+use crate::common::packet_id::PacketId;
+use crate::common::topic::TopicName;
+use crate::common::var_int::VarInt;
+use crate::error::ProtoError;
+use crate::{MessageType, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+use tracing::debug;
 
 /// 一个字节表示的最大长度
 pub const ONE_BYTE_MAX_LEN: usize = 127;
@@ -17,6 +23,108 @@ pub const THREE_BYTE_MAX_LEN: usize = 2097151;
 /// 四个字节表示的最大长度
 pub const FOUR_BYTE_MAX_LEN: usize = 268435455;
 
+// Debug输出中最多显示的payload字节数
+const PAYLOAD_PREVIEW_LEN: usize = 32;
+
+/// 用于Debug输出的payload，只显示长度以及前32个字节的十六进制，例如`len=40 0a0b...`
+pub(crate) struct PayloadPreview<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Debug for PayloadPreview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "len={}", self.0.len())?;
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        f.write_str(" ")?;
+        for byte in self.0.iter().take(PAYLOAD_PREVIEW_LEN) {
+            write!(f, "{:02x}", byte)?;
+        }
+        if self.0.len() > PAYLOAD_PREVIEW_LEN {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+/// topic的零拷贝表示，内部是一个[`MqttString`]，直接持有报文中的Bytes
+///
+/// 与MqttString使用同一套校验：构造时检查UTF-8以及空字符(U+0000)，不会分配新的内存。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+pub struct TopicRef(MqttString);
+
+impl TopicRef {
+    /// 使用Bytes构建TopicRef，bytes不是合法的UTF-8或者包含空字符时返回[`ProtoError::MalformedString`]
+    pub fn new(bytes: Bytes) -> Result<Self, ProtoError> {
+        MqttString::from_bytes(bytes).map(Self)
+    }
+
+    /// 以&str的方式返回topic
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// 返回topic的原始Bytes
+    pub fn as_bytes(&self) -> &Bytes {
+        self.0.as_bytes()
+    }
+
+    pub fn as_mqtt_string(&self) -> &MqttString {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<MqttString> for TopicRef {
+    fn from(topic: MqttString) -> Self {
+        Self(topic)
+    }
+}
+
+impl From<TopicRef> for MqttString {
+    fn from(topic: TopicRef) -> Self {
+        topic.0
+    }
+}
+
+impl TryFrom<String> for TopicRef {
+    type Error = ProtoError;
+
+    fn try_from(topic: String) -> Result<Self, Self::Error> {
+        MqttString::try_from(topic).map(Self)
+    }
+}
+
+impl TryFrom<&str> for TopicRef {
+    type Error = ProtoError;
+
+    fn try_from(topic: &str) -> Result<Self, Self::Error> {
+        MqttString::try_from(topic).map(Self)
+    }
+}
+
+impl From<TopicRef> for String {
+    fn from(topic: TopicRef) -> Self {
+        topic.0.into()
+    }
+}
+
+/// PublishVariableHeader为了零拷贝解码依然持有TopicRef，[`TopicName`]在这里转换
+impl From<TopicName> for TopicRef {
+    fn from(topic: TopicName) -> Self {
+        // TopicName构造时已经检查过空字符
+        Self(MqttString::from_string_unchecked(topic.into_string()))
+    }
+}
+
 /// 一个完整的publish报文的格式如下：
 ///
 /// | 字节 | 7   | 6   | 5   | 4   | 3   | 2   | 1   | 0   | 数值 | 备注     |
@@ -43,7 +151,8 @@ pub const FOUR_BYTE_MAX_LEN: usize = 268435455;
 /// | 20   | 0   | 0   | 1   | 1   | 1   | 0   | 0   | 1   | 57   | 9        |
 /// | 21   | 0   | 0   | 1   | 1   | 0   | 0   | 0   | 0   | 48   | 0        |
 
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Publish {
     // 固定报头
     fixed_header: FixedHeader,
@@ -53,7 +162,42 @@ pub struct Publish {
     payload: Bytes,
 }
 
+/// payload可能很大并且包含敏感内容，Debug只输出长度和前32个字节
+impl fmt::Debug for Publish {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Publish")
+            .field("topic", &self.topic())
+            .field("qos", &self.qos())
+            .field("message_id", &self.variable_header.message_id())
+            .field("retain", &self.retain())
+            .field("dup", &self.dup())
+            .field("payload", &PayloadPreview(&self.payload))
+            .finish()
+    }
+}
+
 impl Publish {
+    /**
+     PUBLISH报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::publish::Publish;
+    use walle_mqtt_protocol::QoS;
+    let publish = Publish::builder()
+        .topic("sport/tennis")
+        .qos(QoS::AtLeastOnce)
+        .packet_id(PacketId::new(1).unwrap())
+        .payload_str("hello")
+        .build()
+        .unwrap();
+    assert_eq!(publish.topic(), "sport/tennis");
+    ```
+     */
+    pub fn builder() -> PublishBuilder {
+        MqttMessageBuilder::publish()
+    }
+
     pub fn new(
         fixed_header: FixedHeader,
         variable_header: PublishVariableHeader,
@@ -66,33 +210,150 @@ impl Publish {
         }
     }
 
+    /// 拆分报文，topic和payload不会被复制
+    pub fn into_parts(self) -> (FixedHeader, PublishVariableHeader, Bytes) {
+        (self.fixed_header, self.variable_header, self.payload)
+    }
+
+    /// 使用[`Publish::into_parts`]拆分出来的各部分重新组装报文，
+    /// 会检查QoS与message_id是否对应，并且重新计算剩余长度
+    pub fn from_parts(
+        mut fixed_header: FixedHeader,
+        variable_header: PublishVariableHeader,
+        payload: Bytes,
+    ) -> Result<Publish, ProtoError> {
+        if fixed_header.message_type() != MessageType::PUBLISH {
+            return Err(ProtoError::MessageTypeMismatch);
+        }
+        let qos = fixed_header.qos().unwrap_or_default();
+        match (qos, variable_header.message_id) {
+            (QoS::AtMostOnce, Some(_)) => return Err(ProtoError::QoS0WithPacketId),
+            (QoS::AtLeastOnce | QoS::ExactlyOnce, None) => {
+                return Err(ProtoError::QoS12RequiresPacketId)
+            }
+            _ => {}
+        }
+        // variable_header的长度与构建时使用的QoS有关，这里按照fixed_header中的QoS重新计算
+        let variable_header = PublishVariableHeader::from_topic_ref(
+            variable_header.topic,
+            variable_header.message_id,
+            Some(qos),
+        );
+        let remaining_length = variable_header.variable_header_len() + payload.len();
+        VarInt::try_from(remaining_length)?;
+        fixed_header.set_remaining_length(remaining_length);
+        Ok(Publish {
+            fixed_header,
+            variable_header,
+            payload,
+        })
+    }
+
+    #[deprecated(note = "请使用fixed_header_ref")]
     pub fn fixed_header(&self) -> FixedHeader {
         self.fixed_header.clone()
     }
 
+    pub fn fixed_header_ref(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+
+    #[deprecated(note = "请使用variable_header_ref")]
     pub fn variable_header(&self) -> PublishVariableHeader {
         self.variable_header.clone()
     }
 
+    pub fn variable_header_ref(&self) -> &PublishVariableHeader {
+        &self.variable_header
+    }
+
+    #[deprecated(note = "请使用payload_ref")]
     pub fn payload(&self) -> Bytes {
         self.payload.clone()
     }
 
-    /// 更新message_id,并且把QoS改为AtLeastOnce
-    /// todo 其他两种QoS会出错
-    pub fn update(self, message_id: usize) -> Self {
-        let fixed_header = self.fixed_header.clone();
-        // fixed_header.set_qos(QoS::AtLeastOnce);
-        let variable_header = self.variable_header.clone().update_message_id(message_id);
-        let payload = self.payload();
-        Self {
-            fixed_header,
-            variable_header,
-            payload,
+    /// 以引用的方式返回payload
+    pub fn payload_ref(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// 以&str的方式返回payload，payload不是合法的UTF-8时返回[`ProtoError::MalformedString`]
+    pub fn try_payload_as_str(&self) -> Result<&str, ProtoError> {
+        std::str::from_utf8(&self.payload).map_err(|_| ProtoError::MalformedString)
+    }
+
+    /// 返回topic，不会复制String
+    pub fn topic(&self) -> &str {
+        self.variable_header.topic_ref()
+    }
+
+    /// 返回QoS，不会复制FixedHeader
+    pub fn qos(&self) -> QoS {
+        self.fixed_header.qos().unwrap_or_default()
+    }
+
+    /// 返回retain标志
+    pub fn retain(&self) -> bool {
+        self.fixed_header.retain().unwrap_or(false)
+    }
+
+    /// 与[`Publish::retain`]相同
+    pub fn is_retained(&self) -> bool {
+        self.retain()
+    }
+
+    /// 返回dup标志
+    pub fn dup(&self) -> bool {
+        self.fixed_header.dup().unwrap_or(false)
+    }
+
+    /// 修改retain标志，编码时会重新计算固定报头的首字节
+    pub fn set_retain(&mut self, retain: bool) {
+        self.fixed_header.set_retain(retain);
+    }
+
+    /// 修改dup标志，用于QoS 1/2的重发。
+    /// QoS 0的报文dup必须为0，此时`set_dup(true)`不做任何修改
+    pub fn set_dup(&mut self, dup: bool) {
+        if dup && self.qos() == QoS::AtMostOnce {
+            return;
         }
+        self.fixed_header.set_dup(dup);
+    }
+
+    #[deprecated(note = "请使用with_message_id")]
+    pub fn update(self, message_id: PacketId) -> Self {
+        self.with_message_id(message_id)
+    }
+
+    /// 更新message_id，QoS保持不变。
+    /// QoS 0的报文中没有message_id，此时返回原报文
+    pub fn with_message_id(mut self, message_id: PacketId) -> Self {
+        if self.qos() != QoS::AtMostOnce {
+            self.variable_header = self.variable_header.update_message_id(message_id);
+        }
+        self
+    }
+
+    /// 复制一个dup=1的报文，用于QoS 1/2超时后的重发，其余字段不变。
+    /// QoS 0的报文不会重发，此时返回原报文的复制
+    pub fn for_retransmission(&self) -> Publish {
+        let mut publish = self.clone();
+        if publish.qos() != QoS::AtMostOnce {
+            publish.set_dup(true);
+        }
+        publish
     }
 }
 
+/// 清除retain和dup标志，broker把消息转发给已有的订阅者时使用。
+/// 因为订阅而发送的保留消息需要保留retain标志，不要调用这个函数
+pub fn strip_retain_flag(mut publish: Publish) -> Publish {
+    publish.set_retain(false);
+    publish.set_dup(false);
+    publish
+}
+
 //////////////////////////////////////////////////////////
 /// 为Publish实现Encoder trait
 /////////////////////////////////////////////////////////
@@ -106,9 +367,10 @@ impl Encoder for Publish {
                 match resp {
                     Ok(variable_header_len) => {
                         debug!("fixed_handler + variable_headler buffer = {:?}", buffer);
-                        buffer.put(self.payload());
+                        buffer.put_slice(self.payload_ref());
                         debug!("buffer = {:?}", buffer);
-                        let resp = fixed_header_len + variable_header_len + self.payload().len();
+                        let resp =
+                            fixed_header_len + variable_header_len + self.payload_ref().len();
                         Ok(resp)
                     }
                     Err(e) => Err(e),
@@ -119,6 +381,12 @@ impl Encoder for Publish {
     }
 }
 
+impl PacketEncoder for Publish {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.variable_header.variable_header_len() + self.payload.len()
+    }
+}
+
 //////////////////////////////////////////////////////////
 /// 为Publish实现Decoder trait
 /////////////////////////////////////////////////////////
@@ -149,20 +417,43 @@ impl Decoder for Publish {
     }
 }
 
+impl BodyDecoder for Publish {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = PublishVariableHeader::decode(&mut body, fixed_header.qos())?;
+        // payload与body共享同一块内存
+        Ok(Publish {
+            fixed_header,
+            variable_header,
+            payload: body,
+        })
+    }
+}
+
 //////////////////////////////////////////////
 /// PublishVariableHeader
 /////////////////////////////////////////////
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PublishVariableHeader {
     // variable_header的长度
     variable_header_len: usize,
     // topic
-    topic: String,
+    topic: TopicRef,
     // message_id
-    message_id: Option<usize>,
+    message_id: Option<PacketId>,
 }
 impl PublishVariableHeader {
-    pub fn new(topic: String, message_id: Option<usize>, qos: Option<QoS>) -> Self {
+    /// topic不是合法的MQTT字符串(包含空字符)时返回[`ProtoError::MalformedString`]
+    pub fn new(
+        topic: String,
+        message_id: Option<PacketId>,
+        qos: Option<QoS>,
+    ) -> Result<Self, ProtoError> {
+        let topic = TopicRef::try_from(topic)?;
+        Ok(Self::from_topic_ref(topic, message_id, qos))
+    }
+
+    pub fn from_topic_ref(topic: TopicRef, message_id: Option<PacketId>, qos: Option<QoS>) -> Self {
         Self {
             variable_header_len: Self::variable_len(topic.as_str(), qos),
             topic,
@@ -186,13 +477,28 @@ impl PublishVariableHeader {
     pub fn variable_header_len(&self) -> usize {
         self.variable_header_len
     }
+    #[deprecated(note = "请使用topic_str")]
     pub fn topic(&self) -> String {
-        self.topic.clone()
+        self.topic.as_str().to_string()
+    }
+    pub fn topic_str(&self) -> &str {
+        self.topic.as_str()
+    }
+    /// 与[`PublishVariableHeader::topic_str`]相同
+    pub fn topic_ref(&self) -> &str {
+        self.topic.as_str()
+    }
+    /// 返回topic原始的Bytes，与报文共享同一块内存
+    pub fn topic_name(&self) -> &TopicRef {
+        &self.topic
     }
     pub fn message_id(&self) -> Option<usize> {
+        self.message_id.map(usize::from)
+    }
+    pub fn packet_id(&self) -> Option<PacketId> {
         self.message_id
     }
-    pub fn update_message_id(mut self, message_id: usize) -> Self {
+    pub fn update_message_id(mut self, message_id: PacketId) -> Self {
         self.message_id = Some(message_id);
         self
     }
@@ -205,31 +511,18 @@ impl VariableDecoder for PublishVariableHeader {
     type Item = PublishVariableHeader;
 
     fn decode(bytes: &mut Bytes, qos: Option<QoS>) -> Result<Self::Item, ProtoError> {
-        let topic_resp = read_mqtt_string(bytes);
-        match topic_resp {
-            Ok(topic) => match qos {
-                Some(qos) => {
-                    if qos == QoS::AtMostOnce {
-                        return Ok(PublishVariableHeader::new(
-                            topic,
-                            None,
-                            Some(QoS::AtMostOnce),
-                        ));
-                    } else {
-                        let message_id = read_u16(bytes).unwrap();
-                        return Ok(PublishVariableHeader::new(
-                            topic,
-                            Some(message_id.into()),
-                            Some(qos),
-                        ));
-                    }
-                }
-                None => {
-                    return Ok(PublishVariableHeader::new(topic, None, None));
-                }
-            },
-            Err(e) => Err(e),
-        }
+        // topic直接引用bytes中的数据，不会复制
+        let topic = TopicRef::from(read_mqtt_str(bytes)?);
+        let message_id = match qos {
+            // QoS为1或2时message_id不能为0
+            Some(QoS::AtLeastOnce) | Some(QoS::ExactlyOnce) => {
+                Some(PacketId::try_from(read_u16(bytes)?)?)
+            }
+            _ => None,
+        };
+        Ok(PublishVariableHeader::from_topic_ref(
+            topic, message_id, qos,
+        ))
     }
 }
 
@@ -239,16 +532,13 @@ impl VariableDecoder for PublishVariableHeader {
 impl Encoder for PublishVariableHeader {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
         debug!("encode PublishVariableHandler");
-        let topic_len = self.topic.len();
-        debug!("topic_len = {}", topic_len);
-        buffer.put_u16(topic_len as u16);
-        let topic = self.topic.clone();
-        debug!("topic = {:?}", topic.as_bytes());
-        buffer.put(topic.as_bytes());
+        debug!("topic_len = {}", self.topic.len());
+        write_mqtt_bytes(buffer, self.topic.as_bytes())?;
+        debug!("topic = {:?}", self.topic.as_bytes());
         let message_id = self.message_id;
         match message_id {
             Some(msg_id) => {
-                buffer.put_u16(msg_id as u16);
+                buffer.put_u16(msg_id.get());
                 debug!("variable_header_len = {}", self.variable_header_len());
                 Ok(self.variable_header_len())
             }
@@ -259,10 +549,33 @@ impl Encoder for PublishVariableHeader {
 
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use crate::common::packet_id::PacketId;
+    use bytes::{Bytes, BytesMut};
 
+    use crate::error::ProtoError;
     use crate::v4::{builder::MqttMessageBuilder, publish::Publish, Decoder, Encoder};
 
+    #[test]
+    fn debug_should_truncate_payload() {
+        let mut payload = vec![0xaa; 32];
+        payload.extend_from_slice(&[0xbb; 8]);
+        let publish = MqttMessageBuilder::publish()
+            .topic("/firmware")
+            .qos(crate::QoS::AtLeastOnce)
+            .packet_id(PacketId::new(42).unwrap())
+            .payload(Bytes::from(payload))
+            .build()
+            .unwrap();
+        let debug = format!("{:?}", publish);
+        assert!(debug.contains("topic: \"/firmware\""), "{}", debug);
+        assert!(debug.contains("message_id: Some(42)"), "{}", debug);
+        assert!(debug.contains(&format!("len=40 {}...", "aa".repeat(32))));
+        assert!(!debug.contains("bb"), "{}", debug);
+
+        let empty = MqttMessageBuilder::publish().topic("/a").build().unwrap();
+        assert!(format!("{:?}", empty).contains("payload: len=0 }"));
+    }
+
     #[test]
     fn publish_to_bytes() {
         if let Ok(publish) = MqttMessageBuilder::publish()
@@ -275,7 +588,7 @@ mod tests {
         {
             let remaining_len = publish.fixed_header.remaining_length();
             let qos = publish.fixed_header.qos();
-            let topic = publish.variable_header.topic();
+            let topic = publish.variable_header.topic_str();
 
             // encode
             let mut buffer = BytesMut::new();
@@ -285,7 +598,7 @@ mod tests {
             if let Ok(new_publish) = Publish::decode(buffer.freeze()) {
                 let new_remaining_len = new_publish.fixed_header.remaining_length();
                 let new_qos = new_publish.fixed_header.qos();
-                let new_topic = new_publish.variable_header.topic();
+                let new_topic = new_publish.variable_header.topic_str();
                 assert_eq!(remaining_len, new_remaining_len);
                 assert_eq!(qos, new_qos);
                 assert_eq!(topic, new_topic);
@@ -298,7 +611,7 @@ mod tests {
         if let Ok(publish) = MqttMessageBuilder::publish()
             .dup(false)
             .qos(crate::QoS::AtMostOnce)
-            .message_id(1) // 当qos=0的时候设置message_id也是无效的
+            .packet_id(PacketId::new(1).unwrap()) // 当qos=0的时候设置message_id也是无效的
             .retain(false)
             .topic("/test")
             .payload_str("hello world !")
@@ -306,8 +619,8 @@ mod tests {
         {
             let _remaining_len = publish.fixed_header.remaining_length();
             let qos = publish.fixed_header.qos().unwrap();
-            let topic = publish.variable_header.topic();
-            let payload = publish.payload();
+            let topic = publish.variable_header.topic_str();
+            let payload = publish.payload_ref();
 
             // encode
             let mut buffer = BytesMut::new();
@@ -317,10 +630,10 @@ mod tests {
             let publish1 = MqttMessageBuilder::publish()
                 .dup(false)
                 .retain(false)
-                .message_id(1)
-                .topic(topic.as_str())
+                .packet_id(PacketId::new(1).unwrap())
+                .topic(topic)
                 .qos(qos)
-                .payload(payload)
+                .payload(payload.clone())
                 .build()
                 .unwrap();
             let mut buffer1 = BytesMut::new();
@@ -334,7 +647,7 @@ mod tests {
         if let Ok(publish) = MqttMessageBuilder::publish()
             .dup(false)
             .qos(crate::QoS::ExactlyOnce)
-            .message_id(21362) // 当qos=0的时候设置message_id也是无效的
+            .packet_id(PacketId::new(21362).unwrap()) // 当qos=0的时候设置message_id也是无效的
             .retain(false)
             .topic("/test")
             .payload_str("123456")
@@ -345,4 +658,275 @@ mod tests {
             println!("{:?}", buff);
         }
     }
+
+    #[test]
+    fn publish_accessors_should_be_work() {
+        let publish = MqttMessageBuilder::publish()
+            .dup(true)
+            .qos(crate::QoS::AtLeastOnce)
+            .packet_id(PacketId::new(7).unwrap())
+            .retain(true)
+            .topic("/test")
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        assert!(publish.dup());
+        assert!(publish.retain());
+        assert_eq!(publish.qos(), crate::QoS::AtLeastOnce);
+        assert_eq!(publish.topic(), "/test");
+        assert_eq!(publish.variable_header.topic_ref(), "/test");
+        assert_eq!(publish.payload_ref().as_ref(), b"hello");
+
+        // 编解码之后标志位保持不变
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        let publish = Publish::decode(buffer.freeze()).unwrap();
+        assert!(publish.dup());
+        assert!(publish.retain());
+        assert_eq!(publish.qos(), crate::QoS::AtLeastOnce);
+        assert_eq!(publish.topic(), "/test");
+        assert_eq!(publish.payload_ref().as_ref(), b"hello");
+    }
+
+    #[test]
+    fn set_flags_should_update_first_byte() {
+        let mut publish = MqttMessageBuilder::publish()
+            .qos(crate::QoS::AtLeastOnce)
+            .packet_id(PacketId::new(7).unwrap())
+            .topic("/test")
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        publish.set_dup(true);
+        publish.set_retain(true);
+        assert!(publish.is_retained());
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        assert_eq!(buffer[0], 0x3b);
+
+        let publish = super::strip_retain_flag(Publish::decode(buffer.freeze()).unwrap());
+        assert!(!publish.is_retained());
+        assert!(!publish.dup());
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        assert_eq!(buffer[0], 0x32);
+    }
+
+    #[test]
+    fn set_dup_on_qos0_should_be_ignored() {
+        let mut publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        publish.set_dup(true);
+        assert!(!publish.dup());
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        assert_eq!(buffer[0], 0x30);
+        // 默认的解码器可以解码
+        assert!(!Publish::decode(buffer.freeze()).unwrap().dup());
+    }
+
+    #[test]
+    fn retransmission_should_only_set_dup() {
+        for qos in [crate::QoS::AtLeastOnce, crate::QoS::ExactlyOnce] {
+            let publish = MqttMessageBuilder::publish()
+                .qos(qos)
+                .packet_id(PacketId::new(7).unwrap())
+                .retain(true)
+                .topic("/test")
+                .payload_str("hello")
+                .build()
+                .unwrap()
+                .with_message_id(PacketId::new(8).unwrap());
+            let mut original = BytesMut::new();
+            publish.encode(&mut original).unwrap();
+            let mut buffer = BytesMut::new();
+            publish.for_retransmission().encode(&mut buffer).unwrap();
+            assert_eq!(buffer[0], original[0] | 0b0000_1000);
+            assert_eq!(buffer[1..], original[1..]);
+            let resend = Publish::decode(buffer.freeze()).unwrap();
+            assert!(resend.dup());
+            assert!(resend.retain());
+            assert_eq!(resend.qos(), qos);
+            assert_eq!(resend.variable_header_ref().message_id(), Some(8));
+        }
+        // QoS 0没有message_id
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .build()
+            .unwrap()
+            .with_message_id(PacketId::new(8).unwrap());
+        assert_eq!(publish.variable_header_ref().message_id(), None);
+        // QoS 0不会重发，dup保持为0
+        let resend = publish.for_retransmission();
+        assert!(!resend.dup());
+        assert_eq!(resend, publish);
+        let mut buffer = BytesMut::new();
+        resend.encode(&mut buffer).unwrap();
+        assert_eq!(buffer[0], 0x30);
+    }
+
+    #[test]
+    fn borrowing_accessors_should_not_copy() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        let frame = buffer.freeze();
+        let publish = Publish::decode(frame.clone()).unwrap();
+        // topic和payload都指向原始报文所在的内存
+        let topic = publish.variable_header_ref().topic_str();
+        assert_eq!(topic.as_ptr(), frame[4..].as_ptr());
+        assert_eq!(publish.payload_ref().as_ptr(), frame[9..].as_ptr());
+        assert_eq!(publish.fixed_header_ref().remaining_length(), 12);
+    }
+
+    #[test]
+    fn from_parts_should_reencode_identically() {
+        for qos in [
+            crate::QoS::AtMostOnce,
+            crate::QoS::AtLeastOnce,
+            crate::QoS::ExactlyOnce,
+        ] {
+            let mut builder = MqttMessageBuilder::publish()
+                .topic("/test")
+                .qos(qos)
+                .retain(true)
+                .payload(Bytes::from(vec![7u8; 200]));
+            if qos != crate::QoS::AtMostOnce {
+                builder = builder.packet_id(PacketId::new(9).unwrap());
+            }
+            let mut original = BytesMut::new();
+            builder.build().unwrap().encode(&mut original).unwrap();
+            let publish = Publish::decode(original.clone().freeze()).unwrap();
+            let (fixed_header, variable_header, payload) = publish.into_parts();
+            let payload_ptr = payload.as_ptr();
+            let publish = Publish::from_parts(fixed_header, variable_header, payload).unwrap();
+            assert_eq!(publish.payload_ref().as_ptr(), payload_ptr);
+            let mut buffer = BytesMut::new();
+            publish.encode(&mut buffer).unwrap();
+            assert_eq!(buffer, original);
+        }
+    }
+
+    #[test]
+    fn from_parts_should_check_packet_id() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .qos(crate::QoS::AtLeastOnce)
+            .packet_id(PacketId::new(9).unwrap())
+            .build()
+            .unwrap();
+        let (mut fixed_header, variable_header, payload) = publish.into_parts();
+        fixed_header.set_qos(crate::QoS::AtMostOnce);
+        assert_eq!(
+            Publish::from_parts(fixed_header.clone(), variable_header, payload.clone())
+                .unwrap_err(),
+            ProtoError::QoS0WithPacketId
+        );
+        let variable_header =
+            super::PublishVariableHeader::new("/test".to_string(), None, None).unwrap();
+        fixed_header.set_qos(crate::QoS::ExactlyOnce);
+        assert_eq!(
+            Publish::from_parts(fixed_header, variable_header, payload).unwrap_err(),
+            ProtoError::QoS12RequiresPacketId
+        );
+    }
+
+    #[test]
+    fn topic_ref_should_reject_invalid_utf8() {
+        use super::TopicRef;
+        use bytes::Bytes;
+        assert_eq!(
+            TopicRef::new(Bytes::from_static(&[0xff, 0xfe])).unwrap_err(),
+            ProtoError::MalformedString
+        );
+        // 解码时topic不合法同样返回MalformedString
+        let frame = Bytes::from_static(&[0x30, 0x05, 0x00, 0x02, 0xff, 0xfe, b'x']);
+        assert_eq!(
+            Publish::decode(frame).unwrap_err(),
+            ProtoError::MalformedString
+        );
+        let topic = TopicRef::new(Bytes::from_static(b"/a/b")).unwrap();
+        assert_eq!(topic.as_str(), "/a/b");
+        assert_eq!(topic.len(), 4);
+    }
+
+    #[test]
+    fn topic_ref_should_reject_null_char() {
+        use super::{PublishVariableHeader, TopicRef};
+        // TopicRef与MqttString使用同一套校验
+        assert_eq!(
+            TopicRef::new(Bytes::from_static(b"/a\0b")).unwrap_err(),
+            ProtoError::MalformedString
+        );
+        assert_eq!(
+            TopicRef::try_from("/a\0b").unwrap_err(),
+            ProtoError::MalformedString
+        );
+        assert_eq!(
+            PublishVariableHeader::new("/a\0b".to_string(), None, None).unwrap_err(),
+            ProtoError::MalformedString
+        );
+        let frame = Bytes::from_static(&[0x30, 0x06, 0x00, 0x03, b'/', 0x00, b'a', b'x']);
+        assert_eq!(
+            Publish::decode(frame).unwrap_err(),
+            ProtoError::MalformedString
+        );
+        let topic = TopicRef::try_from("/a/b").unwrap();
+        assert_eq!(topic.as_mqtt_string(), "/a/b");
+        assert_eq!(String::from(topic), "/a/b");
+    }
+
+    #[test]
+    fn publish_message_id_should_match_qos() {
+        // QoS0：设置的message_id会被忽略
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .qos(crate::QoS::AtMostOnce)
+            .packet_id(PacketId::new(1).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(publish.variable_header.message_id(), None);
+        let mut buffer = BytesMut::new();
+        publish.encode(&mut buffer).unwrap();
+        // fixed_header(2) + topic(2 + 5)，没有message_id
+        assert_eq!(buffer.len(), 9);
+
+        for qos in [crate::QoS::AtLeastOnce, crate::QoS::ExactlyOnce] {
+            // QoS1/QoS2：必须设置message_id
+            let resp = MqttMessageBuilder::publish()
+                .topic("/test")
+                .qos(qos)
+                .build();
+            assert_eq!(resp.unwrap_err(), ProtoError::QoS12RequiresPacketId);
+            let publish = MqttMessageBuilder::publish()
+                .topic("/test")
+                .qos(qos)
+                .packet_id(PacketId::new(2).unwrap())
+                .build()
+                .unwrap();
+            assert_eq!(publish.variable_header.message_id(), Some(2));
+        }
+    }
+
+    #[test]
+    fn encode_too_long_topic_should_return_error() {
+        let topic = "a".repeat(70_000);
+        let publish = MqttMessageBuilder::publish()
+            .topic(&topic)
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        assert_eq!(
+            publish.encode(&mut buffer).unwrap_err(),
+            ProtoError::StringTooLong(70_000)
+        );
+    }
 }
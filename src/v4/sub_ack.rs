@@ -1,10 +1,12 @@
-use bytes::{Buf, BufMut, Bytes, BytesMut};
-use crate::{error::ProtoError, QoS};
+use super::builder::{MqttMessageBuilder, SubAckBuilder};
 use super::{
     decoder::{self},
-    fixed_header::FixedHeader,
-    Decoder, Encoder, GeneralVariableHeader, VariableDecoder,
+    fixed_header::{FixedHeader, FixedHeaderBuilder},
+    subscribe::Subscribe,
+    BodyDecoder, Decoder, Encoder, GeneralVariableHeader, PacketEncoder, VariableDecoder,
 };
+use crate::{error::ProtoError, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 /// 订阅确认
 /// SUBACK报文，反应了broker对client的SUBSCRIBE报文的回应，由于SUBSCRIBE报文可以同事订阅多个Topic，
@@ -28,20 +30,91 @@ use super::{
 /// | byte4 | 报  | 文  | 标   | 识  | 符   | L   | S   | B   |
 /// | byte5 | x   | 0   | 0   | 0   | 0   |  0   | x   | x   |
 ///
-#[derive(Debug,Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SubAck {
     fixed_header: FixedHeader,
     variable_header: GeneralVariableHeader,
-    acks: Vec<u8>,
+    acks: Vec<SubscribeReturnCode>,
+}
+
+/// SUBACK报文中每个topic对应的返回码
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubscribeReturnCode {
+    SuccessQoS0 = 0x00,
+    SuccessQoS1 = 0x01,
+    SuccessQoS2 = 0x02,
+    Failure = 0x80,
+}
+
+impl SubscribeReturnCode {
+    /// 订阅成功时授予的QoS，订阅失败时返回None
+    pub fn qos(&self) -> Option<QoS> {
+        match self {
+            SubscribeReturnCode::SuccessQoS0 => Some(QoS::AtMostOnce),
+            SubscribeReturnCode::SuccessQoS1 => Some(QoS::AtLeastOnce),
+            SubscribeReturnCode::SuccessQoS2 => Some(QoS::ExactlyOnce),
+            SubscribeReturnCode::Failure => None,
+        }
+    }
+}
+
+impl From<QoS> for SubscribeReturnCode {
+    fn from(value: QoS) -> Self {
+        match value {
+            QoS::AtMostOnce => SubscribeReturnCode::SuccessQoS0,
+            QoS::AtLeastOnce => SubscribeReturnCode::SuccessQoS1,
+            QoS::ExactlyOnce => SubscribeReturnCode::SuccessQoS2,
+        }
+    }
+}
+
+impl From<SubscribeReturnCode> for u8 {
+    fn from(value: SubscribeReturnCode) -> Self {
+        value as u8
+    }
+}
+
+impl TryFrom<u8> for SubscribeReturnCode {
+    type Error = ProtoError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(SubscribeReturnCode::SuccessQoS0),
+            0x01 => Ok(SubscribeReturnCode::SuccessQoS1),
+            0x02 => Ok(SubscribeReturnCode::SuccessQoS2),
+            0x80 => Ok(SubscribeReturnCode::Failure),
+            n => Err(ProtoError::InvalidSubAckReturnCode(n)),
+        }
+    }
 }
 
 impl SubAck {
+    /**
+     SUBACK报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::sub_ack::{SubAck, SubscribeReturnCode};
+    let sub_ack = SubAck::builder()
+        .packet_id(PacketId::new(2).unwrap())
+        .acks(vec![SubscribeReturnCode::SuccessQoS0, SubscribeReturnCode::Failure])
+        .build()
+        .unwrap();
+    assert_eq!(sub_ack.acks_raw(), vec![0x00, 0x80]);
+    ```
+     */
+    pub fn builder() -> SubAckBuilder {
+        MqttMessageBuilder::sub_ack()
+    }
+
     pub fn new(
         mut fixed_header: FixedHeader,
         variable_header: GeneralVariableHeader,
-        acks: Vec<u8>,
+        acks: Vec<SubscribeReturnCode>,
     ) -> Self {
-        fixed_header.set_remaining_length(acks.len());
+        fixed_header.set_remaining_length(variable_header.len() + acks.len());
         Self {
             fixed_header,
             variable_header,
@@ -49,8 +122,34 @@ impl SubAck {
         }
     }
 
+    /// 根据SUBSCRIBE报文构建SUBACK报文，message_id与SUBSCRIBE报文保持一致，
+    /// granted中的返回码需要按照SUBSCRIBE报文中topic的顺序排列
+    pub fn from_subscribe(subscribe: &Subscribe, granted: &[SubscribeReturnCode]) -> Self {
+        let fixed_header = FixedHeaderBuilder::new().sub_ack().build().unwrap();
+        let variable_header =
+            GeneralVariableHeader::new(subscribe.variable_header_ref().packet_id());
+        SubAck::new(fixed_header, variable_header, granted.to_vec())
+    }
+
     pub fn message_id(&self) -> usize {
-        self.variable_header.message_id
+        self.variable_header.message_id()
+    }
+
+    pub fn fixed_header_ref(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+
+    pub fn variable_header_ref(&self) -> &GeneralVariableHeader {
+        &self.variable_header
+    }
+
+    pub fn acks(&self) -> &[SubscribeReturnCode] {
+        &self.acks
+    }
+
+    /// 以字节的形式返回所有的返回码
+    pub fn acks_raw(&self) -> Vec<u8> {
+        self.acks.iter().map(|ack| u8::from(*ack)).collect()
     }
 
     pub fn qos(&self) -> Option<QoS> {
@@ -69,9 +168,8 @@ impl Encoder for SubAck {
                 let resp = self.variable_header.encode(buffer);
                 match resp {
                     Ok(variable_header_len) => {
-                        let acks = self.acks.iter();
-                        for ack in acks {
-                            buffer.put_u8(ack.clone());
+                        for ack in &self.acks {
+                            buffer.put_u8(u8::from(*ack));
                         }
                         Ok(fixed_header_len + variable_header_len + self.acks.len())
                     }
@@ -83,6 +181,12 @@ impl Encoder for SubAck {
     }
 }
 
+impl PacketEncoder for SubAck {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.variable_header.len() + self.acks.len()
+    }
+}
+
 impl Decoder for SubAck {
     type Item = SubAck;
     type Error = ProtoError;
@@ -98,10 +202,10 @@ impl Decoder for SubAck {
                 let resp = GeneralVariableHeader::decode(&mut bytes, qos);
                 match resp {
                     Ok(variable_header) => {
-                        let acks: Vec<u8> = Vec::from(bytes);
+                        let acks = read_return_codes(&bytes)?;
                         Ok(SubAck::new(fixed_header, variable_header, acks))
                     }
-                    Err(e) => return Err(e),
+                    Err(e) => Err(e),
                 }
             }
             Err(e) => Err(e),
@@ -109,20 +213,42 @@ impl Decoder for SubAck {
     }
 }
 
+impl BodyDecoder for SubAck {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        let acks = read_return_codes(&body)?;
+        Ok(SubAck::new(fixed_header, variable_header, acks))
+    }
+}
+
+// 读取payload中的返回码，只允许0x00、0x01、0x02和0x80
+fn read_return_codes(payload: &[u8]) -> Result<Vec<SubscribeReturnCode>, ProtoError> {
+    payload
+        .iter()
+        .map(|ack| SubscribeReturnCode::try_from(*ack))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use crate::common::packet_id::PacketId;
+    use bytes::{Bytes, BytesMut};
 
-    use crate::v4::{builder::MqttMessageBuilder, Decoder, Encoder}
-    ;
+    use crate::error::ProtoError;
+    use crate::v4::{builder::MqttMessageBuilder, Decoder, Encoder};
+    use crate::{QoS, Topic};
 
-    use super::SubAck;
+    use super::{SubAck, SubscribeReturnCode};
 
     #[test]
     fn test() {
         let resp = MqttMessageBuilder::sub_ack()
-            .message_id(12)
-            .acks(vec![0, 1, 2, 1, 1, 0])
+            .packet_id(PacketId::new(12).unwrap())
+            .acks(vec![
+                SubscribeReturnCode::SuccessQoS0,
+                SubscribeReturnCode::SuccessQoS1,
+                SubscribeReturnCode::SuccessQoS2,
+            ])
             .build()
             .unwrap();
         println!("原始的sub = {:?}", resp);
@@ -134,4 +260,62 @@ mod tests {
             Err(e) => println!("解码异常 {}", e),
         }
     }
+
+    #[test]
+    fn sub_ack_from_subscribe_should_be_work() {
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(0x1234).unwrap())
+            .topic(Topic::new("/a".to_string(), QoS::AtLeastOnce))
+            .topic(Topic::new("/b".to_string(), QoS::ExactlyOnce))
+            .topic(Topic::new("/c".to_string(), QoS::AtMostOnce))
+            .build()
+            .unwrap();
+        let granted = [
+            SubscribeReturnCode::SuccessQoS1,
+            SubscribeReturnCode::Failure,
+            SubscribeReturnCode::from(QoS::AtMostOnce),
+        ];
+        let sub_ack = SubAck::from_subscribe(&subscribe, &granted);
+        assert_eq!(sub_ack.message_id(), 0x1234);
+        assert_eq!(sub_ack.acks_raw(), vec![0x01, 0x80, 0x00]);
+
+        let mut bytes = BytesMut::new();
+        sub_ack.encode(&mut bytes).unwrap();
+        let sub_ack = SubAck::decode(bytes.freeze()).unwrap();
+        assert_eq!(sub_ack.message_id(), 0x1234);
+        assert_eq!(sub_ack.acks(), &granted);
+        assert_eq!(sub_ack.acks()[1].qos(), None);
+    }
+
+    #[test]
+    fn remaining_length_should_match_acks() {
+        // 订阅数量为200时剩余长度需要两个字节
+        for (count, expected) in [
+            (0, &[0x02][..]),
+            (1, &[0x03]),
+            (3, &[0x05]),
+            (10, &[0x0c]),
+            (200, &[0xca, 0x01]),
+        ] {
+            let sub_ack = MqttMessageBuilder::sub_ack()
+                .packet_id(PacketId::new(1).unwrap())
+                .acks(vec![SubscribeReturnCode::SuccessQoS1; count])
+                .build()
+                .unwrap();
+            let mut bytes = BytesMut::new();
+            let len = sub_ack.encode(&mut bytes).unwrap();
+            assert_eq!(len, bytes.len());
+            assert_eq!(&bytes[1..1 + expected.len()], expected);
+            assert_eq!(bytes.len(), 1 + expected.len() + 2 + count);
+        }
+    }
+
+    #[test]
+    fn invalid_return_code_should_return_error() {
+        let frame = Bytes::from_static(&[0x90, 0x03, 0x00, 0x01, 0x03]);
+        assert_eq!(
+            SubAck::decode(frame).unwrap_err(),
+            ProtoError::InvalidSubAckReturnCode(0x03)
+        );
+    }
 }
@@ -1,10 +1,15 @@
+use super::builder::{MqttMessageBuilder, SubscribeBuilder};
+use super::sub_ack::SubscribeReturnCode;
 use super::{
-    decoder, fixed_header::FixedHeader, Decoder, Encoder, GeneralVariableHeader, VariableDecoder,
+    decoder, fixed_header::FixedHeader, BodyDecoder, Decoder, Encoder, GeneralVariableHeader,
+    PacketEncoder, VariableDecoder,
 };
-use crate::{error::ProtoError, Topic};
+use crate::common::topic::{validate_topic_filter, validate_topic_filters, TopicFilter};
+use crate::{error::ProtoError, granted_qos, QoS, Topic};
 use bytes::{Buf, Bytes, BytesMut};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Subscribe {
     // 固定报头
     fixed_header: FixedHeader,
@@ -12,41 +17,118 @@ pub struct Subscribe {
     variable_header: GeneralVariableHeader,
     // payload中包括了各种订阅的topic，格式为：topic_len|topic|qos,
     // 所以Topic的长度是，2+topic_len+1 = 3+topic_len
-    topices: Vec<Topic>,
+    topices: Vec<TopicFilter>,
 }
 
 impl Subscribe {
-    pub fn new(
+    /**
+     SUBSCRIBE报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::common::topic::TopicFilter;
+    use walle_mqtt_protocol::v4::subscribe::Subscribe;
+    use walle_mqtt_protocol::QoS;
+    let subscribe = Subscribe::builder()
+        .packet_id(PacketId::new(2).unwrap())
+        .topic(TopicFilter::new("sport/#", QoS::AtMostOnce).unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(subscribe.topic_filters().len(), 1);
+    ```
+     */
+    pub fn builder() -> SubscribeBuilder {
+        MqttMessageBuilder::subscribe()
+    }
+
+    /// topices可以是[`TopicFilter`]，也可以是之前的[`Topic`]
+    pub fn new<T: Into<TopicFilter>>(
         fixed_header: FixedHeader,
         variable_header: GeneralVariableHeader,
-        topices: Vec<Topic>,
+        topices: Vec<T>,
     ) -> Self {
         Self {
             fixed_header,
             variable_header,
-            topices,
+            topices: topices.into_iter().map(Into::into).collect(),
         }
         .build()
     }
 
     fn topics_len(&self) -> usize {
-        let mut len = 0;
-        for temp in &self.topices {
-            len += temp.name_len() + 3;
-        }
-        len
+        self.topices.iter().map(|filter| filter.len() + 3).sum()
     }
 
+    #[deprecated(note = "请使用fixed_header_ref")]
     pub fn fixed_header(&self) -> FixedHeader {
         self.fixed_header.clone()
     }
 
+    pub fn fixed_header_ref(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+
+    #[deprecated(note = "请使用variable_header_ref")]
     pub fn variable_header(&self) -> GeneralVariableHeader {
         self.variable_header.clone()
     }
 
+    pub fn variable_header_ref(&self) -> &GeneralVariableHeader {
+        &self.variable_header
+    }
+
+    #[deprecated(note = "请使用topic_filters")]
     pub fn topices(&self) -> Vec<Topic> {
-        self.topices.clone()
+        self.topices.iter().cloned().map(Topic::from).collect()
+    }
+
+    pub fn topic_filters(&self) -> &[TopicFilter] {
+        &self.topices
+    }
+
+    /// 按顺序返回每个topic filter及其请求的QoS
+    pub fn iter(&self) -> impl Iterator<Item = (&str, QoS)> {
+        self.topices
+            .iter()
+            .map(|filter| (filter.path(), filter.qos()))
+    }
+
+    /**
+     broker根据支持的最大QoS计算SUBACK中的返回码，顺序与topic filter一致，
+     不合法的topic filter对应的返回码为Failure，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::sub_ack::{SubAck, SubscribeReturnCode};
+    use walle_mqtt_protocol::v4::subscribe::Subscribe;
+    use walle_mqtt_protocol::QoS;
+    let subscribe = Subscribe::builder()
+        .packet_id(PacketId::MIN)
+        .add("sport/#", QoS::ExactlyOnce)
+        .add("news", QoS::AtMostOnce)
+        .build()
+        .unwrap();
+    let sub_ack = SubAck::from_subscribe(&subscribe, &subscribe.grant(QoS::AtLeastOnce));
+    assert_eq!(
+        sub_ack.acks(),
+        &[SubscribeReturnCode::SuccessQoS1, SubscribeReturnCode::SuccessQoS0]
+    );
+    ```
+     */
+    pub fn grant(&self, max_qos: QoS) -> Vec<SubscribeReturnCode> {
+        self.topices
+            .iter()
+            .map(|filter| match validate_topic_filter(filter.path()) {
+                Ok(_) => granted_qos(filter.qos(), max_qos).into(),
+                Err(_) => SubscribeReturnCode::Failure,
+            })
+            .collect()
+    }
+
+    /// 检查所有的topic filter，解码时不会做这个检查，
+    /// broker可以根据检查结果决定回复SUBACK失败还是断开连接
+    pub fn validate(&self) -> Result<(), ProtoError> {
+        validate_topic_filters(self.topices.iter().map(TopicFilter::path))
     }
 
     fn build(mut self) -> Self {
@@ -68,7 +150,7 @@ impl Encoder for Subscribe {
                 if let Ok(v_len) = self.variable_header.encode(buffer) {
                     let resp = len + v_len;
                     for temp in &self.topices {
-                        let _ = temp.encode(buffer);
+                        temp.encode(buffer)?;
                     }
                     let topic_len = self.topics_len();
                     return Ok(resp + topic_len);
@@ -80,6 +162,12 @@ impl Encoder for Subscribe {
     }
 }
 
+impl PacketEncoder for Subscribe {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len() + self.variable_header.len() + self.topics_len()
+    }
+}
+
 impl Decoder for Subscribe {
     type Item = Subscribe;
     type Error = ProtoError;
@@ -91,9 +179,17 @@ impl Decoder for Subscribe {
                 let qos = fixed_header.qos();
                 let variable_header_index = fixed_header.len();
                 bytes.advance(variable_header_index);
-                if let Ok(variable_header) = GeneralVariableHeader::decode(&mut bytes, qos) {
-                    let topices = Topic::read_topics(&mut bytes);
+                let variable_header = GeneralVariableHeader::decode(&mut bytes, qos);
+                // message_id为0时返回具体的错误
+                if let Err(ProtoError::InvalidPacketId(id)) = variable_header {
+                    return Err(ProtoError::InvalidPacketId(id));
+                }
+                if let Ok(variable_header) = variable_header {
+                    let topices = TopicFilter::read_filters(&mut bytes);
                     match topices {
+                        Ok(topices) if topices.is_empty() => {
+                            return Err(ProtoError::EmptySubscription)
+                        }
                         Ok(topices) => {
                             return Ok(Subscribe {
                                 fixed_header,
@@ -111,16 +207,36 @@ impl Decoder for Subscribe {
     }
 }
 
+impl BodyDecoder for Subscribe {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        let topices = TopicFilter::read_filters(&mut body)?;
+        if topices.is_empty() {
+            return Err(ProtoError::EmptySubscription);
+        }
+        Ok(Subscribe {
+            fixed_header,
+            variable_header,
+            topices,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use crate::common::packet_id::PacketId;
+    use bytes::{Bytes, BytesMut};
 
     use crate::{
-        v4::{builder::MqttMessageBuilder, Decoder, Encoder},
+        error::ProtoError,
+        v4::{builder::MqttMessageBuilder, decode_zero_copy, Decoder, Encoder},
         Topic,
     };
 
     use super::Subscribe;
+    use crate::common::topic::TopicFilter;
+    use crate::v4::sub_ack::SubscribeReturnCode;
+    use crate::QoS;
 
     fn build_sub() -> Subscribe {
         let mut topices = Vec::new();
@@ -131,7 +247,7 @@ mod tests {
         // Subscribe::new(topices, 1892).unwrap()
         MqttMessageBuilder::subscribe()
             .topics(topices)
-            .message_id(1892)
+            .packet_id(PacketId::new(1892).unwrap())
             .build()
             .unwrap()
     }
@@ -139,13 +255,155 @@ mod tests {
     #[test]
     fn encode_and_decode_subscribe_shoud_be_work() {
         let sub = build_sub();
-        println!("原始sub = {:?}", sub);
         let mut bytes = BytesMut::new();
-        let _ = sub.encode(&mut bytes);
-        let resp = Subscribe::decode(bytes.into());
-        match resp {
-            Ok(sub) => println!("新的sub = {:?}", sub),
-            Err(e) => println!("解码异常 {}", e),
+        let len = sub.encode(&mut bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        assert_eq!(Subscribe::decode(bytes.into()).unwrap(), sub);
+    }
+
+    #[test]
+    fn empty_subscribe_should_return_error() {
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::EmptySubscription);
+        // 只有message_id，没有payload的SUBSCRIBE报文
+        let frame = Bytes::from_static(&[0x82, 0x02, 0x00, 0x01]);
+        assert_eq!(
+            Subscribe::decode(frame.clone()).unwrap_err(),
+            ProtoError::EmptySubscription
+        );
+        assert_eq!(
+            decode_zero_copy(frame).unwrap_err(),
+            ProtoError::EmptySubscription
+        );
+    }
+
+    #[test]
+    fn encode_too_long_topic_should_return_error() {
+        let sub = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic(Topic::new("a".repeat(70_000), crate::QoS::AtMostOnce))
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        assert_eq!(
+            sub.encode(&mut bytes).unwrap_err(),
+            ProtoError::StringTooLong(70_000)
+        );
+    }
+
+    #[test]
+    fn invalid_subscription_options_should_return_error() {
+        // 第二个topic的订阅选项分别为：保留位非0且QoS为3、QoS为3、保留位非0
+        for byte in [0x83u8, 0x03, 0x40] {
+            let frame = Bytes::from(vec![
+                0x82, 0x0c, 0x00, 0x01, 0x00, 0x02, b'/', b'a', 0x01, 0x00, 0x02, b'/', b'b', byte,
+            ]);
+            let expected = ProtoError::InvalidSubscriptionOptions {
+                topic_index: 1,
+                byte,
+            };
+            assert_eq!(Subscribe::decode(frame.clone()).unwrap_err(), expected);
+            assert_eq!(decode_zero_copy(frame).unwrap_err(), expected);
+        }
+    }
+
+    #[test]
+    fn empty_topic_filter_should_return_error() {
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic(Topic::new(String::new(), crate::QoS::AtMostOnce))
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::EmptyTopicFilter);
+        let frame = Bytes::from_static(&[0x82, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(
+            Subscribe::decode(frame).unwrap_err(),
+            ProtoError::EmptyTopicFilter
+        );
+    }
+
+    #[test]
+    fn invalid_topic_filter_should_return_error() {
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic(Topic::new("sport/#".to_string(), crate::QoS::AtMostOnce))
+            .topic(Topic::new(
+                "sport/ten#nis".to_string(),
+                crate::QoS::AtMostOnce,
+            ))
+            .build();
+        assert!(matches!(
+            resp,
+            Err(ProtoError::InvalidTopicFilter { topic_index: 1, .. })
+        ));
+        // 解码时不检查，由调用方决定是否调用validate
+        let frame = Bytes::from_static(&[
+            0x82, 0x0b, 0x00, 0x01, 0x00, 0x01, b'#', 0x00, 0x00, 0x03, b'a', b'+', b'b', 0x01,
+        ]);
+        let subscribe = Subscribe::decode(frame).unwrap();
+        assert!(matches!(
+            subscribe.validate(),
+            Err(ProtoError::InvalidTopicFilter { topic_index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn subscribe_with_topic_filter_should_be_work() {
+        let filter = TopicFilter::new("sport/+", crate::QoS::AtLeastOnce).unwrap();
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic(filter.clone())
+            .topic(Topic::new("/a".to_string(), crate::QoS::AtMostOnce))
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        subscribe.encode(&mut bytes).unwrap();
+        let subscribe = Subscribe::decode(bytes.freeze()).unwrap();
+        assert_eq!(subscribe.topic_filters()[0], filter);
+        assert_eq!(subscribe.topic_filters()[1].path(), "/a");
+    }
+
+    #[test]
+    fn grant_should_keep_order_and_fail_invalid_filter() {
+        // "#"、"a+b"、"a/+"，第二个topic filter不合法
+        let frame = Bytes::from_static(&[
+            0x82, 0x12, 0x00, 0x01, 0x00, 0x01, b'#', 0x02, 0x00, 0x03, b'a', b'+', b'b', 0x01,
+            0x00, 0x03, b'a', b'/', b'+', 0x00,
+        ]);
+        let subscribe = Subscribe::decode(frame).unwrap();
+        assert_eq!(
+            subscribe.iter().collect::<Vec<_>>(),
+            vec![
+                ("#", QoS::ExactlyOnce),
+                ("a+b", QoS::AtLeastOnce),
+                ("a/+", QoS::AtMostOnce)
+            ]
+        );
+        assert_eq!(
+            subscribe.grant(QoS::AtLeastOnce),
+            vec![
+                SubscribeReturnCode::SuccessQoS1,
+                SubscribeReturnCode::Failure,
+                SubscribeReturnCode::SuccessQoS0
+            ]
+        );
+        assert_eq!(
+            subscribe.grant(QoS::ExactlyOnce)[0],
+            SubscribeReturnCode::SuccessQoS2
+        );
+    }
+
+    #[cfg(feature = "bytes-strings")]
+    #[test]
+    fn decode_should_share_frame_memory() {
+        let mut bytes = BytesMut::new();
+        build_sub().encode(&mut bytes).unwrap();
+        let frame = bytes.freeze();
+        let range = frame.as_ptr_range();
+        let subscribe = Subscribe::decode(frame.clone()).unwrap();
+        for (path, _) in subscribe.iter() {
+            assert!(range.contains(&path.as_ptr()));
         }
     }
 }
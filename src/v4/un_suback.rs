@@ -1,26 +1,51 @@
+use super::builder::{MqttMessageBuilder, UnsubAckBuilder};
 use super::{
-    fixed_header::FixedHeader,
-    Decoder, Encoder,
+    fixed_header::{FixedHeader, FixedHeaderBuilder},
+    BodyDecoder, Decoder, Encoder, PacketEncoder,
 };
-use crate::v4::{decoder, GeneralVariableHeader, VariableDecoder};
+use crate::common::packet_id::PacketId;
 use crate::error::ProtoError;
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crate::v4::{decoder, GeneralVariableHeader, VariableDecoder};
+use bytes::{Buf, Bytes, BytesMut};
 
-#[derive(Debug,Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnSubAck {
     fixed_header: FixedHeader,
     variable_header: GeneralVariableHeader,
 }
 
 impl UnSubAck {
-    pub fn new(fixed_header: FixedHeader, variable_header: GeneralVariableHeader) -> Self {
+    /**
+     UNSUBACK报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::un_suback::UnSubAck;
+    let unsub_ack = UnSubAck::builder()
+        .packet_id(PacketId::new(3).unwrap())
+        .build()
+        .unwrap();
+    assert_eq!(unsub_ack, UnSubAck::new(PacketId::new(3).unwrap()));
+    ```
+     */
+    pub fn builder() -> UnsubAckBuilder {
+        MqttMessageBuilder::unsub_ack()
+    }
+
+    pub fn new(message_id: PacketId) -> Self {
+        let variable_header = GeneralVariableHeader::new(message_id);
         Self {
-            fixed_header,
+            fixed_header: FixedHeaderBuilder::new()
+                .un_suback()
+                .remaining_length(variable_header.len())
+                .build()
+                .unwrap(),
             variable_header,
         }
     }
     pub fn message_id(&self) -> usize {
-        self.variable_header.message_id
+        self.variable_header.message_id()
     }
 }
 
@@ -29,11 +54,19 @@ impl UnSubAck {
 //////////////////////////////////////////////////////
 impl Encoder for UnSubAck {
     fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
-        if let Ok(_resp) = self.fixed_header.encode(buffer) {
-            buffer.put_u16(self.variable_header.message_id as u16);
-            return Ok(4);
-        }
-        Err(ProtoError::NotKnow)
+        // UNSUBACK报文的剩余长度固定为2，不依赖构建时设置的值
+        let mut fixed_header = self.fixed_header.clone();
+        fixed_header.set_remaining_length(self.variable_header.len());
+        let fixed_header_len = fixed_header.encode(buffer)?;
+        let variable_header_len = self.variable_header.encode(buffer)?;
+        Ok(fixed_header_len + variable_header_len)
+    }
+}
+
+impl PacketEncoder for UnSubAck {
+    fn serialized_len(&self) -> usize {
+        // fixed_header固定为2个字节
+        2 + self.variable_header.len()
     }
 }
 
@@ -62,6 +95,16 @@ impl Decoder for UnSubAck {
         }
     }
 }
+
+impl BodyDecoder for UnSubAck {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        Ok(UnSubAck {
+            fixed_header,
+            variable_header,
+        })
+    }
+}
 //     // 1、判断bytes的长度，PubComp报文只有固定的4个字节
 //     if bytes.len() != 4 {
 //         return Err(ProtoError::NotKnow);
@@ -81,3 +124,44 @@ impl Decoder for UnSubAck {
 //         Err(ProtoError::NotKnow)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::UnSubAck;
+    use crate::common::packet_id::PacketId;
+    use crate::v4::{Decoder, Encoder};
+    use bytes::BytesMut;
+
+    #[test]
+    fn encode_and_decode_for_unsuback_should_be_work() {
+        for message_id in [0x0001, 0xFFFF] {
+            let unsuback = UnSubAck::new(PacketId::new(message_id).unwrap());
+            let mut bytes = BytesMut::new();
+            let len = unsuback.encode(&mut bytes).unwrap();
+            assert_eq!(len, bytes.len());
+            assert_eq!(
+                bytes.as_ref(),
+                &[0xb0, 0x02, (message_id >> 8) as u8, message_id as u8]
+            );
+            let unsuback = UnSubAck::decode(bytes.freeze()).unwrap();
+            assert_eq!(unsuback.message_id(), message_id as usize);
+        }
+    }
+
+    #[test]
+    fn unsuback_remaining_length_should_be_two() {
+        use crate::v4::{decode_zero_copy, Packet, PacketEncoder};
+        let unsuback = UnSubAck::builder()
+            .packet_id(PacketId::new(0x1234).unwrap())
+            .build()
+            .unwrap();
+        let mut bytes = BytesMut::new();
+        unsuback.encode(&mut bytes).unwrap();
+        assert_eq!(bytes[1], 0x02);
+        assert_eq!(unsuback.serialized_len(), bytes.len());
+        match decode_zero_copy(bytes.freeze()).unwrap() {
+            Packet::UnSubAck(decoded) => assert_eq!(decoded, unsuback),
+            packet => panic!("unexpected packet: {:?}", packet),
+        }
+    }
+}
@@ -1,10 +1,13 @@
-use bytes::{Buf, Bytes, BytesMut};
-use crate::{error::ProtoError, v4::VariableDecoder};
+use super::builder::{MqttMessageBuilder, UnsubscriberBuilder};
 use super::{
-    decoder::{self, write_mqtt_string},
-    fixed_header::FixedHeader,
-    Decoder, Encoder, GeneralVariableHeader,
+    decoder, fixed_header::FixedHeader, BodyDecoder, Decoder, Encoder, GeneralVariableHeader,
+    PacketEncoder,
 };
+use crate::common::coder::write_mqtt_string;
+use crate::common::mqtt_string::read_string_field;
+use crate::common::topic::TopicFilter;
+use crate::{error::ProtoError, v4::VariableDecoder, QoS};
+use bytes::{Buf, Bytes, BytesMut};
 
 /// | Bit   | 7   | 6   | 5   | 4   | 3   | 2   | 1   | 0   |
 /// | ----- | --- | --- | --- | --- | --- | --- | --- | --- |
@@ -13,18 +16,38 @@ use super::{
 /// | byte3 | 报   | 文   | 标  | 识   | 符  | M   | S   | B   |
 /// | byte4 | 报   | 文   | 标  | 识   | 符  | L   | S   | B   |
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnSubscribe {
     fixed_header: FixedHeader,
     variable_header: GeneralVariableHeader,
-    topices: Vec<String>,
+    // 只使用TopicFilter的path
+    topices: Vec<TopicFilter>,
 }
 
 impl UnSubscribe {
+    /**
+     UNSUBSCRIBE报文构建器，例如：
+
+    ```rust
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::un_subscribe::UnSubscribe;
+    let unsubscribe = UnSubscribe::builder()
+        .packet_id(PacketId::new(3).unwrap())
+        .topices(vec!["sport/#".to_string()])
+        .build()
+        .unwrap();
+    assert_eq!(unsubscribe.topic_filters()[0].path(), "sport/#");
+    ```
+     */
+    pub fn builder() -> UnsubscriberBuilder {
+        MqttMessageBuilder::unsubscriber()
+    }
+
     pub fn new(
         fixed_header: FixedHeader,
         variable_header: GeneralVariableHeader,
-        topices: Vec<String>,
+        topices: Vec<TopicFilter>,
     ) -> Self {
         Self {
             fixed_header,
@@ -34,11 +57,27 @@ impl UnSubscribe {
     }
 
     pub fn message_id(&self) -> usize {
-        self.variable_header.message_id
+        self.variable_header.message_id()
+    }
+
+    pub fn fixed_header_ref(&self) -> &FixedHeader {
+        &self.fixed_header
     }
 
+    pub fn variable_header_ref(&self) -> &GeneralVariableHeader {
+        &self.variable_header
+    }
+
+    #[deprecated(note = "请使用topic_filters")]
     pub fn topices(&self) -> Vec<String> {
-        self.topices.clone()
+        self.topices
+            .iter()
+            .map(|filter| filter.path().to_string())
+            .collect()
+    }
+
+    pub fn topic_filters(&self) -> &[TopicFilter] {
+        &self.topices
     }
 }
 
@@ -51,9 +90,7 @@ impl Encoder for UnSubscribe {
                     let resp = len + v_len;
                     let mut topics_len = 0;
                     for temp in &self.topices {
-                        write_mqtt_string(buffer, temp);
-                        let topic_len = temp.len() + 2;
-                        topics_len += topic_len;
+                        topics_len += write_mqtt_string(buffer, temp)?;
                     }
                     return Ok(resp + topics_len);
                 }
@@ -64,6 +101,18 @@ impl Encoder for UnSubscribe {
     }
 }
 
+impl PacketEncoder for UnSubscribe {
+    fn serialized_len(&self) -> usize {
+        self.fixed_header.len()
+            + self.variable_header.len()
+            + self
+                .topices
+                .iter()
+                .map(|topic| topic.len() + 2)
+                .sum::<usize>()
+    }
+}
+
 impl Decoder for UnSubscribe {
     type Item = UnSubscribe;
     type Error = ProtoError;
@@ -75,16 +124,26 @@ impl Decoder for UnSubscribe {
                 let qos = fixed_header.qos();
                 let variable_header_index = fixed_header.len();
                 bytes.advance(variable_header_index);
-                if let Ok(variable_header) = GeneralVariableHeader::decode(&mut bytes, qos) {
+                let variable_header = GeneralVariableHeader::decode(&mut bytes, qos);
+                // message_id为0时返回具体的错误
+                if let Err(ProtoError::InvalidPacketId(id)) = variable_header {
+                    return Err(ProtoError::InvalidPacketId(id));
+                }
+                if let Ok(variable_header) = variable_header {
                     let mut topices = Vec::new();
                     // println!("bytes: {:?}", bytes);
                     while !bytes.is_empty() {
-                        let topic = decoder::read_mqtt_string(&mut bytes);
+                        let topic = read_string_field(&mut bytes);
                         match topic {
-                            Ok(topic) => topices.push(topic),
+                            Ok(topic) => {
+                                topices.push(TopicFilter::new_unchecked(topic, QoS::AtMostOnce))
+                            }
                             Err(e) => return Err(e),
                         }
                     }
+                    if topices.is_empty() {
+                        return Err(ProtoError::EmptyUnsubscription);
+                    }
                     return Ok(UnSubscribe::new(fixed_header, variable_header, topices));
                 }
                 Err(ProtoError::DecodeGeneralVariableHeaderError)
@@ -94,11 +153,28 @@ impl Decoder for UnSubscribe {
     }
 }
 
+impl BodyDecoder for UnSubscribe {
+    fn decode_body(fixed_header: FixedHeader, mut body: Bytes) -> Result<Self, ProtoError> {
+        let variable_header = GeneralVariableHeader::decode(&mut body, None)?;
+        let mut topices = Vec::new();
+        while !body.is_empty() {
+            let path = read_string_field(&mut body)?;
+            topices.push(TopicFilter::new_unchecked(path, QoS::AtMostOnce));
+        }
+        if topices.is_empty() {
+            return Err(ProtoError::EmptyUnsubscription);
+        }
+        Ok(UnSubscribe::new(fixed_header, variable_header, topices))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use bytes::BytesMut;
+    use crate::common::packet_id::PacketId;
+    use bytes::{Bytes, BytesMut};
 
-    use crate::v4::{builder::MqttMessageBuilder, Decoder, Encoder};
+    use crate::error::ProtoError;
+    use crate::v4::{builder::MqttMessageBuilder, decode_zero_copy, Decoder, Encoder};
 
     use super::UnSubscribe;
 
@@ -109,25 +185,49 @@ mod tests {
         topices.push(topic1);
         topices.push(topic2);
         // Subscribe::new(topices, 1892).unwrap()
-        let unsub = MqttMessageBuilder::unsubscriber()
-            .message_id(65531)
+        MqttMessageBuilder::unsubscriber()
+            .packet_id(PacketId::new(65531).unwrap())
             .topices(topices)
             .build()
-            .unwrap();
-        unsub
+            .unwrap()
     }
 
     #[test]
     fn encode_and_decode_subscribe_shoud_be_work() {
         let sub = build_sub();
-        println!("原始sub = {:?}", sub);
         let mut bytes = BytesMut::new();
-        let _ = sub.encode(&mut bytes);
-        println!("buffer = {:?}", bytes);
-        let resp = UnSubscribe::decode(bytes.into());
-        match resp {
-            Ok(sub) => println!("新的sub = {:?}", sub),
-            Err(e) => println!("解码异常 {}", e),
-        }
+        let len = sub.encode(&mut bytes).unwrap();
+        assert_eq!(len, bytes.len());
+        assert_eq!(UnSubscribe::decode(bytes.into()).unwrap(), sub);
+    }
+
+    #[test]
+    fn empty_unsubscribe_should_return_error() {
+        let resp = MqttMessageBuilder::unsubscriber()
+            .packet_id(PacketId::new(1).unwrap())
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::EmptyUnsubscription);
+        // 只有message_id，没有payload的UNSUBSCRIBE报文
+        let frame = Bytes::from_static(&[0xa2, 0x02, 0x00, 0x01]);
+        assert_eq!(
+            UnSubscribe::decode(frame.clone()).unwrap_err(),
+            ProtoError::EmptyUnsubscription
+        );
+        assert_eq!(
+            decode_zero_copy(frame).unwrap_err(),
+            ProtoError::EmptyUnsubscription
+        );
+    }
+
+    #[test]
+    fn invalid_topic_filter_should_return_error() {
+        let resp = MqttMessageBuilder::unsubscriber()
+            .packet_id(PacketId::new(1).unwrap())
+            .topices(vec!["sport+".to_string()])
+            .build();
+        assert!(matches!(
+            resp,
+            Err(ProtoError::InvalidTopicFilter { topic_index: 0, .. })
+        ));
     }
 }
@@ -0,0 +1,542 @@
+use super::builder::AuthBuilder;
+use super::property::{
+    properties_len, read_properties, unexpected_property, write_properties, AUTHENTICATION_DATA,
+    AUTHENTICATION_METHOD, REASON_STRING, USER_PROPERTY,
+};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::{
+    read_mqtt_bytes, read_mqtt_string, read_u8, write_mqtt_bytes, write_mqtt_string,
+};
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// AUTH报文的原因码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AuthReasonCode {
+    /// 认证成功
+    Success = 0x00,
+    /// 继续认证，交换下一步的认证数据
+    Continue = 0x18,
+    /// 客户端发起重新认证
+    ReAuthenticate = 0x19,
+}
+
+impl From<AuthReasonCode> for u8 {
+    fn from(code: AuthReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for AuthReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(AuthReasonCode::Success),
+            0x18 => Ok(AuthReasonCode::Continue),
+            0x19 => Ok(AuthReasonCode::ReAuthenticate),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+/// AUTH报文的属性，认证方法是必须的，并且要与CONNECT中的认证方法一致
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthProperties {
+    // 0x15 认证方法
+    pub(crate) authentication_method: String,
+    // 0x16 认证数据
+    pub(crate) authentication_data: Option<Bytes>,
+    // 0x1F 原因字符串
+    pub(crate) reason_string: Option<String>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+}
+
+impl AuthProperties {
+    pub fn new(authentication_method: &str) -> Self {
+        Self {
+            authentication_method: authentication_method.to_string(),
+            authentication_data: None,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    pub fn authentication_method(&self) -> &str {
+        &self.authentication_method
+    }
+    pub fn authentication_data(&self) -> Option<&Bytes> {
+        self.authentication_data.as_ref()
+    }
+    pub fn reason_string(&self) -> Option<&str> {
+        self.reason_string.as_deref()
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 1 + 2 + self.authentication_method.len();
+        if let Some(authentication_data) = &self.authentication_data {
+            len += 1 + 2 + authentication_data.len();
+        }
+        if let Some(reason_string) = &self.reason_string {
+            len += 1 + 2 + reason_string.len();
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容，缺少认证方法时返回[`ProtoError::InvalidAuthMethod`]
+    pub fn decode(bytes: &mut Bytes) -> Result<AuthProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut authentication_method = None;
+        let mut properties = AuthProperties::new("");
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                AUTHENTICATION_METHOD => authentication_method
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                AUTHENTICATION_DATA => properties
+                    .authentication_data
+                    .replace(read_mqtt_bytes(&mut stream)?)
+                    .is_some(),
+                REASON_STRING => properties
+                    .reason_string
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                _ => return Err(unexpected_property(MessageType::AUTH, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        properties.authentication_method =
+            authentication_method.ok_or(ProtoError::InvalidAuthMethod)?;
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为AuthProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for AuthProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        properties.put_u8(AUTHENTICATION_METHOD);
+        write_mqtt_string(&mut properties, &self.authentication_method)?;
+        if let Some(authentication_data) = &self.authentication_data {
+            properties.put_u8(AUTHENTICATION_DATA);
+            write_mqtt_bytes(&mut properties, authentication_data)?;
+        }
+        if let Some(reason_string) = &self.reason_string {
+            properties.put_u8(REASON_STRING);
+            write_mqtt_string(&mut properties, reason_string)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/// MQTT 5.0 AUTH报文，用于增强认证(enhanced authentication)
+///
+/// 携带属性时必须包含认证方法。原因码为Success并且没有属性时，
+/// 原因码和属性都可以省略，剩余长度为0。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Auth {
+    // 原因码
+    reason_code: AuthReasonCode,
+    // 属性，认证方法和认证数据都在属性中，为None时只能是省略了属性的Success
+    properties: Option<AuthProperties>,
+}
+
+impl Auth {
+    pub fn new(reason_code: AuthReasonCode, properties: AuthProperties) -> Self {
+        Self {
+            reason_code,
+            properties: Some(properties),
+        }
+    }
+    /// 没有属性的Success报文，编码为剩余长度为0的短格式
+    pub fn success() -> Self {
+        Self {
+            reason_code: AuthReasonCode::Success,
+            properties: None,
+        }
+    }
+    pub fn builder() -> AuthBuilder {
+        AuthBuilder::new()
+    }
+    pub fn reason_code(&self) -> AuthReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> Option<&AuthProperties> {
+        self.properties.as_ref()
+    }
+    pub fn authentication_method(&self) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .map(AuthProperties::authentication_method)
+    }
+    pub fn authentication_data(&self) -> Option<&Bytes> {
+        self.properties
+            .as_ref()
+            .and_then(AuthProperties::authentication_data)
+    }
+
+    // 剩余长度 = 原因码 + 属性，短格式为0
+    pub(crate) fn remaining_length(&self) -> usize {
+        match &self.properties {
+            Some(properties) => 1 + properties_len(properties.len()),
+            None => 0,
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Auth实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for Auth {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::AUTH, self.remaining_length())?;
+        match &self.properties {
+            Some(properties) => {
+                buffer.put_u8(self.reason_code.into());
+                Ok(fixed_header_len + 1 + properties.encode(buffer)?)
+            }
+            None => Ok(fixed_header_len),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Auth实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for Auth {
+    type Item = Auth;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::AUTH)?;
+        // 剩余长度为0，表示没有属性的Success
+        if !body.has_remaining() {
+            return Ok(Auth::success());
+        }
+        let reason_code = AuthReasonCode::try_from(read_u8(&mut body)?)?;
+        // 只有原因码时同样没有认证方法，只允许Success
+        if !body.has_remaining() {
+            return match reason_code {
+                AuthReasonCode::Success => Ok(Auth::success()),
+                _ => Err(ProtoError::InvalidAuthMethod),
+            };
+        }
+        let properties = AuthProperties::decode(&mut body)?;
+        Ok(Auth::new(reason_code, properties))
+    }
+}
+
+/// 增强认证所处的阶段
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthFlowState {
+    /// 使用认证方法发起认证，还没有交换AUTH报文
+    Begin { method: String },
+    /// 正在交换认证数据，data为最近一个AUTH报文中的认证数据
+    Continue { data: Bytes },
+    /// 认证完成
+    Success,
+}
+
+impl AuthFlowState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthFlowState::Begin { .. } => "Begin",
+            AuthFlowState::Continue { .. } => "Continue",
+            AuthFlowState::Success => "Success",
+        }
+    }
+}
+
+/**
+ 记录一次增强认证的进度，客户端和服务端都可以使用。
+ 每发送或者收到一个AUTH报文都需要调用[`AuthFlowContext::update`]：
+ - Begin或Continue阶段只接受Continue和Success
+ - Success阶段只接受ReAuthenticate，之后重新进入Continue阶段
+ - 首次认证由CONNACK结束，收到成功的CONNACK时调用[`AuthFlowContext::complete`]
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v5::auth::{AuthFlowContext, AuthFlowState};
+let mut context = AuthFlowContext::begin("SCRAM-SHA-256");
+let auth = context.continue_auth(Bytes::from_static(b"r=server-nonce")).unwrap();
+assert_eq!(auth.authentication_method(), Some("SCRAM-SHA-256"));
+assert!(matches!(context.state(), AuthFlowState::Continue { .. }));
+context.complete();
+assert!(context.is_complete());
+```
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthFlowContext {
+    // CONNECT中的认证方法，之后所有的AUTH报文都必须使用相同的认证方法
+    method: String,
+    state: AuthFlowState,
+}
+
+impl AuthFlowContext {
+    pub fn begin(method: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            state: AuthFlowState::Begin {
+                method: method.to_string(),
+            },
+        }
+    }
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+    pub fn state(&self) -> &AuthFlowState {
+        &self.state
+    }
+    pub fn is_complete(&self) -> bool {
+        self.state == AuthFlowState::Success
+    }
+
+    /// 检查发送或收到的AUTH报文，合法时更新认证阶段
+    pub fn update(&mut self, auth: &Auth) -> Result<(), ProtoError> {
+        // 省略了属性的Success沿用之前的认证方法
+        if auth
+            .authentication_method()
+            .is_some_and(|method| method != self.method)
+        {
+            return Err(ProtoError::InvalidAuthMethod);
+        }
+        let data = auth.authentication_data().cloned().unwrap_or_default();
+        self.state = match (&self.state, auth.reason_code()) {
+            (
+                AuthFlowState::Begin { .. } | AuthFlowState::Continue { .. },
+                AuthReasonCode::Continue,
+            )
+            | (AuthFlowState::Success, AuthReasonCode::ReAuthenticate) => {
+                AuthFlowState::Continue { data }
+            }
+            (AuthFlowState::Continue { .. }, AuthReasonCode::Success) => AuthFlowState::Success,
+            _ => {
+                return Err(ProtoError::InvalidPacketForState {
+                    packet_type: MessageType::AUTH,
+                    state: self.state.as_str(),
+                })
+            }
+        };
+        Ok(())
+    }
+
+    /// 构建携带认证数据的Continue报文，并更新认证阶段
+    pub fn continue_auth(&mut self, data: Bytes) -> Result<Auth, ProtoError> {
+        self.next(AuthReasonCode::Continue, Some(data))
+    }
+
+    /// 认证完成后发起重新认证
+    pub fn re_authenticate(&mut self, data: Bytes) -> Result<Auth, ProtoError> {
+        self.next(AuthReasonCode::ReAuthenticate, Some(data))
+    }
+
+    /// 服务端构建认证成功的AUTH报文，用于结束重新认证
+    pub fn success(&mut self) -> Result<Auth, ProtoError> {
+        self.next(AuthReasonCode::Success, None)
+    }
+
+    /// 首次认证以成功的CONNACK结束，收到或发送CONNACK之后调用
+    pub fn complete(&mut self) {
+        self.state = AuthFlowState::Success;
+    }
+
+    fn next(
+        &mut self,
+        reason_code: AuthReasonCode,
+        data: Option<Bytes>,
+    ) -> Result<Auth, ProtoError> {
+        let mut builder = Auth::builder()
+            .reason_code(reason_code)
+            .authentication_method(&self.method);
+        if let Some(data) = data {
+            builder = builder.authentication_data(data);
+        }
+        let auth = builder.build()?;
+        self.update(&auth)?;
+        Ok(auth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Auth, AuthFlowContext, AuthFlowState, AuthReasonCode};
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::MessageType;
+    use bytes::{Bytes, BytesMut};
+
+    // 编码后再解码，模拟报文经过网络传输
+    fn transfer(auth: &Auth) -> Auth {
+        let mut buffer = BytesMut::new();
+        let len = auth.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        assert_eq!(buffer[0], 0xf0);
+        Auth::decode(buffer.freeze()).unwrap()
+    }
+
+    #[test]
+    fn scram_sha_256_flow_should_be_work() {
+        let method = "SCRAM-SHA-256";
+        let mut client = AuthFlowContext::begin(method);
+        let mut server = AuthFlowContext::begin(method);
+        // client-first-message在CONNECT中发送，之后通过AUTH报文交换
+        let exchanges: [(bool, &[u8]); 3] = [
+            (false, b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"),
+            (true, b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="),
+            (false, b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4="),
+        ];
+        for (from_client, data) in exchanges {
+            let (sender, receiver) = match from_client {
+                true => (&mut client, &mut server),
+                false => (&mut server, &mut client),
+            };
+            let auth = sender.continue_auth(Bytes::from_static(data)).unwrap();
+            let received = transfer(&auth);
+            assert_eq!(received, auth);
+            assert_eq!(received.reason_code(), AuthReasonCode::Continue);
+            receiver.update(&received).unwrap();
+            assert_eq!(
+                receiver.state(),
+                &AuthFlowState::Continue {
+                    data: Bytes::from_static(data)
+                }
+            );
+        }
+        // 首次认证由CONNACK结束
+        client.complete();
+        server.complete();
+        assert!(client.is_complete() && server.is_complete());
+
+        // 重新认证由AUTH Success结束
+        let auth = client
+            .re_authenticate(Bytes::from_static(b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"))
+            .unwrap();
+        server.update(&transfer(&auth)).unwrap();
+        let auth = server.continue_auth(Bytes::from_static(b"r=fyko")).unwrap();
+        client.update(&transfer(&auth)).unwrap();
+        let auth = client.continue_auth(Bytes::from_static(b"c=biws")).unwrap();
+        server.update(&transfer(&auth)).unwrap();
+        let auth = server.success().unwrap();
+        assert_eq!(auth.authentication_data(), None);
+        client.update(&transfer(&auth)).unwrap();
+        assert!(client.is_complete() && server.is_complete());
+    }
+
+    #[test]
+    fn unexpected_auth_should_return_error() {
+        let mut context = AuthFlowContext::begin("SCRAM-SHA-256");
+        assert_eq!(
+            context.success().unwrap_err(),
+            ProtoError::InvalidPacketForState {
+                packet_type: MessageType::AUTH,
+                state: "Begin",
+            }
+        );
+        assert!(context.re_authenticate(Bytes::new()).is_err());
+        let other = Auth::builder()
+            .reason_code(AuthReasonCode::Continue)
+            .authentication_method("PLAIN")
+            .build()
+            .unwrap();
+        assert!(context.update(&other).is_err());
+        context.complete();
+        assert!(context.continue_auth(Bytes::new()).is_err());
+        assert!(context.success().is_err());
+    }
+
+    #[test]
+    fn short_form_should_mean_success_without_properties() {
+        for frame in [&[0xf0, 0x00][..], &[0xf0, 0x01, 0x00]] {
+            let auth = Auth::decode(Bytes::copy_from_slice(frame)).unwrap();
+            assert_eq!(auth, Auth::success());
+            assert_eq!(auth.reason_code(), AuthReasonCode::Success);
+            assert_eq!(auth.authentication_method(), None);
+        }
+        let mut buffer = BytesMut::new();
+        assert_eq!(Auth::success().encode(&mut buffer).unwrap(), 2);
+        assert_eq!(buffer.as_ref(), &[0xf0, 0x00]);
+        assert_eq!(Auth::builder().build().unwrap(), Auth::success());
+
+        // 重新认证可以由省略了属性的Success结束
+        let mut context = AuthFlowContext::begin("SCRAM-SHA-256");
+        context.complete();
+        context.re_authenticate(Bytes::new()).unwrap();
+        context.update(&Auth::success()).unwrap();
+        assert!(context.is_complete());
+    }
+
+    #[test]
+    fn continue_with_binary_data_should_round_trip() {
+        let data: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(37) ^ 0xa5).collect();
+        let auth = Auth::builder()
+            .reason_code(AuthReasonCode::Continue)
+            .authentication_method("SCRAM-SHA-1")
+            .authentication_data(Bytes::from(data.clone()))
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        auth.encode(&mut buffer).unwrap();
+        // 原因码 + 属性长度 + (1 + 2 + 11) + (1 + 2 + 64)
+        assert_eq!(&buffer[..4], &[0xf0, 0x53, 0x18, 0x51]);
+        assert_eq!(&buffer[buffer.len() - 64..], data.as_slice());
+        let decoded = transfer(&auth);
+        assert_eq!(decoded, auth);
+        assert_eq!(decoded.authentication_data().unwrap().as_ref(), data);
+    }
+
+    #[test]
+    fn decode_auth_without_method_should_return_error() {
+        // 只有Continue原因码、属性中只有原因字符串
+        let frames: [&[u8]; 2] = [
+            &[0xf0, 0x01, 0x18],
+            &[0xf0, 0x07, 0x18, 0x05, 0x1f, 0x00, 0x02, b'o', b'k'],
+        ];
+        for frame in frames {
+            assert_eq!(
+                Auth::decode(Bytes::from_static(frame)).unwrap_err(),
+                ProtoError::InvalidAuthMethod
+            );
+        }
+        assert_eq!(
+            Auth::builder()
+                .reason_code(AuthReasonCode::Continue)
+                .build()
+                .unwrap_err(),
+            ProtoError::InvalidAuthMethod
+        );
+        // 未知的原因码
+        let frame = Bytes::from_static(&[0xf0, 0x07, 0x01, 0x05, 0x15, 0x00, 0x02, b'o', b'k']);
+        assert_eq!(
+            Auth::decode(frame).unwrap_err(),
+            ProtoError::UnknownReasonCode(0x01)
+        );
+    }
+}
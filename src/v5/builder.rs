@@ -0,0 +1,1062 @@
+use super::auth::{Auth, AuthProperties, AuthReasonCode};
+use super::connect::{Connect, ConnectProperties, LastWill};
+use super::dis_connect::{DisConnect, DisconnectProperties, DisconnectReasonCode};
+use super::pub_ack::{PubAck, PubAckReasonCode};
+use super::pub_comp::PubComp;
+use super::pub_rec::PubRec;
+use super::pub_rel::{PubRel, PubRelReasonCode};
+use super::publish::{Publish, PublishProperties, PublishVariableHeader};
+use super::sub_ack::{SubAck, SubAckReasonCode};
+use super::subscribe::{Subscribe, SubscribeProperties, TopicFilter};
+use super::un_suback::{UnSubAck, UnSubAckReasonCode};
+use super::un_subscribe::{UnSubscribe, UnsubscribeProperties};
+use crate::common::coder::write_mqtt_string;
+use crate::common::packet_id::PacketId;
+use crate::common::topic::{validate_topic_filters, validate_topic_name};
+use crate::error::{ProtoError, TopicAliasError};
+use crate::v4::fixed_header::FixedHeaderBuilder;
+use crate::v5::property::{
+    MAX_VARIABLE_BYTE_INTEGER, REASON_STRING, SUBSCRIPTION_IDENTIFIER, USER_PROPERTY,
+};
+use crate::v5::write_fixed_header;
+use crate::{MessageType, QoS};
+use bytes::{BufMut, Bytes, BytesMut};
+use tracing::warn;
+
+/**
+MQTT-v5.0 报文构建器
+ - ConnectBuilder: 连接报文构建器，支持只设置password
+ - PublishBuilder: 发布报文构建器，支持topic alias
+ - SubscribeBuilder: 订阅报文构建器，支持订阅选项
+ - UnSubscribeBuilder: 取消订阅报文构建器
+ - AuthBuilder: 认证报文构建器，必须设置认证方法
+ - DisconnectBuilder: 断开连接报文构建器，原因码为NormalDisconnection并且没有属性时剩余长度为0
+ - PubAckBuilder、PubRecBuilder、PubRelBuilder、PubCompBuilder: 发布回执报文构建器，
+   原因码为Success并且没有属性时编码为2个字节的短格式
+ - SubAckBuilder、UnSubAckBuilder: 订阅、取消订阅回执报文构建器，每个topic filter对应一个原因码
+*/
+pub struct MqttMessageBuilder {}
+
+impl MqttMessageBuilder {
+    pub fn connect() -> ConnectBuilder {
+        ConnectBuilder::new()
+    }
+    pub fn publish() -> PublishBuilder {
+        PublishBuilder::new()
+    }
+    pub fn subscribe() -> SubscribeBuilder {
+        SubscribeBuilder::new()
+    }
+    pub fn un_subscribe() -> UnSubscribeBuilder {
+        UnSubscribeBuilder::new()
+    }
+    pub fn auth() -> AuthBuilder {
+        AuthBuilder::new()
+    }
+    pub fn disconnect() -> DisconnectBuilder {
+        DisconnectBuilder::new()
+    }
+    pub fn pub_ack() -> PubAckBuilder {
+        PubAckBuilder::new()
+    }
+    pub fn pub_rec() -> PubRecBuilder {
+        PubRecBuilder::new()
+    }
+    pub fn pub_rel() -> PubRelBuilder {
+        PubRelBuilder::new()
+    }
+    pub fn pub_comp() -> PubCompBuilder {
+        PubCompBuilder::new()
+    }
+    pub fn sub_ack() -> SubAckBuilder {
+        SubAckBuilder::new()
+    }
+    pub fn un_sub_ack() -> UnSubAckBuilder {
+        UnSubAckBuilder::new()
+    }
+}
+
+/**
+ 连接报文构建器，用于构建MQTT-v5.0 CONNECT报文，例如：
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+// v5中允许只设置password，例如基于token的认证
+let connect = MqttMessageBuilder::connect()
+             .client_id("client_01")
+             .keep_alive(30)
+             .password(Bytes::from_static(b"token"))
+             .build();
+assert!(connect.is_ok());
+```
+ */
+pub struct ConnectBuilder {
+    clean_start: bool,
+    keep_alive: u16,
+    properties: ConnectProperties,
+    client_id: String,
+    last_will: Option<LastWill>,
+    username: Option<String>,
+    password: Option<Bytes>,
+}
+
+impl ConnectBuilder {
+    fn new() -> Self {
+        Self {
+            clean_start: true,
+            keep_alive: 60,
+            properties: ConnectProperties::default(),
+            client_id: String::new(),
+            last_will: None,
+            username: None,
+            password: None,
+        }
+    }
+    /// 设置client_id
+    pub fn client_id(mut self, client_id: &str) -> Self {
+        self.client_id = client_id.to_string();
+        self
+    }
+    /// 设置心跳间隔，单位为秒
+    pub fn keep_alive(mut self, keep_alive: u16) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+    /// 设置clean_start
+    pub fn clean_start(mut self, clean_start: bool) -> Self {
+        self.clean_start = clean_start;
+        self
+    }
+    /// 设置属性，参见[`ConnectProperties::builder`]
+    pub fn properties(mut self, properties: ConnectProperties) -> Self {
+        self.properties = properties;
+        self
+    }
+    /// 设置遗嘱信息
+    pub fn last_will(mut self, last_will: LastWill) -> Self {
+        self.last_will = Some(last_will);
+        self
+    }
+    /// 设置账号
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+    /// 设置密码
+    pub fn password(mut self, password: Bytes) -> Self {
+        self.password = Some(password);
+        self
+    }
+    /// 构建CONNECT报文
+    pub fn build(self) -> Result<Connect, ProtoError> {
+        let connect = Connect::new(
+            self.clean_start,
+            self.keep_alive,
+            self.properties,
+            self.client_id,
+            self.last_will,
+            self.username,
+            self.password,
+        );
+        // 提前检查剩余长度，避免编码时才发现报文过长
+        write_fixed_header(
+            &mut BytesMut::new(),
+            MessageType::CONNECT,
+            connect.remaining_length(),
+        )?;
+        Ok(connect)
+    }
+}
+
+/**
+ 发布报文构建器，用于构建MQTT-v5.0 PUBLISH报文，例如：
+
+```rust
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+// 第一次发布时同时携带topic和topic alias
+let publish = MqttMessageBuilder::publish()
+             .topic("/sensor/temp")
+             .topic_alias(1)
+             .payload_str("25.1")
+             .build();
+assert!(publish.is_ok());
+// 之后只需要携带topic alias
+let publish = MqttMessageBuilder::publish()
+             .topic_alias(1)
+             .payload_str("25.2")
+             .build();
+assert!(publish.is_ok());
+```
+ */
+pub struct PublishBuilder {
+    // topic，只使用topic alias时为空
+    topic: String,
+    // 属性，包括topic alias
+    properties: PublishProperties,
+    // publish报文的message_id,当QoS为0的时候不设置QoS
+    message_id: Option<usize>,
+    qos: QoS,
+    retain: bool,
+    dup: bool,
+    payload: Bytes,
+}
+
+impl PublishBuilder {
+    fn new() -> Self {
+        Self {
+            topic: String::new(),
+            properties: PublishProperties::default(),
+            message_id: None,
+            qos: QoS::AtMostOnce,
+            retain: false,
+            dup: false,
+            payload: Bytes::new(),
+        }
+    }
+    /// 设置topic
+    pub fn topic(mut self, topic: &str) -> Self {
+        self.topic = topic.to_string();
+        self
+    }
+    /// 设置topic alias
+    pub fn topic_alias(mut self, topic_alias: u16) -> Self {
+        self.properties.topic_alias = Some(topic_alias);
+        self
+    }
+    /// 设置payload是否为UTF-8编码的字符数据
+    pub fn utf8_payload(mut self, utf8_payload: bool) -> Self {
+        self.properties.payload_format_indicator = Some(utf8_payload as u8);
+        self
+    }
+    /// 设置消息过期时间，单位为秒
+    pub fn message_expiry_interval(mut self, message_expiry_interval: u32) -> Self {
+        self.properties.message_expiry_interval = Some(message_expiry_interval);
+        self
+    }
+    /// 设置内容类型，例如MIME类型
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.properties.content_type = Some(content_type.to_string());
+        self
+    }
+    /// 设置响应主题
+    pub fn response_topic(mut self, response_topic: &str) -> Self {
+        self.properties.response_topic = Some(response_topic.to_string());
+        self
+    }
+    /// 设置对比数据
+    pub fn correlation_data(mut self, correlation_data: Bytes) -> Self {
+        self.properties.correlation_data = Some(correlation_data);
+        self
+    }
+    /// 添加订阅标识符，broker转发消息时填写匹配的订阅的标识符，可以调用多次
+    pub fn subscription_identifier(mut self, subscription_identifier: u32) -> Self {
+        self.properties
+            .subscription_identifiers
+            .push(subscription_identifier);
+        self
+    }
+    /// 添加用户属性，可以调用多次
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = Some(packet_id.into());
+        self
+    }
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
+    pub fn message_id(mut self, message_id: usize) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+    /// 设置qos
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+    /// 设置retain
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+    /// 设置dup
+    pub fn dup(mut self, dup: bool) -> Self {
+        self.dup = dup;
+        self
+    }
+    /// 以&str的方式设置payload
+    pub fn payload_str(mut self, payload: &str) -> Self {
+        self.payload = Bytes::from(payload.to_string());
+        self
+    }
+    /// 设置payload
+    pub fn payload(mut self, payload: Bytes) -> Self {
+        self.payload = payload;
+        self
+    }
+    /// 构建PUBLISH报文
+    ///
+    /// QoS为1或2时必须设置message_id，QoS为0时设置的message_id会被忽略
+    pub fn build(self) -> Result<Publish, ProtoError> {
+        match (self.qos, self.message_id) {
+            (QoS::AtMostOnce, Some(message_id)) => {
+                warn!(
+                    "QoS为0的PUBLISH报文不需要message_id，忽略message_id：{}",
+                    message_id
+                );
+            }
+            (QoS::AtLeastOnce | QoS::ExactlyOnce, None) => {
+                return Err(ProtoError::QoS12RequiresPacketId);
+            }
+            _ => {}
+        }
+        //1、检查topic和topic alias
+        match self.properties.topic_alias {
+            Some(0) => return Err(TopicAliasError::InvalidAlias(0).into()),
+            None if self.topic.is_empty() => return Err(TopicAliasError::MissingAlias.into()),
+            _ => {}
+        }
+        if !self.topic.is_empty() {
+            validate_topic_name(&self.topic)?;
+        }
+        for id in &self.properties.subscription_identifiers {
+            if *id == 0 || *id as usize > MAX_VARIABLE_BYTE_INTEGER {
+                return Err(ProtoError::InvalidProperty(SUBSCRIPTION_IDENTIFIER));
+            }
+        }
+        //2、构建fixed_header
+        let mut fixed_header = FixedHeaderBuilder::new()
+            .publish()
+            .dup(Some(self.dup))
+            .retain(Some(self.retain))
+            .qos(Some(self.qos))
+            .build()?;
+        //3、构建variable_header
+        let message_id = match self.qos {
+            QoS::AtMostOnce => None,
+            _ => self.message_id.map(PacketId::try_from).transpose()?,
+        };
+        let variable_header = PublishVariableHeader::new(self.topic, message_id, self.properties);
+        //4、计算剩余长度
+        fixed_header.set_remaining_length(variable_header.len() + self.payload.len());
+        Ok(Publish::new(fixed_header, variable_header, self.payload))
+    }
+}
+
+/// 把v4的PUBLISH构建器转换为v5的构建器，payload_utf8和content_type会转换为对应的属性
+impl<S> From<crate::v4::builder::PublishBuilder<S>> for PublishBuilder {
+    fn from(builder: crate::v4::builder::PublishBuilder<S>) -> Self {
+        let mut properties = PublishProperties::default();
+        if builder.utf8_payload {
+            properties.payload_format_indicator = Some(1);
+        }
+        properties.content_type = builder.content_type;
+        Self {
+            topic: builder.topic,
+            properties,
+            message_id: builder.message_id,
+            qos: builder.qos,
+            retain: builder.retain,
+            dup: builder.dup,
+            payload: builder.payload,
+        }
+    }
+}
+
+/**
+ 订阅报文构建器，用于构建MQTT-v5.0 SUBSCRIBE报文，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::subscribe::{RetainHandling, SubscriptionOptions, TopicFilter};
+use walle_mqtt_protocol::QoS;
+let options = SubscriptionOptions::new(QoS::AtLeastOnce)
+             .no_local(true)
+             .retain_handling(RetainHandling::DoNotSend);
+let subscribe = MqttMessageBuilder::subscribe()
+             .packet_id(PacketId::MIN)
+             .topic_filter(TopicFilter::new("/sensor/+", options))
+             .build();
+assert!(subscribe.is_ok());
+```
+ */
+pub struct SubscribeBuilder {
+    message_id: usize,
+    properties: SubscribeProperties,
+    filters: Vec<TopicFilter>,
+}
+
+impl SubscribeBuilder {
+    fn new() -> Self {
+        Self {
+            message_id: 0,
+            properties: SubscribeProperties::default(),
+            filters: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 以usize设置message_id，0或者超过65535的值在build时返回[`ProtoError::InvalidPacketId`]
+    #[deprecated(note = "请使用packet_id")]
+    pub fn message_id(mut self, message_id: usize) -> Self {
+        self.message_id = message_id;
+        self
+    }
+    /// 添加一个topic filter
+    pub fn topic_filter(mut self, filter: TopicFilter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    /// 设置所有的topic filter
+    pub fn topic_filters(mut self, filters: Vec<TopicFilter>) -> Self {
+        self.filters = filters;
+        self
+    }
+    /// 设置订阅标识符
+    pub fn subscription_identifier(mut self, subscription_identifier: u32) -> Self {
+        self.properties.subscription_identifier = Some(subscription_identifier);
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建SUBSCRIBE报文
+    pub fn build(self) -> Result<Subscribe, ProtoError> {
+        //1、检查topic filter和属性
+        if self.filters.is_empty() {
+            return Err(ProtoError::EmptySubscription);
+        }
+        for filter in &self.filters {
+            filter.validate()?;
+        }
+        if let Some(id) = self.properties.subscription_identifier {
+            if id == 0 || id as usize > MAX_VARIABLE_BYTE_INTEGER {
+                return Err(ProtoError::InvalidProperty(SUBSCRIPTION_IDENTIFIER));
+            }
+        }
+        //2、构建fixed_header，剩余长度由Subscribe::new计算
+        let fixed_header = FixedHeaderBuilder::new().subscribe().qos(None).build()?;
+        Ok(Subscribe::new(
+            fixed_header,
+            PacketId::try_from(self.message_id)?,
+            self.properties,
+            self.filters,
+        ))
+    }
+}
+
+/**
+ 取消订阅报文构建器，build时检查topic filter的格式，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+let un_subscribe = MqttMessageBuilder::un_subscribe()
+             .packet_id(PacketId::MIN)
+             .topic("/sensor/+")
+             .topic("/device/#")
+             .build();
+assert!(un_subscribe.is_ok());
+```
+ */
+pub struct UnSubscribeBuilder {
+    message_id: usize,
+    properties: UnsubscribeProperties,
+    filters: Vec<String>,
+}
+
+impl UnSubscribeBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            properties: UnsubscribeProperties::default(),
+            filters: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 添加一个topic filter
+    pub fn topic(mut self, filter: &str) -> Self {
+        self.filters.push(filter.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建UNSUBSCRIBE报文，topic filter格式错误时返回[`ProtoError::InvalidTopicFilter`]
+    pub fn build(self) -> Result<UnSubscribe, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        if self.filters.is_empty() {
+            return Err(ProtoError::EmptyUnsubscription);
+        }
+        validate_topic_filters(self.filters.iter().map(String::as_str))?;
+        UnSubscribe::new(packet_id, self.properties, self.filters)
+    }
+}
+
+/**
+ 认证报文构建器，用于构建MQTT-v5.0 AUTH报文，除了没有属性的Success之外，
+ 没有设置认证方法时build返回错误，例如：
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v5::auth::AuthReasonCode;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+let auth = MqttMessageBuilder::auth()
+             .reason_code(AuthReasonCode::Continue)
+             .authentication_method("SCRAM-SHA-256")
+             .authentication_data(Bytes::from_static(b"r=nonce"))
+             .build();
+assert!(auth.is_ok());
+assert!(MqttMessageBuilder::auth()
+    .reason_code(AuthReasonCode::Continue)
+    .build()
+    .is_err());
+```
+ */
+pub struct AuthBuilder {
+    reason_code: AuthReasonCode,
+    authentication_method: Option<String>,
+    authentication_data: Option<Bytes>,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl AuthBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            reason_code: AuthReasonCode::Success,
+            authentication_method: None,
+            authentication_data: None,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置原因码，默认为Success
+    pub fn reason_code(mut self, reason_code: AuthReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置认证方法
+    pub fn authentication_method(mut self, authentication_method: &str) -> Self {
+        self.authentication_method = Some(authentication_method.to_string());
+        self
+    }
+    /// 设置认证数据
+    pub fn authentication_data(mut self, authentication_data: Bytes) -> Self {
+        self.authentication_data = Some(authentication_data);
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建AUTH报文，没有设置任何属性的Success报文使用短格式，
+    /// 其他情况下没有设置认证方法时返回[`ProtoError::InvalidAuthMethod`]
+    pub fn build(self) -> Result<Auth, ProtoError> {
+        if self.reason_code == AuthReasonCode::Success
+            && self.authentication_method.is_none()
+            && self.authentication_data.is_none()
+            && self.reason_string.is_none()
+            && self.user_properties.is_empty()
+        {
+            return Ok(Auth::success());
+        }
+        let authentication_method = self
+            .authentication_method
+            .ok_or(ProtoError::InvalidAuthMethod)?;
+        let mut properties = AuthProperties::new(&authentication_method);
+        properties.authentication_data = self.authentication_data;
+        properties.reason_string = self.reason_string;
+        properties.user_properties = self.user_properties;
+        let auth = Auth::new(self.reason_code, properties);
+        // 提前检查剩余长度，避免编码时才发现报文过长
+        write_fixed_header(
+            &mut BytesMut::new(),
+            MessageType::AUTH,
+            auth.remaining_length(),
+        )?;
+        Ok(auth)
+    }
+}
+
+/**
+ 断开连接报文构建器，例如服务端要求客户端连接到另一个服务端：
+
+```rust
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::dis_connect::DisconnectReasonCode;
+let disconnect = MqttMessageBuilder::disconnect()
+             .reason_code(DisconnectReasonCode::UseAnotherServer)
+             .server_reference("broker2:1883")
+             .build();
+assert!(disconnect.is_ok());
+```
+ */
+pub struct DisconnectBuilder {
+    reason_code: DisconnectReasonCode,
+    properties: DisconnectProperties,
+}
+
+impl DisconnectBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            reason_code: DisconnectReasonCode::NormalDisconnection,
+            properties: DisconnectProperties::default(),
+        }
+    }
+    /// 设置原因码，默认为NormalDisconnection
+    pub fn reason_code(mut self, reason_code: DisconnectReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置会话过期时间，单位为秒
+    pub fn session_expiry_interval(mut self, session_expiry_interval: u32) -> Self {
+        self.properties.session_expiry_interval = Some(session_expiry_interval);
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.properties.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 设置服务端参考，客户端可以使用它连接到另一个服务端
+    pub fn server_reference(mut self, server_reference: &str) -> Self {
+        self.properties.server_reference = Some(server_reference.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建DISCONNECT报文
+    pub fn build(self) -> Result<DisConnect, ProtoError> {
+        Ok(DisConnect::new(self.reason_code, self.properties))
+    }
+}
+
+// 回执报文的属性内容(不包括属性长度)：原因字符串 + 用户属性
+fn ack_properties(
+    reason_string: Option<&str>,
+    user_properties: &[(String, String)],
+) -> Result<Bytes, ProtoError> {
+    let mut properties = BytesMut::new();
+    if let Some(reason_string) = reason_string {
+        properties.put_u8(REASON_STRING);
+        write_mqtt_string(&mut properties, reason_string)?;
+    }
+    for (key, value) in user_properties {
+        properties.put_u8(USER_PROPERTY);
+        write_mqtt_string(&mut properties, key)?;
+        write_mqtt_string(&mut properties, value)?;
+    }
+    Ok(properties.freeze())
+}
+
+/**
+ PUBACK报文构建器，用于回复QoS为1的PUBLISH报文，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::pub_ack::PubAckReasonCode;
+let pub_ack = MqttMessageBuilder::pub_ack()
+             .packet_id(PacketId::MIN)
+             .reason_code(PubAckReasonCode::NoMatchingSubscribers)
+             .reason_string("no subscribers")
+             .build();
+assert!(pub_ack.is_ok());
+```
+ */
+pub struct PubAckBuilder {
+    message_id: usize,
+    reason_code: PubAckReasonCode,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl PubAckBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_code: PubAckReasonCode::Success,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 设置原因码，默认为Success
+    pub fn reason_code(mut self, reason_code: PubAckReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建PUBACK报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]
+    pub fn build(self) -> Result<PubAck, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(PubAck::new(packet_id, self.reason_code, properties))
+    }
+}
+
+/**
+ PUBREC报文构建器，用于回复QoS为2的PUBLISH报文，原因码与PUBACK相同，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::pub_ack::PubAckReasonCode;
+let pub_rec = MqttMessageBuilder::pub_rec()
+             .packet_id(PacketId::MIN)
+             .reason_code(PubAckReasonCode::QuotaExceeded)
+             .reason_string("quota exceeded")
+             .build();
+assert!(pub_rec.is_ok());
+```
+ */
+pub struct PubRecBuilder {
+    message_id: usize,
+    reason_code: PubAckReasonCode,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl PubRecBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_code: PubAckReasonCode::Success,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 设置原因码，默认为Success
+    pub fn reason_code(mut self, reason_code: PubAckReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建PUBREC报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]
+    pub fn build(self) -> Result<PubRec, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(PubRec::new(packet_id, self.reason_code, properties))
+    }
+}
+
+/**
+ PUBREL报文构建器，用于回复PUBREC报文，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::pub_rel::PubRelReasonCode;
+let pub_rel = MqttMessageBuilder::pub_rel()
+             .packet_id(PacketId::MIN)
+             .reason_code(PubRelReasonCode::PacketIdentifierNotFound)
+             .reason_string("unknown id")
+             .build();
+assert!(pub_rel.is_ok());
+```
+ */
+pub struct PubRelBuilder {
+    message_id: usize,
+    reason_code: PubRelReasonCode,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl PubRelBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_code: PubRelReasonCode::Success,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 设置原因码，默认为Success
+    pub fn reason_code(mut self, reason_code: PubRelReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建PUBREL报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]
+    pub fn build(self) -> Result<PubRel, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(PubRel::new(packet_id, self.reason_code, properties))
+    }
+}
+
+/**
+ PUBCOMP报文构建器，用于回复PUBREL报文，原因码与PUBREL相同，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::pub_rel::PubRelReasonCode;
+let pub_comp = MqttMessageBuilder::pub_comp()
+             .packet_id(PacketId::MIN)
+             .reason_code(PubRelReasonCode::PacketIdentifierNotFound)
+             .reason_string("unknown id")
+             .build();
+assert!(pub_comp.is_ok());
+```
+ */
+pub struct PubCompBuilder {
+    message_id: usize,
+    reason_code: PubRelReasonCode,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl PubCompBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_code: PubRelReasonCode::Success,
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 设置原因码，默认为Success
+    pub fn reason_code(mut self, reason_code: PubRelReasonCode) -> Self {
+        self.reason_code = reason_code;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建PUBCOMP报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]
+    pub fn build(self) -> Result<PubComp, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(PubComp::new(packet_id, self.reason_code, properties))
+    }
+}
+
+/**
+ SUBACK报文构建器，用于回复SUBSCRIBE报文，原因码的顺序与请求中的topic filter一致，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::sub_ack::SubAckReasonCode;
+let sub_ack = MqttMessageBuilder::sub_ack()
+             .packet_id(PacketId::MIN)
+             .reason_code(SubAckReasonCode::GrantedQoS1)
+             .reason_code(SubAckReasonCode::NotAuthorized)
+             .build();
+assert!(sub_ack.is_ok());
+```
+ */
+pub struct SubAckBuilder {
+    message_id: usize,
+    reason_codes: Vec<SubAckReasonCode>,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl SubAckBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_codes: Vec::new(),
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 追加一个原因码
+    pub fn reason_code(mut self, reason_code: SubAckReasonCode) -> Self {
+        self.reason_codes.push(reason_code);
+        self
+    }
+    /// 设置全部原因码，会覆盖之前添加的原因码
+    pub fn reason_codes(mut self, reason_codes: Vec<SubAckReasonCode>) -> Self {
+        self.reason_codes = reason_codes;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建SUBACK报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]，
+    /// 没有原因码时返回[`ProtoError::EmptyReasonCodes`]
+    pub fn build(self) -> Result<SubAck, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        if self.reason_codes.is_empty() {
+            return Err(ProtoError::EmptyReasonCodes);
+        }
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(SubAck::new(packet_id, properties, self.reason_codes))
+    }
+}
+
+/**
+ UNSUBACK报文构建器，用于回复UNSUBSCRIBE报文，原因码的顺序与请求中的topic filter一致，例如：
+
+```rust
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v5::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v5::un_suback::UnSubAckReasonCode;
+let un_sub_ack = MqttMessageBuilder::un_sub_ack()
+             .packet_id(PacketId::MIN)
+             .reason_code(UnSubAckReasonCode::Success)
+             .reason_code(UnSubAckReasonCode::NotAuthorized)
+             .build();
+assert!(un_sub_ack.is_ok());
+```
+ */
+pub struct UnSubAckBuilder {
+    message_id: usize,
+    reason_codes: Vec<UnSubAckReasonCode>,
+    reason_string: Option<String>,
+    user_properties: Vec<(String, String)>,
+}
+
+impl UnSubAckBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            message_id: 0,
+            reason_codes: Vec::new(),
+            reason_string: None,
+            user_properties: Vec::new(),
+        }
+    }
+    /// 设置message_id
+    pub fn packet_id(mut self, packet_id: PacketId) -> Self {
+        self.message_id = packet_id.into();
+        self
+    }
+    /// 追加一个原因码
+    pub fn reason_code(mut self, reason_code: UnSubAckReasonCode) -> Self {
+        self.reason_codes.push(reason_code);
+        self
+    }
+    /// 设置全部原因码，会覆盖之前添加的原因码
+    pub fn reason_codes(mut self, reason_codes: Vec<UnSubAckReasonCode>) -> Self {
+        self.reason_codes = reason_codes;
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 构建UNSUBACK报文，没有设置message_id时返回[`ProtoError::InvalidPacketId`]，
+    /// 没有原因码时返回[`ProtoError::EmptyReasonCodes`]
+    pub fn build(self) -> Result<UnSubAck, ProtoError> {
+        let packet_id = PacketId::try_from(self.message_id)?;
+        if self.reason_codes.is_empty() {
+            return Err(ProtoError::EmptyReasonCodes);
+        }
+        let properties = ack_properties(self.reason_string.as_deref(), &self.user_properties)?;
+        Ok(UnSubAck::new(packet_id, properties, self.reason_codes))
+    }
+}
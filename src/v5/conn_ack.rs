@@ -0,0 +1,789 @@
+use super::property::{
+    properties_len, read_bool, read_properties, unexpected_property, write_properties,
+    ASSIGNED_CLIENT_IDENTIFIER, AUTHENTICATION_DATA, AUTHENTICATION_METHOD, MAXIMUM_PACKET_SIZE,
+    MAXIMUM_QOS, REASON_STRING, RECEIVE_MAXIMUM, RESPONSE_INFORMATION, RETAIN_AVAILABLE,
+    SERVER_KEEP_ALIVE, SERVER_REFERENCE, SESSION_EXPIRY_INTERVAL, SHARED_SUBSCRIPTION_AVAILABLE,
+    SUBSCRIPTION_IDENTIFIER_AVAILABLE, TOPIC_ALIAS_MAXIMUM, USER_PROPERTY,
+    WILDCARD_SUBSCRIPTION_AVAILABLE,
+};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::{
+    read_mqtt_bytes, read_mqtt_string, read_u16, read_u32, read_u8, write_mqtt_bytes,
+    write_mqtt_string,
+};
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::{MessageType, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+
+/// MQTT 5.0 CONNACK报文
+///
+/// 可变报头为：连接确认标志(session_present) + 原因码 + 属性，没有payload。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnAck {
+    // 服务端是否存在该客户端的会话
+    session_present: bool,
+    // 原因码
+    reason_code: ConnectReasonCode,
+    // 属性
+    properties: ConnAckProperties,
+}
+
+impl ConnAck {
+    pub fn new(
+        session_present: bool,
+        reason_code: ConnectReasonCode,
+        properties: ConnAckProperties,
+    ) -> Self {
+        Self {
+            session_present,
+            reason_code,
+            properties,
+        }
+    }
+    pub fn session_present(&self) -> bool {
+        self.session_present
+    }
+    pub fn reason_code(&self) -> ConnectReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> &ConnAckProperties {
+        &self.properties
+    }
+
+    // 剩余长度 = 连接确认标志 + 原因码 + 属性
+    fn remaining_length(&self) -> usize {
+        2 + properties_len(self.properties.len())
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为ConnAck实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for ConnAck {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::CONNACK, self.remaining_length())?;
+        buffer.put_u8(self.session_present as u8);
+        buffer.put_u8(self.reason_code.into());
+        let properties_len = self.properties.encode(buffer)?;
+        Ok(fixed_header_len + 2 + properties_len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为ConnAck实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for ConnAck {
+    type Item = ConnAck;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::CONNACK)?;
+        // 连接确认标志的1-7位为保留位，必须为0
+        let session_present = match read_u8(&mut body)? {
+            0 => false,
+            1 => true,
+            _ => return Err(ProtoError::ConnAckReservedFlagError),
+        };
+        let reason_code = ConnectReasonCode::try_from(read_u8(&mut body)?)?;
+        let properties = ConnAckProperties::decode(&mut body)?;
+        Ok(ConnAck {
+            session_present,
+            reason_code,
+            properties,
+        })
+    }
+}
+
+/// CONNACK报文中的属性，没有设置的属性使用协议规定的默认值
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnAckProperties {
+    // 0x11 服务端使用的会话过期时间
+    pub(crate) session_expiry_interval: Option<u32>,
+    // 0x21 服务端愿意同时处理的QoS 1和QoS 2消息的最大数量
+    pub(crate) receive_maximum: Option<u16>,
+    // 0x24 服务端支持的最大QoS，只能为0或1，没有时表示支持QoS 2
+    pub(crate) maximum_qos: Option<QoS>,
+    // 0x25 服务端是否支持保留消息
+    pub(crate) retain_available: Option<bool>,
+    // 0x27 服务端愿意接收的最大报文长度
+    pub(crate) maximum_packet_size: Option<u32>,
+    // 0x12 客户端使用空client_id连接时，服务端分配的client_id
+    pub(crate) assigned_client_identifier: Option<String>,
+    // 0x22 服务端能接收的topic alias的最大值
+    pub(crate) topic_alias_maximum: Option<u16>,
+    // 0x1F 原因字符串
+    pub(crate) reason_string: Option<String>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+    // 0x28 服务端是否支持通配符订阅
+    pub(crate) wildcard_subscription_available: Option<bool>,
+    // 0x29 服务端是否支持订阅标识符
+    pub(crate) subscription_identifiers_available: Option<bool>,
+    // 0x2A 服务端是否支持共享订阅
+    pub(crate) shared_subscription_available: Option<bool>,
+    // 0x13 服务端要求使用的心跳间隔
+    pub(crate) server_keep_alive: Option<u16>,
+    // 0x1A 响应信息，用于构建响应主题
+    pub(crate) response_information: Option<String>,
+    // 0x1C 客户端可以使用的其他服务端
+    pub(crate) server_reference: Option<String>,
+    // 0x15 增强认证的认证方法
+    pub(crate) authentication_method: Option<String>,
+    // 0x16 增强认证的认证数据
+    pub(crate) authentication_data: Option<Bytes>,
+}
+
+impl ConnAckProperties {
+    pub fn builder() -> ConnAckPropertiesBuilder {
+        ConnAckPropertiesBuilder::default()
+    }
+    pub fn session_expiry_interval(&self) -> Option<u32> {
+        self.session_expiry_interval
+    }
+    pub fn receive_maximum(&self) -> Option<u16> {
+        self.receive_maximum
+    }
+    pub fn maximum_qos(&self) -> Option<QoS> {
+        self.maximum_qos
+    }
+    pub fn retain_available(&self) -> Option<bool> {
+        self.retain_available
+    }
+    pub fn maximum_packet_size(&self) -> Option<u32> {
+        self.maximum_packet_size
+    }
+    pub fn assigned_client_identifier(&self) -> Option<&str> {
+        self.assigned_client_identifier.as_deref()
+    }
+    pub fn topic_alias_maximum(&self) -> Option<u16> {
+        self.topic_alias_maximum
+    }
+    pub fn reason_string(&self) -> Option<&str> {
+        self.reason_string.as_deref()
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+    pub fn wildcard_subscription_available(&self) -> Option<bool> {
+        self.wildcard_subscription_available
+    }
+    pub fn subscription_identifiers_available(&self) -> Option<bool> {
+        self.subscription_identifiers_available
+    }
+    pub fn shared_subscription_available(&self) -> Option<bool> {
+        self.shared_subscription_available
+    }
+    pub fn server_keep_alive(&self) -> Option<u16> {
+        self.server_keep_alive
+    }
+    pub fn response_information(&self) -> Option<&str> {
+        self.response_information.as_deref()
+    }
+    pub fn server_reference(&self) -> Option<&str> {
+        self.server_reference.as_deref()
+    }
+    pub fn authentication_method(&self) -> Option<&str> {
+        self.authentication_method.as_deref()
+    }
+    pub fn authentication_data(&self) -> Option<&Bytes> {
+        self.authentication_data.as_ref()
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if self.session_expiry_interval.is_some() {
+            len += 1 + 4;
+        }
+        if self.receive_maximum.is_some() {
+            len += 1 + 2;
+        }
+        if self.maximum_qos.is_some() {
+            len += 1 + 1;
+        }
+        if self.retain_available.is_some() {
+            len += 1 + 1;
+        }
+        if self.maximum_packet_size.is_some() {
+            len += 1 + 4;
+        }
+        if let Some(assigned_client_identifier) = &self.assigned_client_identifier {
+            len += 1 + 2 + assigned_client_identifier.len();
+        }
+        if self.topic_alias_maximum.is_some() {
+            len += 1 + 2;
+        }
+        if let Some(reason_string) = &self.reason_string {
+            len += 1 + 2 + reason_string.len();
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        if self.wildcard_subscription_available.is_some() {
+            len += 1 + 1;
+        }
+        if self.subscription_identifiers_available.is_some() {
+            len += 1 + 1;
+        }
+        if self.shared_subscription_available.is_some() {
+            len += 1 + 1;
+        }
+        if self.server_keep_alive.is_some() {
+            len += 1 + 2;
+        }
+        if let Some(response_information) = &self.response_information {
+            len += 1 + 2 + response_information.len();
+        }
+        if let Some(server_reference) = &self.server_reference {
+            len += 1 + 2 + server_reference.len();
+        }
+        if let Some(authentication_method) = &self.authentication_method {
+            len += 1 + 2 + authentication_method.len();
+        }
+        if let Some(authentication_data) = &self.authentication_data {
+            len += 1 + 2 + authentication_data.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<ConnAckProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = ConnAckProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                SESSION_EXPIRY_INTERVAL => properties
+                    .session_expiry_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                RECEIVE_MAXIMUM => match read_u16(&mut stream)? {
+                    0 => return Err(ProtoError::InvalidProperty(id)),
+                    value => properties.receive_maximum.replace(value).is_some(),
+                },
+                MAXIMUM_QOS => {
+                    let qos = match read_bool(&mut stream, id)? {
+                        false => QoS::AtMostOnce,
+                        true => QoS::AtLeastOnce,
+                    };
+                    properties.maximum_qos.replace(qos).is_some()
+                }
+                RETAIN_AVAILABLE => properties
+                    .retain_available
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                MAXIMUM_PACKET_SIZE => match read_u32(&mut stream)? {
+                    0 => return Err(ProtoError::InvalidProperty(id)),
+                    value => properties.maximum_packet_size.replace(value).is_some(),
+                },
+                ASSIGNED_CLIENT_IDENTIFIER => properties
+                    .assigned_client_identifier
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                TOPIC_ALIAS_MAXIMUM => properties
+                    .topic_alias_maximum
+                    .replace(read_u16(&mut stream)?)
+                    .is_some(),
+                REASON_STRING => properties
+                    .reason_string
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                WILDCARD_SUBSCRIPTION_AVAILABLE => properties
+                    .wildcard_subscription_available
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                SUBSCRIPTION_IDENTIFIER_AVAILABLE => properties
+                    .subscription_identifiers_available
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                SHARED_SUBSCRIPTION_AVAILABLE => properties
+                    .shared_subscription_available
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                SERVER_KEEP_ALIVE => properties
+                    .server_keep_alive
+                    .replace(read_u16(&mut stream)?)
+                    .is_some(),
+                RESPONSE_INFORMATION => properties
+                    .response_information
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                SERVER_REFERENCE => properties
+                    .server_reference
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                AUTHENTICATION_METHOD => properties
+                    .authentication_method
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                AUTHENTICATION_DATA => properties
+                    .authentication_data
+                    .replace(read_mqtt_bytes(&mut stream)?)
+                    .is_some(),
+                _ => return Err(unexpected_property(MessageType::CONNACK, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为ConnAckProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for ConnAckProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(session_expiry_interval) = self.session_expiry_interval {
+            properties.put_u8(SESSION_EXPIRY_INTERVAL);
+            properties.put_u32(session_expiry_interval);
+        }
+        if let Some(receive_maximum) = self.receive_maximum {
+            properties.put_u8(RECEIVE_MAXIMUM);
+            properties.put_u16(receive_maximum);
+        }
+        if let Some(maximum_qos) = self.maximum_qos {
+            properties.put_u8(MAXIMUM_QOS);
+            properties.put_u8(maximum_qos.into());
+        }
+        if let Some(retain_available) = self.retain_available {
+            properties.put_u8(RETAIN_AVAILABLE);
+            properties.put_u8(retain_available as u8);
+        }
+        if let Some(maximum_packet_size) = self.maximum_packet_size {
+            properties.put_u8(MAXIMUM_PACKET_SIZE);
+            properties.put_u32(maximum_packet_size);
+        }
+        if let Some(assigned_client_identifier) = &self.assigned_client_identifier {
+            properties.put_u8(ASSIGNED_CLIENT_IDENTIFIER);
+            write_mqtt_string(&mut properties, assigned_client_identifier)?;
+        }
+        if let Some(topic_alias_maximum) = self.topic_alias_maximum {
+            properties.put_u8(TOPIC_ALIAS_MAXIMUM);
+            properties.put_u16(topic_alias_maximum);
+        }
+        if let Some(reason_string) = &self.reason_string {
+            properties.put_u8(REASON_STRING);
+            write_mqtt_string(&mut properties, reason_string)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        if let Some(available) = self.wildcard_subscription_available {
+            properties.put_u8(WILDCARD_SUBSCRIPTION_AVAILABLE);
+            properties.put_u8(available as u8);
+        }
+        if let Some(available) = self.subscription_identifiers_available {
+            properties.put_u8(SUBSCRIPTION_IDENTIFIER_AVAILABLE);
+            properties.put_u8(available as u8);
+        }
+        if let Some(available) = self.shared_subscription_available {
+            properties.put_u8(SHARED_SUBSCRIPTION_AVAILABLE);
+            properties.put_u8(available as u8);
+        }
+        if let Some(server_keep_alive) = self.server_keep_alive {
+            properties.put_u8(SERVER_KEEP_ALIVE);
+            properties.put_u16(server_keep_alive);
+        }
+        if let Some(response_information) = &self.response_information {
+            properties.put_u8(RESPONSE_INFORMATION);
+            write_mqtt_string(&mut properties, response_information)?;
+        }
+        if let Some(server_reference) = &self.server_reference {
+            properties.put_u8(SERVER_REFERENCE);
+            write_mqtt_string(&mut properties, server_reference)?;
+        }
+        if let Some(authentication_method) = &self.authentication_method {
+            properties.put_u8(AUTHENTICATION_METHOD);
+            write_mqtt_string(&mut properties, authentication_method)?;
+        }
+        if let Some(authentication_data) = &self.authentication_data {
+            properties.put_u8(AUTHENTICATION_DATA);
+            write_mqtt_bytes(&mut properties, authentication_data)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/**
+ CONNACK属性构建器，例如：
+
+```rust
+use walle_mqtt_protocol::v5::conn_ack::ConnAckProperties;
+use walle_mqtt_protocol::QoS;
+let properties = ConnAckProperties::builder()
+                .assigned_client_identifier("auto-1F2E")
+                .maximum_qos(QoS::AtLeastOnce)
+                .server_keep_alive(30)
+                .build();
+assert_eq!(properties.assigned_client_identifier(), Some("auto-1F2E"));
+assert_eq!(properties.receive_maximum(), None);
+```
+ */
+#[derive(Debug, Default)]
+pub struct ConnAckPropertiesBuilder {
+    properties: ConnAckProperties,
+}
+
+impl ConnAckPropertiesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 设置会话过期时间，单位为秒
+    pub fn session_expiry_interval(mut self, session_expiry_interval: u32) -> Self {
+        self.properties.session_expiry_interval = Some(session_expiry_interval);
+        self
+    }
+    /// 设置同时处理的QoS 1和QoS 2消息的最大数量
+    pub fn receive_maximum(mut self, receive_maximum: u16) -> Self {
+        self.properties.receive_maximum = Some(receive_maximum);
+        self
+    }
+    /// 设置支持的最大QoS，QoS 2是默认值，不会写入属性
+    pub fn maximum_qos(mut self, maximum_qos: QoS) -> Self {
+        self.properties.maximum_qos = match maximum_qos {
+            QoS::ExactlyOnce => None,
+            qos => Some(qos),
+        };
+        self
+    }
+    /// 设置是否支持保留消息
+    pub fn retain_available(mut self, retain_available: bool) -> Self {
+        self.properties.retain_available = Some(retain_available);
+        self
+    }
+    /// 设置愿意接收的最大报文长度
+    pub fn maximum_packet_size(mut self, maximum_packet_size: u32) -> Self {
+        self.properties.maximum_packet_size = Some(maximum_packet_size);
+        self
+    }
+    /// 设置分配给客户端的client_id
+    pub fn assigned_client_identifier(mut self, assigned_client_identifier: &str) -> Self {
+        self.properties.assigned_client_identifier = Some(assigned_client_identifier.to_string());
+        self
+    }
+    /// 设置topic alias的最大值
+    pub fn topic_alias_maximum(mut self, topic_alias_maximum: u16) -> Self {
+        self.properties.topic_alias_maximum = Some(topic_alias_maximum);
+        self
+    }
+    /// 设置原因字符串
+    pub fn reason_string(mut self, reason_string: &str) -> Self {
+        self.properties.reason_string = Some(reason_string.to_string());
+        self
+    }
+    /// 添加一个用户属性，可以调用多次
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 设置是否支持通配符订阅
+    pub fn wildcard_subscription_available(mut self, available: bool) -> Self {
+        self.properties.wildcard_subscription_available = Some(available);
+        self
+    }
+    /// 设置是否支持订阅标识符
+    pub fn subscription_identifiers_available(mut self, available: bool) -> Self {
+        self.properties.subscription_identifiers_available = Some(available);
+        self
+    }
+    /// 设置是否支持共享订阅
+    pub fn shared_subscription_available(mut self, available: bool) -> Self {
+        self.properties.shared_subscription_available = Some(available);
+        self
+    }
+    /// 设置客户端必须使用的心跳间隔，单位为秒
+    pub fn server_keep_alive(mut self, server_keep_alive: u16) -> Self {
+        self.properties.server_keep_alive = Some(server_keep_alive);
+        self
+    }
+    /// 设置响应信息
+    pub fn response_information(mut self, response_information: &str) -> Self {
+        self.properties.response_information = Some(response_information.to_string());
+        self
+    }
+    /// 设置客户端可以使用的其他服务端
+    pub fn server_reference(mut self, server_reference: &str) -> Self {
+        self.properties.server_reference = Some(server_reference.to_string());
+        self
+    }
+    /// 设置认证方法
+    pub fn authentication_method(mut self, authentication_method: &str) -> Self {
+        self.properties.authentication_method = Some(authentication_method.to_string());
+        self
+    }
+    /// 设置认证数据
+    pub fn authentication_data(mut self, authentication_data: Bytes) -> Self {
+        self.properties.authentication_data = Some(authentication_data);
+        self
+    }
+    pub fn build(self) -> ConnAckProperties {
+        self.properties
+    }
+}
+
+/// MQTT 5.0 CONNACK报文中的原因码(Connect Reason Code)
+///
+/// 0x00表示连接成功，大于等于0x80的值都表示连接失败。
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectReasonCode {
+    Success = 0x00,
+    UnspecifiedError = 0x80,
+    MalformedPacket = 0x81,
+    ProtocolError = 0x82,
+    ImplementationSpecificError = 0x83,
+    UnsupportedProtocolVersion = 0x84,
+    ClientIdentifierNotValid = 0x85,
+    BadUserNameOrPassword = 0x86,
+    NotAuthorized = 0x87,
+    ServerUnavailable = 0x88,
+    ServerBusy = 0x89,
+    Banned = 0x8A,
+    BadAuthenticationMethod = 0x8C,
+    TopicNameInvalid = 0x90,
+    PacketTooLarge = 0x95,
+    QuotaExceeded = 0x97,
+    PayloadFormatInvalid = 0x99,
+    RetainNotSupported = 0x9A,
+    QoSNotSupported = 0x9B,
+    UseAnotherServer = 0x9C,
+    ServerMoved = 0x9D,
+    ConnectionRateExceeded = 0x9F,
+}
+
+impl ConnectReasonCode {
+    /// 连接是否成功
+    pub fn is_success(&self) -> bool {
+        *self == ConnectReasonCode::Success
+    }
+
+    /// 连接是否失败
+    pub fn is_error(&self) -> bool {
+        (*self as u8) >= 0x80
+    }
+}
+
+impl From<ConnectReasonCode> for u8 {
+    fn from(code: ConnectReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for ConnectReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(ConnectReasonCode::Success),
+            0x80 => Ok(ConnectReasonCode::UnspecifiedError),
+            0x81 => Ok(ConnectReasonCode::MalformedPacket),
+            0x82 => Ok(ConnectReasonCode::ProtocolError),
+            0x83 => Ok(ConnectReasonCode::ImplementationSpecificError),
+            0x84 => Ok(ConnectReasonCode::UnsupportedProtocolVersion),
+            0x85 => Ok(ConnectReasonCode::ClientIdentifierNotValid),
+            0x86 => Ok(ConnectReasonCode::BadUserNameOrPassword),
+            0x87 => Ok(ConnectReasonCode::NotAuthorized),
+            0x88 => Ok(ConnectReasonCode::ServerUnavailable),
+            0x89 => Ok(ConnectReasonCode::ServerBusy),
+            0x8A => Ok(ConnectReasonCode::Banned),
+            0x8C => Ok(ConnectReasonCode::BadAuthenticationMethod),
+            0x90 => Ok(ConnectReasonCode::TopicNameInvalid),
+            0x95 => Ok(ConnectReasonCode::PacketTooLarge),
+            0x97 => Ok(ConnectReasonCode::QuotaExceeded),
+            0x99 => Ok(ConnectReasonCode::PayloadFormatInvalid),
+            0x9A => Ok(ConnectReasonCode::RetainNotSupported),
+            0x9B => Ok(ConnectReasonCode::QoSNotSupported),
+            0x9C => Ok(ConnectReasonCode::UseAnotherServer),
+            0x9D => Ok(ConnectReasonCode::ServerMoved),
+            0x9F => Ok(ConnectReasonCode::ConnectionRateExceeded),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+impl fmt::Display for ConnectReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ConnectReasonCode::Success => "Success",
+            ConnectReasonCode::UnspecifiedError => "Unspecified error",
+            ConnectReasonCode::MalformedPacket => "Malformed Packet",
+            ConnectReasonCode::ProtocolError => "Protocol Error",
+            ConnectReasonCode::ImplementationSpecificError => "Implementation specific error",
+            ConnectReasonCode::UnsupportedProtocolVersion => "Unsupported Protocol Version",
+            ConnectReasonCode::ClientIdentifierNotValid => "Client Identifier not valid",
+            ConnectReasonCode::BadUserNameOrPassword => "Bad User Name or Password",
+            ConnectReasonCode::NotAuthorized => "Not authorized",
+            ConnectReasonCode::ServerUnavailable => "Server unavailable",
+            ConnectReasonCode::ServerBusy => "Server busy",
+            ConnectReasonCode::Banned => "Banned",
+            ConnectReasonCode::BadAuthenticationMethod => "Bad authentication method",
+            ConnectReasonCode::TopicNameInvalid => "Topic Name invalid",
+            ConnectReasonCode::PacketTooLarge => "Packet too large",
+            ConnectReasonCode::QuotaExceeded => "Quota exceeded",
+            ConnectReasonCode::PayloadFormatInvalid => "Payload format invalid",
+            ConnectReasonCode::RetainNotSupported => "Retain not supported",
+            ConnectReasonCode::QoSNotSupported => "QoS not supported",
+            ConnectReasonCode::UseAnotherServer => "Use another server",
+            ConnectReasonCode::ServerMoved => "Server moved",
+            ConnectReasonCode::ConnectionRateExceeded => "Connection rate exceeded",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnAck, ConnAckProperties, ConnectReasonCode};
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::{MessageType, QoS};
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn try_from_u8_should_be_work() {
+        for code in 0x85..=0x8A {
+            let reason_code = ConnectReasonCode::try_from(code).unwrap();
+            assert_eq!(u8::from(reason_code), code);
+            assert!(reason_code.is_error());
+        }
+        assert_eq!(
+            ConnectReasonCode::try_from(0x87),
+            Ok(ConnectReasonCode::NotAuthorized)
+        );
+        assert_eq!(
+            ConnectReasonCode::try_from(0x8B),
+            Err(ProtoError::UnknownReasonCode(0x8B))
+        );
+        assert_eq!(
+            ConnectReasonCode::try_from(0x01),
+            Err(ProtoError::UnknownReasonCode(0x01))
+        );
+    }
+
+    #[test]
+    fn success_and_display_should_be_work() {
+        assert!(ConnectReasonCode::Success.is_success());
+        assert!(!ConnectReasonCode::Success.is_error());
+        assert!(!ConnectReasonCode::Banned.is_success());
+        assert_eq!(ConnectReasonCode::Banned.to_string(), "Banned");
+        assert_eq!(
+            ConnectReasonCode::BadUserNameOrPassword.to_string(),
+            "Bad User Name or Password"
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_conn_ack_should_be_work() {
+        let conn_ack = ConnAck::new(
+            false,
+            ConnectReasonCode::NotAuthorized,
+            ConnAckProperties::default(),
+        );
+        let mut buffer = BytesMut::new();
+        assert_eq!(conn_ack.encode(&mut buffer).unwrap(), 5);
+        assert_eq!(buffer.as_ref(), &[0x20, 0x03, 0x00, 0x87, 0x00]);
+        assert_eq!(ConnAck::decode(buffer.freeze()).unwrap(), conn_ack);
+        // 连接确认标志的保留位不为0
+        let frame = Bytes::from_static(&[0x20, 0x03, 0x02, 0x00, 0x00]);
+        assert_eq!(
+            ConnAck::decode(frame).unwrap_err(),
+            ProtoError::ConnAckReservedFlagError
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_all_properties_should_be_work() {
+        let properties = ConnAckProperties::builder()
+            .session_expiry_interval(u32::MAX)
+            .receive_maximum(u16::MAX)
+            .maximum_qos(QoS::AtMostOnce)
+            .retain_available(false)
+            .maximum_packet_size(1)
+            .assigned_client_identifier("auto-1F2E")
+            .topic_alias_maximum(0)
+            .reason_string("ok")
+            .user_property("region", "cn")
+            .user_property("region", "us")
+            .wildcard_subscription_available(true)
+            .subscription_identifiers_available(false)
+            .shared_subscription_available(true)
+            .server_keep_alive(0)
+            .response_information("resp/")
+            .server_reference("broker2:1883")
+            .authentication_method("SCRAM-SHA-256")
+            .authentication_data(Bytes::from_static(b"v=xyz"))
+            .build();
+        let conn_ack = ConnAck::new(true, ConnectReasonCode::Success, properties);
+        let mut buffer = BytesMut::new();
+        let len = conn_ack.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        let decoded = ConnAck::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, conn_ack);
+        let properties = decoded.properties();
+        // 0x12是字符串类型的assigned client identifier，而不是receive maximum
+        assert_eq!(properties.assigned_client_identifier(), Some("auto-1F2E"));
+        assert_eq!(properties.receive_maximum(), Some(u16::MAX));
+        assert_eq!(properties.user_properties().len(), 2);
+        // QoS 2是默认值，不需要写入属性
+        let properties = ConnAckProperties::builder()
+            .maximum_qos(QoS::ExactlyOnce)
+            .build();
+        assert_eq!(properties, ConnAckProperties::default());
+    }
+
+    #[test]
+    fn invalid_conn_ack_properties_should_return_error() {
+        let cases: [(&[u8], u8); 5] = [
+            // receive maximum为0
+            (&[0x21, 0x00, 0x00], 0x21),
+            // maximum packet size为0
+            (&[0x27, 0x00, 0x00, 0x00, 0x00], 0x27),
+            // maximum qos只能为0或1
+            (&[0x24, 0x02], 0x24),
+            // shared subscription available只能为0或1
+            (&[0x2a, 0x02], 0x2a),
+            // 重复的server keep alive
+            (&[0x13, 0x00, 0x0a, 0x13, 0x00, 0x0a], 0x13),
+        ];
+        for (properties, id) in cases {
+            let mut frame = vec![0x20, 3 + properties.len() as u8, 0x00, 0x00];
+            frame.push(properties.len() as u8);
+            frame.extend_from_slice(properties);
+            assert_eq!(
+                ConnAck::decode(Bytes::from(frame)).unwrap_err(),
+                ProtoError::InvalidProperty(id)
+            );
+        }
+        // CONNECT中的request problem information和遗嘱属性中的will delay interval不能出现在CONNACK中
+        let cases: [&[u8]; 2] = [&[0x17, 0x01], &[0x18, 0x00, 0x00, 0x00, 0x1e]];
+        for properties in cases {
+            let mut frame = vec![0x20, 3 + properties.len() as u8, 0x00, 0x00];
+            frame.push(properties.len() as u8);
+            frame.extend_from_slice(properties);
+            assert_eq!(
+                ConnAck::decode(Bytes::from(frame)).unwrap_err(),
+                ProtoError::PropertyNotAllowed {
+                    packet: MessageType::CONNACK,
+                    id: properties[0]
+                }
+            );
+        }
+    }
+}
@@ -0,0 +1,1080 @@
+use super::property::{
+    properties_len, read_bool, read_properties, unexpected_property, write_properties,
+    AUTHENTICATION_DATA, AUTHENTICATION_METHOD, CONTENT_TYPE, CORRELATION_DATA,
+    MAXIMUM_PACKET_SIZE, MESSAGE_EXPIRY_INTERVAL, PAYLOAD_FORMAT_INDICATOR, RECEIVE_MAXIMUM,
+    REQUEST_PROBLEM_INFORMATION, REQUEST_RESPONSE_INFORMATION, RESPONSE_TOPIC,
+    SESSION_EXPIRY_INTERVAL, TOPIC_ALIAS_MAXIMUM, USER_PROPERTY, WILL_DELAY_INTERVAL,
+};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::{
+    read_mqtt_bytes, read_mqtt_string, read_u16, read_u32, read_u8, write_mqtt_bytes,
+    write_mqtt_string,
+};
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::{MessageType, QoS, PROTOCOL_NAME};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// MQTT 5.0中CONNECT报文的protocol level
+const PROTOCOL_LEVEL: u8 = 5;
+
+/// MQTT 5.0 CONNECT报文
+///
+/// 与v3.1.1相比，可变报头的keep_alive之后增加了属性部分，遗嘱消息也增加了遗嘱属性，
+/// 并且允许只设置password而不设置username。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connect {
+    // 为true时丢弃服务端保存的会话，对应v3.1.1中的clean_session
+    clean_start: bool,
+    // 心跳间隔，单位为秒
+    keep_alive: u16,
+    // 可变报头中的属性
+    properties: ConnectProperties,
+    // 客户端标识符
+    client_id: String,
+    // 遗嘱信息
+    last_will: Option<LastWill>,
+    // 账号
+    username: Option<String>,
+    // 密码，二进制数据
+    password: Option<Bytes>,
+}
+
+impl Connect {
+    pub(crate) fn new(
+        clean_start: bool,
+        keep_alive: u16,
+        properties: ConnectProperties,
+        client_id: String,
+        last_will: Option<LastWill>,
+        username: Option<String>,
+        password: Option<Bytes>,
+    ) -> Self {
+        Self {
+            clean_start,
+            keep_alive,
+            properties,
+            client_id,
+            last_will,
+            username,
+            password,
+        }
+    }
+    pub fn clean_start(&self) -> bool {
+        self.clean_start
+    }
+    pub fn keep_alive(&self) -> u16 {
+        self.keep_alive
+    }
+    pub fn properties(&self) -> &ConnectProperties {
+        &self.properties
+    }
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+    pub fn last_will(&self) -> Option<&LastWill> {
+        self.last_will.as_ref()
+    }
+    /// 遗嘱延时，没有遗嘱或者没有设置时返回None，此时连接断开之后立即发布遗嘱消息
+    pub fn will_delay_interval(&self) -> Option<u32> {
+        self.last_will
+            .as_ref()
+            .and_then(|last_will| last_will.properties.will_delay_interval())
+    }
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+    pub fn password(&self) -> Option<&Bytes> {
+        self.password.as_ref()
+    }
+
+    // 连接标志位，保留位为0
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.username.is_some() {
+            flags |= 0b1000_0000;
+        }
+        if self.password.is_some() {
+            flags |= 0b0100_0000;
+        }
+        if let Some(last_will) = &self.last_will {
+            flags |= 0b0000_0100 | (last_will.qos as u8) << 3;
+            if last_will.retain {
+                flags |= 0b0010_0000;
+            }
+        }
+        if self.clean_start {
+            flags |= 0b0000_0010;
+        }
+        flags
+    }
+
+    // 剩余长度 = protocol name + protocol level + flags + keep_alive + 属性 + payload
+    pub(crate) fn remaining_length(&self) -> usize {
+        let mut len = 2 + PROTOCOL_NAME.len() + 1 + 1 + 2;
+        len += properties_len(self.properties.len());
+        len += 2 + self.client_id.len();
+        if let Some(last_will) = &self.last_will {
+            len += last_will.len();
+        }
+        if let Some(username) = &self.username {
+            len += 2 + username.len();
+        }
+        if let Some(password) = &self.password {
+            len += 2 + password.len();
+        }
+        len
+    }
+}
+
+/// 客户端遗嘱信息，与v3.1.1相比增加了遗嘱属性
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastWill {
+    // 遗嘱属性
+    pub properties: WillProperties,
+    // 主题
+    pub topic_name: String,
+    // 遗嘱消息的内容
+    pub message: Bytes,
+    // 遗嘱消息的质量
+    pub qos: QoS,
+    // 遗嘱保留
+    pub retain: bool,
+}
+
+impl LastWill {
+    pub fn new(topic_name: String, message: Bytes, qos: QoS, retain: bool) -> Self {
+        Self {
+            properties: WillProperties::default(),
+            topic_name,
+            message,
+            qos,
+            retain,
+        }
+    }
+
+    // 编码之后的长度：遗嘱属性 + topic + message
+    fn len(&self) -> usize {
+        properties_len(self.properties.len()) + 2 + self.topic_name.len() + 2 + self.message.len()
+    }
+}
+
+//////////////////////////////////////////////
+/// WillProperties
+/////////////////////////////////////////////
+/// 遗嘱属性，位于CONNECT报文payload中client_id之后、will topic之前，
+/// 有自己的属性长度，可以通过[`WillPropertiesBuilder`]构建
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WillProperties {
+    // 0x18 连接断开之后延迟发送遗嘱消息的时间，单位为秒
+    pub(crate) will_delay_interval: Option<u32>,
+    // 0x01 payload格式：0表示未指定格式的字节，1表示UTF-8编码的字符数据
+    pub(crate) payload_format_indicator: Option<u8>,
+    // 0x02 遗嘱消息的过期时间，单位为秒
+    pub(crate) message_expiry_interval: Option<u32>,
+    // 0x03 内容类型
+    pub(crate) content_type: Option<String>,
+    // 0x08 响应主题
+    pub(crate) response_topic: Option<String>,
+    // 0x09 对比数据
+    pub(crate) correlation_data: Option<Bytes>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+}
+
+impl WillProperties {
+    pub fn builder() -> WillPropertiesBuilder {
+        WillPropertiesBuilder::default()
+    }
+    pub fn will_delay_interval(&self) -> Option<u32> {
+        self.will_delay_interval
+    }
+    pub fn payload_format_indicator(&self) -> Option<u8> {
+        self.payload_format_indicator
+    }
+    pub fn message_expiry_interval(&self) -> Option<u32> {
+        self.message_expiry_interval
+    }
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+    pub fn response_topic(&self) -> Option<&str> {
+        self.response_topic.as_deref()
+    }
+    pub fn correlation_data(&self) -> Option<&Bytes> {
+        self.correlation_data.as_ref()
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if self.will_delay_interval.is_some() {
+            len += 1 + 4;
+        }
+        if self.payload_format_indicator.is_some() {
+            len += 1 + 1;
+        }
+        if self.message_expiry_interval.is_some() {
+            len += 1 + 4;
+        }
+        if let Some(content_type) = &self.content_type {
+            len += 1 + 2 + content_type.len();
+        }
+        if let Some(response_topic) = &self.response_topic {
+            len += 1 + 2 + response_topic.len();
+        }
+        if let Some(correlation_data) = &self.correlation_data {
+            len += 1 + 2 + correlation_data.len();
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<WillProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = WillProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                WILL_DELAY_INTERVAL => properties
+                    .will_delay_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                PAYLOAD_FORMAT_INDICATOR => properties
+                    .payload_format_indicator
+                    .replace(read_bool(&mut stream, id)? as u8)
+                    .is_some(),
+                MESSAGE_EXPIRY_INTERVAL => properties
+                    .message_expiry_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                CONTENT_TYPE => properties
+                    .content_type
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                RESPONSE_TOPIC => properties
+                    .response_topic
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                CORRELATION_DATA => properties
+                    .correlation_data
+                    .replace(read_mqtt_bytes(&mut stream)?)
+                    .is_some(),
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                _ => return Err(unexpected_property(MessageType::CONNECT, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为WillProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for WillProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(will_delay_interval) = self.will_delay_interval {
+            properties.put_u8(WILL_DELAY_INTERVAL);
+            properties.put_u32(will_delay_interval);
+        }
+        if let Some(payload_format_indicator) = self.payload_format_indicator {
+            properties.put_u8(PAYLOAD_FORMAT_INDICATOR);
+            properties.put_u8(payload_format_indicator);
+        }
+        if let Some(message_expiry_interval) = self.message_expiry_interval {
+            properties.put_u8(MESSAGE_EXPIRY_INTERVAL);
+            properties.put_u32(message_expiry_interval);
+        }
+        if let Some(content_type) = &self.content_type {
+            properties.put_u8(CONTENT_TYPE);
+            write_mqtt_string(&mut properties, content_type)?;
+        }
+        if let Some(response_topic) = &self.response_topic {
+            properties.put_u8(RESPONSE_TOPIC);
+            write_mqtt_string(&mut properties, response_topic)?;
+        }
+        if let Some(correlation_data) = &self.correlation_data {
+            properties.put_u8(CORRELATION_DATA);
+            write_mqtt_bytes(&mut properties, correlation_data)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/**
+ 遗嘱属性构建器，例如：
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v5::connect::{LastWill, WillProperties};
+use walle_mqtt_protocol::QoS;
+let mut last_will = LastWill::new(
+    "/will".to_string(),
+    Bytes::from_static(b"offline"),
+    QoS::AtLeastOnce,
+    false,
+);
+last_will.properties = WillProperties::builder()
+    .will_delay_interval(10)
+    .utf8_payload(true)
+    .build();
+```
+ */
+#[derive(Debug, Default)]
+pub struct WillPropertiesBuilder {
+    properties: WillProperties,
+}
+
+impl WillPropertiesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 设置连接断开之后延迟发送遗嘱消息的时间，单位为秒
+    pub fn will_delay_interval(mut self, will_delay_interval: u32) -> Self {
+        self.properties.will_delay_interval = Some(will_delay_interval);
+        self
+    }
+    /// 设置遗嘱消息是否为UTF-8编码的字符数据
+    pub fn utf8_payload(mut self, utf8_payload: bool) -> Self {
+        self.properties.payload_format_indicator = Some(utf8_payload as u8);
+        self
+    }
+    /// 设置遗嘱消息的过期时间，单位为秒
+    pub fn message_expiry_interval(mut self, message_expiry_interval: u32) -> Self {
+        self.properties.message_expiry_interval = Some(message_expiry_interval);
+        self
+    }
+    /// 设置内容类型
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.properties.content_type = Some(content_type.to_string());
+        self
+    }
+    /// 设置响应主题
+    pub fn response_topic(mut self, response_topic: &str) -> Self {
+        self.properties.response_topic = Some(response_topic.to_string());
+        self
+    }
+    /// 设置对比数据
+    pub fn correlation_data(mut self, correlation_data: Bytes) -> Self {
+        self.properties.correlation_data = Some(correlation_data);
+        self
+    }
+    /// 添加一个用户属性，可以调用多次
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    pub fn build(self) -> WillProperties {
+        self.properties
+    }
+}
+
+//////////////////////////////////////////////
+/// ConnectProperties
+/////////////////////////////////////////////
+/// CONNECT报文可变报头中的属性，除了用户属性之外每个属性最多出现一次，
+/// 可以通过[`ConnectPropertiesBuilder`]构建
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectProperties {
+    // 0x11 会话过期时间，单位为秒
+    pub(crate) session_expiry_interval: Option<u32>,
+    // 0x21 客户端愿意同时处理的QoS 1和QoS 2消息的最大数量
+    pub(crate) receive_maximum: Option<u16>,
+    // 0x27 客户端愿意接收的最大报文长度
+    pub(crate) maximum_packet_size: Option<u32>,
+    // 0x22 客户端能接收的topic alias的最大值
+    pub(crate) topic_alias_maximum: Option<u16>,
+    // 0x19 请求服务端在CONNACK中返回响应信息
+    pub(crate) request_response_information: Option<bool>,
+    // 0x17 出错时是否需要原因字符串和用户属性
+    pub(crate) request_problem_information: Option<bool>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+    // 0x15 增强认证的认证方法
+    pub(crate) authentication_method: Option<String>,
+    // 0x16 增强认证的认证数据
+    pub(crate) authentication_data: Option<Bytes>,
+}
+
+impl ConnectProperties {
+    pub fn builder() -> ConnectPropertiesBuilder {
+        ConnectPropertiesBuilder::default()
+    }
+    pub fn session_expiry_interval(&self) -> Option<u32> {
+        self.session_expiry_interval
+    }
+    pub fn receive_maximum(&self) -> Option<u16> {
+        self.receive_maximum
+    }
+    pub fn maximum_packet_size(&self) -> Option<u32> {
+        self.maximum_packet_size
+    }
+    pub fn topic_alias_maximum(&self) -> Option<u16> {
+        self.topic_alias_maximum
+    }
+    pub fn request_response_information(&self) -> Option<bool> {
+        self.request_response_information
+    }
+    pub fn request_problem_information(&self) -> Option<bool> {
+        self.request_problem_information
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+    pub fn authentication_method(&self) -> Option<&str> {
+        self.authentication_method.as_deref()
+    }
+    pub fn authentication_data(&self) -> Option<&Bytes> {
+        self.authentication_data.as_ref()
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if self.session_expiry_interval.is_some() {
+            len += 1 + 4;
+        }
+        if self.receive_maximum.is_some() {
+            len += 1 + 2;
+        }
+        if self.maximum_packet_size.is_some() {
+            len += 1 + 4;
+        }
+        if self.topic_alias_maximum.is_some() {
+            len += 1 + 2;
+        }
+        if self.request_response_information.is_some() {
+            len += 1 + 1;
+        }
+        if self.request_problem_information.is_some() {
+            len += 1 + 1;
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        if let Some(authentication_method) = &self.authentication_method {
+            len += 1 + 2 + authentication_method.len();
+        }
+        if let Some(authentication_data) = &self.authentication_data {
+            len += 1 + 2 + authentication_data.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<ConnectProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = ConnectProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                SESSION_EXPIRY_INTERVAL => properties
+                    .session_expiry_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                RECEIVE_MAXIMUM => match read_u16(&mut stream)? {
+                    0 => return Err(ProtoError::InvalidProperty(id)),
+                    value => properties.receive_maximum.replace(value).is_some(),
+                },
+                MAXIMUM_PACKET_SIZE => match read_u32(&mut stream)? {
+                    0 => return Err(ProtoError::InvalidProperty(id)),
+                    value => properties.maximum_packet_size.replace(value).is_some(),
+                },
+                TOPIC_ALIAS_MAXIMUM => properties
+                    .topic_alias_maximum
+                    .replace(read_u16(&mut stream)?)
+                    .is_some(),
+                REQUEST_RESPONSE_INFORMATION => properties
+                    .request_response_information
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                REQUEST_PROBLEM_INFORMATION => properties
+                    .request_problem_information
+                    .replace(read_bool(&mut stream, id)?)
+                    .is_some(),
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                AUTHENTICATION_METHOD => properties
+                    .authentication_method
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                AUTHENTICATION_DATA => properties
+                    .authentication_data
+                    .replace(read_mqtt_bytes(&mut stream)?)
+                    .is_some(),
+                _ => return Err(unexpected_property(MessageType::CONNECT, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        // 没有认证方法时不能携带认证数据
+        if properties.authentication_data.is_some() && properties.authentication_method.is_none() {
+            return Err(ProtoError::InvalidProperty(AUTHENTICATION_DATA));
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为ConnectProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for ConnectProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(session_expiry_interval) = self.session_expiry_interval {
+            properties.put_u8(SESSION_EXPIRY_INTERVAL);
+            properties.put_u32(session_expiry_interval);
+        }
+        if let Some(receive_maximum) = self.receive_maximum {
+            properties.put_u8(RECEIVE_MAXIMUM);
+            properties.put_u16(receive_maximum);
+        }
+        if let Some(maximum_packet_size) = self.maximum_packet_size {
+            properties.put_u8(MAXIMUM_PACKET_SIZE);
+            properties.put_u32(maximum_packet_size);
+        }
+        if let Some(topic_alias_maximum) = self.topic_alias_maximum {
+            properties.put_u8(TOPIC_ALIAS_MAXIMUM);
+            properties.put_u16(topic_alias_maximum);
+        }
+        if let Some(request_response_information) = self.request_response_information {
+            properties.put_u8(REQUEST_RESPONSE_INFORMATION);
+            properties.put_u8(request_response_information as u8);
+        }
+        if let Some(request_problem_information) = self.request_problem_information {
+            properties.put_u8(REQUEST_PROBLEM_INFORMATION);
+            properties.put_u8(request_problem_information as u8);
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        if let Some(authentication_method) = &self.authentication_method {
+            properties.put_u8(AUTHENTICATION_METHOD);
+            write_mqtt_string(&mut properties, authentication_method)?;
+        }
+        if let Some(authentication_data) = &self.authentication_data {
+            properties.put_u8(AUTHENTICATION_DATA);
+            write_mqtt_bytes(&mut properties, authentication_data)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/**
+ CONNECT属性构建器，例如：
+
+```rust
+use bytes::Bytes;
+use walle_mqtt_protocol::v4::Encoder;
+use walle_mqtt_protocol::v5::connect::ConnectProperties;
+let properties = ConnectProperties::builder()
+                .session_expiry_interval(3600)
+                .receive_maximum(20)
+                .user_property("region", "cn")
+                .build();
+let mut buffer = bytes::BytesMut::new();
+properties.encode(&mut buffer).unwrap();
+assert_eq!(
+    &buffer[..],
+    &[
+        0x15, // 属性长度
+        0x11, 0x00, 0x00, 0x0e, 0x10, // Session Expiry Interval
+        0x21, 0x00, 0x14, // Receive Maximum
+        0x26, 0x00, 0x06, b'r', b'e', b'g', b'i', b'o', b'n', 0x00, 0x02, b'c', b'n',
+    ][..]
+);
+```
+ */
+#[derive(Debug, Default)]
+pub struct ConnectPropertiesBuilder {
+    properties: ConnectProperties,
+}
+
+impl ConnectPropertiesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 设置会话过期时间，单位为秒
+    pub fn session_expiry_interval(mut self, session_expiry_interval: u32) -> Self {
+        self.properties.session_expiry_interval = Some(session_expiry_interval);
+        self
+    }
+    /// 设置同时处理的QoS 1和QoS 2消息的最大数量
+    pub fn receive_maximum(mut self, receive_maximum: u16) -> Self {
+        self.properties.receive_maximum = Some(receive_maximum);
+        self
+    }
+    /// 设置愿意接收的最大报文长度
+    pub fn maximum_packet_size(mut self, maximum_packet_size: u32) -> Self {
+        self.properties.maximum_packet_size = Some(maximum_packet_size);
+        self
+    }
+    /// 设置topic alias的最大值
+    pub fn topic_alias_maximum(mut self, topic_alias_maximum: u16) -> Self {
+        self.properties.topic_alias_maximum = Some(topic_alias_maximum);
+        self
+    }
+    /// 设置是否请求响应信息
+    pub fn request_response_information(mut self, request_response_information: bool) -> Self {
+        self.properties.request_response_information = Some(request_response_information);
+        self
+    }
+    /// 设置出错时是否需要原因字符串和用户属性
+    pub fn request_problem_information(mut self, request_problem_information: bool) -> Self {
+        self.properties.request_problem_information = Some(request_problem_information);
+        self
+    }
+    /// 添加一个用户属性，可以调用多次
+    pub fn user_property(mut self, key: &str, value: &str) -> Self {
+        self.properties
+            .user_properties
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+    /// 设置认证方法
+    pub fn authentication_method(mut self, authentication_method: &str) -> Self {
+        self.properties.authentication_method = Some(authentication_method.to_string());
+        self
+    }
+    /// 设置认证数据
+    pub fn authentication_data(mut self, authentication_data: Bytes) -> Self {
+        self.properties.authentication_data = Some(authentication_data);
+        self
+    }
+    pub fn build(self) -> ConnectProperties {
+        self.properties
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Connect实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for Connect {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let remaining_length = self.remaining_length();
+        let fixed_header_len = write_fixed_header(buffer, MessageType::CONNECT, remaining_length)?;
+        // 可变报头
+        write_mqtt_string(buffer, PROTOCOL_NAME)?;
+        buffer.put_u8(PROTOCOL_LEVEL);
+        buffer.put_u8(self.flags());
+        buffer.put_u16(self.keep_alive);
+        self.properties.encode(buffer)?;
+        // payload
+        write_mqtt_string(buffer, &self.client_id)?;
+        if let Some(last_will) = &self.last_will {
+            last_will.properties.encode(buffer)?;
+            write_mqtt_string(buffer, &last_will.topic_name)?;
+            write_mqtt_bytes(buffer, &last_will.message)?;
+        }
+        if let Some(username) = &self.username {
+            write_mqtt_string(buffer, username)?;
+        }
+        if let Some(password) = &self.password {
+            write_mqtt_bytes(buffer, password)?;
+        }
+        Ok(fixed_header_len + remaining_length)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Connect实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for Connect {
+    type Item = Connect;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::CONNECT)?;
+        // 可变报头
+        if read_mqtt_string(&mut body)? != PROTOCOL_NAME {
+            return Err(ProtoError::InvalidProtocolName);
+        }
+        let level = read_u8(&mut body)?;
+        if level != PROTOCOL_LEVEL {
+            return Err(ProtoError::UnsupportedVersion(level));
+        }
+        let flags = read_u8(&mut body)?;
+        if flags & 0b0000_0001 != 0 {
+            return Err(ProtoError::ConnectReservedFlagError);
+        }
+        let will_flag = flags & 0b0000_0100 != 0;
+        if !will_flag && flags & 0b0011_1000 != 0 {
+            return Err(ProtoError::WillFlagsWithoutWill);
+        }
+        let keep_alive = read_u16(&mut body)?;
+        let properties = ConnectProperties::decode(&mut body)?;
+        // payload
+        let client_id = read_mqtt_string(&mut body)?;
+        let last_will = if will_flag {
+            let properties = WillProperties::decode(&mut body)?;
+            let topic_name = read_mqtt_string(&mut body)?;
+            let message = read_mqtt_bytes(&mut body)?;
+            Some(LastWill {
+                properties,
+                topic_name,
+                message,
+                qos: QoS::try_from((flags & 0b0001_1000) >> 3)?,
+                retain: flags & 0b0010_0000 != 0,
+            })
+        } else {
+            None
+        };
+        let username = match flags & 0b1000_0000 {
+            0 => None,
+            _ => Some(read_mqtt_string(&mut body)?),
+        };
+        let password = match flags & 0b0100_0000 {
+            0 => None,
+            _ => Some(read_mqtt_bytes(&mut body)?),
+        };
+        Ok(Connect {
+            clean_start: flags & 0b0000_0010 != 0,
+            keep_alive,
+            properties,
+            client_id,
+            last_will,
+            username,
+            password,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connect, ConnectProperties, LastWill, WillProperties};
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use crate::QoS;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn encode_and_decode_connect_should_be_work() {
+        let mut last_will = LastWill::new(
+            "/will".to_string(),
+            Bytes::from_static(b"offline"),
+            QoS::AtLeastOnce,
+            true,
+        );
+        last_will.properties = WillProperties::builder()
+            .will_delay_interval(10)
+            .utf8_payload(true)
+            .user_property("k", "v")
+            .build();
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .keep_alive(30)
+            .clean_start(false)
+            .last_will(last_will)
+            .password(Bytes::from_static(b"token"))
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        let len = connect.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        // 协议名称、protocol level以及连接标志位
+        assert_eq!(&buffer[2..10], b"\x00\x04MQTT\x05\x6c");
+        let decoded = Connect::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, connect);
+        assert_eq!(decoded.username(), None);
+        let last_will = decoded.last_will().unwrap();
+        assert_eq!(last_will.qos, QoS::AtLeastOnce);
+        assert_eq!(last_will.properties.will_delay_interval(), Some(10));
+        assert_eq!(last_will.properties.user_properties().len(), 1);
+    }
+
+    #[test]
+    fn fixed_header_should_declare_body_length() {
+        use crate::common::var_int::VarInt;
+        for password_len in [0, 200] {
+            let connect = MqttMessageBuilder::connect()
+                .client_id("client_01")
+                .password(Bytes::from(vec![b'p'; password_len]))
+                .build()
+                .unwrap();
+            let mut buffer = BytesMut::new();
+            connect.encode(&mut buffer).unwrap();
+            assert_eq!(buffer[0], 0x10);
+            let mut body = buffer.clone().freeze().split_off(1);
+            let (remaining_length, len) = VarInt::decode(&mut body).unwrap();
+            assert_eq!(len, if password_len == 0 { 1 } else { 2 });
+            assert_eq!(u32::from(remaining_length) as usize, body.len());
+            assert_eq!(&body[..7], b"\x00\x04MQTT\x05");
+            assert_eq!(Connect::decode(buffer.clone().freeze()).unwrap(), connect);
+
+            // 报文类型错误或者内容不完整时返回错误
+            let mut wrong_type = buffer.clone();
+            wrong_type[0] = 0x20;
+            assert_eq!(
+                Connect::decode(wrong_type.freeze()).unwrap_err(),
+                ProtoError::MessageTypeMismatch
+            );
+            buffer.truncate(buffer.len() - 1);
+            assert!(Connect::decode(buffer.freeze()).is_err());
+        }
+    }
+
+    #[test]
+    fn connect_should_match_wire_bytes() {
+        let mut last_will = LastWill::new(
+            "/w".to_string(),
+            Bytes::from_static(b"x"),
+            QoS::AtLeastOnce,
+            false,
+        );
+        last_will.properties = WillProperties::builder().will_delay_interval(5).build();
+        let connect = MqttMessageBuilder::connect()
+            .client_id("c1")
+            .keep_alive(30)
+            .clean_start(true)
+            .properties(
+                ConnectProperties::builder()
+                    .session_expiry_interval(60)
+                    .build(),
+            )
+            .last_will(last_will)
+            .username("u")
+            .password(Bytes::from_static(b"p"))
+            .build()
+            .unwrap();
+        #[rustfmt::skip]
+        let frame: &[u8] = &[
+            0x10, 0x27,
+            // protocol name、protocol level、连接标志位、keep_alive
+            0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0xce, 0x00, 0x1e,
+            // Session Expiry Interval
+            0x05, 0x11, 0x00, 0x00, 0x00, 0x3c,
+            0x00, 0x02, b'c', b'1',
+            // Will Delay Interval
+            0x05, 0x18, 0x00, 0x00, 0x00, 0x05,
+            0x00, 0x02, b'/', b'w',
+            0x00, 0x01, b'x',
+            0x00, 0x01, b'u',
+            0x00, 0x01, b'p',
+        ];
+        let mut buffer = BytesMut::new();
+        connect.encode(&mut buffer).unwrap();
+        assert_eq!(&buffer[..], frame);
+
+        let decoded = Connect::decode(Bytes::from_static(frame)).unwrap();
+        assert_eq!(decoded, connect);
+        assert!(decoded.clean_start());
+        assert_eq!(decoded.client_id(), "c1");
+        assert_eq!(decoded.properties().session_expiry_interval(), Some(60));
+        assert_eq!(decoded.username(), Some("u"));
+        assert_eq!(decoded.password(), Some(&Bytes::from_static(b"p")));
+    }
+
+    #[test]
+    fn will_payload_and_auth_should_match_wire_bytes() {
+        // 遗嘱消息超过255字节，长度前缀为两个字节；认证方法和认证数据是CONNECT的属性，不在payload中
+        let last_will = LastWill::new(
+            "/w".to_string(),
+            Bytes::from(vec![0xab; 300]),
+            QoS::AtMostOnce,
+            false,
+        );
+        let connect = MqttMessageBuilder::connect()
+            .client_id("c")
+            .keep_alive(10)
+            .clean_start(true)
+            .properties(
+                ConnectProperties::builder()
+                    .authentication_method("PLAIN")
+                    .authentication_data(Bytes::from_static(b"ab"))
+                    .build(),
+            )
+            .last_will(last_will)
+            .build()
+            .unwrap();
+        #[rustfmt::skip]
+        let mut frame = vec![
+            0x10, 0xce, 0x02,
+            0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x06, 0x00, 0x0a,
+            // Authentication Method、Authentication Data
+            0x0d,
+            0x15, 0x00, 0x05, b'P', b'L', b'A', b'I', b'N',
+            0x16, 0x00, 0x02, b'a', b'b',
+            0x00, 0x01, b'c',
+            // 遗嘱属性为空
+            0x00,
+            0x00, 0x02, b'/', b'w',
+            0x01, 0x2c,
+        ];
+        frame.extend_from_slice(&[0xab; 300]);
+        let mut buffer = BytesMut::new();
+        assert_eq!(connect.encode(&mut buffer).unwrap(), frame.len());
+        assert_eq!(&buffer[..], &frame[..]);
+
+        let decoded = Connect::decode(Bytes::from(frame)).unwrap();
+        assert_eq!(decoded, connect);
+        assert_eq!(decoded.properties().authentication_method(), Some("PLAIN"));
+        assert_eq!(
+            decoded.properties().authentication_data(),
+            Some(&Bytes::from_static(b"ab"))
+        );
+        assert_eq!(decoded.last_will().unwrap().message.len(), 300);
+        assert_eq!(decoded.password(), None);
+    }
+
+    #[test]
+    fn will_delay_interval_should_be_work() {
+        let mut last_will = LastWill::new(
+            "/w".to_string(),
+            Bytes::from_static(b"x"),
+            QoS::AtMostOnce,
+            false,
+        );
+        last_will.properties = WillProperties::builder().will_delay_interval(30).build();
+        let connect = MqttMessageBuilder::connect()
+            .client_id("c")
+            .last_will(last_will)
+            .build()
+            .unwrap();
+        assert_eq!(connect.will_delay_interval(), Some(30));
+        let mut buffer = BytesMut::new();
+        connect.encode(&mut buffer).unwrap();
+        // fixed_header(2) + 可变报头(10) + 属性(1) + client_id(3)之后是遗嘱属性
+        assert_eq!(&buffer[16..22], &[0x05, 0x18, 0x00, 0x00, 0x00, 0x1e]);
+        assert_eq!(&buffer[22..], b"\x00\x02/w\x00\x01x");
+        let decoded = Connect::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded.will_delay_interval(), Some(30));
+
+        let connect = MqttMessageBuilder::connect()
+            .client_id("c")
+            .build()
+            .unwrap();
+        assert_eq!(connect.will_delay_interval(), None);
+    }
+
+    #[test]
+    fn invalid_connect_flags_should_return_error() {
+        // 没有遗嘱时will_qos不为0
+        let frame = Bytes::from_static(&[
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x08, 0x00, 0x3c, 0x00, 0x00,
+            0x00,
+        ]);
+        assert_eq!(
+            Connect::decode(frame).unwrap_err(),
+            ProtoError::WillFlagsWithoutWill
+        );
+    }
+
+    #[test]
+    fn invalid_protocol_should_return_error() {
+        // protocol level为4
+        let frame = Bytes::from_static(&[
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x02, 0x00, 0x3c, 0x00, 0x00,
+            0x00,
+        ]);
+        assert_eq!(
+            Connect::decode(frame).unwrap_err(),
+            ProtoError::UnsupportedVersion(4)
+        );
+        // MQTT 3.1的protocol name
+        let frame = Bytes::from_static(&[
+            0x10, 0x0f, 0x00, 0x06, b'M', b'Q', b'I', b's', b'd', b'p', 0x05, 0x02, 0x00, 0x3c,
+            0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(
+            Connect::decode(frame).unwrap_err(),
+            ProtoError::InvalidProtocolName
+        );
+        // 属性长度为5，但是之后只剩2个字节
+        let frame = Bytes::from_static(&[
+            0x10, 0x0d, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x05, 0x02, 0x00, 0x3c, 0x05, 0x00,
+            0x00,
+        ]);
+        assert_eq!(
+            Connect::decode(frame).unwrap_err(),
+            ProtoError::TruncatedProperties {
+                len: 5,
+                remaining: 2
+            }
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_connect_properties_should_be_work() {
+        let properties = ConnectProperties::builder()
+            .session_expiry_interval(3600)
+            .receive_maximum(20)
+            .maximum_packet_size(1024)
+            .topic_alias_maximum(10)
+            .request_response_information(true)
+            .request_problem_information(false)
+            .user_property("k", "v1")
+            .user_property("k", "v2")
+            .authentication_method("SCRAM-SHA-1")
+            .authentication_data(Bytes::from_static(b"data"))
+            .build();
+        let connect = MqttMessageBuilder::connect()
+            .client_id("client_01")
+            .properties(properties.clone())
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        let len = connect.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        let decoded = Connect::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded.properties(), &properties);
+        assert_eq!(decoded.properties().user_properties().len(), 2);
+    }
+
+    #[test]
+    fn invalid_connect_properties_should_return_error() {
+        let cases: [(&[u8], u8); 4] = [
+            // Receive Maximum为0
+            (&[0x03, 0x21, 0x00, 0x00], 0x21),
+            // Request Problem Information为2
+            (&[0x02, 0x17, 0x02], 0x17),
+            // 重复的Session Expiry Interval
+            (
+                &[
+                    0x0a, 0x11, 0x00, 0x00, 0x00, 0x01, 0x11, 0x00, 0x00, 0x00, 0x02,
+                ],
+                0x11,
+            ),
+            // 只有认证数据，没有认证方法
+            (&[0x04, 0x16, 0x00, 0x01, 0x00], 0x16),
+        ];
+        for (bytes, id) in cases {
+            let mut bytes = Bytes::copy_from_slice(bytes);
+            assert_eq!(
+                ConnectProperties::decode(&mut bytes).unwrap_err(),
+                ProtoError::InvalidProperty(id)
+            );
+        }
+    }
+}
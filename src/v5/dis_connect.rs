@@ -0,0 +1,482 @@
+use super::builder::DisconnectBuilder;
+use super::property::{
+    properties_len, read_properties, unexpected_property, write_properties, REASON_STRING,
+    SERVER_REFERENCE, SESSION_EXPIRY_INTERVAL, USER_PROPERTY,
+};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::{read_mqtt_string, read_u32, read_u8, write_mqtt_string};
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+
+/// DISCONNECT报文的原因码，客户端只能使用0x00、0x04以及部分错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum DisconnectReasonCode {
+    /// 正常断开连接，不发送遗嘱消息
+    #[default]
+    NormalDisconnection = 0x00,
+    /// 断开连接，并且发送遗嘱消息
+    DisconnectWithWillMessage = 0x04,
+    /// 未指明的错误
+    UnspecifiedError = 0x80,
+    /// 报文格式错误
+    MalformedPacket = 0x81,
+    /// 违反了协议
+    ProtocolError = 0x82,
+    /// 实现的特定错误
+    ImplementationSpecificError = 0x83,
+    /// 未授权
+    NotAuthorized = 0x87,
+    /// 服务端繁忙
+    ServerBusy = 0x89,
+    /// 服务端正在关闭
+    ServerShuttingDown = 0x8B,
+    /// 在1.5倍的keep_alive时间内没有收到报文
+    KeepAliveTimeout = 0x8D,
+    /// 相同client_id的另一个连接接管了会话
+    SessionTakenOver = 0x8E,
+    /// topic filter格式正确，但是不被接受
+    TopicFilterInvalid = 0x8F,
+    /// topic name格式正确，但是不被接受
+    TopicNameInvalid = 0x90,
+    /// 超过了接收最大值
+    ReceiveMaximumExceeded = 0x93,
+    /// topic alias非法
+    TopicAliasInvalid = 0x94,
+    /// 报文超过了最大报文长度
+    PacketTooLarge = 0x95,
+    /// 消息速率过高
+    MessageRateTooHigh = 0x96,
+    /// 超出了配额
+    QuotaExceeded = 0x97,
+    /// 管理员操作
+    AdministrativeAction = 0x98,
+    /// payload与payload格式指示不一致
+    PayloadFormatInvalid = 0x99,
+    /// 不支持保留消息
+    RetainNotSupported = 0x9A,
+    /// 不支持的QoS
+    QoSNotSupported = 0x9B,
+    /// 临时使用另一个服务端
+    UseAnotherServer = 0x9C,
+    /// 服务端已经永久迁移
+    ServerMoved = 0x9D,
+    /// 不支持共享订阅
+    SharedSubscriptionsNotSupported = 0x9E,
+    /// 连接速率超出限制
+    ConnectionRateExceeded = 0x9F,
+    /// 超过了最大连接时间
+    MaximumConnectTime = 0xA0,
+    /// 不支持订阅标识符
+    SubscriptionIdentifiersNotSupported = 0xA1,
+    /// 不支持通配符订阅
+    WildcardSubscriptionsNotSupported = 0xA2,
+}
+
+impl DisconnectReasonCode {
+    /// 是否因为错误断开连接，0x00和0x04不是错误
+    pub fn is_error(&self) -> bool {
+        (*self as u8) >= 0x80
+    }
+}
+
+impl From<DisconnectReasonCode> for u8 {
+    fn from(code: DisconnectReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for DisconnectReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        use DisconnectReasonCode::*;
+        let reason_code = match code {
+            0x00 => NormalDisconnection,
+            0x04 => DisconnectWithWillMessage,
+            0x80 => UnspecifiedError,
+            0x81 => MalformedPacket,
+            0x82 => ProtocolError,
+            0x83 => ImplementationSpecificError,
+            0x87 => NotAuthorized,
+            0x89 => ServerBusy,
+            0x8B => ServerShuttingDown,
+            0x8D => KeepAliveTimeout,
+            0x8E => SessionTakenOver,
+            0x8F => TopicFilterInvalid,
+            0x90 => TopicNameInvalid,
+            0x93 => ReceiveMaximumExceeded,
+            0x94 => TopicAliasInvalid,
+            0x95 => PacketTooLarge,
+            0x96 => MessageRateTooHigh,
+            0x97 => QuotaExceeded,
+            0x98 => AdministrativeAction,
+            0x99 => PayloadFormatInvalid,
+            0x9A => RetainNotSupported,
+            0x9B => QoSNotSupported,
+            0x9C => UseAnotherServer,
+            0x9D => ServerMoved,
+            0x9E => SharedSubscriptionsNotSupported,
+            0x9F => ConnectionRateExceeded,
+            0xA0 => MaximumConnectTime,
+            0xA1 => SubscriptionIdentifiersNotSupported,
+            0xA2 => WildcardSubscriptionsNotSupported,
+            code => return Err(ProtoError::UnknownReasonCode(code)),
+        };
+        Ok(reason_code)
+    }
+}
+
+/// 输出协议中定义的原因码名称
+impl fmt::Display for DisconnectReasonCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            DisconnectReasonCode::NormalDisconnection => "Normal disconnection",
+            DisconnectReasonCode::DisconnectWithWillMessage => "Disconnect with Will Message",
+            DisconnectReasonCode::UnspecifiedError => "Unspecified error",
+            DisconnectReasonCode::MalformedPacket => "Malformed Packet",
+            DisconnectReasonCode::ProtocolError => "Protocol Error",
+            DisconnectReasonCode::ImplementationSpecificError => "Implementation specific error",
+            DisconnectReasonCode::NotAuthorized => "Not authorized",
+            DisconnectReasonCode::ServerBusy => "Server busy",
+            DisconnectReasonCode::ServerShuttingDown => "Server shutting down",
+            DisconnectReasonCode::KeepAliveTimeout => "Keep Alive timeout",
+            DisconnectReasonCode::SessionTakenOver => "Session taken over",
+            DisconnectReasonCode::TopicFilterInvalid => "Topic Filter invalid",
+            DisconnectReasonCode::TopicNameInvalid => "Topic Name invalid",
+            DisconnectReasonCode::ReceiveMaximumExceeded => "Receive Maximum exceeded",
+            DisconnectReasonCode::TopicAliasInvalid => "Topic Alias invalid",
+            DisconnectReasonCode::PacketTooLarge => "Packet too large",
+            DisconnectReasonCode::MessageRateTooHigh => "Message rate too high",
+            DisconnectReasonCode::QuotaExceeded => "Quota exceeded",
+            DisconnectReasonCode::AdministrativeAction => "Administrative action",
+            DisconnectReasonCode::PayloadFormatInvalid => "Payload format invalid",
+            DisconnectReasonCode::RetainNotSupported => "Retain not supported",
+            DisconnectReasonCode::QoSNotSupported => "QoS not supported",
+            DisconnectReasonCode::UseAnotherServer => "Use another server",
+            DisconnectReasonCode::ServerMoved => "Server moved",
+            DisconnectReasonCode::SharedSubscriptionsNotSupported => {
+                "Shared Subscriptions not supported"
+            }
+            DisconnectReasonCode::ConnectionRateExceeded => "Connection rate exceeded",
+            DisconnectReasonCode::MaximumConnectTime => "Maximum connect time",
+            DisconnectReasonCode::SubscriptionIdentifiersNotSupported => {
+                "Subscription Identifiers not supported"
+            }
+            DisconnectReasonCode::WildcardSubscriptionsNotSupported => {
+                "Wildcard Subscriptions not supported"
+            }
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// DISCONNECT报文可以携带的属性
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisconnectProperties {
+    // 0x11 会话过期时间，单位为秒
+    pub(crate) session_expiry_interval: Option<u32>,
+    // 0x1F 原因字符串
+    pub(crate) reason_string: Option<String>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+    // 0x1C 服务端参考，配合UseAnotherServer、ServerMoved使用
+    pub(crate) server_reference: Option<String>,
+}
+
+impl DisconnectProperties {
+    pub fn session_expiry_interval(&self) -> Option<u32> {
+        self.session_expiry_interval
+    }
+    pub fn reason_string(&self) -> Option<&str> {
+        self.reason_string.as_deref()
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+    pub fn server_reference(&self) -> Option<&str> {
+        self.server_reference.as_deref()
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if self.session_expiry_interval.is_some() {
+            len += 1 + 4;
+        }
+        if let Some(reason_string) = &self.reason_string {
+            len += 1 + 2 + reason_string.len();
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        if let Some(server_reference) = &self.server_reference {
+            len += 1 + 2 + server_reference.len();
+        }
+        len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<DisconnectProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = DisconnectProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                SESSION_EXPIRY_INTERVAL => properties
+                    .session_expiry_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                REASON_STRING => properties
+                    .reason_string
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                SERVER_REFERENCE => properties
+                    .server_reference
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                _ => return Err(unexpected_property(MessageType::DISCONNECT, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为DisconnectProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for DisconnectProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(session_expiry_interval) = self.session_expiry_interval {
+            properties.put_u8(SESSION_EXPIRY_INTERVAL);
+            properties.put_u32(session_expiry_interval);
+        }
+        if let Some(reason_string) = &self.reason_string {
+            properties.put_u8(REASON_STRING);
+            write_mqtt_string(&mut properties, reason_string)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        if let Some(server_reference) = &self.server_reference {
+            properties.put_u8(SERVER_REFERENCE);
+            write_mqtt_string(&mut properties, server_reference)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/// MQTT 5.0 DISCONNECT报文
+///
+/// 与v3.1.1相比，增加了原因码和属性，客户端和服务端都可以发送。
+/// 原因码为0x00(Normal disconnection)且没有属性时，剩余长度为0，与v3.1.1的报文相同；
+/// 只有原因码时省略属性长度。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisConnect {
+    // 原因码
+    reason_code: DisconnectReasonCode,
+    // 属性
+    properties: DisconnectProperties,
+}
+
+impl DisConnect {
+    pub fn new(reason_code: DisconnectReasonCode, properties: DisconnectProperties) -> Self {
+        Self {
+            reason_code,
+            properties,
+        }
+    }
+    pub fn builder() -> DisconnectBuilder {
+        DisconnectBuilder::new()
+    }
+    pub fn reason_code(&self) -> DisconnectReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> &DisconnectProperties {
+        &self.properties
+    }
+
+    // 剩余长度 = 原因码 + 属性，可以省略的部分不计入
+    fn remaining_length(&self) -> usize {
+        match (self.reason_code, self.properties.is_empty()) {
+            (DisconnectReasonCode::NormalDisconnection, true) => 0,
+            (_, true) => 1,
+            _ => 1 + properties_len(self.properties.len()),
+        }
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为DisConnect实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for DisConnect {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let remaining_length = self.remaining_length();
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::DISCONNECT, remaining_length)?;
+        if remaining_length > 0 {
+            buffer.put_u8(self.reason_code.into());
+        }
+        if remaining_length > 1 {
+            self.properties.encode(buffer)?;
+        }
+        Ok(fixed_header_len + remaining_length)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为DisConnect实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for DisConnect {
+    type Item = DisConnect;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::DISCONNECT)?;
+        // 省略的原因码视为0x00，省略的属性视为没有属性
+        if !body.has_remaining() {
+            return Ok(DisConnect::default());
+        }
+        let reason_code = DisconnectReasonCode::try_from(read_u8(&mut body)?)?;
+        let properties = match body.has_remaining() {
+            true => DisconnectProperties::decode(&mut body)?,
+            false => DisconnectProperties::default(),
+        };
+        Ok(DisConnect {
+            reason_code,
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DisConnect, DisconnectReasonCode};
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn normal_disconnection_should_be_compatible_with_v4() {
+        let mut buffer = BytesMut::new();
+        DisConnect::default().encode(&mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), &[0xe0, 0x00]);
+        assert_eq!(
+            DisConnect::decode(Bytes::from_static(&[0xe0, 0x00])).unwrap(),
+            DisConnect::default()
+        );
+        // 0x04：Disconnect with Will Message
+        let disconnect = DisConnect::decode(Bytes::from_static(&[0xe0, 0x01, 0x04])).unwrap();
+        assert_eq!(
+            disconnect.reason_code(),
+            DisconnectReasonCode::DisconnectWithWillMessage
+        );
+        assert!(disconnect.properties().is_empty());
+    }
+
+    #[test]
+    fn disconnect_builder_should_use_shortest_form() {
+        // 只有原因码
+        let disconnect = MqttMessageBuilder::disconnect()
+            .reason_code(DisconnectReasonCode::DisconnectWithWillMessage)
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        assert_eq!(disconnect.encode(&mut buffer).unwrap(), 3);
+        assert_eq!(buffer.as_ref(), &[0xe0, 0x01, 0x04]);
+        assert_eq!(DisConnect::decode(buffer.freeze()).unwrap(), disconnect);
+
+        // 原因码和属性
+        let disconnect = MqttMessageBuilder::disconnect()
+            .reason_code(DisconnectReasonCode::UseAnotherServer)
+            .session_expiry_interval(60)
+            .reason_string("move")
+            .user_property("k", "v")
+            .server_reference("b:1883")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        let len = disconnect.encode(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0xe0, 0x1e, 0x9c, 0x1c,
+            0x11, 0x00, 0x00, 0x00, 0x3c,
+            0x1f, 0x00, 0x04, b'm', b'o', b'v', b'e',
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+            0x1c, 0x00, 0x06, b'b', b':', b'1', b'8', b'8', b'3',
+        ];
+        assert_eq!(buffer.as_ref(), expected);
+        assert_eq!(len, expected.len());
+        let decoded = DisConnect::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, disconnect);
+        assert_eq!(decoded.properties().server_reference(), Some("b:1883"));
+        assert_eq!(decoded.properties().session_expiry_interval(), Some(60));
+
+        // 原因码为0x00但是有属性时不能省略原因码
+        let disconnect = MqttMessageBuilder::disconnect()
+            .reason_string("bye")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        disconnect.encode(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], &[0xe0, 0x08, 0x00, 0x06]);
+    }
+
+    #[test]
+    fn invalid_disconnect_should_return_error() {
+        // 0x8D Keep Alive Timeout，属性长度为0
+        let disconnect = DisConnect::decode(Bytes::from_static(&[0xe0, 0x02, 0x8d, 0x00])).unwrap();
+        assert_eq!(
+            disconnect.reason_code(),
+            DisconnectReasonCode::KeepAliveTimeout
+        );
+        // 0x01不是DISCONNECT的原因码
+        assert_eq!(
+            DisConnect::decode(Bytes::from_static(&[0xe0, 0x01, 0x01])).unwrap_err(),
+            ProtoError::UnknownReasonCode(0x01)
+        );
+        // 0x15认证方法不能出现在DISCONNECT中
+        let frame = Bytes::from_static(&[0xe0, 0x07, 0x00, 0x05, 0x15, 0x00, 0x02, b'a', b'b']);
+        assert!(matches!(
+            DisConnect::decode(frame).unwrap_err(),
+            ProtoError::PropertyNotAllowed { id: 0x15, .. }
+        ));
+        let codes: Vec<u8> = (0x00..=0xff)
+            .filter(|code| DisconnectReasonCode::try_from(*code).is_ok())
+            .collect();
+        assert_eq!(codes.len(), 29);
+        for code in codes {
+            let reason_code = DisconnectReasonCode::try_from(code).unwrap();
+            assert_eq!(u8::from(reason_code), code);
+            assert_eq!(reason_code.is_error(), code >= 0x80);
+        }
+        assert_eq!(
+            DisconnectReasonCode::DisconnectWithWillMessage.to_string(),
+            "Disconnect with Will Message"
+        );
+        assert_eq!(
+            DisconnectReasonCode::KeepAliveTimeout.to_string(),
+            "Keep Alive timeout"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! MQTT-v5.0版本的报文
+//!
+//! v5报文的固定报头与v3.1.1保持一致，所以这里直接复用了v4中的`FixedHeader`，
+//! 区别主要在于可变报头中增加了属性(Properties)部分。
+pub mod auth;
+pub mod builder;
+pub mod conn_ack;
+pub mod connect;
+pub mod dis_connect;
+pub mod property;
+pub mod pub_ack;
+pub mod pub_comp;
+pub mod pub_rec;
+pub mod pub_rel;
+pub mod publish;
+pub mod sub_ack;
+pub mod subscribe;
+pub mod topic_alias;
+pub mod un_suback;
+pub mod un_subscribe;
+
+use self::auth::Auth;
+use self::conn_ack::ConnAck;
+use self::connect::Connect;
+use self::dis_connect::DisConnect;
+use self::property::{properties_len, read_properties, write_properties};
+use self::pub_ack::PubAck;
+use self::pub_comp::PubComp;
+use self::pub_rec::PubRec;
+use self::pub_rel::PubRel;
+use self::publish::Publish;
+use self::sub_ack::SubAck;
+use self::subscribe::Subscribe;
+use self::un_suback::UnSubAck;
+use self::un_subscribe::UnSubscribe;
+use crate::common::coder::read_u8;
+use crate::common::var_int::VarInt;
+use crate::error::ProtoError;
+use crate::v4::decoder::{read_fixed_header, take_body};
+use crate::v4::fixed_header::FixedHeader;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// PINGREQ、PINGRESP报文在v5中没有任何变化，直接复用v4的实现
+pub use crate::v4::ping_req::PingReq;
+pub use crate::v4::ping_resp::PingResp;
+/// CONNACK和DISCONNECT的原因码，可以直接通过`v5::`访问
+pub use conn_ack::ConnectReasonCode;
+pub use dis_connect::DisconnectReasonCode;
+
+/// MQTT报文，包含了MQTT-v5.0版本中的所有MQTT报文
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    // 连接报文，属性较多，放在堆上避免Packet过大
+    Connect(Box<Connect>),
+    // 连接回执报文
+    ConnAck(ConnAck),
+    // 发布报文
+    Publish(Publish),
+    // 发布回执报文
+    PubAck(PubAck),
+    PubRec(PubRec),
+    PubRel(PubRel),
+    PubComp(PubComp),
+    // 订阅报文
+    Subscribe(Subscribe),
+    // 订阅回执报文
+    SubAck(SubAck),
+    // 取消订阅报文
+    UnSubscribe(UnSubscribe),
+    // 取消订阅回执报文
+    UnSubAck(UnSubAck),
+    // 心跳报文
+    PingReq(PingReq),
+    // 心跳回执报文
+    PingResp(PingResp),
+    // 断开链接报文
+    DisConnect(DisConnect),
+    // 认证报文
+    Auth(Auth),
+}
+
+/// 根据首字节的高4位将bytes解析为对应的v5报文
+///
+/// PUBLISH报文中的topic alias不会在这里解析，只携带alias的报文topic为空，
+/// 需要调用[`Publish::resolve_topic_alias`]得到真正的topic。
+pub fn decode(bytes: Bytes) -> Result<Packet, ProtoError> {
+    if bytes.is_empty() {
+        return Err(ProtoError::FixedHeaderLengthError(0));
+    }
+    let packet = match FixedHeader::check_with_u8(bytes[0])? {
+        MessageType::CONNECT => Packet::Connect(Box::new(Connect::decode(bytes)?)),
+        MessageType::CONNACK => Packet::ConnAck(ConnAck::decode(bytes)?),
+        MessageType::PUBLISH => Packet::Publish(Publish::decode_unresolved(bytes)?),
+        MessageType::PUBACK => Packet::PubAck(PubAck::decode(bytes)?),
+        MessageType::PUBREC => Packet::PubRec(PubRec::decode(bytes)?),
+        MessageType::PUBREL => Packet::PubRel(PubRel::decode(bytes)?),
+        MessageType::PUBCOMP => Packet::PubComp(PubComp::decode(bytes)?),
+        MessageType::SUBSCRIBE => Packet::Subscribe(Subscribe::decode(bytes)?),
+        MessageType::SUBACK => Packet::SubAck(SubAck::decode(bytes)?),
+        MessageType::UNSUBSCRIBE => Packet::UnSubscribe(UnSubscribe::decode(bytes)?),
+        MessageType::UNSUBACK => Packet::UnSubAck(UnSubAck::decode(bytes)?),
+        MessageType::PINGREQ => Packet::PingReq(PingReq::decode(bytes)?),
+        MessageType::PINGRESP => Packet::PingResp(PingResp::decode(bytes)?),
+        MessageType::DISCONNECT => Packet::DisConnect(DisConnect::decode(bytes)?),
+        MessageType::AUTH => Packet::Auth(Auth::decode(bytes)?),
+    };
+    Ok(packet)
+}
+
+/// 将报文编码到buf中，返回写入的字节数
+pub fn encode(packet: &Packet, buf: &mut BytesMut) -> Result<usize, ProtoError> {
+    match packet {
+        Packet::Connect(packet) => packet.encode(buf),
+        Packet::ConnAck(packet) => packet.encode(buf),
+        Packet::Publish(packet) => packet.encode(buf),
+        Packet::PubAck(packet) => packet.encode(buf),
+        Packet::PubRec(packet) => packet.encode(buf),
+        Packet::PubRel(packet) => packet.encode(buf),
+        Packet::PubComp(packet) => packet.encode(buf),
+        Packet::Subscribe(packet) => packet.encode(buf),
+        Packet::SubAck(packet) => packet.encode(buf),
+        Packet::UnSubscribe(packet) => packet.encode(buf),
+        Packet::UnSubAck(packet) => packet.encode(buf),
+        Packet::PingReq(packet) => packet.encode(buf),
+        Packet::PingResp(packet) => packet.encode(buf),
+        Packet::DisConnect(packet) => packet.encode(buf),
+        Packet::Auth(packet) => packet.encode(buf),
+    }
+}
+
+// 写入fixed_header：报文类型及标志位 + 剩余长度，返回写入的字节数
+pub(crate) fn write_fixed_header(
+    buffer: &mut BytesMut,
+    message_type: MessageType,
+    remaining_length: usize,
+) -> Result<usize, ProtoError> {
+    let flags = match message_type {
+        MessageType::PUBREL | MessageType::SUBSCRIBE | MessageType::UNSUBSCRIBE => 0b0010,
+        _ => 0,
+    };
+    buffer.put_u8(u8::from(message_type) << 4 | flags);
+    Ok(1 + VarInt::try_from(remaining_length)?.encode(buffer))
+}
+
+// 读取fixed_header并校验报文类型，返回fixed_header以及剩余长度范围内的报文内容，
+// 报文不完整时返回ProtoError::Incomplete
+pub(crate) fn split_body(
+    mut bytes: Bytes,
+    message_type: MessageType,
+) -> Result<(FixedHeader, Bytes), ProtoError> {
+    let fixed_header = read_fixed_header(&mut bytes)?;
+    if fixed_header.message_type() != message_type {
+        return Err(ProtoError::MessageTypeMismatch);
+    }
+    let body = take_body(bytes, &fixed_header)?;
+    Ok((fixed_header, body))
+}
+
+// 原因码和属性编码之后的长度：原因码为0x00且没有属性时两者都可以省略，没有属性时可以省略属性长度
+pub(crate) fn reason_code_len(reason_code: u8, properties: &Bytes) -> usize {
+    match (reason_code, properties.is_empty()) {
+        (0x00, true) => 0,
+        (_, true) => 1,
+        _ => 1 + properties_len(properties.len()),
+    }
+}
+
+// 写入原因码和属性，返回写入的字节数
+pub(crate) fn write_reason_code(
+    buffer: &mut BytesMut,
+    reason_code: u8,
+    properties: &Bytes,
+) -> Result<usize, ProtoError> {
+    match (reason_code, properties.is_empty()) {
+        (0x00, true) => Ok(0),
+        (_, true) => {
+            buffer.put_u8(reason_code);
+            Ok(1)
+        }
+        _ => {
+            buffer.put_u8(reason_code);
+            Ok(1 + write_properties(buffer, properties)?)
+        }
+    }
+}
+
+// 读取原因码和属性，省略的原因码视为0x00
+pub(crate) fn read_reason_code(bytes: &mut Bytes) -> Result<(u8, Bytes), ProtoError> {
+    if !bytes.has_remaining() {
+        return Ok((0x00, Bytes::new()));
+    }
+    let reason_code = read_u8(bytes)?;
+    if !bytes.has_remaining() {
+        return Ok((reason_code, Bytes::new()));
+    }
+    Ok((reason_code, read_properties(bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Packet};
+    use crate::common::packet_id::PacketId;
+    use crate::error::{BuildError, ProtoError};
+    use crate::v5::{
+        auth::AuthReasonCode,
+        builder::MqttMessageBuilder,
+        conn_ack::{ConnAck, ConnAckProperties},
+        pub_ack::{PubAck, PubAckReasonCode},
+        pub_comp::PubComp,
+        pub_rec::PubRec,
+        pub_rel::{PubRel, PubRelReasonCode},
+        sub_ack::{SubAck, SubAckReasonCode},
+        subscribe::{SubscriptionOptions, TopicFilter},
+        topic_alias::TopicAliasMap,
+        un_suback::{UnSubAck, UnSubAckReasonCode},
+        ConnectReasonCode, DisconnectReasonCode, PingReq, PingResp,
+    };
+    use crate::QoS;
+    use bytes::{Bytes, BytesMut};
+
+    fn packets() -> Vec<Packet> {
+        let packet_id = PacketId::new(7).unwrap();
+        let properties = Bytes::from_static(&[0x1f, 0x00, 0x02, b'o', b'k']);
+        vec![
+            Packet::Connect(Box::new(
+                MqttMessageBuilder::connect()
+                    .client_id("client_01")
+                    .keep_alive(30)
+                    .username("rump")
+                    .password(Bytes::from_static(b"mq"))
+                    .build()
+                    .unwrap(),
+            )),
+            Packet::ConnAck(ConnAck::new(
+                true,
+                ConnectReasonCode::Success,
+                ConnAckProperties::builder()
+                    .assigned_client_identifier("auto-01")
+                    .build(),
+            )),
+            Packet::Publish(
+                MqttMessageBuilder::publish()
+                    .topic("/a")
+                    .qos(QoS::AtLeastOnce)
+                    .packet_id(packet_id)
+                    .payload_str("hello")
+                    .build()
+                    .unwrap(),
+            ),
+            Packet::PubAck(PubAck::new(
+                packet_id,
+                PubAckReasonCode::Success,
+                Bytes::new(),
+            )),
+            Packet::PubRec(PubRec::new(
+                packet_id,
+                PubAckReasonCode::NoMatchingSubscribers,
+                Bytes::new(),
+            )),
+            Packet::PubRel(PubRel::new(
+                packet_id,
+                PubRelReasonCode::PacketIdentifierNotFound,
+                properties.clone(),
+            )),
+            Packet::PubComp(PubComp::new(
+                packet_id,
+                PubRelReasonCode::Success,
+                Bytes::new(),
+            )),
+            Packet::Subscribe(
+                MqttMessageBuilder::subscribe()
+                    .packet_id(packet_id)
+                    .topic_filter(TopicFilter::new(
+                        "/a/+",
+                        SubscriptionOptions::new(QoS::AtLeastOnce),
+                    ))
+                    .build()
+                    .unwrap(),
+            ),
+            Packet::SubAck(SubAck::new(
+                packet_id,
+                Bytes::new(),
+                vec![
+                    SubAckReasonCode::GrantedQoS1,
+                    SubAckReasonCode::UnspecifiedError,
+                ],
+            )),
+            Packet::UnSubscribe(
+                MqttMessageBuilder::un_subscribe()
+                    .packet_id(packet_id)
+                    .topic("/a/+")
+                    .build()
+                    .unwrap(),
+            ),
+            Packet::UnSubAck(UnSubAck::new(
+                packet_id,
+                properties.clone(),
+                vec![UnSubAckReasonCode::Success],
+            )),
+            Packet::PingReq(PingReq::new()),
+            Packet::PingResp(PingResp::new()),
+            Packet::DisConnect(
+                MqttMessageBuilder::disconnect()
+                    .reason_code(DisconnectReasonCode::KeepAliveTimeout)
+                    .reason_string("timeout")
+                    .build()
+                    .unwrap(),
+            ),
+            Packet::Auth(
+                MqttMessageBuilder::auth()
+                    .reason_code(AuthReasonCode::Continue)
+                    .authentication_method("SCRAM-SHA-256")
+                    .authentication_data(Bytes::from_static(b"r=nonce"))
+                    .user_property("k", "v")
+                    .build()
+                    .unwrap(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn encode_and_decode_all_packets_should_be_work() {
+        for packet in packets() {
+            let mut buf = BytesMut::new();
+            let len = encode(&packet, &mut buf).unwrap();
+            assert_eq!(len, buf.len(), "{:?}", packet);
+            let decoded = decode(buf.freeze()).unwrap();
+            assert_eq!(decoded, packet);
+        }
+    }
+
+    #[test]
+    fn decode_should_not_resolve_topic_alias() {
+        let publish = MqttMessageBuilder::publish()
+            .topic_alias(3)
+            .payload_str("25.2")
+            .build()
+            .unwrap();
+        let mut buf = BytesMut::new();
+        encode(&Packet::Publish(publish), &mut buf).unwrap();
+        let mut publish = match decode(buf.freeze()).unwrap() {
+            Packet::Publish(publish) => publish,
+            packet => panic!("unexpected packet: {:?}", packet),
+        };
+        assert_eq!(publish.topic(), "");
+        let mut aliases = TopicAliasMap::new();
+        aliases.insert(3, "/sensor/temp").unwrap();
+        publish.resolve_topic_alias(&aliases).unwrap();
+        assert_eq!(publish.topic(), "/sensor/temp");
+    }
+
+    #[test]
+    fn decode_invalid_bytes_should_return_error() {
+        assert_eq!(
+            decode(Bytes::new()).unwrap_err(),
+            ProtoError::FixedHeaderLengthError(0)
+        );
+        assert_eq!(
+            decode(Bytes::from_static(&[0x00, 0x00])).unwrap_err(),
+            ProtoError::MessageTypeError(BuildError::MessageTypeError(0))
+        );
+        // 剩余长度为3，只到达了1个字节
+        assert_eq!(
+            decode(Bytes::from_static(&[0x40, 0x03, 0x00])).unwrap_err(),
+            ProtoError::Incomplete { needed: 2 }
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::common::coder::read_u8;
+use crate::common::var_int::VarInt;
+use crate::error::ProtoError;
+use crate::MessageType;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Payload Format Indicator属性标识符，值为单字节：0表示未指定格式，1表示UTF-8
+pub const PAYLOAD_FORMAT_INDICATOR: u8 = 0x01;
+/// Message Expiry Interval属性标识符，值为四字节整数，单位为秒
+pub const MESSAGE_EXPIRY_INTERVAL: u8 = 0x02;
+/// Content Type属性标识符，值为UTF-8字符串
+pub const CONTENT_TYPE: u8 = 0x03;
+/// Response Topic属性标识符，值为UTF-8字符串
+pub const RESPONSE_TOPIC: u8 = 0x08;
+/// Correlation Data属性标识符，值为二进制数据
+pub const CORRELATION_DATA: u8 = 0x09;
+/// Subscription Identifier属性标识符，值为Variable Byte Integer
+pub const SUBSCRIPTION_IDENTIFIER: u8 = 0x0B;
+/// Session Expiry Interval属性标识符，值为四字节整数，单位为秒
+pub const SESSION_EXPIRY_INTERVAL: u8 = 0x11;
+/// Assigned Client Identifier属性标识符，值为UTF-8字符串
+pub const ASSIGNED_CLIENT_IDENTIFIER: u8 = 0x12;
+/// Server Keep Alive属性标识符，值为两字节整数，单位为秒
+pub const SERVER_KEEP_ALIVE: u8 = 0x13;
+/// Authentication Method属性标识符，值为UTF-8字符串
+pub const AUTHENTICATION_METHOD: u8 = 0x15;
+/// Authentication Data属性标识符，值为二进制数据
+pub const AUTHENTICATION_DATA: u8 = 0x16;
+/// Request Problem Information属性标识符，值为单字节：0或1
+pub const REQUEST_PROBLEM_INFORMATION: u8 = 0x17;
+/// Request Response Information属性标识符，值为单字节：0或1
+pub const REQUEST_RESPONSE_INFORMATION: u8 = 0x19;
+/// Response Information属性标识符，值为UTF-8字符串
+pub const RESPONSE_INFORMATION: u8 = 0x1A;
+/// Server Reference属性标识符，值为UTF-8字符串
+pub const SERVER_REFERENCE: u8 = 0x1C;
+/// Receive Maximum属性标识符，值为两字节整数，不能为0
+pub const RECEIVE_MAXIMUM: u8 = 0x21;
+/// Topic Alias Maximum属性标识符，值为两字节整数
+pub const TOPIC_ALIAS_MAXIMUM: u8 = 0x22;
+/// Will Delay Interval属性标识符，值为四字节整数，单位为秒
+pub const WILL_DELAY_INTERVAL: u8 = 0x18;
+/// Topic Alias属性标识符，值为两字节整数
+pub const TOPIC_ALIAS: u8 = 0x23;
+/// Reason String属性标识符，值为UTF-8字符串
+pub const REASON_STRING: u8 = 0x1F;
+/// User Property属性标识符，值为UTF-8字符串对，可以出现多次
+pub const USER_PROPERTY: u8 = 0x26;
+/// Maximum QoS属性标识符，值为单字节：0或1
+pub const MAXIMUM_QOS: u8 = 0x24;
+/// Retain Available属性标识符，值为单字节：0或1
+pub const RETAIN_AVAILABLE: u8 = 0x25;
+/// Maximum Packet Size属性标识符，值为四字节整数，不能为0
+pub const MAXIMUM_PACKET_SIZE: u8 = 0x27;
+/// Wildcard Subscription Available属性标识符，值为单字节：0或1
+pub const WILDCARD_SUBSCRIPTION_AVAILABLE: u8 = 0x28;
+/// Subscription Identifier Available属性标识符，值为单字节：0或1
+pub const SUBSCRIPTION_IDENTIFIER_AVAILABLE: u8 = 0x29;
+/// Shared Subscription Available属性标识符，值为单字节：0或1
+pub const SHARED_SUBSCRIPTION_AVAILABLE: u8 = 0x2A;
+
+/// Variable Byte Integer能表示的最大值
+pub const MAX_VARIABLE_BYTE_INTEGER: usize = VarInt::MAX as usize;
+
+/// 读取一个Variable Byte Integer
+pub fn read_variable_byte_integer(stream: &mut Bytes) -> Result<usize, ProtoError> {
+    let (value, _) = VarInt::decode(stream)?;
+    Ok(u32::from(value) as usize)
+}
+
+/// 写入一个Variable Byte Integer，返回写入的字节数
+pub fn write_variable_byte_integer(
+    buffer: &mut BytesMut,
+    value: usize,
+) -> Result<usize, ProtoError> {
+    Ok(VarInt::try_from(value)?.encode(buffer))
+}
+
+/// Variable Byte Integer编码之后所占的字节数
+pub fn variable_byte_integer_len(value: usize) -> usize {
+    VarInt::try_from(value).map_or(4, |value| value.encoded_len())
+}
+
+/// 属性标识符是否在MQTT 5.0的属性表中
+pub fn is_known_property(id: u8) -> bool {
+    matches!(
+        id,
+        0x01..=0x03 | 0x08 | 0x09 | 0x0B | 0x11..=0x13 | 0x15..=0x1A | 0x1C | 0x1F | 0x21..=0x2A
+    )
+}
+
+/// 报文中出现了不允许的属性：没有定义的标识符返回UnknownProperty，
+/// 已定义但不能出现在packet报文中的属性返回PropertyNotAllowed
+pub(crate) fn unexpected_property(packet: MessageType, id: u8) -> ProtoError {
+    match is_known_property(id) {
+        true => ProtoError::PropertyNotAllowed { packet, id },
+        false => ProtoError::UnknownProperty(id),
+    }
+}
+
+/// 读取属性部分：先读取属性长度，再从stream中切出对应长度的属性内容
+pub fn read_properties(stream: &mut Bytes) -> Result<Bytes, ProtoError> {
+    let len = read_variable_byte_integer(stream)?;
+    if len > stream.len() {
+        return Err(ProtoError::TruncatedProperties {
+            len,
+            remaining: stream.len(),
+        });
+    }
+    Ok(stream.split_to(len))
+}
+
+/// 读取取值只能为0或1的单字节属性
+pub(crate) fn read_bool(stream: &mut Bytes, id: u8) -> Result<bool, ProtoError> {
+    match read_u8(stream)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ProtoError::InvalidProperty(id)),
+    }
+}
+
+/// 写入属性部分：属性长度 + 属性内容，返回写入的字节数
+pub fn write_properties(buffer: &mut BytesMut, properties: &[u8]) -> Result<usize, ProtoError> {
+    let len = write_variable_byte_integer(buffer, properties.len())?;
+    buffer.put_slice(properties);
+    Ok(len + properties.len())
+}
+
+/// 属性部分编码之后的总长度(包括属性长度本身)
+pub fn properties_len(len: usize) -> usize {
+    variable_byte_integer_len(len) + len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn variable_byte_integer_should_be_work() {
+        for value in [
+            0,
+            127,
+            128,
+            16_383,
+            16_384,
+            2_097_151,
+            2_097_152,
+            268_435_455,
+        ] {
+            let mut buffer = BytesMut::new();
+            let len = write_variable_byte_integer(&mut buffer, value).unwrap();
+            assert_eq!(len, variable_byte_integer_len(value));
+            let mut bytes = buffer.freeze();
+            assert_eq!(read_variable_byte_integer(&mut bytes).unwrap(), value);
+            assert!(bytes.is_empty());
+        }
+        assert!(write_variable_byte_integer(&mut BytesMut::new(), 268_435_456).is_err());
+    }
+
+    #[test]
+    fn read_properties_should_only_consume_declared_length() {
+        // 属性长度之后紧跟着payload，读取属性时不能多读
+        let mut buffer = BytesMut::new();
+        let properties = [0x11, 0x00, 0x00, 0x0e, 0x10];
+        assert_eq!(write_properties(&mut buffer, &properties).unwrap(), 6);
+        buffer.extend_from_slice(b"\x00\x02id");
+        let mut stream = buffer.freeze();
+        assert_eq!(read_properties(&mut stream).unwrap(), &properties[..]);
+        assert_eq!(stream, Bytes::from_static(b"\x00\x02id"));
+
+        // 属性长度超过剩余的内容
+        let mut stream = Bytes::from_static(&[0x05, 0x11, 0x00]);
+        assert_eq!(
+            read_properties(&mut stream).unwrap_err(),
+            ProtoError::TruncatedProperties {
+                len: 5,
+                remaining: 2
+            }
+        );
+        // 属性长度超过4个字节
+        let mut stream = Bytes::from_static(&[0x80, 0x80, 0x80, 0x80, 0x01]);
+        assert!(read_variable_byte_integer(&mut stream).is_err());
+    }
+
+    #[test]
+    fn is_known_property_should_match_property_table() {
+        let known = [
+            PAYLOAD_FORMAT_INDICATOR,
+            MESSAGE_EXPIRY_INTERVAL,
+            CONTENT_TYPE,
+            RESPONSE_TOPIC,
+            CORRELATION_DATA,
+            SUBSCRIPTION_IDENTIFIER,
+            SESSION_EXPIRY_INTERVAL,
+            ASSIGNED_CLIENT_IDENTIFIER,
+            SERVER_KEEP_ALIVE,
+            AUTHENTICATION_METHOD,
+            AUTHENTICATION_DATA,
+            REQUEST_PROBLEM_INFORMATION,
+            WILL_DELAY_INTERVAL,
+            REQUEST_RESPONSE_INFORMATION,
+            RESPONSE_INFORMATION,
+            SERVER_REFERENCE,
+            REASON_STRING,
+            RECEIVE_MAXIMUM,
+            TOPIC_ALIAS_MAXIMUM,
+            TOPIC_ALIAS,
+            MAXIMUM_QOS,
+            RETAIN_AVAILABLE,
+            USER_PROPERTY,
+            MAXIMUM_PACKET_SIZE,
+            WILDCARD_SUBSCRIPTION_AVAILABLE,
+            SUBSCRIPTION_IDENTIFIER_AVAILABLE,
+            SHARED_SUBSCRIPTION_AVAILABLE,
+        ];
+        for id in 0..=u8::MAX {
+            assert_eq!(is_known_property(id), known.contains(&id), "{:#04x}", id);
+            let expected = match known.contains(&id) {
+                true => ProtoError::PropertyNotAllowed {
+                    packet: MessageType::CONNACK,
+                    id,
+                },
+                false => ProtoError::UnknownProperty(id),
+            };
+            assert_eq!(unexpected_property(MessageType::CONNACK, id), expected);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::builder::PubAckBuilder;
+use super::{read_reason_code, reason_code_len, split_body, write_fixed_header, write_reason_code};
+use crate::common::coder::read_u16;
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// PUBACK和PUBREC报文的原因码，小于0x80表示成功
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum PubAckReasonCode {
+    /// 消息被接受
+    #[default]
+    Success = 0x00,
+    /// 消息被接受，但是没有订阅者
+    NoMatchingSubscribers = 0x10,
+    /// 未指明的错误
+    UnspecifiedError = 0x80,
+    /// 实现的特定错误
+    ImplementationSpecificError = 0x83,
+    /// 没有发布权限
+    NotAuthorized = 0x87,
+    /// topic name格式正确，但是不被接受
+    TopicNameInvalid = 0x90,
+    /// message_id正在使用中
+    PacketIdentifierInUse = 0x91,
+    /// 超出了配额
+    QuotaExceeded = 0x97,
+    /// payload与payload格式指示不一致
+    PayloadFormatInvalid = 0x99,
+}
+
+impl PubAckReasonCode {
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+}
+
+impl From<PubAckReasonCode> for u8 {
+    fn from(code: PubAckReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for PubAckReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(PubAckReasonCode::Success),
+            0x10 => Ok(PubAckReasonCode::NoMatchingSubscribers),
+            0x80 => Ok(PubAckReasonCode::UnspecifiedError),
+            0x83 => Ok(PubAckReasonCode::ImplementationSpecificError),
+            0x87 => Ok(PubAckReasonCode::NotAuthorized),
+            0x90 => Ok(PubAckReasonCode::TopicNameInvalid),
+            0x91 => Ok(PubAckReasonCode::PacketIdentifierInUse),
+            0x97 => Ok(PubAckReasonCode::QuotaExceeded),
+            0x99 => Ok(PubAckReasonCode::PayloadFormatInvalid),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+/// MQTT 5.0 PUBACK报文，发布回执报文，QoS为1的PUBLISH报文的响应
+///
+/// 与v3.1.1相比，message_id之后增加了原因码和属性，
+/// 原因码为0x00(Success)且没有属性时，这两部分都可以省略。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PubAck {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 原因码
+    reason_code: PubAckReasonCode,
+    // 属性
+    properties: Bytes,
+}
+
+impl PubAck {
+    pub fn new(packet_id: PacketId, reason_code: PubAckReasonCode, properties: Bytes) -> Self {
+        Self {
+            packet_id,
+            reason_code,
+            properties,
+        }
+    }
+    pub fn builder() -> PubAckBuilder {
+        PubAckBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn reason_code(&self) -> PubAckReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> &Bytes {
+        &self.properties
+    }
+
+    // 剩余长度 = message_id + 原因码 + 属性
+    fn remaining_length(&self) -> usize {
+        2 + reason_code_len(self.reason_code.into(), &self.properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubAck实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for PubAck {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::PUBACK, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let reason_code_len = write_reason_code(buffer, self.reason_code.into(), &self.properties)?;
+        Ok(fixed_header_len + 2 + reason_code_len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubAck实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for PubAck {
+    type Item = PubAck;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::PUBACK)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let (reason_code, properties) = read_reason_code(&mut body)?;
+        Ok(PubAck {
+            packet_id,
+            reason_code: PubAckReasonCode::try_from(reason_code)?,
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PubAck, PubAckReasonCode};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn reason_code_and_properties_can_be_omitted() {
+        let packet_id = PacketId::new(0x1234).unwrap();
+        // 原因码为0x00且没有属性、只有原因码、原因码和属性
+        let cases: [(PubAck, &[u8]); 3] = [
+            (
+                PubAck::new(packet_id, PubAckReasonCode::Success, Bytes::new()),
+                &[0x40, 0x02, 0x12, 0x34],
+            ),
+            (
+                PubAck::new(
+                    packet_id,
+                    PubAckReasonCode::PacketIdentifierInUse,
+                    Bytes::new(),
+                ),
+                &[0x40, 0x03, 0x12, 0x34, 0x91],
+            ),
+            (
+                PubAck::new(
+                    packet_id,
+                    PubAckReasonCode::UnspecifiedError,
+                    Bytes::from_static(&[0x1f, 0x00, 0x01, b'e']),
+                ),
+                &[0x40, 0x08, 0x12, 0x34, 0x80, 0x04, 0x1f, 0x00, 0x01, b'e'],
+            ),
+        ];
+        for (packet, expected) in cases {
+            let mut buffer = BytesMut::new();
+            assert_eq!(packet.encode(&mut buffer).unwrap(), expected.len());
+            assert_eq!(buffer.as_ref(), expected);
+            assert_eq!(PubAck::decode(buffer.freeze()).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn pub_ack_builder_should_be_work() {
+        let pub_ack = MqttMessageBuilder::pub_ack()
+            .packet_id(PacketId::MIN)
+            .build()
+            .unwrap();
+        assert_eq!(pub_ack.reason_code(), PubAckReasonCode::Success);
+        let mut buffer = BytesMut::new();
+        pub_ack.encode(&mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), &[0x40, 0x02, 0x00, 0x01]);
+
+        let pub_ack = MqttMessageBuilder::pub_ack()
+            .packet_id(PacketId::MIN)
+            .reason_code(PubAckReasonCode::NoMatchingSubscribers)
+            .reason_string("ok")
+            .user_property("k", "v")
+            .build()
+            .unwrap();
+        assert!(pub_ack.reason_code().is_success());
+        let mut buffer = BytesMut::new();
+        pub_ack.encode(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x40, 0x10, 0x00, 0x01, 0x10, 0x0c,
+            0x1f, 0x00, 0x02, b'o', b'k',
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+        ];
+        assert_eq!(buffer.as_ref(), expected);
+        assert_eq!(PubAck::decode(buffer.freeze()).unwrap(), pub_ack);
+
+        assert_eq!(
+            MqttMessageBuilder::pub_ack().build().unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+    }
+
+    #[test]
+    fn unknown_reason_code_should_return_error() {
+        // 0x92只能用于PUBREL和PUBCOMP
+        let frame = Bytes::from_static(&[0x40, 0x03, 0x00, 0x01, 0x92]);
+        assert_eq!(
+            PubAck::decode(frame).unwrap_err(),
+            ProtoError::UnknownReasonCode(0x92)
+        );
+        for code in [0x00, 0x10, 0x80, 0x83, 0x87, 0x90, 0x91, 0x97, 0x99] {
+            let reason_code = PubAckReasonCode::try_from(code).unwrap();
+            assert_eq!(u8::from(reason_code), code);
+            assert_eq!(reason_code.is_success(), code < 0x80);
+        }
+    }
+}
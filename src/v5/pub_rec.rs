@@ -0,0 +1,126 @@
+use super::builder::PubRecBuilder;
+use super::pub_ack::PubAckReasonCode;
+use super::{read_reason_code, reason_code_len, split_body, write_fixed_header, write_reason_code};
+use crate::common::coder::read_u16;
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// MQTT 5.0 PUBREC报文，发布收到报文，QoS为2的PUBLISH报文的第一个响应
+///
+/// 结构与[`PubAck`](super::pub_ack::PubAck)相同：message_id + 可以省略的原因码和属性。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PubRec {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 原因码
+    reason_code: PubAckReasonCode,
+    // 属性
+    properties: Bytes,
+}
+
+impl PubRec {
+    pub fn new(packet_id: PacketId, reason_code: PubAckReasonCode, properties: Bytes) -> Self {
+        Self {
+            packet_id,
+            reason_code,
+            properties,
+        }
+    }
+    pub fn builder() -> PubRecBuilder {
+        PubRecBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn reason_code(&self) -> PubAckReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> &Bytes {
+        &self.properties
+    }
+
+    // 剩余长度 = message_id + 原因码 + 属性
+    fn remaining_length(&self) -> usize {
+        2 + reason_code_len(self.reason_code.into(), &self.properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubRec实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for PubRec {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::PUBREC, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let reason_code_len = write_reason_code(buffer, self.reason_code.into(), &self.properties)?;
+        Ok(fixed_header_len + 2 + reason_code_len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubRec实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for PubRec {
+    type Item = PubRec;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::PUBREC)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let (reason_code, properties) = read_reason_code(&mut body)?;
+        Ok(PubRec {
+            packet_id,
+            reason_code: PubAckReasonCode::try_from(reason_code)?,
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PubRec;
+    use crate::common::packet_id::PacketId;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use crate::v5::pub_ack::PubAckReasonCode;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn pub_rec_short_and_full_form_should_be_work() {
+        // 原因码为Success并且没有属性时只有message_id
+        let pub_rec = MqttMessageBuilder::pub_rec()
+            .packet_id(PacketId::new(3).unwrap())
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        pub_rec.encode(&mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), &[0x50, 0x02, 0x00, 0x03]);
+        assert_eq!(PubRec::decode(buffer.freeze()).unwrap(), pub_rec);
+
+        let pub_rec = MqttMessageBuilder::pub_rec()
+            .packet_id(PacketId::new(3).unwrap())
+            .reason_code(PubAckReasonCode::QuotaExceeded)
+            .user_property("k", "v")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        pub_rec.encode(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x50, 0x0b, 0x00, 0x03, 0x97, 0x07,
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+        ];
+        assert_eq!(buffer.as_ref(), expected);
+        assert_eq!(PubRec::decode(buffer.freeze()).unwrap(), pub_rec);
+
+        // 只有原因码，省略了属性长度
+        let frame = Bytes::from_static(&[0x50, 0x03, 0x00, 0x03, 0x97]);
+        let decoded = PubRec::decode(frame).unwrap();
+        assert_eq!(decoded.reason_code(), PubAckReasonCode::QuotaExceeded);
+        assert!(decoded.properties().is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use super::builder::PubRelBuilder;
+use super::{read_reason_code, reason_code_len, split_body, write_fixed_header, write_reason_code};
+use crate::common::coder::read_u16;
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// PUBREL和PUBCOMP报文的原因码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum PubRelReasonCode {
+    /// 成功
+    #[default]
+    Success = 0x00,
+    /// 没有找到对应的message_id
+    PacketIdentifierNotFound = 0x92,
+}
+
+impl From<PubRelReasonCode> for u8 {
+    fn from(code: PubRelReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for PubRelReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(PubRelReasonCode::Success),
+            0x92 => Ok(PubRelReasonCode::PacketIdentifierNotFound),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+/// MQTT 5.0 PUBREL报文，发布释放报文，PUBREC报文的响应
+///
+/// 结构与[`PubAck`](super::pub_ack::PubAck)相同：message_id + 可以省略的原因码和属性。
+/// 固定报头的标志位固定为0b0010。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PubRel {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 原因码
+    reason_code: PubRelReasonCode,
+    // 属性
+    properties: Bytes,
+}
+
+impl PubRel {
+    pub fn new(packet_id: PacketId, reason_code: PubRelReasonCode, properties: Bytes) -> Self {
+        Self {
+            packet_id,
+            reason_code,
+            properties,
+        }
+    }
+    pub fn builder() -> PubRelBuilder {
+        PubRelBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn reason_code(&self) -> PubRelReasonCode {
+        self.reason_code
+    }
+    pub fn properties(&self) -> &Bytes {
+        &self.properties
+    }
+
+    // 剩余长度 = message_id + 原因码 + 属性
+    fn remaining_length(&self) -> usize {
+        2 + reason_code_len(self.reason_code.into(), &self.properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubRel实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for PubRel {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::PUBREL, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let reason_code_len = write_reason_code(buffer, self.reason_code.into(), &self.properties)?;
+        Ok(fixed_header_len + 2 + reason_code_len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PubRel实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for PubRel {
+    type Item = PubRel;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::PUBREL)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let (reason_code, properties) = read_reason_code(&mut body)?;
+        Ok(PubRel {
+            packet_id,
+            reason_code: PubRelReasonCode::try_from(reason_code)?,
+            properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PubRel, PubRelReasonCode};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn encode_and_decode_pub_rel_should_be_work() {
+        let pub_rel = PubRel::new(
+            PacketId::new(1).unwrap(),
+            PubRelReasonCode::PacketIdentifierNotFound,
+            Bytes::new(),
+        );
+        let mut buffer = BytesMut::new();
+        pub_rel.encode(&mut buffer).unwrap();
+        // 固定报头的标志位为0b0010
+        assert_eq!(buffer.as_ref(), &[0x62, 0x03, 0x00, 0x01, 0x92]);
+        assert_eq!(PubRel::decode(buffer.freeze()).unwrap(), pub_rel);
+    }
+
+    #[test]
+    fn pub_rel_short_and_full_form_should_be_work() {
+        let pub_rel = MqttMessageBuilder::pub_rel()
+            .packet_id(PacketId::new(2).unwrap())
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        pub_rel.encode(&mut buffer).unwrap();
+        assert_eq!(buffer.as_ref(), &[0x62, 0x02, 0x00, 0x02]);
+        assert_eq!(PubRel::decode(buffer.freeze()).unwrap(), pub_rel);
+
+        let pub_rel = MqttMessageBuilder::pub_rel()
+            .packet_id(PacketId::new(2).unwrap())
+            .reason_string("x")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        pub_rel.encode(&mut buffer).unwrap();
+        // 原因码为Success，但是有属性时不能省略原因码
+        assert_eq!(
+            buffer.as_ref(),
+            &[0x62, 0x08, 0x00, 0x02, 0x00, 0x04, 0x1f, 0x00, 0x01, b'x']
+        );
+        assert_eq!(PubRel::decode(buffer.freeze()).unwrap(), pub_rel);
+
+        let frame = Bytes::from_static(&[0x62, 0x03, 0x00, 0x02, 0x10]);
+        assert_eq!(
+            PubRel::decode(frame).unwrap_err(),
+            ProtoError::UnknownReasonCode(0x10)
+        );
+    }
+}
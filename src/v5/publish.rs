@@ -0,0 +1,739 @@
+use super::property::{
+    properties_len, read_properties, read_variable_byte_integer, unexpected_property,
+    variable_byte_integer_len, write_properties, write_variable_byte_integer, CONTENT_TYPE,
+    CORRELATION_DATA, MESSAGE_EXPIRY_INTERVAL, PAYLOAD_FORMAT_INDICATOR, RESPONSE_TOPIC,
+    SUBSCRIPTION_IDENTIFIER, TOPIC_ALIAS, USER_PROPERTY,
+};
+use super::topic_alias::TopicAliasMap;
+use crate::common::coder::{
+    read_mqtt_bytes, read_mqtt_string, read_u16, read_u32, read_u8, write_mqtt_bytes,
+    write_mqtt_string,
+};
+use crate::common::packet_id::PacketId;
+use crate::error::{ProtoError, TopicAliasError};
+use crate::v4::{decoder, fixed_header::FixedHeader, Encoder, VariableDecoder};
+use crate::{MessageType, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::time::Duration;
+
+/// MQTT 5.0 PUBLISH报文
+///
+/// 与v3.1.1相比，可变报头中在message_id之后增加了属性部分。
+/// 当携带topic alias时，topic可以为空字符串，此时接收方需要通过`TopicAliasMap`找到真正的topic。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Publish {
+    // 固定报头
+    fixed_header: FixedHeader,
+    // 可变报头
+    variable_header: PublishVariableHeader,
+    // payload 有效载荷
+    payload: Bytes,
+}
+
+impl Publish {
+    pub fn new(
+        fixed_header: FixedHeader,
+        variable_header: PublishVariableHeader,
+        payload: Bytes,
+    ) -> Self {
+        Self {
+            fixed_header,
+            variable_header,
+            payload,
+        }
+    }
+
+    pub fn fixed_header(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+
+    pub fn variable_header(&self) -> &PublishVariableHeader {
+        &self.variable_header
+    }
+
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    pub fn topic(&self) -> &str {
+        self.variable_header.topic()
+    }
+
+    pub fn topic_alias(&self) -> Option<u16> {
+        self.variable_header.topic_alias()
+    }
+
+    pub fn message_id(&self) -> Option<usize> {
+        self.variable_header.message_id()
+    }
+
+    pub fn properties(&self) -> &PublishProperties {
+        self.variable_header.properties()
+    }
+
+    /// payload是否为UTF-8编码的字符数据
+    pub fn is_utf8_payload(&self) -> bool {
+        self.variable_header.properties.is_utf8_payload()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.variable_header.properties.content_type()
+    }
+
+    /// 消息的过期时间，为None时消息永不过期
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.variable_header.properties.expires_in()
+    }
+
+    pub fn qos(&self) -> QoS {
+        self.fixed_header.qos().unwrap_or_default()
+    }
+
+    pub fn retain(&self) -> bool {
+        self.fixed_header.retain().unwrap_or(false)
+    }
+
+    pub fn dup(&self) -> bool {
+        self.fixed_header.dup().unwrap_or(false)
+    }
+
+    // 剩余长度 = 可变报头 + payload
+    fn remaining_length(&self) -> usize {
+        self.variable_header.len() + self.payload.len()
+    }
+
+    /// 将bytes解析为PUBLISH报文，topic为空时通过alias_map查找topic alias对应的topic
+    pub fn decode(bytes: Bytes, alias_map: &TopicAliasMap) -> Result<Publish, ProtoError> {
+        let mut publish = Publish::decode_unresolved(bytes)?;
+        publish.resolve_topic_alias(alias_map)?;
+        Ok(publish)
+    }
+
+    /// topic为空时通过alias_map查找topic alias对应的topic，topic不为空时不做任何处理
+    pub fn resolve_topic_alias(&mut self, alias_map: &TopicAliasMap) -> Result<(), ProtoError> {
+        if self.variable_header.topic.is_empty() {
+            let alias = self
+                .variable_header
+                .topic_alias()
+                .ok_or(TopicAliasError::MissingAlias)?;
+            let topic = alias_map
+                .topic(alias)
+                .ok_or(TopicAliasError::AliasNotFound(alias))?;
+            self.variable_header.topic = topic.to_string();
+        }
+        Ok(())
+    }
+
+    // 解析PUBLISH报文，但是不处理topic alias，只携带alias的报文topic为空
+    pub(crate) fn decode_unresolved(mut bytes: Bytes) -> Result<Publish, ProtoError> {
+        // 读取fixed_header
+        let fixed_header = decoder::read_fixed_header(&mut bytes)?;
+        let mut bytes = decoder::take_body(bytes, &fixed_header)?;
+        // 读取variable_header
+        let variable_header = PublishVariableHeader::decode(&mut bytes, fixed_header.qos())?;
+        Ok(Publish {
+            fixed_header,
+            variable_header,
+            payload: bytes,
+        })
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Publish实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for Publish {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut fixed_header = self.fixed_header.clone();
+        fixed_header.set_remaining_length(self.remaining_length());
+        let fixed_header_len = fixed_header.encode(buffer)?;
+        let variable_header_len = self.variable_header.encode(buffer)?;
+        buffer.put_slice(&self.payload);
+        Ok(fixed_header_len + variable_header_len + self.payload.len())
+    }
+}
+
+//////////////////////////////////////////////
+/// PublishVariableHeader
+/////////////////////////////////////////////
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublishVariableHeader {
+    // topic，使用topic alias时可以为空
+    topic: String,
+    // message_id，QoS为0时没有message_id
+    message_id: Option<PacketId>,
+    // 属性
+    properties: PublishProperties,
+}
+
+impl PublishVariableHeader {
+    pub fn new(topic: String, message_id: Option<PacketId>, properties: PublishProperties) -> Self {
+        Self {
+            topic,
+            message_id,
+            properties,
+        }
+    }
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+    pub fn message_id(&self) -> Option<usize> {
+        self.message_id.map(usize::from)
+    }
+    pub fn packet_id(&self) -> Option<PacketId> {
+        self.message_id
+    }
+    pub fn topic_alias(&self) -> Option<u16> {
+        self.properties.topic_alias
+    }
+    pub fn properties(&self) -> &PublishProperties {
+        &self.properties
+    }
+
+    /// 可变报头的长度
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 2 + self.topic.len();
+        if self.message_id.is_some() {
+            len += 2;
+        }
+        len + properties_len(self.properties.len())
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PublishVariableHeader实现VariableDecode trait
+/////////////////////////////////////////////////////////
+impl VariableDecoder for PublishVariableHeader {
+    type Item = PublishVariableHeader;
+
+    fn decode(bytes: &mut Bytes, qos: Option<QoS>) -> Result<Self::Item, ProtoError> {
+        let topic = read_mqtt_string(bytes)?;
+        let message_id = match qos {
+            // QoS为1或2时message_id不能为0
+            Some(QoS::AtLeastOnce) | Some(QoS::ExactlyOnce) => {
+                Some(PacketId::try_from(read_u16(bytes)?)?)
+            }
+            _ => None,
+        };
+        let properties = PublishProperties::decode(bytes)?;
+        Ok(PublishVariableHeader::new(topic, message_id, properties))
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PublishVariableHeader实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for PublishVariableHeader {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        write_mqtt_string(buffer, &self.topic)?;
+        if let Some(message_id) = self.message_id {
+            buffer.put_u16(message_id.get());
+        }
+        self.properties.encode(buffer)?;
+        Ok(self.len())
+    }
+}
+
+//////////////////////////////////////////////
+/// PublishProperties
+/////////////////////////////////////////////
+/// PUBLISH报文可以携带的属性，除了订阅标识符和用户属性之外，每个属性最多出现一次
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublishProperties {
+    // 0x01 payload格式：0表示未指定格式的字节，1表示UTF-8编码的字符数据
+    pub(crate) payload_format_indicator: Option<u8>,
+    // 0x02 消息过期时间，单位为秒
+    pub(crate) message_expiry_interval: Option<u32>,
+    // 0x03 内容类型，例如MIME类型
+    pub(crate) content_type: Option<String>,
+    // 0x08 响应主题
+    pub(crate) response_topic: Option<String>,
+    // 0x09 对比数据
+    pub(crate) correlation_data: Option<Bytes>,
+    // 0x23 topic alias
+    pub(crate) topic_alias: Option<u16>,
+    // 0x0B 订阅标识符，由broker在转发消息时填写，消息匹配多个订阅时可以出现多次
+    pub(crate) subscription_identifiers: Vec<u32>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+}
+
+impl PublishProperties {
+    pub fn payload_format_indicator(&self) -> Option<u8> {
+        self.payload_format_indicator
+    }
+    pub fn is_utf8_payload(&self) -> bool {
+        self.payload_format_indicator == Some(1)
+    }
+    pub fn message_expiry_interval(&self) -> Option<u32> {
+        self.message_expiry_interval
+    }
+    pub fn expires_in(&self) -> Option<Duration> {
+        self.message_expiry_interval
+            .map(|secs| Duration::from_secs(secs as u64))
+    }
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+    pub fn response_topic(&self) -> Option<&str> {
+        self.response_topic.as_deref()
+    }
+    pub fn correlation_data(&self) -> Option<&Bytes> {
+        self.correlation_data.as_ref()
+    }
+    pub fn topic_alias(&self) -> Option<u16> {
+        self.topic_alias
+    }
+    pub fn subscription_identifiers(&self) -> &[u32] {
+        &self.subscription_identifiers
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if self.payload_format_indicator.is_some() {
+            len += 1 + 1;
+        }
+        if self.message_expiry_interval.is_some() {
+            len += 1 + 4;
+        }
+        if let Some(content_type) = &self.content_type {
+            len += 1 + 2 + content_type.len();
+        }
+        if let Some(response_topic) = &self.response_topic {
+            len += 1 + 2 + response_topic.len();
+        }
+        if let Some(correlation_data) = &self.correlation_data {
+            len += 1 + 2 + correlation_data.len();
+        }
+        if self.topic_alias.is_some() {
+            len += 1 + 2;
+        }
+        for id in &self.subscription_identifiers {
+            len += 1 + variable_byte_integer_len(*id as usize);
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<PublishProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = PublishProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            let duplicated = match id {
+                PAYLOAD_FORMAT_INDICATOR => {
+                    let value = read_u8(&mut stream)?;
+                    if value > 1 {
+                        return Err(ProtoError::InvalidProperty(id));
+                    }
+                    properties.payload_format_indicator.replace(value).is_some()
+                }
+                MESSAGE_EXPIRY_INTERVAL => properties
+                    .message_expiry_interval
+                    .replace(read_u32(&mut stream)?)
+                    .is_some(),
+                CONTENT_TYPE => properties
+                    .content_type
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                RESPONSE_TOPIC => properties
+                    .response_topic
+                    .replace(read_mqtt_string(&mut stream)?)
+                    .is_some(),
+                CORRELATION_DATA => properties
+                    .correlation_data
+                    .replace(read_mqtt_bytes(&mut stream)?)
+                    .is_some(),
+                TOPIC_ALIAS => {
+                    let alias = read_u16(&mut stream)?;
+                    if alias == 0 {
+                        return Err(TopicAliasError::InvalidAlias(alias).into());
+                    }
+                    properties.topic_alias.replace(alias).is_some()
+                }
+                SUBSCRIPTION_IDENTIFIER => {
+                    let value = read_variable_byte_integer(&mut stream)?;
+                    if value == 0 {
+                        return Err(ProtoError::InvalidProperty(id));
+                    }
+                    properties.subscription_identifiers.push(value as u32);
+                    false
+                }
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                    false
+                }
+                _ => return Err(unexpected_property(MessageType::PUBLISH, id)),
+            };
+            if duplicated {
+                return Err(ProtoError::InvalidProperty(id));
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为PublishProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for PublishProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(payload_format_indicator) = self.payload_format_indicator {
+            properties.put_u8(PAYLOAD_FORMAT_INDICATOR);
+            properties.put_u8(payload_format_indicator);
+        }
+        if let Some(message_expiry_interval) = self.message_expiry_interval {
+            properties.put_u8(MESSAGE_EXPIRY_INTERVAL);
+            properties.put_u32(message_expiry_interval);
+        }
+        if let Some(content_type) = &self.content_type {
+            properties.put_u8(CONTENT_TYPE);
+            write_mqtt_string(&mut properties, content_type)?;
+        }
+        if let Some(response_topic) = &self.response_topic {
+            properties.put_u8(RESPONSE_TOPIC);
+            write_mqtt_string(&mut properties, response_topic)?;
+        }
+        if let Some(correlation_data) = &self.correlation_data {
+            properties.put_u8(CORRELATION_DATA);
+            write_mqtt_bytes(&mut properties, correlation_data)?;
+        }
+        if let Some(topic_alias) = self.topic_alias {
+            properties.put_u8(TOPIC_ALIAS);
+            properties.put_u16(topic_alias);
+        }
+        for id in &self.subscription_identifiers {
+            properties.put_u8(SUBSCRIPTION_IDENTIFIER);
+            write_variable_byte_integer(&mut properties, *id as usize)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Publish, PublishProperties};
+    use crate::common::packet_id::PacketId;
+    use crate::error::{ProtoError, TopicAliasError};
+    use crate::v4::Encoder;
+    use crate::v5::builder::MqttMessageBuilder;
+    use crate::v5::topic_alias::TopicAliasMap;
+    use crate::{MessageType, QoS};
+    use bytes::{Bytes, BytesMut};
+    use std::time::Duration;
+
+    fn encode(publish: &Publish) -> BytesMut {
+        let mut buffer = BytesMut::new();
+        let len = publish.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        buffer
+    }
+
+    #[test]
+    fn encode_and_decode_without_alias_should_be_work() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/test")
+            .qos(QoS::AtLeastOnce)
+            .packet_id(PacketId::new(10).unwrap())
+            .payload_str("hello")
+            .build()
+            .unwrap();
+        let buffer = encode(&publish);
+        let publish = Publish::decode(buffer.freeze(), &TopicAliasMap::new()).unwrap();
+        assert_eq!(publish.topic(), "/test");
+        assert_eq!(publish.topic_alias(), None);
+        assert_eq!(publish.message_id(), Some(10));
+        assert_eq!(publish.payload().as_ref(), b"hello");
+    }
+
+    #[test]
+    fn topic_alias_register_and_reuse_should_be_work() {
+        // 客户端：第一次发布时同时携带topic和alias
+        let mut client_aliases = TopicAliasMap::new();
+        client_aliases.insert(1, "/sensor/temp").unwrap();
+        let first = MqttMessageBuilder::publish()
+            .topic("/sensor/temp")
+            .topic_alias(1)
+            .payload_str("25.1")
+            .build()
+            .unwrap();
+        // 之后只携带alias
+        let alias = client_aliases.alias("/sensor/temp").unwrap();
+        let second = MqttMessageBuilder::publish()
+            .topic_alias(alias)
+            .payload_str("25.2")
+            .build()
+            .unwrap();
+        let second_bytes = encode(&second);
+        // topic长度为0
+        assert_eq!(&second_bytes[2..4], &[0, 0]);
+
+        // 服务端：收到第一条报文时记录alias
+        let mut server_aliases = TopicAliasMap::new();
+        let first = Publish::decode(encode(&first).freeze(), &server_aliases).unwrap();
+        server_aliases
+            .insert(first.topic_alias().unwrap(), first.topic())
+            .unwrap();
+        let second = Publish::decode(second_bytes.freeze(), &server_aliases).unwrap();
+        assert_eq!(second.topic(), "/sensor/temp");
+        assert_eq!(second.topic_alias(), Some(1));
+        assert_eq!(second.payload().as_ref(), b"25.2");
+    }
+
+    #[test]
+    fn unknown_topic_alias_should_return_error() {
+        let publish = MqttMessageBuilder::publish()
+            .topic_alias(9)
+            .payload_str("25.2")
+            .build()
+            .unwrap();
+        let resp = Publish::decode(encode(&publish).freeze(), &TopicAliasMap::new());
+        assert_eq!(
+            resp.unwrap_err(),
+            ProtoError::TopicAliasError(TopicAliasError::AliasNotFound(9))
+        );
+    }
+
+    #[test]
+    fn truncated_publish_should_return_incomplete() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_str("25.2")
+            .build()
+            .unwrap();
+        let bytes = encode(&publish).freeze();
+        let resp = Publish::decode(bytes.slice(..bytes.len() - 3), &TopicAliasMap::new());
+        assert_eq!(resp.unwrap_err(), ProtoError::Incomplete { needed: 3 });
+    }
+
+    #[test]
+    fn empty_topic_without_alias_should_return_error() {
+        let resp = MqttMessageBuilder::publish().payload_str("25.2").build();
+        assert_eq!(
+            resp.unwrap_err(),
+            ProtoError::TopicAliasError(TopicAliasError::MissingAlias)
+        );
+    }
+
+    fn round_trip(properties: PublishProperties) {
+        let mut buffer = BytesMut::new();
+        let len = properties.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        // 属性之后的数据不应被消费
+        buffer.extend_from_slice(b"payload");
+        let mut bytes = buffer.freeze();
+        assert_eq!(PublishProperties::decode(&mut bytes).unwrap(), properties);
+        assert_eq!(bytes.as_ref(), b"payload");
+    }
+
+    #[test]
+    fn payload_format_indicator_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            payload_format_indicator: Some(0),
+            ..Default::default()
+        });
+        round_trip(PublishProperties {
+            payload_format_indicator: Some(1),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn message_expiry_interval_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            message_expiry_interval: Some(u32::MAX),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn content_type_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            content_type: Some("application/json".to_string()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn response_topic_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            response_topic: Some("/reply/1".to_string()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn correlation_data_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            correlation_data: Some(Bytes::from_static(&[0x00, 0xff, 0x10])),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn subscription_identifier_and_user_property_round_trip_should_be_work() {
+        round_trip(PublishProperties {
+            subscription_identifiers: vec![1, 268_435_455],
+            user_properties: vec![
+                ("k".to_string(), "v1".to_string()),
+                ("k".to_string(), "v2".to_string()),
+            ],
+            ..Default::default()
+        });
+        // 订阅标识符不能为0
+        let mut bytes = Bytes::from_static(&[0x02, 0x0b, 0x00]);
+        assert_eq!(
+            PublishProperties::decode(&mut bytes).unwrap_err(),
+            ProtoError::InvalidProperty(0x0b)
+        );
+        let resp = MqttMessageBuilder::publish()
+            .topic("/a")
+            .subscription_identifier(0)
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::InvalidProperty(0x0b));
+    }
+
+    #[test]
+    fn qos0_publish_should_not_contain_packet_id() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_str("x")
+            .build()
+            .unwrap();
+        // topic之后直接是属性长度
+        assert_eq!(&encode(&publish)[..], b"\x30\x06\x00\x02/a\x00x");
+        let decoded = Publish::decode(encode(&publish).freeze(), &TopicAliasMap::new()).unwrap();
+        assert_eq!(decoded.message_id(), None);
+        assert_eq!(decoded, publish);
+    }
+
+    #[test]
+    fn alias_only_publish_should_keep_empty_topic_until_resolved() {
+        let publish = MqttMessageBuilder::publish()
+            .topic_alias(3)
+            .qos(QoS::AtLeastOnce)
+            .packet_id(PacketId::MIN)
+            .build()
+            .unwrap();
+        let bytes = encode(&publish).freeze();
+        assert_eq!(
+            &bytes[..],
+            &[0x32, 0x08, 0x00, 0x00, 0x00, 0x01, 0x03, 0x23, 0x00, 0x03]
+        );
+        let mut decoded = Publish::decode_unresolved(bytes).unwrap();
+        assert_eq!(decoded.topic(), "");
+        let mut aliases = TopicAliasMap::new();
+        aliases.insert(3, "/sensor").unwrap();
+        decoded.resolve_topic_alias(&aliases).unwrap();
+        assert_eq!(decoded.topic(), "/sensor");
+    }
+
+    #[test]
+    fn long_properties_should_use_two_byte_length() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/a")
+            .user_property("key", &"v".repeat(200))
+            .payload_str("x")
+            .build()
+            .unwrap();
+        let properties_len = publish.properties().len();
+        assert_eq!(properties_len, 1 + 2 + 3 + 2 + 200);
+        let bytes = encode(&publish).freeze();
+        // 属性长度208的Variable Byte Integer编码为0xd0 0x01
+        assert_eq!(&bytes[7..9], &[0xd0, 0x01]);
+        let decoded = Publish::decode(bytes, &TopicAliasMap::new()).unwrap();
+        assert_eq!(decoded, publish);
+        assert_eq!(decoded.properties().user_properties()[0].1.len(), 200);
+        assert_eq!(decoded.payload().as_ref(), b"x");
+    }
+
+    #[test]
+    fn publish_with_all_properties_should_be_work() {
+        let publish = MqttMessageBuilder::publish()
+            .topic("/sensor/temp")
+            .topic_alias(2)
+            .utf8_payload(true)
+            .message_expiry_interval(60)
+            .content_type("text/plain")
+            .response_topic("/reply")
+            .correlation_data(Bytes::from_static(b"id-1"))
+            .payload_str("25.1")
+            .build()
+            .unwrap();
+        let publish = Publish::decode(encode(&publish).freeze(), &TopicAliasMap::new()).unwrap();
+        assert!(publish.is_utf8_payload());
+        assert_eq!(publish.expires_in(), Some(Duration::from_secs(60)));
+        assert_eq!(publish.content_type(), Some("text/plain"));
+        assert_eq!(publish.properties().response_topic(), Some("/reply"));
+        assert_eq!(
+            publish.properties().correlation_data().unwrap().as_ref(),
+            b"id-1"
+        );
+        assert_eq!(publish.topic_alias(), Some(2));
+        assert_eq!(publish.payload().as_ref(), b"25.1");
+    }
+
+    #[test]
+    fn invalid_or_duplicated_property_should_return_error() {
+        // payload format indicator只能为0或1
+        let mut bytes = Bytes::from_static(&[0x02, 0x01, 0x02]);
+        assert_eq!(
+            PublishProperties::decode(&mut bytes).unwrap_err(),
+            ProtoError::InvalidProperty(0x01)
+        );
+        // 同一个属性不能出现两次
+        let mut bytes = Bytes::from_static(&[0x04, 0x01, 0x01, 0x01, 0x00]);
+        assert_eq!(
+            PublishProperties::decode(&mut bytes).unwrap_err(),
+            ProtoError::InvalidProperty(0x01)
+        );
+        // PUBLISH报文不支持的属性
+        let mut bytes = Bytes::from_static(&[0x02, 0x11, 0x00]);
+        assert_eq!(
+            PublishProperties::decode(&mut bytes).unwrap_err(),
+            ProtoError::PropertyNotAllowed {
+                packet: MessageType::PUBLISH,
+                id: 0x11
+            }
+        );
+        // MQTT 5.0中没有定义的属性
+        let mut bytes = Bytes::from_static(&[0x02, 0x30, 0x00]);
+        assert_eq!(
+            PublishProperties::decode(&mut bytes).unwrap_err(),
+            ProtoError::UnknownProperty(0x30)
+        );
+    }
+
+    #[test]
+    fn v4_builder_should_convert_to_v5() {
+        let builder = crate::v4::builder::MqttMessageBuilder::publish()
+            .topic("/a")
+            .payload_utf8("{}")
+            .content_type("application/json");
+        let publish = crate::v5::builder::PublishBuilder::from(builder)
+            .build()
+            .unwrap();
+        assert!(publish.properties().is_utf8_payload());
+        assert_eq!(publish.content_type(), Some("application/json"));
+        let decoded = Publish::decode(encode(&publish).freeze(), &TopicAliasMap::new()).unwrap();
+        assert_eq!(decoded.content_type(), Some("application/json"));
+        assert!(decoded.properties().is_utf8_payload());
+    }
+}
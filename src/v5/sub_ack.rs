@@ -0,0 +1,265 @@
+use super::builder::SubAckBuilder;
+use super::property::{properties_len, read_properties, write_properties};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::read_u16;
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::{MessageType, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// SUBACK报文的原因码，小于0x80表示订阅成功，值为授予的最大QoS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SubAckReasonCode {
+    /// 订阅成功，最大QoS为0
+    GrantedQoS0 = 0x00,
+    /// 订阅成功，最大QoS为1
+    GrantedQoS1 = 0x01,
+    /// 订阅成功，最大QoS为2
+    GrantedQoS2 = 0x02,
+    /// 未指明的错误
+    UnspecifiedError = 0x80,
+    /// 实现的特定错误
+    ImplementationSpecificError = 0x83,
+    /// 没有订阅权限
+    NotAuthorized = 0x87,
+    /// topic filter格式正确，但是不被接受
+    TopicFilterInvalid = 0x8F,
+    /// message_id正在使用中
+    PacketIdentifierInUse = 0x91,
+    /// 超出了配额
+    QuotaExceeded = 0x97,
+    /// 服务端不支持共享订阅
+    SharedSubscriptionsNotSupported = 0x9E,
+    /// 服务端不支持订阅标识符
+    SubscriptionIdentifiersNotSupported = 0xA1,
+    /// 服务端不支持通配符订阅
+    WildcardSubscriptionsNotSupported = 0xA2,
+}
+
+impl SubAckReasonCode {
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+    /// 订阅成功时返回授予的最大QoS
+    pub fn granted_qos(&self) -> Option<QoS> {
+        match self {
+            SubAckReasonCode::GrantedQoS0 => Some(QoS::AtMostOnce),
+            SubAckReasonCode::GrantedQoS1 => Some(QoS::AtLeastOnce),
+            SubAckReasonCode::GrantedQoS2 => Some(QoS::ExactlyOnce),
+            _ => None,
+        }
+    }
+}
+
+impl From<QoS> for SubAckReasonCode {
+    fn from(qos: QoS) -> Self {
+        match qos {
+            QoS::AtMostOnce => SubAckReasonCode::GrantedQoS0,
+            QoS::AtLeastOnce => SubAckReasonCode::GrantedQoS1,
+            QoS::ExactlyOnce => SubAckReasonCode::GrantedQoS2,
+        }
+    }
+}
+
+impl From<SubAckReasonCode> for u8 {
+    fn from(code: SubAckReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for SubAckReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(SubAckReasonCode::GrantedQoS0),
+            0x01 => Ok(SubAckReasonCode::GrantedQoS1),
+            0x02 => Ok(SubAckReasonCode::GrantedQoS2),
+            0x80 => Ok(SubAckReasonCode::UnspecifiedError),
+            0x83 => Ok(SubAckReasonCode::ImplementationSpecificError),
+            0x87 => Ok(SubAckReasonCode::NotAuthorized),
+            0x8F => Ok(SubAckReasonCode::TopicFilterInvalid),
+            0x91 => Ok(SubAckReasonCode::PacketIdentifierInUse),
+            0x97 => Ok(SubAckReasonCode::QuotaExceeded),
+            0x9E => Ok(SubAckReasonCode::SharedSubscriptionsNotSupported),
+            0xA1 => Ok(SubAckReasonCode::SubscriptionIdentifiersNotSupported),
+            0xA2 => Ok(SubAckReasonCode::WildcardSubscriptionsNotSupported),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+/// MQTT 5.0 SUBACK报文，SUBSCRIBE报文的响应，每个topic filter对应一个原因码
+///
+/// 可变报头为message_id + 属性，payload为原因码列表。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubAck {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 可变报头中的属性
+    properties: Bytes,
+    // payload，与请求中的topic filter一一对应
+    reason_codes: Vec<SubAckReasonCode>,
+}
+
+impl SubAck {
+    pub fn new(
+        packet_id: PacketId,
+        properties: Bytes,
+        reason_codes: Vec<SubAckReasonCode>,
+    ) -> Self {
+        Self {
+            packet_id,
+            properties,
+            reason_codes,
+        }
+    }
+    pub fn builder() -> SubAckBuilder {
+        SubAckBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn properties(&self) -> &Bytes {
+        &self.properties
+    }
+    pub fn reason_codes(&self) -> &[SubAckReasonCode] {
+        &self.reason_codes
+    }
+
+    // 剩余长度 = message_id + 属性 + 原因码列表
+    fn remaining_length(&self) -> usize {
+        2 + properties_len(self.properties.len()) + self.reason_codes.len()
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为SubAck实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for SubAck {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::SUBACK, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let properties_len = write_properties(buffer, &self.properties)?;
+        for code in &self.reason_codes {
+            buffer.put_u8((*code).into());
+        }
+        Ok(fixed_header_len + 2 + properties_len + self.reason_codes.len())
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为SubAck实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for SubAck {
+    type Item = SubAck;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::SUBACK)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let properties = read_properties(&mut body)?;
+        let reason_codes = body
+            .chunk()
+            .iter()
+            .map(|code| SubAckReasonCode::try_from(*code))
+            .collect::<Result<_, _>>()?;
+        Ok(SubAck {
+            packet_id,
+            properties,
+            reason_codes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubAck, SubAckReasonCode};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use crate::QoS;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn encode_and_decode_sub_ack_should_be_work() {
+        // 第一个topic filter授权QoS1，第二个被拒绝(0x87 Not authorized)
+        let sub_ack = SubAck::new(
+            PacketId::new(10).unwrap(),
+            Bytes::new(),
+            vec![
+                SubAckReasonCode::GrantedQoS1,
+                SubAckReasonCode::NotAuthorized,
+            ],
+        );
+        let mut buffer = BytesMut::new();
+        assert_eq!(sub_ack.encode(&mut buffer).unwrap(), 7);
+        assert_eq!(buffer.as_ref(), &[0x90, 0x05, 0x00, 0x0a, 0x00, 0x01, 0x87]);
+        assert_eq!(SubAck::decode(buffer.freeze()).unwrap(), sub_ack);
+    }
+
+    #[test]
+    fn sub_ack_builder_should_mix_granted_and_error_codes() {
+        let sub_ack = MqttMessageBuilder::sub_ack()
+            .packet_id(PacketId::MIN)
+            .reason_code(QoS::ExactlyOnce.into())
+            .reason_code(SubAckReasonCode::GrantedQoS0)
+            .reason_code(SubAckReasonCode::SharedSubscriptionsNotSupported)
+            .reason_code(SubAckReasonCode::WildcardSubscriptionsNotSupported)
+            .reason_string("no")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        sub_ack.encode(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x90, 0x0c, 0x00, 0x01, 0x05,
+            0x1f, 0x00, 0x02, b'n', b'o',
+            0x02, 0x00, 0x9e, 0xa2,
+        ];
+        assert_eq!(buffer.as_ref(), expected);
+        let decoded = SubAck::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, sub_ack);
+        let granted: Vec<_> = decoded
+            .reason_codes()
+            .iter()
+            .map(|code| code.granted_qos())
+            .collect();
+        assert_eq!(
+            granted,
+            [Some(QoS::ExactlyOnce), Some(QoS::AtMostOnce), None, None]
+        );
+        assert_eq!(
+            MqttMessageBuilder::sub_ack().build().unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+        assert_eq!(
+            MqttMessageBuilder::sub_ack()
+                .packet_id(PacketId::MIN)
+                .build()
+                .unwrap_err(),
+            ProtoError::EmptyReasonCodes
+        );
+    }
+
+    #[test]
+    fn unknown_reason_code_should_return_error() {
+        // 0x11只能用于UNSUBACK
+        let frame = Bytes::from_static(&[0x90, 0x05, 0x00, 0x01, 0x00, 0x00, 0x11]);
+        assert_eq!(
+            SubAck::decode(frame).unwrap_err(),
+            ProtoError::UnknownReasonCode(0x11)
+        );
+        for code in [
+            0x00, 0x01, 0x02, 0x80, 0x83, 0x87, 0x8f, 0x91, 0x97, 0x9e, 0xa1, 0xa2,
+        ] {
+            let reason_code = SubAckReasonCode::try_from(code).unwrap();
+            assert_eq!(u8::from(reason_code), code);
+            assert_eq!(reason_code.is_success(), code < 0x80);
+        }
+    }
+}
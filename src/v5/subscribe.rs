@@ -0,0 +1,514 @@
+use super::property::{
+    properties_len, read_properties, read_variable_byte_integer, unexpected_property,
+    variable_byte_integer_len, write_properties, write_variable_byte_integer,
+    SUBSCRIPTION_IDENTIFIER, USER_PROPERTY,
+};
+use super::split_body;
+use crate::common::coder::{read_mqtt_string, read_u16, read_u8, write_mqtt_string};
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{fixed_header::FixedHeader, Decoder, Encoder};
+use crate::{MessageType, QoS};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+pub use crate::common::topic::SHARED_SUBSCRIPTION_PREFIX;
+
+/// 订阅时服务端发送保留消息的方式
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RetainHandling {
+    /// 订阅时发送保留消息
+    #[default]
+    SendOnSubscribe = 0,
+    /// 订阅时，只有订阅之前不存在时才发送保留消息
+    SendOnSubscribeIfNew = 1,
+    /// 订阅时不发送保留消息
+    DoNotSend = 2,
+}
+
+impl TryFrom<u8> for RetainHandling {
+    type Error = ProtoError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(RetainHandling::SendOnSubscribe),
+            1 => Ok(RetainHandling::SendOnSubscribeIfNew),
+            2 => Ok(RetainHandling::DoNotSend),
+            n => Err(ProtoError::InvalidSubscriptionOptions {
+                topic_index: 0,
+                byte: n << 4,
+            }),
+        }
+    }
+}
+
+/**
+ 订阅选项，在报文中占用一个字节：
+
+| Bit   | 7   | 6   | 5   | 4   | 3   | 2   | 1   | 0   |
+| ----- | --- | --- | --- | --- | --- | --- | --- | --- |
+|       | 保  | 留  | Retain | Handling | RAP | NL | QoS | QoS |
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionOptions {
+    pub qos: QoS,
+    /// 为true时，服务端不会将该客户端自己发布的消息转发给它
+    pub no_local: bool,
+    /// 为true时，转发消息时保持发布时的retain标志
+    pub retain_as_published: bool,
+    pub retain_handling: RetainHandling,
+}
+
+impl SubscriptionOptions {
+    pub fn new(qos: QoS) -> Self {
+        Self {
+            qos,
+            ..Default::default()
+        }
+    }
+    pub fn no_local(mut self, no_local: bool) -> Self {
+        self.no_local = no_local;
+        self
+    }
+    pub fn retain_as_published(mut self, retain_as_published: bool) -> Self {
+        self.retain_as_published = retain_as_published;
+        self
+    }
+    pub fn retain_handling(mut self, retain_handling: RetainHandling) -> Self {
+        self.retain_handling = retain_handling;
+        self
+    }
+}
+
+impl From<SubscriptionOptions> for u8 {
+    fn from(value: SubscriptionOptions) -> Self {
+        let mut byte = value.qos as u8;
+        if value.no_local {
+            byte |= 0b0000_0100;
+        }
+        if value.retain_as_published {
+            byte |= 0b0000_1000;
+        }
+        byte | (value.retain_handling as u8) << 4
+    }
+}
+
+impl TryFrom<u8> for SubscriptionOptions {
+    type Error = ProtoError;
+
+    /// 单独转换时无法得知所在topic filter的位置，topic_index固定为0，
+    /// 由[`Subscribe`]的解码过程填入实际的位置
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let invalid = ProtoError::InvalidSubscriptionOptions {
+            topic_index: 0,
+            byte: value,
+        };
+        // 保留位必须为0，QoS不能为3
+        if value & 0b1100_0000 != 0 {
+            return Err(invalid);
+        }
+        Ok(Self {
+            qos: QoS::try_from(value & 0b0000_0011).map_err(|_| invalid)?,
+            no_local: value & 0b0000_0100 != 0,
+            retain_as_published: value & 0b0000_1000 != 0,
+            retain_handling: RetainHandling::try_from((value >> 4) & 0b0000_0011)
+                .map_err(|_| invalid)?,
+        })
+    }
+}
+
+/// topic filter及其订阅选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicFilter {
+    filter: String,
+    options: SubscriptionOptions,
+}
+
+impl TopicFilter {
+    pub fn new(filter: &str, options: SubscriptionOptions) -> Self {
+        Self {
+            filter: filter.to_string(),
+            options,
+        }
+    }
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+    pub fn options(&self) -> SubscriptionOptions {
+        self.options
+    }
+    /// 是否为共享订阅，即以`$share/`开头
+    pub fn is_shared(&self) -> bool {
+        self.filter.starts_with(SHARED_SUBSCRIPTION_PREFIX)
+    }
+
+    /// 共享订阅不能设置no_local
+    pub(crate) fn validate(&self) -> Result<(), ProtoError> {
+        if self.filter.is_empty() {
+            return Err(ProtoError::EmptyTopicFilter);
+        }
+        if self.is_shared() && self.options.no_local {
+            return Err(ProtoError::NoLocalOnSharedSubscription);
+        }
+        Ok(())
+    }
+
+    // 编码之后的长度：2字节长度 + filter + 1字节订阅选项
+    fn len(&self) -> usize {
+        2 + self.filter.len() + 1
+    }
+}
+
+/// SUBSCRIBE报文可以携带的属性
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscribeProperties {
+    // 0x0B 订阅标识符，取值范围为1到268435455
+    pub(crate) subscription_identifier: Option<u32>,
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+}
+
+impl SubscribeProperties {
+    pub fn subscription_identifier(&self) -> Option<u32> {
+        self.subscription_identifier
+    }
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        let mut len = 0;
+        if let Some(id) = self.subscription_identifier {
+            len += 1 + variable_byte_integer_len(id as usize);
+        }
+        for (key, value) in &self.user_properties {
+            len += 1 + 2 + key.len() + 2 + value.len();
+        }
+        len
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<SubscribeProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = SubscribeProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            match id {
+                SUBSCRIPTION_IDENTIFIER => {
+                    let value = read_variable_byte_integer(&mut stream)?;
+                    if value == 0 || properties.subscription_identifier.is_some() {
+                        return Err(ProtoError::InvalidProperty(id));
+                    }
+                    properties.subscription_identifier = Some(value as u32);
+                }
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                }
+                _ => return Err(unexpected_property(MessageType::SUBSCRIBE, id)),
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为SubscribeProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for SubscribeProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        if let Some(id) = self.subscription_identifier {
+            properties.put_u8(SUBSCRIPTION_IDENTIFIER);
+            write_variable_byte_integer(&mut properties, id as usize)?;
+        }
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/// MQTT 5.0 SUBSCRIBE报文
+///
+/// 与v3.1.1相比，可变报头中在message_id之后增加了属性部分，
+/// payload中每个topic filter之后的一个字节由QoS扩展为订阅选项。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscribe {
+    // 固定报头
+    fixed_header: FixedHeader,
+    // 可变报头中的message_id
+    message_id: PacketId,
+    // 可变报头中的属性
+    properties: SubscribeProperties,
+    // payload，至少包含一个topic filter
+    filters: Vec<TopicFilter>,
+}
+
+impl Subscribe {
+    pub fn new(
+        mut fixed_header: FixedHeader,
+        message_id: PacketId,
+        properties: SubscribeProperties,
+        filters: Vec<TopicFilter>,
+    ) -> Self {
+        let remaining_length = Self::remaining_length_of(&properties, &filters);
+        fixed_header.set_remaining_length(remaining_length);
+        Self {
+            fixed_header,
+            message_id,
+            properties,
+            filters,
+        }
+    }
+    pub fn fixed_header(&self) -> &FixedHeader {
+        &self.fixed_header
+    }
+    pub fn message_id(&self) -> usize {
+        self.message_id.into()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.message_id
+    }
+    pub fn properties(&self) -> &SubscribeProperties {
+        &self.properties
+    }
+    pub fn filters(&self) -> &[TopicFilter] {
+        &self.filters
+    }
+
+    // 剩余长度 = message_id + 属性 + payload
+    fn remaining_length_of(properties: &SubscribeProperties, filters: &[TopicFilter]) -> usize {
+        2 + properties_len(properties.len()) + filters.iter().map(TopicFilter::len).sum::<usize>()
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Subscribe实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for Subscribe {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len = self.fixed_header.encode(buffer)?;
+        buffer.put_u16(self.message_id.get());
+        let properties_len = self.properties.encode(buffer)?;
+        let mut filters_len = 0;
+        for filter in &self.filters {
+            filters_len += write_mqtt_string(buffer, &filter.filter)?;
+            buffer.put_u8(filter.options.into());
+            filters_len += 1;
+        }
+        Ok(fixed_header_len + 2 + properties_len + filters_len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为Subscribe实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for Subscribe {
+    type Item = Subscribe;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        // 读取fixed_header
+        let (fixed_header, mut bytes) = split_body(bytes, MessageType::SUBSCRIBE)?;
+        // 读取variable_header
+        let message_id = PacketId::try_from(read_u16(&mut bytes)?)?;
+        let properties = SubscribeProperties::decode(&mut bytes)?;
+        // 读取payload
+        let mut filters = Vec::new();
+        while bytes.has_remaining() {
+            let filter = read_mqtt_string(&mut bytes)?;
+            let byte = read_u8(&mut bytes)?;
+            let options = SubscriptionOptions::try_from(byte).map_err(|_| {
+                ProtoError::InvalidSubscriptionOptions {
+                    topic_index: filters.len(),
+                    byte,
+                }
+            })?;
+            let filter = TopicFilter { filter, options };
+            filter.validate()?;
+            filters.push(filter);
+        }
+        if filters.is_empty() {
+            return Err(ProtoError::EmptySubscription);
+        }
+        Ok(Subscribe {
+            fixed_header,
+            message_id,
+            properties,
+            filters,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RetainHandling, Subscribe, SubscriptionOptions, TopicFilter};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use crate::QoS;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn subscription_options_to_and_from_u8_should_be_work() {
+        let options = SubscriptionOptions::new(QoS::ExactlyOnce)
+            .no_local(true)
+            .retain_as_published(true)
+            .retain_handling(RetainHandling::DoNotSend);
+        assert_eq!(u8::from(options), 0b0010_1110);
+        assert_eq!(SubscriptionOptions::try_from(0b0010_1110).unwrap(), options);
+        assert_eq!(
+            SubscriptionOptions::try_from(0b0100_0000).unwrap_err(),
+            ProtoError::InvalidSubscriptionOptions {
+                topic_index: 0,
+                byte: 0b0100_0000
+            }
+        );
+        assert_eq!(
+            SubscriptionOptions::try_from(0b0011_0000).unwrap_err(),
+            ProtoError::InvalidSubscriptionOptions {
+                topic_index: 0,
+                byte: 0b0011_0000
+            }
+        );
+        assert_eq!(
+            SubscriptionOptions::try_from(0b0000_0011).unwrap_err(),
+            ProtoError::InvalidSubscriptionOptions {
+                topic_index: 0,
+                byte: 0b0000_0011
+            }
+        );
+    }
+
+    #[test]
+    fn encode_and_decode_subscribe_should_be_work() {
+        let filters = vec![
+            TopicFilter::new("/a", SubscriptionOptions::new(QoS::AtMostOnce)),
+            TopicFilter::new(
+                "/b/+",
+                SubscriptionOptions::new(QoS::AtLeastOnce)
+                    .no_local(true)
+                    .retain_handling(RetainHandling::SendOnSubscribeIfNew),
+            ),
+            TopicFilter::new(
+                "$share/group/c",
+                SubscriptionOptions::new(QoS::ExactlyOnce).retain_as_published(true),
+            ),
+        ];
+        let subscribe = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(42).unwrap())
+            .topic_filters(filters.clone())
+            .subscription_identifier(300)
+            .user_property("k", "v")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        let len = subscribe.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        let subscribe = Subscribe::decode(buffer.freeze()).unwrap();
+        assert_eq!(subscribe.message_id(), 42);
+        assert_eq!(subscribe.filters(), filters.as_slice());
+        assert!(subscribe.filters()[2].is_shared());
+        assert_eq!(subscribe.properties().subscription_identifier(), Some(300));
+        assert_eq!(
+            subscribe.properties().user_properties(),
+            &[("k".to_string(), "v".to_string())]
+        );
+    }
+
+    #[test]
+    fn max_subscription_identifier_should_round_trip() {
+        let builder = || {
+            MqttMessageBuilder::subscribe()
+                .packet_id(PacketId::MIN)
+                .topic_filter(TopicFilter::new(
+                    "/a",
+                    SubscriptionOptions::new(QoS::AtLeastOnce),
+                ))
+        };
+        let subscribe = builder()
+            .subscription_identifier(268_435_455)
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        subscribe.encode(&mut buffer).unwrap();
+        // 属性长度为5：标识符 + 4个字节的Variable Byte Integer
+        assert_eq!(&buffer[4..10], &[0x05, 0x0b, 0xff, 0xff, 0xff, 0x7f]);
+        let decoded = Subscribe::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, subscribe);
+        assert_eq!(
+            decoded.properties().subscription_identifier(),
+            Some(268_435_455)
+        );
+        // 超过Variable Byte Integer的最大值
+        assert_eq!(
+            builder()
+                .subscription_identifier(268_435_456)
+                .build()
+                .unwrap_err(),
+            ProtoError::InvalidProperty(0x0b)
+        );
+    }
+
+    #[test]
+    fn no_local_on_shared_subscription_should_return_error() {
+        let filter = TopicFilter::new(
+            "$share/group/c",
+            SubscriptionOptions::new(QoS::AtMostOnce).no_local(true),
+        );
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .topic_filter(filter)
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::NoLocalOnSharedSubscription);
+        // message_id=1，没有属性，topic filter为"$share/g/c"，订阅选项设置了no_local
+        let mut frame = vec![0x82, 0x10, 0x00, 0x01, 0x00, 0x00, 0x0a];
+        frame.extend_from_slice(b"$share/g/c");
+        frame.push(0b0000_0100);
+        assert_eq!(
+            Subscribe::decode(Bytes::from(frame)).unwrap_err(),
+            ProtoError::NoLocalOnSharedSubscription
+        );
+    }
+
+    #[test]
+    fn empty_subscribe_should_return_error() {
+        let resp = MqttMessageBuilder::subscribe()
+            .packet_id(PacketId::new(1).unwrap())
+            .build();
+        assert_eq!(resp.unwrap_err(), ProtoError::EmptySubscription);
+        let frame = Bytes::from_static(&[0x82, 0x03, 0x00, 0x01, 0x00]);
+        assert_eq!(
+            Subscribe::decode(frame).unwrap_err(),
+            ProtoError::EmptySubscription
+        );
+        let frame = Bytes::from_static(&[0x82, 0x06, 0x00, 0x01, 0x00]);
+        assert_eq!(
+            Subscribe::decode(frame).unwrap_err(),
+            ProtoError::Incomplete { needed: 3 }
+        );
+    }
+
+    #[test]
+    fn invalid_subscription_options_should_report_topic_index() {
+        // message_id=1，没有属性，第二个topic filter的订阅选项中retain_handling为3
+        let frame = Bytes::from_static(&[
+            0x82, 0x0d, 0x00, 0x01, 0x00, 0x00, 0x02, b'/', b'a', 0x01, 0x00, 0x02, b'/', b'b',
+            0x30,
+        ]);
+        assert_eq!(
+            Subscribe::decode(frame).unwrap_err(),
+            ProtoError::InvalidSubscriptionOptions {
+                topic_index: 1,
+                byte: 0x30
+            }
+        );
+    }
+}
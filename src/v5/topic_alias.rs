@@ -0,0 +1,114 @@
+use crate::error::{ProtoError, TopicAliasError};
+use std::collections::HashMap;
+
+/// topic alias映射表
+///
+/// MQTT 5.0中，发送方在第一次发布某个topic时同时携带topic和topic alias，之后只需要携带topic alias，
+/// topic置为空字符串即可。
+/// - 服务端(接收方)使用`topic()`通过alias查找topic
+/// - 客户端(发送方)使用`alias()`通过topic查找已经建立的alias
+#[derive(Debug, Clone, Default)]
+pub struct TopicAliasMap {
+    // alias -> topic
+    topics: HashMap<u16, String>,
+    // topic -> alias
+    aliases: HashMap<String, u16>,
+}
+
+impl TopicAliasMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 建立alias和topic的映射，alias已经存在时覆盖原来的topic，
+    /// topic已经有alias时删除原来的alias，保证两个方向的映射一一对应
+    pub fn insert(&mut self, alias: u16, topic: &str) -> Result<(), ProtoError> {
+        if alias == 0 {
+            return Err(TopicAliasError::InvalidAlias(alias).into());
+        }
+        if let Some(old_alias) = self.aliases.insert(topic.to_string(), alias) {
+            if old_alias != alias {
+                self.topics.remove(&old_alias);
+            }
+        }
+        if let Some(old_topic) = self.topics.insert(alias, topic.to_string()) {
+            // 原来的topic可能已经映射到了其他alias，此时不能删除
+            if old_topic != topic && self.aliases.get(&old_topic) == Some(&alias) {
+                self.aliases.remove(&old_topic);
+            }
+        }
+        Ok(())
+    }
+
+    /// 根据alias查找topic
+    pub fn topic(&self, alias: u16) -> Option<&str> {
+        self.topics.get(&alias).map(|topic| topic.as_str())
+    }
+
+    /// 根据topic查找alias
+    pub fn alias(&self, topic: &str) -> Option<u16> {
+        self.aliases.get(topic).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.topics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.topics.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TopicAliasMap;
+    use crate::error::{ProtoError, TopicAliasError};
+
+    #[test]
+    fn insert_and_lookup_should_be_work() {
+        let mut map = TopicAliasMap::new();
+        map.insert(1, "/a/b").unwrap();
+        assert_eq!(map.topic(1), Some("/a/b"));
+        assert_eq!(map.alias("/a/b"), Some(1));
+        assert_eq!(map.topic(2), None);
+
+        // 同一个alias重新映射到新的topic
+        map.insert(1, "/c").unwrap();
+        assert_eq!(map.topic(1), Some("/c"));
+        assert_eq!(map.alias("/a/b"), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn reassigned_topic_should_not_leave_stale_mapping() {
+        let mut map = TopicAliasMap::new();
+        map.insert(1, "/a").unwrap();
+        // "/a"改用alias 2，alias 1不再指向"/a"
+        map.insert(2, "/a").unwrap();
+        assert_eq!(map.topic(1), None);
+        assert_eq!(map.alias("/a"), Some(2));
+        // alias 1重新使用时不能删除"/a" -> 2
+        map.insert(1, "/b").unwrap();
+        assert_eq!(map.alias("/a"), Some(2));
+        assert_eq!(map.topic(2), Some("/a"));
+        assert_eq!(map.alias("/b"), Some(1));
+        assert_eq!(map.topic(1), Some("/b"));
+        assert_eq!(map.len(), 2);
+        // 相同的映射重复插入
+        map.insert(1, "/b").unwrap();
+        assert_eq!(map.alias("/b"), Some(1));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn zero_alias_should_be_rejected() {
+        let mut map = TopicAliasMap::new();
+        assert_eq!(
+            map.insert(0, "/a"),
+            Err(ProtoError::TopicAliasError(TopicAliasError::InvalidAlias(
+                0
+            )))
+        );
+        assert!(map.is_empty());
+    }
+}
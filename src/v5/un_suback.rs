@@ -0,0 +1,201 @@
+use super::builder::UnSubAckBuilder;
+use super::property::{properties_len, read_properties, write_properties};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::read_u16;
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// UNSUBACK报文的原因码，小于0x80表示取消订阅成功
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum UnSubAckReasonCode {
+    /// 订阅已被删除
+    #[default]
+    Success = 0x00,
+    /// 不存在对应的订阅
+    NoSubscriptionExisted = 0x11,
+    /// 未指明的错误
+    UnspecifiedError = 0x80,
+    /// 实现的特定错误
+    ImplementationSpecificError = 0x83,
+    /// 没有取消订阅的权限
+    NotAuthorized = 0x87,
+    /// topic filter格式正确，但是不被接受
+    TopicFilterInvalid = 0x8F,
+    /// message_id正在使用中
+    PacketIdentifierInUse = 0x91,
+}
+
+impl UnSubAckReasonCode {
+    pub fn is_success(&self) -> bool {
+        (*self as u8) < 0x80
+    }
+}
+
+impl From<UnSubAckReasonCode> for u8 {
+    fn from(code: UnSubAckReasonCode) -> Self {
+        code as u8
+    }
+}
+
+impl TryFrom<u8> for UnSubAckReasonCode {
+    type Error = ProtoError;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        match code {
+            0x00 => Ok(UnSubAckReasonCode::Success),
+            0x11 => Ok(UnSubAckReasonCode::NoSubscriptionExisted),
+            0x80 => Ok(UnSubAckReasonCode::UnspecifiedError),
+            0x83 => Ok(UnSubAckReasonCode::ImplementationSpecificError),
+            0x87 => Ok(UnSubAckReasonCode::NotAuthorized),
+            0x8F => Ok(UnSubAckReasonCode::TopicFilterInvalid),
+            0x91 => Ok(UnSubAckReasonCode::PacketIdentifierInUse),
+            code => Err(ProtoError::UnknownReasonCode(code)),
+        }
+    }
+}
+
+/// MQTT 5.0 UNSUBACK报文，UNSUBSCRIBE报文的响应，每个topic filter对应一个原因码
+///
+/// 可变报头为message_id + 属性，payload为原因码列表。
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnSubAck {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 可变报头中的属性
+    properties: Bytes,
+    // payload，与请求中的topic filter一一对应
+    reason_codes: Vec<UnSubAckReasonCode>,
+}
+
+impl UnSubAck {
+    pub fn new(
+        packet_id: PacketId,
+        properties: Bytes,
+        reason_codes: Vec<UnSubAckReasonCode>,
+    ) -> Self {
+        Self {
+            packet_id,
+            properties,
+            reason_codes,
+        }
+    }
+    pub fn builder() -> UnSubAckBuilder {
+        UnSubAckBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn properties(&self) -> &Bytes {
+        &self.properties
+    }
+    pub fn reason_codes(&self) -> &[UnSubAckReasonCode] {
+        &self.reason_codes
+    }
+
+    // 剩余长度 = message_id + 属性 + 原因码列表
+    fn remaining_length(&self) -> usize {
+        2 + properties_len(self.properties.len()) + self.reason_codes.len()
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为UnSubAck实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for UnSubAck {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::UNSUBACK, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let properties_len = write_properties(buffer, &self.properties)?;
+        for code in &self.reason_codes {
+            buffer.put_u8((*code).into());
+        }
+        Ok(fixed_header_len + 2 + properties_len + self.reason_codes.len())
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为UnSubAck实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for UnSubAck {
+    type Item = UnSubAck;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::UNSUBACK)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let properties = read_properties(&mut body)?;
+        let reason_codes = body
+            .chunk()
+            .iter()
+            .map(|code| UnSubAckReasonCode::try_from(*code))
+            .collect::<Result<_, _>>()?;
+        Ok(UnSubAck {
+            packet_id,
+            properties,
+            reason_codes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UnSubAck, UnSubAckReasonCode};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn un_sub_ack_builder_should_mix_success_and_error_codes() {
+        let un_sub_ack = MqttMessageBuilder::un_sub_ack()
+            .packet_id(PacketId::new(7).unwrap())
+            .reason_codes(vec![
+                UnSubAckReasonCode::Success,
+                UnSubAckReasonCode::NoSubscriptionExisted,
+                UnSubAckReasonCode::TopicFilterInvalid,
+            ])
+            .user_property("k", "v")
+            .build()
+            .unwrap();
+        let mut buffer = BytesMut::new();
+        un_sub_ack.encode(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0xb0, 0x0d, 0x00, 0x07, 0x07,
+            0x26, 0x00, 0x01, b'k', 0x00, 0x01, b'v',
+            0x00, 0x11, 0x8f,
+        ];
+        assert_eq!(buffer.as_ref(), expected);
+        let decoded = UnSubAck::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, un_sub_ack);
+        let success: Vec<_> = decoded
+            .reason_codes()
+            .iter()
+            .map(|code| code.is_success())
+            .collect();
+        assert_eq!(success, [true, true, false]);
+    }
+
+    #[test]
+    fn unknown_reason_code_should_return_error() {
+        // 0x01和0xa2只能用于SUBACK
+        for code in [0x01, 0xa2] {
+            let frame = Bytes::copy_from_slice(&[0xb0, 0x04, 0x00, 0x01, 0x00, code]);
+            assert_eq!(
+                UnSubAck::decode(frame).unwrap_err(),
+                ProtoError::UnknownReasonCode(code)
+            );
+        }
+        for code in [0x00, 0x11, 0x80, 0x83, 0x87, 0x8f, 0x91] {
+            let reason_code = UnSubAckReasonCode::try_from(code).unwrap();
+            assert_eq!(u8::from(reason_code), code);
+            assert_eq!(reason_code.is_success(), code < 0x80);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use super::builder::UnSubscribeBuilder;
+use super::property::{
+    properties_len, read_properties, unexpected_property, write_properties, USER_PROPERTY,
+};
+use super::{split_body, write_fixed_header};
+use crate::common::coder::{read_mqtt_string, read_u16, read_u8, write_mqtt_string};
+use crate::common::packet_id::PacketId;
+use crate::error::ProtoError;
+use crate::v4::{Decoder, Encoder};
+use crate::MessageType;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// UNSUBSCRIBE报文可以携带的属性，只有用户属性
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnsubscribeProperties {
+    // 0x26 用户属性，可以出现多次
+    pub(crate) user_properties: Vec<(String, String)>,
+}
+
+impl UnsubscribeProperties {
+    pub fn user_properties(&self) -> &[(String, String)] {
+        &self.user_properties
+    }
+
+    /// 属性内容的长度，不包括属性长度本身
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.user_properties
+            .iter()
+            .map(|(key, value)| 1 + 2 + key.len() + 2 + value.len())
+            .sum()
+    }
+
+    /// 读取属性长度，并只从bytes中消费对应长度的属性内容
+    pub fn decode(bytes: &mut Bytes) -> Result<UnsubscribeProperties, ProtoError> {
+        let mut stream = read_properties(bytes)?;
+        let mut properties = UnsubscribeProperties::default();
+        while stream.has_remaining() {
+            let id = read_u8(&mut stream)?;
+            match id {
+                USER_PROPERTY => {
+                    let key = read_mqtt_string(&mut stream)?;
+                    let value = read_mqtt_string(&mut stream)?;
+                    properties.user_properties.push((key, value));
+                }
+                _ => return Err(unexpected_property(MessageType::UNSUBSCRIBE, id)),
+            }
+        }
+        Ok(properties)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为UnsubscribeProperties实现Encoder trait，写入属性长度 + 属性内容
+/////////////////////////////////////////////////////////
+impl Encoder for UnsubscribeProperties {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let mut properties = BytesMut::with_capacity(self.len());
+        for (key, value) in &self.user_properties {
+            properties.put_u8(USER_PROPERTY);
+            write_mqtt_string(&mut properties, key)?;
+            write_mqtt_string(&mut properties, value)?;
+        }
+        write_properties(buffer, &properties)
+    }
+}
+
+/// MQTT 5.0 UNSUBSCRIBE报文
+///
+/// 与v3.1.1相比，可变报头中在message_id之后增加了属性部分，payload不变。
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnSubscribe {
+    // 可变报头中的message_id
+    packet_id: PacketId,
+    // 可变报头中的属性
+    properties: UnsubscribeProperties,
+    // payload，至少包含一个topic filter
+    filters: Vec<String>,
+}
+
+impl UnSubscribe {
+    /// filters为空或者包含空的topic filter时返回错误
+    pub fn new(
+        packet_id: PacketId,
+        properties: UnsubscribeProperties,
+        filters: Vec<String>,
+    ) -> Result<Self, ProtoError> {
+        if filters.is_empty() {
+            return Err(ProtoError::EmptyUnsubscription);
+        }
+        if filters.iter().any(String::is_empty) {
+            return Err(ProtoError::EmptyTopicFilter);
+        }
+        Ok(Self {
+            packet_id,
+            properties,
+            filters,
+        })
+    }
+    pub fn builder() -> UnSubscribeBuilder {
+        UnSubscribeBuilder::new()
+    }
+    pub fn packet_id(&self) -> PacketId {
+        self.packet_id
+    }
+    pub fn properties(&self) -> &UnsubscribeProperties {
+        &self.properties
+    }
+    pub fn filters(&self) -> &[String] {
+        &self.filters
+    }
+
+    // 剩余长度 = message_id + 属性 + 每个topic filter(2字节长度 + filter)
+    fn remaining_length(&self) -> usize {
+        2 + properties_len(self.properties.len())
+            + self.filters.iter().map(|f| 2 + f.len()).sum::<usize>()
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为UnSubscribe实现Encoder trait
+/////////////////////////////////////////////////////////
+impl Encoder for UnSubscribe {
+    fn encode(&self, buffer: &mut BytesMut) -> Result<usize, ProtoError> {
+        let fixed_header_len =
+            write_fixed_header(buffer, MessageType::UNSUBSCRIBE, self.remaining_length())?;
+        buffer.put_u16(self.packet_id.get());
+        let mut len = fixed_header_len + 2 + self.properties.encode(buffer)?;
+        for filter in &self.filters {
+            len += write_mqtt_string(buffer, filter)?;
+        }
+        Ok(len)
+    }
+}
+
+//////////////////////////////////////////////////////////
+/// 为UnSubscribe实现Decoder trait
+/////////////////////////////////////////////////////////
+impl Decoder for UnSubscribe {
+    type Item = UnSubscribe;
+    type Error = ProtoError;
+
+    fn decode(bytes: Bytes) -> Result<Self::Item, Self::Error> {
+        let (_, mut body) = split_body(bytes, MessageType::UNSUBSCRIBE)?;
+        let packet_id = PacketId::try_from(read_u16(&mut body)?)?;
+        let properties = UnsubscribeProperties::decode(&mut body)?;
+        let mut filters = Vec::new();
+        while body.has_remaining() {
+            filters.push(read_mqtt_string(&mut body)?);
+        }
+        UnSubscribe::new(packet_id, properties, filters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UnSubscribe, UnsubscribeProperties};
+    use crate::common::packet_id::PacketId;
+    use crate::error::ProtoError;
+    use crate::v4::{Decoder, Encoder};
+    use crate::v5::builder::MqttMessageBuilder;
+    use bytes::{Bytes, BytesMut};
+
+    #[test]
+    fn encode_and_decode_un_subscribe_should_be_work() {
+        let filters = vec!["/a".to_string(), "/b/#".to_string()];
+        let un_subscribe =
+            UnSubscribe::new(PacketId::MIN, UnsubscribeProperties::default(), filters).unwrap();
+        let mut buffer = BytesMut::new();
+        let len = un_subscribe.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        assert_eq!(&buffer[..5], &[0xa2, 0x0d, 0x00, 0x01, 0x00]);
+        assert_eq!(UnSubscribe::decode(buffer.freeze()).unwrap(), un_subscribe);
+    }
+
+    #[test]
+    fn un_subscribe_builder_should_round_trip_utf8_filters() {
+        let un_subscribe = MqttMessageBuilder::un_subscribe()
+            .packet_id(PacketId::new(5).unwrap())
+            .topic("传感器/温度/+")
+            .topic("设备/#")
+            .user_property("区域", "华东")
+            .build()
+            .unwrap();
+        assert_eq!(un_subscribe.filters(), ["传感器/温度/+", "设备/#"]);
+        let mut buffer = BytesMut::new();
+        let len = un_subscribe.encode(&mut buffer).unwrap();
+        assert_eq!(len, buffer.len());
+        // 属性长度：1 + (2 + 6) + (2 + 6) = 17
+        assert_eq!(&buffer[2..6], &[0x00, 0x05, 0x11, 0x26]);
+        let decoded = UnSubscribe::decode(buffer.freeze()).unwrap();
+        assert_eq!(decoded, un_subscribe);
+        assert_eq!(
+            decoded.properties().user_properties(),
+            [("区域".to_string(), "华东".to_string())]
+        );
+    }
+
+    #[test]
+    fn invalid_un_subscribe_builder_should_return_error() {
+        let builder = || MqttMessageBuilder::un_subscribe().packet_id(PacketId::MIN);
+        assert_eq!(
+            builder().build().unwrap_err(),
+            ProtoError::EmptyUnsubscription
+        );
+        assert_eq!(
+            builder().topic("/a").topic("/b/#/c").build().unwrap_err(),
+            ProtoError::InvalidTopicFilter {
+                topic_index: 1,
+                reason: "#必须单独作为最后一层",
+            }
+        );
+        assert_eq!(
+            MqttMessageBuilder::un_subscribe()
+                .topic("/a")
+                .build()
+                .unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+    }
+
+    #[test]
+    fn empty_un_subscribe_should_return_error() {
+        let resp = UnSubscribe::new(PacketId::MIN, UnsubscribeProperties::default(), Vec::new());
+        assert_eq!(resp.unwrap_err(), ProtoError::EmptyUnsubscription);
+        // message_id=1，没有属性，也没有topic filter
+        let frame = Bytes::from_static(&[0xa2, 0x03, 0x00, 0x01, 0x00]);
+        assert_eq!(
+            UnSubscribe::decode(frame).unwrap_err(),
+            ProtoError::EmptyUnsubscription
+        );
+        // message_id=0
+        let frame = Bytes::from_static(&[0xa2, 0x06, 0x00, 0x00, 0x00, 0x00, 0x01, b'a']);
+        assert_eq!(
+            UnSubscribe::decode(frame).unwrap_err(),
+            ProtoError::InvalidPacketId(0)
+        );
+        // 0x0b订阅标识符不能出现在UNSUBSCRIBE中
+        let frame =
+            Bytes::from_static(&[0xa2, 0x08, 0x00, 0x01, 0x02, 0x0b, 0x01, 0x00, 0x01, b'a']);
+        assert!(matches!(
+            UnSubscribe::decode(frame).unwrap_err(),
+            ProtoError::PropertyNotAllowed { id: 0x0b, .. }
+        ));
+    }
+}
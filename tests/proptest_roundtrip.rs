@@ -0,0 +1,207 @@
+//! 使用proptest随机生成所有v4报文，编码后再解码，检查报文没有发生变化
+use bytes::Bytes;
+use proptest::prelude::*;
+use walle_mqtt_protocol::v4::{decode_zero_copy, Packet};
+
+mod proptest_strategies {
+    use bytes::Bytes;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use walle_mqtt_protocol::common::packet_id::PacketId;
+    use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+    use walle_mqtt_protocol::v4::conn_ack::{ConnAck, ConnAckType};
+    use walle_mqtt_protocol::v4::ping_req::PingReq;
+    use walle_mqtt_protocol::v4::ping_resp::PingResp;
+    use walle_mqtt_protocol::v4::pub_ack::PubAck;
+    use walle_mqtt_protocol::v4::pub_comp::PubComp;
+    use walle_mqtt_protocol::v4::pub_rec::PubRec;
+    use walle_mqtt_protocol::v4::pub_rel::PubRel;
+    use walle_mqtt_protocol::v4::sub_ack::SubscribeReturnCode;
+    use walle_mqtt_protocol::v4::un_suback::UnSubAck;
+    use walle_mqtt_protocol::v4::Packet;
+    use walle_mqtt_protocol::{QoS, Topic};
+
+    pub fn qos() -> impl Strategy<Value = QoS> {
+        prop_oneof![
+            Just(QoS::AtMostOnce),
+            Just(QoS::AtLeastOnce),
+            Just(QoS::ExactlyOnce),
+        ]
+    }
+
+    pub fn packet_id() -> impl Strategy<Value = PacketId> {
+        (1..=u16::MAX).prop_map(|id| PacketId::new(id).unwrap())
+    }
+
+    /// 合法的topic name：UTF-8，不包含空字符和通配符
+    pub fn topic_name() -> impl Strategy<Value = String> {
+        "[a-zA-Z0-9/_ 温度传感器$-]{1,50}"
+    }
+
+    /// 合法的topic filter：在topic name的基础上追加`+`或者`#`
+    pub fn topic_filter() -> impl Strategy<Value = String> {
+        (topic_name(), prop_oneof![Just(""), Just("/+"), Just("/#")])
+            .prop_map(|(name, suffix)| format!("{}{}", name, suffix))
+    }
+
+    pub fn payload() -> impl Strategy<Value = Bytes> {
+        vec(any::<u8>(), 0..=1000).prop_map(Bytes::from)
+    }
+
+    pub fn connect() -> impl Strategy<Value = Packet> {
+        (
+            "[a-zA-Z0-9]{1,23}",
+            any::<bool>(),
+            any::<u16>(),
+            proptest::option::of((topic_name(), payload(), qos(), any::<bool>())),
+            proptest::option::of(("[a-z0-9]{1,20}", vec(any::<u8>(), 0..64))),
+        )
+            .prop_map(|(client_id, clean_session, keep_alive, will, login)| {
+                let mut builder = MqttMessageBuilder::connect()
+                    .client_id(&client_id)
+                    .clean_session(clean_session)
+                    .keep_alive(keep_alive);
+                if let Some((topic, message, qos, retain)) = will {
+                    builder = builder.will(&topic, message, qos, retain);
+                }
+                if let Some((username, password)) = login {
+                    builder = builder
+                        .username(&username)
+                        .password_bytes(Bytes::from(password));
+                }
+                Packet::Connect(builder.build().unwrap())
+            })
+    }
+
+    pub fn conn_ack() -> impl Strategy<Value = Packet> {
+        prop_oneof![
+            Just(ConnAckType::Success),
+            Just(ConnAckType::ProtoVersionError),
+            Just(ConnAckType::IdentifierRejected),
+            Just(ConnAckType::ServiceUnavailable),
+            Just(ConnAckType::BadUsernameOrPassword),
+            Just(ConnAckType::NotAuthorized),
+        ]
+        .prop_map(|conn_ack_type| Packet::ConnAck(ConnAck::new(conn_ack_type).unwrap()))
+    }
+
+    pub fn publish() -> impl Strategy<Value = Packet> {
+        (
+            topic_name(),
+            qos(),
+            packet_id(),
+            any::<bool>(),
+            any::<bool>(),
+            payload(),
+        )
+            .prop_map(|(topic, qos, packet_id, retain, dup, payload)| {
+                let mut builder = MqttMessageBuilder::publish()
+                    .topic(&topic)
+                    .qos(qos)
+                    .retain(retain)
+                    .payload(payload);
+                // QoS 0的报文没有message_id，dup也必须为0
+                if qos != QoS::AtMostOnce {
+                    builder = builder.packet_id(packet_id).dup(dup);
+                }
+                Packet::Publish(builder.build().unwrap())
+            })
+    }
+
+    pub fn subscribe() -> impl Strategy<Value = Packet> {
+        (packet_id(), vec((topic_filter(), qos()), 1..10)).prop_map(|(packet_id, filters)| {
+            let mut builder = MqttMessageBuilder::subscribe().packet_id(packet_id);
+            for (filter, qos) in filters {
+                builder = builder.topic(Topic::new(filter, qos));
+            }
+            Packet::Subscribe(builder.build().unwrap())
+        })
+    }
+
+    pub fn sub_ack() -> impl Strategy<Value = Packet> {
+        let ack = prop_oneof![
+            Just(SubscribeReturnCode::SuccessQoS0),
+            Just(SubscribeReturnCode::SuccessQoS1),
+            Just(SubscribeReturnCode::SuccessQoS2),
+            Just(SubscribeReturnCode::Failure),
+        ];
+        (packet_id(), vec(ack, 1..10)).prop_map(|(packet_id, acks)| {
+            let sub_ack = MqttMessageBuilder::sub_ack()
+                .packet_id(packet_id)
+                .acks(acks)
+                .build()
+                .unwrap();
+            Packet::SubAck(sub_ack)
+        })
+    }
+
+    pub fn unsubscribe() -> impl Strategy<Value = Packet> {
+        (packet_id(), vec(topic_filter(), 1..10)).prop_map(|(packet_id, filters)| {
+            let unsubscribe = MqttMessageBuilder::unsubscriber()
+                .packet_id(packet_id)
+                .topices(filters)
+                .build()
+                .unwrap();
+            Packet::UnSubscribe(unsubscribe)
+        })
+    }
+
+    /// 只包含message_id的报文，以及没有可变报头的报文
+    pub fn simple() -> impl Strategy<Value = Packet> {
+        prop_oneof![
+            packet_id().prop_map(|id| Packet::PubAck(PubAck::new(id))),
+            packet_id().prop_map(|id| Packet::PubRec(PubRec::new(id))),
+            packet_id().prop_map(|id| Packet::PubRel(PubRel::new(id))),
+            packet_id().prop_map(|id| Packet::PubComp(PubComp::new(id))),
+            packet_id().prop_map(|id| Packet::UnSubAck(UnSubAck::new(id))),
+            Just(()).prop_map(|_| Packet::PingReq(PingReq::new())),
+            Just(()).prop_map(|_| Packet::PingResp(PingResp::new())),
+            Just(()).prop_map(|_| {
+                Packet::DisConnect(MqttMessageBuilder::disconnect().build().unwrap())
+            }),
+        ]
+    }
+
+    pub fn packet() -> impl Strategy<Value = Packet> {
+        prop_oneof![
+            connect(),
+            conn_ack(),
+            publish(),
+            subscribe(),
+            sub_ack(),
+            unsubscribe(),
+            simple(),
+        ]
+    }
+}
+
+/// 编码->解码->再编码，两次编码的结果必须相同
+fn round_trip(packet: &Packet) -> Result<(), TestCaseError> {
+    let encoded = packet
+        .encode_to_bytes()
+        .map_err(|e| TestCaseError::fail(format!("编码{:?}失败：{}", packet, e)))?
+        .freeze();
+    let decoded = decode_zero_copy(encoded.clone()).map_err(|e| {
+        TestCaseError::fail(format!("解码{:?}失败：{}，报文：{:?}", packet, e, encoded))
+    })?;
+    prop_assert_eq!(&decoded, packet);
+    let reencoded: Bytes = decoded
+        .encode_to_bytes()
+        .map_err(|e| TestCaseError::fail(format!("重新编码{:?}失败：{}", decoded, e)))?
+        .freeze();
+    prop_assert_eq!(
+        &reencoded,
+        &encoded,
+        "原始报文：{:?}，解码后：{:?}",
+        packet,
+        decoded
+    );
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn v4_packet_round_trip(packet in proptest_strategies::packet()) {
+        round_trip(&packet)?;
+    }
+}
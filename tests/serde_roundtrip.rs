@@ -0,0 +1,97 @@
+//! 开启serde特性后，报文序列化再反序列化之后必须保持不变，例如将未完成的QoS 1/2消息保存到磁盘
+#![cfg(feature = "serde")]
+use bytes::Bytes;
+use walle_mqtt_protocol::common::packet_id::PacketId;
+use walle_mqtt_protocol::v4::builder::MqttMessageBuilder;
+use walle_mqtt_protocol::v4::publish::Publish;
+use walle_mqtt_protocol::v4::{Encoder, Packet};
+use walle_mqtt_protocol::QoS;
+
+fn qos2_publish() -> Publish {
+    MqttMessageBuilder::publish()
+        .topic("/device/温度")
+        .qos(QoS::ExactlyOnce)
+        .packet_id(PacketId::new(513).unwrap())
+        .dup(true)
+        .retain(true)
+        .payload(Bytes::from((0..=255u8).collect::<Vec<u8>>()))
+        .build()
+        .unwrap()
+}
+
+fn encode(publish: &Publish) -> Bytes {
+    let mut bytes = bytes::BytesMut::new();
+    publish.encode(&mut bytes).unwrap();
+    bytes.freeze()
+}
+
+#[test]
+fn qos2_publish_json_round_trip() {
+    let publish = qos2_publish();
+    let json = serde_json::to_string(&publish).unwrap();
+    // topic按照字符串保存
+    assert!(json.contains("\"/device/温度\""), "{}", json);
+    let decoded: Publish = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, publish);
+    assert_eq!(encode(&decoded), encode(&publish));
+}
+
+#[test]
+fn qos2_publish_bincode_round_trip() {
+    let publish = qos2_publish();
+    let bytes = bincode::serialize(&publish).unwrap();
+    let decoded: Publish = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(decoded, publish);
+    assert_eq!(encode(&decoded), encode(&publish));
+}
+
+#[test]
+fn packet_round_trip() {
+    let connect = MqttMessageBuilder::connect()
+        .client_id("client01")
+        .username("rump")
+        .password_bytes(Bytes::from_static(&[0, 159, 146, 150]))
+        .will(
+            "/will",
+            Bytes::from_static(&[0xff, 0x00]),
+            QoS::AtLeastOnce,
+            true,
+        )
+        .build()
+        .unwrap();
+    let subscribe = MqttMessageBuilder::subscribe()
+        .packet_id(PacketId::MIN)
+        .add("/a/#", QoS::AtLeastOnce)
+        .build()
+        .unwrap();
+    for packet in [
+        Packet::Connect(connect),
+        Packet::Subscribe(subscribe),
+        Packet::Publish(qos2_publish()),
+    ] {
+        let json = serde_json::to_vec(&packet).unwrap();
+        assert_eq!(serde_json::from_slice::<Packet>(&json).unwrap(), packet);
+        let bytes = bincode::serialize(&packet).unwrap();
+        assert_eq!(bincode::deserialize::<Packet>(&bytes).unwrap(), packet);
+    }
+}
+
+#[test]
+fn zero_packet_id_should_not_deserialize() {
+    assert!(serde_json::from_str::<PacketId>("0").is_err());
+    assert_eq!(serde_json::from_str::<PacketId>("7").unwrap().get(), 7);
+}
+
+#[test]
+fn shared_topic_filter_round_trip() {
+    use walle_mqtt_protocol::common::topic::{SharedTopicFilter, TopicFilter};
+    let shared = SharedTopicFilter::parse("$share/g1/a/+", QoS::AtLeastOnce).unwrap();
+    let filter = TopicFilter::from(shared);
+    let json = serde_json::to_string(&filter).unwrap();
+    assert_eq!(json, r#"{"path":"$share/g1/a/+","qos":"AtLeastOnce"}"#);
+    // 反序列化之后依然是共享订阅
+    let decoded = serde_json::from_str::<TopicFilter>(&json).unwrap();
+    assert_eq!(decoded, filter);
+    assert_eq!(decoded.share_name(), Some("g1"));
+    assert!(decoded.matches("a/b"));
+}